@@ -0,0 +1,366 @@
+use rand::Rng;
+use rand_distr::{Exp, Uniform};
+use serde::Deserialize;
+use std::collections::VecDeque;
+
+use crate::book::Book;
+use crate::ids::OrderIds;
+use crate::order::{Order, OrderType, Side};
+use crate::regime::RegimeParams;
+use crate::sizes::SizeDist;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AgentConfig {
+    pub enabled: bool,
+    /// Keep the regime-driven statistical flow running alongside the agents.
+    pub statistical_flow: bool,
+    pub market_makers: usize,
+    pub momentum_traders: usize,
+    pub noise_traders: usize,
+    /// Participant ID of the first agent; agents are numbered consecutively.
+    pub first_id: u32,
+    pub mm_quote_size: u32,
+    /// Market makers requote once mid has moved this many ticks from their quote.
+    pub mm_requote_ticks: f64,
+    pub momentum_lookback: f64,
+    /// Absolute return over the lookback that triggers momentum buying/selling.
+    pub momentum_threshold: f64,
+    /// Market orders per second per momentum agent while the signal is active.
+    pub momentum_rate: f64,
+    /// Orders per second per noise trader.
+    pub noise_rate: f64,
+    pub spoofers: usize,
+    /// Mean seconds between spoofing episodes per spoofer.
+    pub spoof_interval: f64,
+    /// Number of layered limit orders per episode.
+    pub spoof_layers: usize,
+    /// Tick spacing between layers.
+    pub spoof_layer_spacing: f64,
+    pub spoof_layer_size: u32,
+    /// Seconds the layers rest before the mass cancel.
+    pub spoof_hold: f64,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            statistical_flow: true,
+            market_makers: 2,
+            momentum_traders: 4,
+            noise_traders: 8,
+            first_id: 10_001,
+            mm_quote_size: 100,
+            mm_requote_ticks: 2.0,
+            momentum_lookback: 1.0,
+            momentum_threshold: 0.001,
+            momentum_rate: 2.0,
+            noise_rate: 1.0,
+            spoofers: 0,
+            spoof_interval: 30.0,
+            spoof_layers: 5,
+            spoof_layer_spacing: 1.0,
+            spoof_layer_size: 500,
+            spoof_hold: 2.0,
+        }
+    }
+}
+
+pub enum AgentAction {
+    Submit(Order),
+    Cancel(u64),
+    /// Ground-truth label for manipulation patterns, written to the event log.
+    Label(String),
+}
+
+enum Kind {
+    /// Resting (bid_id, ask_id) and the mid they were quoted around.
+    MarketMaker {
+        quotes: Option<(u64, u64)>,
+        quoted_mid: f64,
+    },
+    Momentum,
+    Noise,
+    /// Resting layer IDs, the side they were placed on, and when to pull them.
+    Spoofer {
+        layers: Vec<u64>,
+        side: Side,
+        release_at: f64,
+    },
+}
+
+struct Agent {
+    participant_id: u32,
+    kind: Kind,
+}
+
+/// Per-tick market state handed to the agents.
+pub struct AgentContext<'a> {
+    pub mid: f64,
+    pub time: f64,
+    pub tick_size: f64,
+    pub params: &'a RegimeParams,
+    pub book: &'a Book,
+    pub size_dist: &'a SizeDist,
+    pub ttl_dist: &'a Uniform<f64>,
+}
+
+/// Population of trading agents layered on top of the statistical flow.
+pub struct AgentLayer {
+    cfg: AgentConfig,
+    agents: Vec<Agent>,
+    dt_seconds: f64,
+    mid_history: VecDeque<f64>,
+    lookback_ticks: usize,
+}
+
+impl AgentLayer {
+    pub fn new(cfg: &AgentConfig, dt_seconds: f64) -> Self {
+        let mut agents = Vec::new();
+        let mut id = cfg.first_id;
+        let mut add = |kind: Kind| {
+            agents.push(Agent {
+                participant_id: id,
+                kind,
+            });
+            id += 1;
+        };
+        for _ in 0..cfg.market_makers {
+            add(Kind::MarketMaker {
+                quotes: None,
+                quoted_mid: 0.0,
+            });
+        }
+        for _ in 0..cfg.momentum_traders {
+            add(Kind::Momentum);
+        }
+        for _ in 0..cfg.noise_traders {
+            add(Kind::Noise);
+        }
+        for _ in 0..cfg.spoofers {
+            add(Kind::Spoofer {
+                layers: Vec::new(),
+                side: Side::Buy,
+                release_at: 0.0,
+            });
+        }
+
+        let lookback_ticks = ((cfg.momentum_lookback / dt_seconds).round() as usize).max(1);
+        Self {
+            cfg: cfg.clone(),
+            agents,
+            dt_seconds,
+            mid_history: VecDeque::with_capacity(lookback_ticks + 1),
+            lookback_ticks,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.agents.len()
+    }
+
+    /// Let every agent act once. New order IDs are taken from `ids`.
+    pub fn step(
+        &mut self,
+        ctx: &AgentContext,
+        ids: &mut OrderIds,
+        rng: &mut impl Rng,
+    ) -> Vec<AgentAction> {
+        self.mid_history.push_back(ctx.mid);
+        if self.mid_history.len() > self.lookback_ticks + 1 {
+            self.mid_history.pop_front();
+        }
+        let momentum = match self.mid_history.front() {
+            Some(&past) if self.mid_history.len() > self.lookback_ticks => ctx.mid / past - 1.0,
+            _ => 0.0,
+        };
+
+        let mut actions = Vec::new();
+        let momentum_prob = self.cfg.momentum_rate * self.dt_seconds;
+        let noise_prob = self.cfg.noise_rate * self.dt_seconds;
+        let spoof_prob = self.dt_seconds / self.cfg.spoof_interval;
+
+        for agent in &mut self.agents {
+            let pid = agent.participant_id;
+            match &mut agent.kind {
+                Kind::MarketMaker { quotes, quoted_mid } => {
+                    let moved = (ctx.mid - *quoted_mid).abs() / ctx.tick_size;
+                    let missing =
+                        quotes.is_some_and(|(b, a)| !ctx.book.contains(b) || !ctx.book.contains(a));
+                    if quotes.is_some() && moved < self.cfg.mm_requote_ticks && !missing {
+                        continue;
+                    }
+                    if let Some((b, a)) = quotes.take() {
+                        for id in [b, a] {
+                            if ctx.book.contains(id) {
+                                actions.push(AgentAction::Cancel(id));
+                            }
+                        }
+                    }
+                    let bid = limit_order(
+                        ids.next(),
+                        Side::Buy,
+                        ctx.mid - ctx.params.half_spread,
+                        self.cfg.mm_quote_size,
+                        pid,
+                        ctx,
+                    );
+                    let ask = limit_order(
+                        ids.next(),
+                        Side::Sell,
+                        ctx.mid + ctx.params.half_spread,
+                        self.cfg.mm_quote_size,
+                        pid,
+                        ctx,
+                    );
+                    *quotes = Some((bid.id, ask.id));
+                    *quoted_mid = ctx.mid;
+                    actions.push(AgentAction::Submit(bid));
+                    actions.push(AgentAction::Submit(ask));
+                }
+                Kind::Momentum => {
+                    if momentum.abs() < self.cfg.momentum_threshold
+                        || rng.gen::<f64>() >= momentum_prob
+                    {
+                        continue;
+                    }
+                    let side = if momentum > 0.0 {
+                        Side::Buy
+                    } else {
+                        Side::Sell
+                    };
+                    let size = ctx.size_dist.sample(1.0, rng);
+                    actions.push(AgentAction::Submit(market_order(
+                        ids.next(),
+                        side,
+                        size,
+                        pid,
+                        ctx,
+                    )));
+                }
+                Kind::Noise => {
+                    if rng.gen::<f64>() >= noise_prob {
+                        continue;
+                    }
+                    let side = if rng.gen::<bool>() {
+                        Side::Buy
+                    } else {
+                        Side::Sell
+                    };
+                    let size = ctx.size_dist.sample(1.0, rng);
+                    let order = if rng.gen::<f64>() < 0.3 {
+                        market_order(ids.next(), side, size, pid, ctx)
+                    } else {
+                        let offset = ctx.params.half_spread
+                            + rng.sample::<f64, _>(Exp::new(ctx.params.offset_lambda).unwrap());
+                        let price = match side {
+                            Side::Buy => ctx.mid - offset,
+                            Side::Sell => ctx.mid + offset,
+                        };
+                        let mut o = limit_order(ids.next(), side, price, size, pid, ctx);
+                        o.ttl = rng.sample(ctx.ttl_dist);
+                        o
+                    };
+                    actions.push(AgentAction::Submit(order));
+                }
+                Kind::Spoofer {
+                    layers,
+                    side,
+                    release_at,
+                } => {
+                    if layers.is_empty() {
+                        if rng.gen::<f64>() >= spoof_prob {
+                            continue;
+                        }
+                        // Layer one side to fake pressure, then trade the other.
+                        *side = if rng.gen::<bool>() {
+                            Side::Buy
+                        } else {
+                            Side::Sell
+                        };
+                        *release_at = ctx.time + self.cfg.spoof_hold;
+                        for k in 0..self.cfg.spoof_layers {
+                            let offset = ctx.params.half_spread
+                                + k as f64 * self.cfg.spoof_layer_spacing * ctx.tick_size;
+                            let price = match side {
+                                Side::Buy => ctx.mid - offset,
+                                Side::Sell => ctx.mid + offset,
+                            };
+                            let order = limit_order(
+                                ids.next(),
+                                *side,
+                                price,
+                                self.cfg.spoof_layer_size,
+                                pid,
+                                ctx,
+                            );
+                            layers.push(order.id);
+                            actions.push(AgentAction::Submit(order));
+                        }
+                        actions.push(AgentAction::Label(format!(
+                            "SPOOF_LAYER participant={} side={} layers={} size={}",
+                            pid, side, self.cfg.spoof_layers, self.cfg.spoof_layer_size
+                        )));
+                    } else if ctx.time >= *release_at {
+                        for id in layers.drain(..) {
+                            if ctx.book.contains(id) {
+                                actions.push(AgentAction::Cancel(id));
+                            }
+                        }
+                        let opposite = match side {
+                            Side::Buy => Side::Sell,
+                            Side::Sell => Side::Buy,
+                        };
+                        let size = ctx.size_dist.sample(1.0, rng);
+                        actions.push(AgentAction::Submit(market_order(
+                            ids.next(),
+                            opposite,
+                            size,
+                            pid,
+                            ctx,
+                        )));
+                        actions.push(AgentAction::Label(format!(
+                            "SPOOF_EXECUTE participant={} cancelled_side={} market_side={} size={}",
+                            pid, side, opposite, size
+                        )));
+                    }
+                }
+            }
+        }
+        actions
+    }
+}
+
+/// Limit order at `price` rounded to the tick grid. TTL 0 means it rests until cancelled.
+fn limit_order(id: u64, side: Side, price: f64, size: u32, pid: u32, ctx: &AgentContext) -> Order {
+    Order {
+        id,
+        side,
+        order_type: OrderType::Limit,
+        price: ((price / ctx.tick_size).round() * ctx.tick_size).max(ctx.tick_size),
+        size,
+        reserve: 0,
+        participant_id: pid,
+        cl_ord_id: String::new(),
+        instrument: ctx.book.instrument(),
+        created_at: ctx.time,
+        ttl: 0.0,
+    }
+}
+
+fn market_order(id: u64, side: Side, size: u32, pid: u32, ctx: &AgentContext) -> Order {
+    Order {
+        id,
+        side,
+        order_type: OrderType::Market,
+        price: 0.0,
+        size,
+        reserve: 0,
+        participant_id: pid,
+        cl_ord_id: String::new(),
+        instrument: ctx.book.instrument(),
+        created_at: ctx.time,
+        ttl: 0.0,
+    }
+}
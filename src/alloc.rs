@@ -0,0 +1,48 @@
+#[cfg(feature = "bench")]
+use std::alloc::{GlobalAlloc, Layout, System};
+#[cfg(feature = "bench")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// System allocator wrapper that counts allocations, used by bench mode
+/// to report allocation pressure on the hot path.
+#[cfg(feature = "bench")]
+pub struct CountingAlloc;
+
+#[cfg(feature = "bench")]
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+#[cfg(feature = "bench")]
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(feature = "bench")]
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Snapshot of (allocation count, allocated bytes) since process start.
+#[cfg(feature = "bench")]
+pub fn snapshot() -> Option<(u64, u64)> {
+    Some((
+        ALLOCATIONS.load(Ordering::Relaxed),
+        ALLOCATED_BYTES.load(Ordering::Relaxed),
+    ))
+}
+
+/// Without the `bench` feature nothing is counted.
+#[cfg(not(feature = "bench"))]
+pub fn snapshot() -> Option<(u64, u64)> {
+    None
+}
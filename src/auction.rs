@@ -0,0 +1,632 @@
+use serde::Deserialize;
+
+use crate::book::{Book, Fill, SelfMatchMode};
+use crate::order::{Order, OrderType, Side, Trade};
+use crate::wire::{self, Price, WireMessage, MSG_IMBALANCE, MSG_UNCROSS};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    /// Seconds of opening auction at the start of the run (0 = open directly).
+    pub opening_auction: f64,
+    /// Sim time at which the closing auction starts; the run ends after it.
+    pub close_at: Option<f64>,
+    /// Length of the closing auction in seconds.
+    pub closing_auction: f64,
+    /// Seconds between IMBALANCE messages during an auction.
+    pub imbalance_interval: f64,
+    /// Daily trading hours as `"HH:MM"` or `"HH:MM:SS"`. With both set the
+    /// run follows a schedule instead of `close_at`: sim time 0 is the first
+    /// open, and each day's closing auction is followed by the next open.
+    pub open: Option<String>,
+    pub close: Option<String>,
+    /// Optional midday break, e.g. `["12:00", "13:00"]`.
+    pub lunch: Option<(String, String)>,
+    /// Trading days to run with a schedule (unlimited when omitted).
+    pub days: Option<u32>,
+    /// Log return applied to mid between one day's close and the next open.
+    pub overnight: OvernightConfig,
+}
+
+/// `[session.overnight]`: the overnight gap is `N(mean, std²)` in log terms.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OvernightConfig {
+    pub mean: f64,
+    pub std: f64,
+}
+
+impl Default for OvernightConfig {
+    fn default() -> Self {
+        Self {
+            mean: 0.0,
+            std: 0.01,
+        }
+    }
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            opening_auction: 0.0,
+            close_at: None,
+            closing_auction: 30.0,
+            imbalance_interval: 1.0,
+            open: None,
+            close: None,
+            lunch: None,
+            days: None,
+            overnight: OvernightConfig::default(),
+        }
+    }
+}
+
+const DAY: f64 = 86_400.0;
+
+/// Seconds after midnight for `"HH:MM"` or `"HH:MM:SS"`.
+fn parse_time_of_day(s: &str) -> Result<f64, Box<dyn std::error::Error>> {
+    let parts: Vec<&str> = s.split(':').collect();
+    let field = |i: usize, max: u32| -> Result<u32, Box<dyn std::error::Error>> {
+        match parts.get(i).map(|p| p.parse::<u32>()) {
+            Some(Ok(v)) if v < max => Ok(v),
+            None => Ok(0),
+            _ => Err(format!("invalid time of day '{}': expected HH:MM or HH:MM:SS", s).into()),
+        }
+    };
+    if !(2..=3).contains(&parts.len()) {
+        return Err(format!("invalid time of day '{}': expected HH:MM or HH:MM:SS", s).into());
+    }
+    Ok(f64::from(
+        field(0, 24)? * 3600 + field(1, 60)? * 60 + field(2, 60)?,
+    ))
+}
+
+fn format_time_of_day(secs: f64) -> String {
+    let s = secs as u32;
+    match s % 60 {
+        0 => format!("{:02}:{:02}", s / 3600, s / 60 % 60),
+        sec => format!("{:02}:{:02}:{:02}", s / 3600, s / 60 % 60, sec),
+    }
+}
+
+/// Daily trading hours, in seconds after midnight.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    open: f64,
+    close: f64,
+    lunch: Option<(f64, f64)>,
+}
+
+impl Schedule {
+    /// The schedule in `[session]`, if `open` and `close` are both set.
+    pub fn from_config(cfg: &SessionConfig) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let (open, close) = match (&cfg.open, &cfg.close) {
+            (Some(open), Some(close)) => (parse_time_of_day(open)?, parse_time_of_day(close)?),
+            (None, None) => return Ok(None),
+            _ => return Err("session open and close must be set together".into()),
+        };
+        if open >= close {
+            return Err(format!(
+                "session open ({}) must be before close ({})",
+                format_time_of_day(open),
+                format_time_of_day(close)
+            )
+            .into());
+        }
+        let lunch = match &cfg.lunch {
+            Some((start, end)) => {
+                let (start, end) = (parse_time_of_day(start)?, parse_time_of_day(end)?);
+                if !(open < start && start < end && end < close) {
+                    return Err("session lunch must start and end within trading hours".into());
+                }
+                Some((start, end))
+            }
+            None => None,
+        };
+        if cfg.opening_auction >= lunch.map_or(close, |(start, _)| start) - open {
+            return Err("opening_auction must be shorter than the first trading period".into());
+        }
+        Ok(Some(Self { open, close, lunch }))
+    }
+
+    /// Sim time of `time_of_day` on `day` (day 0 opens at sim time 0).
+    fn at(&self, day: u32, time_of_day: f64) -> f64 {
+        f64::from(day) * DAY + time_of_day - self.open
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            format_time_of_day(self.open),
+            format_time_of_day(self.close)
+        )?;
+        if let Some((start, end)) = self.lunch {
+            write!(
+                f,
+                " (lunch {}-{})",
+                format_time_of_day(start),
+                format_time_of_day(end)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    OpeningAuction,
+    Continuous,
+    ClosingAuction,
+    /// Outside trading hours (lunch or overnight) until `phase_end`.
+    Break,
+    Closed,
+}
+
+/// What the engine has to act on after `Session::poll`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    AuctionStart,
+    /// The auction ended and the book should be uncrossed.
+    Uncross,
+    /// Continuous trading paused for the lunch break.
+    Break,
+    /// Trading hours resumed after the lunch break or, with `new_day`,
+    /// overnight; either directly or with an opening auction.
+    Open {
+        new_day: bool,
+    },
+}
+
+/// Session phases: an optional opening auction, continuous trading and an
+/// optional closing auction, repeated daily (with an optional lunch break)
+/// when there is a schedule. Market orders received during an auction are
+/// held here until the uncross.
+pub struct Session {
+    cfg: SessionConfig,
+    schedule: Option<Schedule>,
+    phase: Phase,
+    phase_end: f64,
+    next_imbalance: f64,
+    markets: Vec<Order>,
+    /// Trading day under a schedule, counted from 0.
+    day: u32,
+    lunch_taken: bool,
+}
+
+impl Session {
+    pub fn new(cfg: &SessionConfig, schedule: Option<Schedule>) -> Self {
+        let phase = if cfg.opening_auction > 0.0 {
+            Phase::OpeningAuction
+        } else {
+            Phase::Continuous
+        };
+        Self {
+            cfg: cfg.clone(),
+            schedule,
+            phase,
+            phase_end: cfg.opening_auction,
+            next_imbalance: 0.0,
+            markets: Vec::new(),
+            day: 0,
+            lunch_taken: false,
+        }
+    }
+
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+
+    /// When trading hours resume, while outside them.
+    pub fn resume_at(&self) -> Option<f64> {
+        (self.phase == Phase::Break).then_some(self.phase_end)
+    }
+
+    /// Start the next trading period at `now`, with an opening auction if configured.
+    fn reopen(&mut self, now: f64) {
+        if self.cfg.opening_auction > 0.0 {
+            self.phase = Phase::OpeningAuction;
+            self.phase_end = now + self.cfg.opening_auction;
+            self.next_imbalance = now;
+        } else {
+            self.phase = Phase::Continuous;
+        }
+    }
+
+    pub fn in_auction(&self) -> bool {
+        matches!(self.phase, Phase::OpeningAuction | Phase::ClosingAuction)
+    }
+
+    /// No further trading: the run is over.
+    pub fn is_closed(&self) -> bool {
+        self.phase == Phase::Closed
+    }
+
+    /// In continuous trading (not in an auction, a break or closed).
+    pub fn is_continuous(&self) -> bool {
+        self.phase == Phase::Continuous
+    }
+
+    /// Advance the phase clock.
+    pub fn poll(&mut self, now: f64) -> Option<SessionEvent> {
+        // (epsilon absorbs float drift in the accumulated sim clock)
+        let due = |t: f64| now + 1e-9 >= t;
+        match self.phase {
+            Phase::OpeningAuction if due(self.phase_end) => {
+                self.phase = Phase::Continuous;
+                Some(SessionEvent::Uncross)
+            }
+            Phase::Continuous => {
+                let (lunch, close_at) = match &self.schedule {
+                    Some(s) => (
+                        s.lunch
+                            .filter(|_| !self.lunch_taken)
+                            .map(|(start, end)| (s.at(self.day, start), s.at(self.day, end))),
+                        Some(s.at(self.day, s.close)),
+                    ),
+                    None => (None, self.cfg.close_at),
+                };
+                if let Some((_, end)) = lunch.filter(|&(start, _)| due(start)) {
+                    self.lunch_taken = true;
+                    self.phase = Phase::Break;
+                    self.phase_end = end;
+                    Some(SessionEvent::Break)
+                } else if close_at.is_some_and(due) {
+                    self.phase = Phase::ClosingAuction;
+                    self.phase_end = now + self.cfg.closing_auction;
+                    self.next_imbalance = now;
+                    Some(SessionEvent::AuctionStart)
+                } else {
+                    None
+                }
+            }
+            Phase::ClosingAuction if due(self.phase_end) => {
+                let next_day = self.day + 1;
+                match &self.schedule {
+                    Some(s) if self.cfg.days.is_none_or(|d| next_day < d) => {
+                        self.phase = Phase::Break;
+                        self.phase_end = s.at(next_day, s.open).max(now);
+                    }
+                    _ => self.phase = Phase::Closed,
+                }
+                Some(SessionEvent::Uncross)
+            }
+            Phase::Break if due(self.phase_end) => {
+                let new_day = self
+                    .schedule
+                    .as_ref()
+                    .is_some_and(|s| self.phase_end >= s.at(self.day, s.close));
+                if new_day {
+                    self.day += 1;
+                    self.lunch_taken = false;
+                }
+                self.reopen(now);
+                Some(SessionEvent::Open { new_day })
+            }
+            _ => None,
+        }
+    }
+
+    /// Hold a market order for the uncross.
+    pub fn collect(&mut self, order: Order) {
+        self.markets.push(order);
+    }
+
+    pub fn take_markets(&mut self) -> Vec<Order> {
+        std::mem::take(&mut self.markets)
+    }
+
+    pub fn markets(&self) -> &[Order] {
+        &self.markets
+    }
+
+    /// Whether an IMBALANCE message is due this tick.
+    pub fn imbalance_due(&mut self, now: f64) -> bool {
+        // (epsilon absorbs float drift in the accumulated sim clock)
+        if !self.in_auction() || now + 1e-9 < self.next_imbalance {
+            return false;
+        }
+        self.next_imbalance += self.cfg.imbalance_interval;
+        true
+    }
+}
+
+/// Price that would clear the auction right now.
+#[derive(Debug, Clone, Copy)]
+pub struct Indicative {
+    /// `None` when nothing would trade.
+    pub price: Option<f64>,
+    pub matched: u64,
+    /// Unmatched buy and sell quantity at `price`.
+    pub buy_surplus: u64,
+    pub sell_surplus: u64,
+}
+
+fn market_qty(markets: &[Order], side: Side) -> u64 {
+    markets
+        .iter()
+        .filter(|o| o.side == side)
+        .map(|o| o.size as u64)
+        .sum()
+}
+
+/// Find the price maximizing executable volume over crossed limit orders and
+/// held market orders. Ties go to the smallest imbalance, then to the price
+/// nearest `reference`.
+pub fn indicative(book: &Book, markets: &[Order], reference: f64) -> Indicative {
+    let market_buys = market_qty(markets, Side::Buy);
+    let market_sells = market_qty(markets, Side::Sell);
+    let bids = book.top(Side::Buy, usize::MAX);
+    let asks = book.top(Side::Sell, usize::MAX);
+
+    let mut best = Indicative {
+        price: None,
+        matched: 0,
+        buy_surplus: market_buys,
+        sell_surplus: market_sells,
+    };
+    for &(p, _) in bids.iter().chain(asks.iter()) {
+        let buys = market_buys
+            + bids
+                .iter()
+                .filter(|(bp, _)| *bp >= p)
+                .map(|(_, l)| l.size)
+                .sum::<u64>();
+        let sells = market_sells
+            + asks
+                .iter()
+                .filter(|(ap, _)| *ap <= p)
+                .map(|(_, l)| l.size)
+                .sum::<u64>();
+        let matched = buys.min(sells);
+        if matched == 0 {
+            continue;
+        }
+        let imbalance = buys.abs_diff(sells);
+        let better = match best.price {
+            None => true,
+            Some(bp) => {
+                let best_imbalance = best.buy_surplus.abs_diff(best.sell_surplus);
+                matched > best.matched
+                    || (matched == best.matched && imbalance < best_imbalance)
+                    || (matched == best.matched
+                        && imbalance == best_imbalance
+                        && (p - reference).abs() < (bp - reference).abs())
+            }
+        };
+        if better {
+            best = Indicative {
+                price: Some(p),
+                matched,
+                buy_surplus: buys - matched,
+                sell_surplus: sells - matched,
+            };
+        }
+    }
+    best
+}
+
+/// What the uncross publishes after UNCROSS, in order.
+pub enum UncrossFill {
+    Trade(Trade),
+    /// An iceberg refreshed from its reserve, after its last TRADE.
+    Refresh(Order),
+}
+
+/// One order's share of the uncross, in allocation order.
+struct Allocation {
+    id: u64,
+    qty: u32,
+    resting: bool,
+    /// The order's iceberg refresh, published once its share has traded.
+    replenished: Option<Order>,
+}
+
+/// Execute the auction at its indicative price. Held market orders fill
+/// first; resting orders at or through the price fill in price-time priority.
+/// Returns the uncross and its fills: buys and sells paired off in that
+/// order into TRADEs at the uncross price. A held market order takes from a
+/// resting one; between two of a kind the side with the larger surplus is
+/// the aggressor.
+pub fn uncross(
+    book: &mut Book,
+    markets: &[Order],
+    reference: f64,
+    time: f64,
+) -> (Uncross, Vec<UncrossFill>) {
+    let ind = indicative(book, markets, reference);
+    let mut fills = Vec::new();
+    if let Some(price) = ind.price {
+        // Each side: market orders, then the resting quantity that fills once
+        // they are done, consumed by a synthetic taker from the other side.
+        let mut sides: [Vec<Allocation>; 2] = Default::default();
+        for (i, (resting, taker_side)) in [(Side::Buy, Side::Sell), (Side::Sell, Side::Buy)]
+            .into_iter()
+            .enumerate()
+        {
+            let allocations = &mut sides[i];
+            allocations.extend(
+                markets
+                    .iter()
+                    .filter(|o| o.side == resting)
+                    .map(|o| Allocation {
+                        id: o.id,
+                        qty: o.size,
+                        resting: false,
+                        replenished: None,
+                    }),
+            );
+            let limit_qty = ind.matched.saturating_sub(market_qty(markets, resting));
+            if limit_qty == 0 {
+                continue;
+            }
+            let taker = Order {
+                id: 0,
+                side: taker_side,
+                order_type: OrderType::Limit,
+                price,
+                size: limit_qty.min(u32::MAX as u64) as u32,
+                reserve: 0,
+                participant_id: 0,
+                cl_ord_id: String::new(),
+                instrument: book.instrument(),
+                created_at: time,
+                ttl: 0.0,
+            };
+            for exec in book.execute(&taker, time, SelfMatchMode::Allow) {
+                if let Fill::Trade(t) = exec.fill {
+                    allocations.push(Allocation {
+                        id: t.maker_id,
+                        qty: t.size,
+                        resting: true,
+                        replenished: exec.replenished,
+                    });
+                }
+            }
+        }
+
+        let surplus_side = if ind.buy_surplus >= ind.sell_surplus {
+            Side::Buy
+        } else {
+            Side::Sell
+        };
+        let [mut buys, mut sells] = sides.map(|a| a.into_iter().peekable());
+        let mut remaining = ind.matched;
+        while remaining > 0 {
+            let (Some(buy), Some(sell)) = (buys.peek_mut(), sells.peek_mut()) else {
+                break;
+            };
+            let qty = buy
+                .qty
+                .min(sell.qty)
+                .min(remaining.min(u32::MAX as u64) as u32);
+            let aggressor = match (buy.resting, sell.resting) {
+                (true, false) => Side::Sell,
+                (false, true) => Side::Buy,
+                _ => surplus_side,
+            };
+            let (maker_id, taker_id) = match aggressor {
+                Side::Buy => (sell.id, buy.id),
+                Side::Sell => (buy.id, sell.id),
+            };
+            buy.qty -= qty;
+            sell.qty -= qty;
+            remaining -= qty as u64;
+            fills.push(UncrossFill::Trade(Trade {
+                maker_id,
+                taker_id,
+                aggressor,
+                price,
+                size: qty,
+                instrument: book.instrument(),
+                time,
+            }));
+            for side in [&mut buys, &mut sells] {
+                if side.peek().is_some_and(|a| a.qty == 0) {
+                    let done = side.next().expect("peeked");
+                    fills.extend(done.replenished.map(UncrossFill::Refresh));
+                }
+            }
+        }
+    }
+    (
+        Uncross {
+            price: ind.price.unwrap_or(0.0),
+            volume: ind.matched,
+            instrument: book.instrument(),
+            time,
+        },
+        fills,
+    )
+}
+
+/// Periodic auction state: indicative price, matched volume and surplus.
+#[derive(Debug, Clone)]
+pub struct Imbalance {
+    pub indicative: Indicative,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl WireMessage for Imbalance {
+    fn to_wire_text(&self) -> String {
+        let ind = &self.indicative;
+        let (side, qty) = surplus(ind);
+        let mut msg = format!(
+            "IMBALANCE|price={}|matched={}|imbalance={}|side={}|time={:.3}",
+            Price(ind.price.unwrap_or(0.0)),
+            ind.matched,
+            qty,
+            side.map_or("NONE".to_string(), |s| s.to_string()),
+            self.time,
+        );
+        let _ = wire::write_instrument(&mut msg, self.instrument);
+        msg
+    }
+
+    /// Binary imbalance (v2), little-endian:
+    /// header msg_type=11, price:f64 (0 = none), matched:u64, imbalance:u64,
+    /// side:u8 (0 none, 1 buy, 2 sell), time:f64, instrument:u16
+    fn to_wire_binary(&self) -> Vec<u8> {
+        let ind = &self.indicative;
+        let (side, qty) = surplus(ind);
+        let mut out = wire::binary_frame(MSG_IMBALANCE, 8 + 8 + 8 + 1 + 8 + 2);
+        out.extend_from_slice(&ind.price.unwrap_or(0.0).to_le_bytes());
+        out.extend_from_slice(&ind.matched.to_le_bytes());
+        out.extend_from_slice(&qty.to_le_bytes());
+        out.push(match side {
+            None => 0,
+            Some(Side::Buy) => 1,
+            Some(Side::Sell) => 2,
+        });
+        out.extend_from_slice(&self.time.to_le_bytes());
+        out.extend_from_slice(&self.instrument.to_le_bytes());
+        out
+    }
+}
+
+pub(crate) fn surplus(ind: &Indicative) -> (Option<Side>, u64) {
+    if ind.buy_surplus > ind.sell_surplus {
+        (Some(Side::Buy), ind.buy_surplus)
+    } else if ind.sell_surplus > ind.buy_surplus {
+        (Some(Side::Sell), ind.sell_surplus)
+    } else {
+        (None, 0)
+    }
+}
+
+/// The single auction trade.
+#[derive(Debug, Clone)]
+pub struct Uncross {
+    /// 0 when nothing traded.
+    pub price: f64,
+    pub volume: u64,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl WireMessage for Uncross {
+    fn to_wire_text(&self) -> String {
+        let mut msg = format!(
+            "UNCROSS|price={}|volume={}|time={:.3}",
+            Price(self.price),
+            self.volume,
+            self.time
+        );
+        let _ = wire::write_instrument(&mut msg, self.instrument);
+        msg
+    }
+
+    /// Binary uncross (v2), little-endian:
+    /// header msg_type=12, price:f64, volume:u64, time:f64, instrument:u16
+    fn to_wire_binary(&self) -> Vec<u8> {
+        let mut out = wire::binary_frame(MSG_UNCROSS, 8 + 8 + 8 + 2);
+        out.extend_from_slice(&self.price.to_le_bytes());
+        out.extend_from_slice(&self.volume.to_le_bytes());
+        out.extend_from_slice(&self.time.to_le_bytes());
+        out.extend_from_slice(&self.instrument.to_le_bytes());
+        out
+    }
+}
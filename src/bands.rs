@@ -0,0 +1,210 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::VecDeque;
+
+use crate::order::{Order, Side};
+use crate::wire::{self, Price, WireMessage, MSG_REJECT};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PriceBandConfig {
+    pub enabled: bool,
+    /// Band half-width as a fraction of the reference price.
+    pub band_pct: f64,
+    /// Seconds of mid history averaged into the reference price.
+    pub reference_window: f64,
+}
+
+impl Default for PriceBandConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            band_pct: 0.05,
+            reference_window: 300.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RejectConfig {
+    /// Probability that a generated limit order is erroneous and rejected.
+    pub error_prob: f64,
+    /// Share of erroneous orders with zero size; the rest are mispriced.
+    pub zero_size_share: f64,
+    /// Static collar half-width as a fraction of mid. Mispriced orders land
+    /// twice as far from mid, on a random side.
+    pub collar_pct: f64,
+}
+
+impl Default for RejectConfig {
+    fn default() -> Self {
+        Self {
+            error_prob: 0.0,
+            zero_size_share: 0.5,
+            collar_pct: 0.2,
+        }
+    }
+}
+
+/// Limit-up/limit-down bands around a rolling average of mid.
+pub struct PriceBands {
+    cfg: PriceBandConfig,
+    /// (time, mid) samples within the reference window, oldest first.
+    history: VecDeque<(f64, f64)>,
+    sum: f64,
+}
+
+impl PriceBands {
+    pub fn new(cfg: &PriceBandConfig) -> Self {
+        Self {
+            cfg: cfg.clone(),
+            history: VecDeque::new(),
+            sum: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, now: f64, mid: f64) {
+        if !self.cfg.enabled {
+            return;
+        }
+        self.history.push_back((now, mid));
+        self.sum += mid;
+        while self
+            .history
+            .front()
+            .is_some_and(|&(t, _)| now - t > self.cfg.reference_window)
+        {
+            let (_, p) = self.history.pop_front().expect("front exists");
+            self.sum -= p;
+        }
+    }
+
+    /// Current (lower, upper) band, or `None` when disabled or before the first update.
+    pub fn limits(&self) -> Option<(f64, f64)> {
+        if !self.cfg.enabled || self.history.is_empty() {
+            return None;
+        }
+        let reference = self.sum / self.history.len() as f64;
+        Some((
+            reference * (1.0 - self.cfg.band_pct),
+            reference * (1.0 + self.cfg.band_pct),
+        ))
+    }
+}
+
+/// Reasons an order can be rejected instead of published.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    PriceBand,
+    ZeroSize,
+    PriceCollar,
+}
+
+/// Turns a share of generated limit orders into erroneous ones, for
+/// consumers' reject handling. They never reach the book.
+pub struct ErrorInjector {
+    cfg: RejectConfig,
+    tick_size: f64,
+}
+
+impl ErrorInjector {
+    pub fn new(cfg: &RejectConfig, tick_size: f64) -> Self {
+        Self {
+            cfg: cfg.clone(),
+            tick_size,
+        }
+    }
+
+    /// With probability `error_prob`, the reject for a damaged copy of a
+    /// resting `order`: zero size, or priced outside the collar around `mid`.
+    pub fn inject(&self, order: &Order, mid: f64, time: f64, rng: &mut StdRng) -> Option<Reject> {
+        if self.cfg.error_prob <= 0.0 || !order.is_resting() || !rng.gen_bool(self.cfg.error_prob) {
+            return None;
+        }
+        let mut order = order.clone();
+        let lower = mid * (1.0 - self.cfg.collar_pct);
+        let upper = mid * (1.0 + self.cfg.collar_pct);
+        let reason = if rng.gen_bool(self.cfg.zero_size_share) {
+            order.size = 0;
+            RejectReason::ZeroSize
+        } else {
+            let offset = 2.0 * self.cfg.collar_pct * mid;
+            let raw = if rng.gen_bool(0.5) {
+                mid + offset
+            } else {
+                mid - offset
+            };
+            order.price = ((raw / self.tick_size).round() * self.tick_size).max(self.tick_size);
+            RejectReason::PriceCollar
+        };
+        Some(Reject {
+            order,
+            reason,
+            lower,
+            upper,
+            time,
+        })
+    }
+}
+
+/// An order refused at entry; published in place of the ORDER.
+#[derive(Debug, Clone)]
+pub struct Reject {
+    pub order: Order,
+    pub reason: RejectReason,
+    pub lower: f64,
+    pub upper: f64,
+    pub time: f64,
+}
+
+impl WireMessage for Reject {
+    fn to_wire_text(&self) -> String {
+        let reason = match self.reason {
+            RejectReason::PriceBand => "PRICE_BAND",
+            RejectReason::ZeroSize => "ZERO_SIZE",
+            RejectReason::PriceCollar => "PRICE_COLLAR",
+        };
+        let mut msg = format!(
+            "REJECT|id={}|side={}|price={}|size={}|participant={}|reason={}|lower={}|upper={}|time={:.3}",
+            self.order.id,
+            self.order.side,
+            Price(self.order.price),
+            self.order.size,
+            self.order.participant_id,
+            reason,
+            Price(self.lower),
+            Price(self.upper),
+            self.time,
+        );
+        let _ = wire::write_instrument(&mut msg, self.order.instrument);
+        msg
+    }
+
+    /// Binary reject (v2), little-endian:
+    /// header msg_type=10, id:u64, side:u8 (1 buy, 2 sell), price:f64, size:u32,
+    /// participant_id:u32, reason:u8 (1 price band, 2 zero size, 3 price collar),
+    /// lower:f64, upper:f64, time:f64, instrument:u16 (the order's)
+    fn to_wire_binary(&self) -> Vec<u8> {
+        let mut out = wire::binary_frame(MSG_REJECT, 8 + 1 + 8 + 4 + 4 + 1 + 8 + 8 + 8 + 2);
+        out.extend_from_slice(&self.order.id.to_le_bytes());
+        out.push(match self.order.side {
+            Side::Buy => 1,
+            Side::Sell => 2,
+        });
+        out.extend_from_slice(&self.order.price.to_le_bytes());
+        out.extend_from_slice(&self.order.size.to_le_bytes());
+        out.extend_from_slice(&self.order.participant_id.to_le_bytes());
+        out.push(match self.reason {
+            RejectReason::PriceBand => 1,
+            RejectReason::ZeroSize => 2,
+            RejectReason::PriceCollar => 3,
+        });
+        out.extend_from_slice(&self.lower.to_le_bytes());
+        out.extend_from_slice(&self.upper.to_le_bytes());
+        out.extend_from_slice(&self.time.to_le_bytes());
+        out.extend_from_slice(&self.order.instrument.to_le_bytes());
+        out
+    }
+}
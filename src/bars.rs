@@ -0,0 +1,114 @@
+use std::fmt;
+
+use crate::wire::{self, FrameWriter, Price, WireMessage, MSG_BAR};
+
+/// OHLCV bar aggregated from simulated trades over `[start, end)` sim-seconds.
+#[derive(Debug, Clone)]
+pub struct Bar {
+    pub start: f64,
+    pub end: f64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: u64,
+    pub trades: u32,
+    pub instrument: u16,
+}
+
+impl WireMessage for Bar {
+    fn to_wire_text(&self) -> String {
+        wire::text_of(self)
+    }
+
+    fn to_wire_binary(&self) -> Vec<u8> {
+        wire::binary_of(self, 4 + 8 * 7 + 4 + 2)
+    }
+
+    fn write_text(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        write!(
+            w,
+            "BAR|start={:.3}|end={:.3}|open={}|high={}|low={}|close={}|volume={}|trades={}",
+            self.start,
+            self.end,
+            Price(self.open),
+            Price(self.high),
+            Price(self.low),
+            Price(self.close),
+            self.volume,
+            self.trades,
+        )?;
+        wire::write_instrument(w, self.instrument)
+    }
+
+    /// Binary bar (v2), little-endian:
+    /// header msg_type=4, start:f64, end:f64, open:f64, high:f64, low:f64,
+    /// close:f64, volume:u64, trades:u32, instrument:u16
+    fn encode_into(&self, out: &mut [u8]) -> usize {
+        let mut w = FrameWriter::new(out, MSG_BAR);
+        w.put(&self.start.to_le_bytes());
+        w.put(&self.end.to_le_bytes());
+        w.put(&self.open.to_le_bytes());
+        w.put(&self.high.to_le_bytes());
+        w.put(&self.low.to_le_bytes());
+        w.put(&self.close.to_le_bytes());
+        w.put(&self.volume.to_le_bytes());
+        w.put(&self.trades.to_le_bytes());
+        w.put(&self.instrument.to_le_bytes());
+        w.len()
+    }
+}
+
+/// Accumulates trades into fixed-length bars aligned to multiples of `interval`.
+/// Intervals without trades produce no bar.
+pub struct BarAggregator {
+    interval: f64,
+    instrument: u16,
+    bar_start: f64,
+    current: Option<Bar>,
+}
+
+impl BarAggregator {
+    pub fn new(interval: f64, instrument: u16) -> Self {
+        Self {
+            interval,
+            instrument,
+            bar_start: 0.0,
+            current: None,
+        }
+    }
+
+    /// Close the current bar if `now` has reached its end, returning it.
+    pub fn roll(&mut self, now: f64) -> Option<Bar> {
+        if now < self.bar_start + self.interval {
+            return None;
+        }
+        self.bar_start = (now / self.interval).floor() * self.interval;
+        self.current.take()
+    }
+
+    pub fn record(&mut self, price: f64, size: u32) {
+        match &mut self.current {
+            Some(bar) => {
+                bar.high = bar.high.max(price);
+                bar.low = bar.low.min(price);
+                bar.close = price;
+                bar.volume += size as u64;
+                bar.trades += 1;
+            }
+            None => {
+                self.current = Some(Bar {
+                    start: self.bar_start,
+                    end: self.bar_start + self.interval,
+                    open: price,
+                    high: price,
+                    low: price,
+                    close: price,
+                    volume: size as u64,
+                    trades: 1,
+                    instrument: self.instrument,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,858 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+
+use crate::order::{Order, OrderType, Side, Trade};
+use crate::wire::{
+    self, FrameWriter, Price, WireMessage, MSG_DEPTH, MSG_LEVEL, MSG_QUOTE, MSG_SELF_MATCH,
+};
+
+/// Aggregated resting quantity at one price.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Level {
+    pub size: u64,
+    pub count: u32,
+}
+
+/// One price level: displayed quantity and order IDs in time priority.
+#[derive(Default)]
+struct PriceLevel {
+    size: u64,
+    queue: VecDeque<u64>,
+}
+
+impl PriceLevel {
+    fn snapshot(&self) -> Level {
+        Level {
+            size: self.size,
+            count: self.queue.len() as u32,
+        }
+    }
+}
+
+/// What happens when a taker meets a resting order of its own participant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfMatchMode {
+    /// They trade like any other pair.
+    #[default]
+    Allow,
+    /// The resting order is cancelled and the taker carries on.
+    CancelOldest,
+    /// Both lose the quantity that would have traded.
+    Decrement,
+}
+
+impl fmt::Display for SelfMatchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelfMatchMode::Allow => write!(f, "allow"),
+            SelfMatchMode::CancelOldest => write!(f, "cancel_oldest"),
+            SelfMatchMode::Decrement => write!(f, "decrement"),
+        }
+    }
+}
+
+/// A taker meeting one resting order.
+pub enum Fill {
+    Trade(Trade),
+    /// Self-match prevention stopped the trade.
+    SelfMatch(SelfMatch),
+}
+
+/// Result of one fill against a resting order.
+pub struct Execution {
+    pub fill: Fill,
+    /// Set when an iceberg's display was consumed and refreshed from its reserve.
+    pub replenished: Option<Order>,
+}
+
+/// Resting limit orders keyed by id, plus per-side price levels keyed by
+/// integer tick so best prices and depth are cheap to read.
+pub struct Book {
+    tick_size: f64,
+    /// Symbol directory index stamped on the book's trades and quotes.
+    instrument: u16,
+    /// Ordered so that random picks from `orders`/`order_ids` are reproducible.
+    orders: BTreeMap<u64, Order>,
+    bids: BTreeMap<i64, PriceLevel>,
+    asks: BTreeMap<i64, PriceLevel>,
+    /// Display slice size of each resting iceberg.
+    iceberg_peaks: HashMap<u64, u32>,
+    /// With `track_levels`: every level (bids, asks) changed since the last
+    /// `take_level_changes`, as it was before the first change.
+    changed: Option<[BTreeMap<i64, Level>; 2]>,
+    /// Reused by `execute`, so matching doesn't allocate once it has grown.
+    fills: Vec<Execution>,
+    /// Queues of emptied levels, handed to new levels instead of allocating.
+    spare_queues: Vec<VecDeque<u64>>,
+}
+
+impl Book {
+    pub fn new(tick_size: f64, instrument: u16) -> Self {
+        Self {
+            tick_size,
+            instrument,
+            orders: BTreeMap::new(),
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            iceberg_peaks: HashMap::new(),
+            changed: None,
+            fills: Vec::new(),
+            spare_queues: Vec::new(),
+        }
+    }
+
+    /// Record level changes from now on, for `take_level_changes`.
+    pub fn track_levels(&mut self) {
+        self.changed = Some(Default::default());
+    }
+
+    /// Note the level at `t` on `side` as it is before a change.
+    fn touch(&mut self, side: Side, t: i64) {
+        let Some(changed) = self.changed.as_mut() else {
+            return;
+        };
+        let (changed, levels) = match side {
+            Side::Buy => (&mut changed[0], &self.bids),
+            Side::Sell => (&mut changed[1], &self.asks),
+        };
+        changed.entry(t).or_insert_with(|| {
+            levels
+                .get(&t)
+                .map_or_else(Level::default, PriceLevel::snapshot)
+        });
+    }
+
+    /// One LEVEL per level whose size or count differs from the last call
+    /// (or `track_levels`), bids then asks, best price first. An emptied
+    /// level has size and count 0.
+    pub fn take_level_changes(&mut self, time: f64) -> Vec<LevelUpdate> {
+        let Some(changed) = self.changed.as_mut() else {
+            return Vec::new();
+        };
+        let [bids, asks] = std::mem::take(changed);
+        let now = |levels: &BTreeMap<i64, PriceLevel>, t: &i64| {
+            levels
+                .get(t)
+                .map_or_else(Level::default, PriceLevel::snapshot)
+        };
+        let bids = bids
+            .into_iter()
+            .rev()
+            .map(|(t, before)| (Side::Buy, t, before, now(&self.bids, &t)));
+        let asks = asks
+            .into_iter()
+            .map(|(t, before)| (Side::Sell, t, before, now(&self.asks, &t)));
+        bids.chain(asks)
+            .filter(|(_, _, before, level)| before != level)
+            .map(|(side, t, _, level)| LevelUpdate {
+                side,
+                price: self.price(t),
+                size: level.size,
+                count: level.count,
+                instrument: self.instrument,
+                time,
+            })
+            .collect()
+    }
+
+    /// Every level as a LEVEL, bids then asks, best price first.
+    pub fn level_snapshot(&self, time: f64) -> Vec<LevelUpdate> {
+        [Side::Buy, Side::Sell]
+            .into_iter()
+            .flat_map(|side| {
+                self.top(side, usize::MAX)
+                    .into_iter()
+                    .map(move |(price, level)| LevelUpdate {
+                        side,
+                        price,
+                        size: level.size,
+                        count: level.count,
+                        instrument: self.instrument,
+                        time,
+                    })
+            })
+            .collect()
+    }
+
+    fn ticks(&self, price: f64) -> i64 {
+        (price / self.tick_size).round() as i64
+    }
+
+    fn price(&self, ticks: i64) -> f64 {
+        ticks as f64 * self.tick_size
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut BTreeMap<i64, PriceLevel> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    pub fn insert(&mut self, order: Order) {
+        if self.orders.contains_key(&order.id) {
+            self.remove(order.id);
+        }
+        let t = self.ticks(order.price);
+        self.touch(order.side, t);
+        let spare = &mut self.spare_queues;
+        let levels = match order.side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        };
+        let level = levels.entry(t).or_insert_with(|| PriceLevel {
+            size: 0,
+            queue: spare.pop().unwrap_or_default(),
+        });
+        level.size += order.size as u64;
+        level.queue.push_back(order.id);
+        if order.reserve > 0 {
+            self.iceberg_peaks.insert(order.id, order.size);
+        }
+        self.orders.insert(order.id, order);
+    }
+
+    pub fn remove(&mut self, id: u64) -> Option<Order> {
+        let order = self.orders.remove(&id)?;
+        self.iceberg_peaks.remove(&id);
+        let t = self.ticks(order.price);
+        self.touch(order.side, t);
+        let side = match order.side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        };
+        if let Some(level) = side.get_mut(&t) {
+            level.size = level.size.saturating_sub(order.size as u64);
+            level.queue.retain(|&q| q != id);
+            if level.queue.is_empty() {
+                let emptied = side.remove(&t).expect("level exists");
+                self.spare_queues.push(emptied.queue);
+            }
+        }
+        Some(order)
+    }
+
+    /// Take `qty` off a resting order's displayed size, removing it once
+    /// nothing is displayed. Returns false if `id` is not resting.
+    pub fn reduce(&mut self, id: u64, qty: u32) -> bool {
+        let Some(order) = self.orders.get_mut(&id) else {
+            return false;
+        };
+        if qty >= order.size {
+            self.remove(id);
+            return true;
+        }
+        order.size -= qty;
+        let (side, price) = (order.side, order.price);
+        let t = self.ticks(price);
+        self.touch(side, t);
+        if let Some(level) = self.side_mut(side).get_mut(&t) {
+            level.size = level.size.saturating_sub(qty as u64);
+        }
+        true
+    }
+
+    /// Whether a limit or iceberg order is priced at or through the
+    /// opposite touch, so inserting it would lock or cross the book.
+    pub fn marketable(&self, order: &Order) -> bool {
+        let t = self.ticks(order.price);
+        match order.side {
+            Side::Buy => self.asks.keys().next().is_some_and(|&a| a <= t),
+            Side::Sell => self.bids.keys().next_back().is_some_and(|&b| b >= t),
+        }
+    }
+
+    /// Displayed quantity on the opposite side that `taker` could trade
+    /// with: all of it for a market order, otherwise the levels at or
+    /// through its price.
+    pub fn reachable(&self, taker: &Order) -> u64 {
+        let market = taker.order_type == OrderType::Market;
+        let t = self.ticks(taker.price);
+        match taker.side {
+            Side::Buy => self
+                .asks
+                .range(..=if market { i64::MAX } else { t })
+                .map(|(_, l)| l.size)
+                .sum(),
+            Side::Sell => self
+                .bids
+                .range(if market { i64::MIN } else { t }..)
+                .map(|(_, l)| l.size)
+                .sum(),
+        }
+    }
+
+    /// The price a limit or iceberg order rests at without matching: its
+    /// own price, or one tick behind the opposite touch if that would lock
+    /// or cross the book.
+    pub fn passive_price(&self, order: &Order) -> f64 {
+        if !self.marketable(order) {
+            return order.price;
+        }
+        let t = match order.side {
+            Side::Buy => self.asks.keys().next().map_or(0, |&a| a - 1).max(1),
+            Side::Sell => self.bids.keys().next_back().map_or(0, |&b| b + 1),
+        };
+        self.price(t)
+    }
+
+    /// Match a marketable order against the opposite side in price-time
+    /// priority. A non-market taker only trades at or through its price.
+    /// Any quantity left when the side is exhausted is dropped. Resting
+    /// orders of the taker's own participant are handled per `self_match`.
+    /// The fills are drained from a buffer the book keeps between calls.
+    pub fn execute(
+        &mut self,
+        taker: &Order,
+        time: f64,
+        self_match: SelfMatchMode,
+    ) -> std::vec::Drain<'_, Execution> {
+        let mut remaining = taker.size;
+        let mut fills = std::mem::take(&mut self.fills);
+        fills.clear();
+        let limit = (taker.order_type != OrderType::Market).then(|| self.ticks(taker.price));
+
+        while remaining > 0 {
+            let (maker_side, best) = match taker.side {
+                Side::Buy => (Side::Sell, self.asks.keys().next().copied()),
+                Side::Sell => (Side::Buy, self.bids.keys().next_back().copied()),
+            };
+            let Some(t) = best else { break };
+            let through_limit = match (taker.side, limit) {
+                (Side::Buy, Some(l)) => t > l,
+                (Side::Sell, Some(l)) => t < l,
+                (_, None) => false,
+            };
+            if through_limit {
+                break;
+            }
+            self.touch(maker_side, t);
+            let levels = match maker_side {
+                Side::Buy => &mut self.bids,
+                Side::Sell => &mut self.asks,
+            };
+            let level = levels.get_mut(&t).expect("best level exists");
+            let maker_id = *level.queue.front().expect("levels are never empty");
+            let maker = self
+                .orders
+                .get_mut(&maker_id)
+                .expect("queued order is resting");
+            let prevent =
+                self_match != SelfMatchMode::Allow && maker.participant_id == taker.participant_id;
+
+            if prevent && self_match == SelfMatchMode::CancelOldest {
+                let size = maker.total_size();
+                level.queue.pop_front();
+                level.size -= maker.size as u64;
+                self.orders.remove(&maker_id);
+                self.iceberg_peaks.remove(&maker_id);
+                if level.queue.is_empty() {
+                    let emptied = levels.remove(&t).expect("best level exists");
+                    self.spare_queues.push(emptied.queue);
+                }
+                fills.push(Execution {
+                    fill: Fill::SelfMatch(SelfMatch {
+                        maker_id,
+                        taker_id: taker.id,
+                        participant_id: taker.participant_id,
+                        mode: self_match,
+                        size,
+                        instrument: self.instrument,
+                        time,
+                    }),
+                    replenished: None,
+                });
+                continue;
+            }
+
+            let qty = remaining.min(maker.size);
+            maker.size -= qty;
+            level.size -= qty as u64;
+            remaining -= qty;
+
+            let fill = if prevent {
+                Fill::SelfMatch(SelfMatch {
+                    maker_id,
+                    taker_id: taker.id,
+                    participant_id: taker.participant_id,
+                    mode: self_match,
+                    size: qty,
+                    instrument: self.instrument,
+                    time,
+                })
+            } else {
+                Fill::Trade(Trade {
+                    maker_id,
+                    taker_id: taker.id,
+                    aggressor: taker.side,
+                    price: t as f64 * self.tick_size,
+                    size: qty,
+                    instrument: self.instrument,
+                    time,
+                })
+            };
+
+            let mut replenished = None;
+            if maker.size == 0 {
+                level.queue.pop_front();
+                if maker.reserve > 0 {
+                    // Refresh the display slice; it rejoins at the back of the queue.
+                    let peak = self.iceberg_peaks.get(&maker_id).copied().unwrap_or(1);
+                    let slice = peak.min(maker.reserve);
+                    maker.size = slice;
+                    maker.reserve -= slice;
+                    maker.created_at = time;
+                    level.size += slice as u64;
+                    level.queue.push_back(maker_id);
+                    replenished = Some(maker.clone());
+                } else {
+                    self.orders.remove(&maker_id);
+                    self.iceberg_peaks.remove(&maker_id);
+                }
+                if level.queue.is_empty() {
+                    let emptied = levels.remove(&t).expect("best level exists");
+                    self.spare_queues.push(emptied.queue);
+                }
+            }
+            fills.push(Execution { fill, replenished });
+        }
+        self.fills = fills;
+        self.fills.drain(..)
+    }
+
+    pub fn tick_size(&self) -> f64 {
+        self.tick_size
+    }
+
+    pub fn instrument(&self) -> u16 {
+        self.instrument
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.orders.contains_key(&id)
+    }
+
+    pub fn orders(&self) -> impl Iterator<Item = &Order> {
+        self.orders.values()
+    }
+
+    /// Resting orders level by level, bids then asks, best price first and
+    /// each level in time priority: inserting them in this order rebuilds
+    /// the same queues.
+    pub fn snapshot(&self) -> Vec<&Order> {
+        self.bids
+            .values()
+            .rev()
+            .chain(self.asks.values())
+            .flat_map(|level| &level.queue)
+            .filter_map(|id| self.orders.get(id))
+            .collect()
+    }
+
+    pub fn order_ids(&self) -> Vec<u64> {
+        self.orders.keys().copied().collect()
+    }
+
+    pub fn best_bid(&self) -> Option<(f64, Level)> {
+        self.bids
+            .iter()
+            .next_back()
+            .map(|(&t, l)| (self.price(t), l.snapshot()))
+    }
+
+    pub fn best_ask(&self) -> Option<(f64, Level)> {
+        self.asks
+            .iter()
+            .next()
+            .map(|(&t, l)| (self.price(t), l.snapshot()))
+    }
+
+    /// Top-of-book size imbalance `(bid - ask) / (bid + ask)` in `-1..=1`;
+    /// positive when bid depth dominates, 0 for an empty book.
+    pub fn imbalance(&self) -> f64 {
+        let bid = self.best_bid().map_or(0, |(_, l)| l.size) as f64;
+        let ask = self.best_ask().map_or(0, |(_, l)| l.size) as f64;
+        if bid + ask > 0.0 {
+            (bid - ask) / (bid + ask)
+        } else {
+            0.0
+        }
+    }
+
+    /// Best `n` levels on `side`, best price first.
+    pub fn top(&self, side: Side, n: usize) -> Vec<(f64, Level)> {
+        match side {
+            Side::Buy => self
+                .bids
+                .iter()
+                .rev()
+                .take(n)
+                .map(|(&t, l)| (self.price(t), l.snapshot()))
+                .collect(),
+            Side::Sell => self
+                .asks
+                .iter()
+                .take(n)
+                .map(|(&t, l)| (self.price(t), l.snapshot()))
+                .collect(),
+        }
+    }
+}
+
+/// Top-of-book update. An empty side is published as price 0 / size 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub bid_price: f64,
+    pub bid_size: u64,
+    pub ask_price: f64,
+    pub ask_size: u64,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl Quote {
+    pub fn from_book(book: &Book, time: f64) -> Self {
+        let (bid_price, bid) = book.best_bid().unwrap_or_default();
+        let (ask_price, ask) = book.best_ask().unwrap_or_default();
+        Self {
+            bid_price,
+            bid_size: bid.size,
+            ask_price,
+            ask_size: ask.size,
+            instrument: book.instrument,
+            time,
+        }
+    }
+
+    /// Same top of book, ignoring the timestamp.
+    pub fn same_bbo(&self, other: &Quote) -> bool {
+        self.bid_price == other.bid_price
+            && self.bid_size == other.bid_size
+            && self.ask_price == other.ask_price
+            && self.ask_size == other.ask_size
+    }
+}
+
+impl WireMessage for Quote {
+    fn to_wire_text(&self) -> String {
+        wire::text_of(self)
+    }
+
+    fn to_wire_binary(&self) -> Vec<u8> {
+        wire::binary_of(self, 4 + 8 * 5 + 2)
+    }
+
+    fn write_text(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        write!(
+            w,
+            "QUOTE|bid={}|bid_size={}|ask={}|ask_size={}|time={:.3}",
+            Price(self.bid_price),
+            self.bid_size,
+            Price(self.ask_price),
+            self.ask_size,
+            self.time,
+        )?;
+        wire::write_instrument(w, self.instrument)
+    }
+
+    /// Binary quote (v2), little-endian:
+    /// header msg_type=5, bid:f64, bid_size:u64, ask:f64, ask_size:u64, time:f64,
+    /// instrument:u16
+    fn encode_into(&self, out: &mut [u8]) -> usize {
+        let mut w = FrameWriter::new(out, MSG_QUOTE);
+        w.put(&self.bid_price.to_le_bytes());
+        w.put(&self.bid_size.to_le_bytes());
+        w.put(&self.ask_price.to_le_bytes());
+        w.put(&self.ask_size.to_le_bytes());
+        w.put(&self.time.to_le_bytes());
+        w.put(&self.instrument.to_le_bytes());
+        w.len()
+    }
+}
+
+/// Market-by-price snapshot of the top levels on each side.
+#[derive(Debug, Clone)]
+pub struct Depth {
+    pub bids: Vec<(f64, Level)>,
+    pub asks: Vec<(f64, Level)>,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl Depth {
+    pub fn from_book(book: &Book, levels: usize, time: f64) -> Self {
+        Self {
+            bids: book.top(Side::Buy, levels),
+            asks: book.top(Side::Sell, levels),
+            instrument: book.instrument,
+            time,
+        }
+    }
+}
+
+fn write_levels(w: &mut impl fmt::Write, levels: &[(f64, Level)]) -> fmt::Result {
+    for (i, (p, l)) in levels.iter().enumerate() {
+        if i > 0 {
+            w.write_char(';')?;
+        }
+        write!(w, "{}:{}:{}", Price(*p), l.size, l.count)?;
+    }
+    Ok(())
+}
+
+impl WireMessage for Depth {
+    fn to_wire_text(&self) -> String {
+        wire::text_of(self)
+    }
+
+    fn to_wire_binary(&self) -> Vec<u8> {
+        wire::binary_of(
+            self,
+            4 + 8 + 2 + 2 + (self.bids.len() + self.asks.len()) * 20,
+        )
+    }
+
+    fn write_text(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        write!(w, "DEPTH|time={:.3}|bids=", self.time)?;
+        write_levels(w, &self.bids)?;
+        w.write_str("|asks=")?;
+        write_levels(w, &self.asks)?;
+        wire::write_instrument(w, self.instrument)
+    }
+
+    /// Binary depth snapshot (v2), little-endian:
+    /// header msg_type=6, time:f64, instrument:u16, bid_levels:u8,
+    /// ask_levels:u8, then bids followed by asks, each level price:f64,
+    /// size:u64, count:u32
+    fn encode_into(&self, out: &mut [u8]) -> usize {
+        let mut w = FrameWriter::new(out, MSG_DEPTH);
+        w.put(&self.time.to_le_bytes());
+        w.put(&self.instrument.to_le_bytes());
+        w.put_u8(self.bids.len() as u8);
+        w.put_u8(self.asks.len() as u8);
+        for (price, level) in self.bids.iter().chain(&self.asks) {
+            w.put(&price.to_le_bytes());
+            w.put(&level.size.to_le_bytes());
+            w.put(&level.count.to_le_bytes());
+        }
+        w.len()
+    }
+}
+
+/// Market-by-price update of one level (`book_view = "mbp"`): its new
+/// displayed size and order count, both 0 once the level is empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelUpdate {
+    pub side: Side,
+    pub price: f64,
+    pub size: u64,
+    pub count: u32,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl WireMessage for LevelUpdate {
+    fn to_wire_text(&self) -> String {
+        wire::text_of(self)
+    }
+
+    fn to_wire_binary(&self) -> Vec<u8> {
+        wire::binary_of(self, 4 + 1 + 8 + 8 + 4 + 8 + 2)
+    }
+
+    fn write_text(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        write!(
+            w,
+            "LEVEL|side={}|price={}|size={}|count={}|time={:.3}",
+            self.side,
+            Price(self.price),
+            self.size,
+            self.count,
+            self.time,
+        )?;
+        wire::write_instrument(w, self.instrument)
+    }
+
+    /// Binary level update (v2), little-endian:
+    /// header msg_type=19, side:u8 (1=BUY, 2=SELL), price:f64, size:u64,
+    /// count:u32, time:f64, instrument:u16
+    fn encode_into(&self, out: &mut [u8]) -> usize {
+        let mut w = FrameWriter::new(out, MSG_LEVEL);
+        w.put_u8(match self.side {
+            Side::Buy => 1,
+            Side::Sell => 2,
+        });
+        w.put(&self.price.to_le_bytes());
+        w.put(&self.size.to_le_bytes());
+        w.put(&self.count.to_le_bytes());
+        w.put(&self.time.to_le_bytes());
+        w.put(&self.instrument.to_le_bytes());
+        w.len()
+    }
+}
+
+/// Self-match prevention in place of a trade (`[book] self_match`). `size`
+/// is what the resting order lost: all that was left of it, reserve
+/// included, for `cancel_oldest`; for `decrement`, the quantity that would
+/// have traded, which the taker loses too.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfMatch {
+    pub maker_id: u64,
+    pub taker_id: u64,
+    pub participant_id: u32,
+    pub mode: SelfMatchMode,
+    pub size: u32,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl WireMessage for SelfMatch {
+    fn to_wire_text(&self) -> String {
+        wire::text_of(self)
+    }
+
+    fn to_wire_binary(&self) -> Vec<u8> {
+        wire::binary_of(self, 4 + 8 + 8 + 4 + 1 + 4 + 8 + 2)
+    }
+
+    fn write_text(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        let action = match self.mode {
+            SelfMatchMode::Allow => "ALLOW",
+            SelfMatchMode::CancelOldest => "CANCEL_OLDEST",
+            SelfMatchMode::Decrement => "DECREMENT",
+        };
+        write!(
+            w,
+            "SELF_MATCH|maker={}|taker={}|participant={}|action={}|size={}|time={:.3}",
+            self.maker_id, self.taker_id, self.participant_id, action, self.size, self.time,
+        )?;
+        wire::write_instrument(w, self.instrument)
+    }
+
+    /// Binary self-match (v2), little-endian:
+    /// header msg_type=21, maker_id:u64, taker_id:u64, participant_id:u32,
+    /// action:u8 (1=CANCEL_OLDEST, 2=DECREMENT), size:u32, time:f64, instrument:u16
+    fn encode_into(&self, out: &mut [u8]) -> usize {
+        let mut w = FrameWriter::new(out, MSG_SELF_MATCH);
+        w.put(&self.maker_id.to_le_bytes());
+        w.put(&self.taker_id.to_le_bytes());
+        w.put(&self.participant_id.to_le_bytes());
+        w.put_u8(match self.mode {
+            SelfMatchMode::Allow => 0,
+            SelfMatchMode::CancelOldest => 1,
+            SelfMatchMode::Decrement => 2,
+        });
+        w.put(&self.size.to_le_bytes());
+        w.put(&self.time.to_le_bytes());
+        w.put(&self.instrument.to_le_bytes());
+        w.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn limit(id: u64, side: Side, price: f64, size: u32) -> Order {
+        Order {
+            id,
+            side,
+            order_type: OrderType::Limit,
+            price,
+            size,
+            reserve: 0,
+            participant_id: 0,
+            cl_ord_id: String::new(),
+            instrument: 0,
+            created_at: 0.0,
+            ttl: 0.0,
+        }
+    }
+
+    #[test]
+    fn marketable_limits_trade_before_resting_so_the_book_never_crosses() {
+        let mut book = Book::new(0.01, 0);
+        let mut rng = StdRng::seed_from_u64(3);
+        for id in 0..5_000 {
+            let side = if rng.gen::<bool>() {
+                Side::Buy
+            } else {
+                Side::Sell
+            };
+            let price = (rng.gen_range(9_900..=10_100) as f64) * 0.01;
+            let mut order = limit(id, side, price, rng.gen_range(1..=500));
+            if book.marketable(&order) {
+                let filled: u32 = book
+                    .execute(&order, 0.0, SelfMatchMode::Allow)
+                    .map(|e| match e.fill {
+                        Fill::Trade(t) => t.size,
+                        Fill::SelfMatch(_) => 0,
+                    })
+                    .sum();
+                order.size -= filled;
+                assert!(!book.marketable(&order) || order.size == 0);
+            }
+            if order.size > 0 {
+                book.insert(order);
+            }
+            if let (Some((bid, _)), Some((ask, _))) = (book.best_bid(), book.best_ask()) {
+                assert!(
+                    bid < ask,
+                    "crossed after order {}: bid={} ask={}",
+                    id,
+                    bid,
+                    ask
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn marketable_is_at_or_through_the_opposite_touch() {
+        let mut book = Book::new(0.01, 0);
+        book.insert(limit(1, Side::Sell, 100.05, 10));
+        book.insert(limit(2, Side::Buy, 99.95, 10));
+        assert!(book.marketable(&limit(3, Side::Buy, 100.05, 1)));
+        assert!(book.marketable(&limit(3, Side::Sell, 99.90, 1)));
+        assert!(!book.marketable(&limit(3, Side::Buy, 100.04, 1)));
+        assert!(!book.marketable(&limit(3, Side::Sell, 99.96, 1)));
+    }
+
+    #[test]
+    fn passive_price_rests_one_tick_behind_the_opposite_touch() {
+        let mut book = Book::new(0.01, 0);
+        book.insert(limit(1, Side::Sell, 100.05, 10));
+        book.insert(limit(2, Side::Buy, 99.95, 10));
+        let price = |side, p| book.passive_price(&limit(3, side, p, 1));
+        assert!((price(Side::Buy, 100.20) - 100.04).abs() < 1e-9);
+        assert!((price(Side::Sell, 99.80) - 99.96).abs() < 1e-9);
+        assert_eq!(price(Side::Buy, 100.00), 100.00);
+    }
+
+    #[test]
+    fn reachable_counts_opposite_levels_up_to_the_limit() {
+        let mut book = Book::new(0.01, 0);
+        book.insert(limit(1, Side::Sell, 100.05, 10));
+        book.insert(limit(2, Side::Sell, 100.10, 20));
+        book.insert(limit(3, Side::Sell, 100.50, 40));
+        book.insert(limit(4, Side::Buy, 99.95, 5));
+        assert_eq!(book.reachable(&limit(5, Side::Buy, 100.10, 1)), 30);
+        assert_eq!(book.reachable(&limit(5, Side::Buy, 100.04, 1)), 0);
+        assert_eq!(book.reachable(&limit(5, Side::Sell, 99.00, 1)), 5);
+        let mut market = limit(5, Side::Buy, 0.0, 1);
+        market.order_type = OrderType::Market;
+        assert_eq!(book.reachable(&market), 70);
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Where a run stood after `ticks` ticks (`--checkpoint` / `--resume`).
+///
+/// The generator is deterministic for a given seed and config, so a resumed
+/// run rebuilds the whole engine state (RNG streams, book, price model,
+/// agents, scenario timers, ...) by replaying the first `ticks` ticks with
+/// nothing published and the logged control commands applied again at the
+/// ticks they arrived, each `reload` with the config file it read then. Sim
+/// time, mid, regime, next order ID, resting orders
+/// and message count are saved to check that the replay landed on the same
+/// state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Decimal string, since TOML integers stop at `i64::MAX`.
+    pub seed: String,
+    pub ticks: u64,
+    pub sim_time: f64,
+    pub mid: f64,
+    pub regime: String,
+    pub next_id: u64,
+    pub active_orders: usize,
+    pub messages: u64,
+    #[serde(default)]
+    pub commands: Vec<LoggedCommand>,
+    /// What each `reload` read, in the order they ran.
+    #[serde(default)]
+    pub reloads: Vec<LoggedReload>,
+}
+
+/// A control command that changed the simulation, and the tick it was applied at.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggedCommand {
+    pub tick: u64,
+    pub command: String,
+}
+
+/// The config file a `reload` read, so a replay applies the same settings
+/// whatever the file holds by then. No contents: the read failed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggedReload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contents: Option<String>,
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read checkpoint {}: {}", path.display(), e))?;
+        let checkpoint: Checkpoint = toml::from_str(&contents)
+            .map_err(|e| format!("failed to parse checkpoint {}: {}", path.display(), e))?;
+        checkpoint.seed()?;
+        Ok(checkpoint)
+    }
+
+    /// Write to a temporary file first, so a crash mid-write keeps the previous checkpoint.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let contents = toml::to_string(self)?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, contents)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn seed(&self) -> Result<u64, Box<dyn std::error::Error>> {
+        self.seed
+            .parse()
+            .map_err(|_| format!("invalid checkpoint seed '{}'", self.seed).into())
+    }
+}
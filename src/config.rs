@@ -1,45 +1,132 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use serde::Deserialize;
 use std::fmt;
 use std::net::Ipv4Addr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use crate::scenario::Scenario;
+use crate::agents::AgentConfig;
+use crate::auction::{Schedule, SessionConfig};
+use crate::bands::{PriceBandConfig, RejectConfig};
+use crate::book::SelfMatchMode;
+use crate::checkpoint::Checkpoint;
+use crate::events::EventsConfig;
+use crate::ids::IdScheme;
+use crate::logfile::Rotation;
+use crate::multicast::{self, MulticastOptions};
+use crate::options::OptionsConfig;
+use crate::price::{
+    BasisConfig, FundamentalConfig, GarchConfig, JumpConfig, OuConfig, PriceModelKind,
+};
+use crate::refdata::{self, Instrument, UniverseRow};
+use crate::regime::{self, Regime, RegimeFeedbackConfig, RegimeValues, SpreadConfig};
+use crate::scenario::{
+    CustomScenarioConfig, DroughtConfig, NewsConfig, Scenario, ScenarioStep, SqueezeConfig,
+};
+use crate::seasonality::SeasonalityConfig;
+use crate::sizes::SizeDistributionKind;
+use crate::stops::StopConfig;
+use crate::venue::CircuitBreakerConfig;
+use crate::wire;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+/// Largest iceberg total as a multiple of its display size.
+const MAX_ICEBERG_MULT: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum OutputMode {
+    #[default]
     Console,
     File,
     Both,
     Quiet,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+/// How log events are rendered on the console and in the log file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
 #[serde(rename_all = "lowercase")]
-pub enum WireFormat {
+pub enum LogFormat {
+    #[default]
     Text,
-    Binary,
+    /// One JSON record per line.
+    Json,
 }
 
-impl Default for WireFormat {
-    fn default() -> Self {
-        Self::Text
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogFormat::Text => write!(f, "text"),
+            LogFormat::Json => write!(f, "json"),
+        }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WireFormat {
+    #[default]
+    Text,
+    Binary,
+    /// Binary messages in the SBE layout of `schema/orderflow.xml`.
+    Sbe,
+    /// `Datagram`s of `proto/orderflow.proto`.
+    Protobuf,
+    /// Size-prefixed `Message`s of `schema/orderflow.fbs` (`flatbuffers` feature).
+    FlatBuffers,
+}
+
 impl fmt::Display for WireFormat {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             WireFormat::Text => write!(f, "text"),
             WireFormat::Binary => write!(f, "binary"),
+            WireFormat::Sbe => write!(f, "sbe"),
+            WireFormat::Protobuf => write!(f, "protobuf"),
+            WireFormat::FlatBuffers => write!(f, "flatbuffers"),
         }
     }
 }
 
-impl Default for OutputMode {
-    fn default() -> Self {
-        Self::Console
+/// How datagrams reach the socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SenderBackend {
+    /// `sendmmsg` on Linux, `send_to` elsewhere.
+    #[default]
+    Socket,
+    /// SENDMSG submissions to an io_uring (Linux, `io-uring` feature).
+    IoUring,
+}
+
+impl fmt::Display for SenderBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SenderBackend::Socket => write!(f, "socket"),
+            SenderBackend::IoUring => write!(f, "io_uring"),
+        }
+    }
+}
+
+/// What the feed publishes about the book: individual orders (market by
+/// order), aggregated price levels (market by price), or both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookView {
+    /// ORDER, CANCEL and MASS_CANCEL messages.
+    #[default]
+    Mbo,
+    /// LEVEL messages in place of ORDER, CANCEL and MASS_CANCEL.
+    Mbp,
+    Both,
+}
+
+impl fmt::Display for BookView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookView::Mbo => write!(f, "mbo"),
+            BookView::Mbp => write!(f, "mbp"),
+            BookView::Both => write!(f, "both"),
+        }
     }
 }
 
@@ -54,6 +141,25 @@ impl fmt::Display for OutputMode {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pacing {
+    #[default]
+    Sleep,
+    Spin,
+    Hybrid,
+}
+
+impl fmt::Display for Pacing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pacing::Sleep => write!(f, "sleep"),
+            Pacing::Spin => write!(f, "spin"),
+            Pacing::Hybrid => write!(f, "hybrid"),
+        }
+    }
+}
+
 fn parse_output_mode(s: &str) -> Result<OutputMode, Box<dyn std::error::Error>> {
     match s {
         "console" => Ok(OutputMode::Console),
@@ -68,11 +174,65 @@ fn parse_output_mode(s: &str) -> Result<OutputMode, Box<dyn std::error::Error>>
     }
 }
 
-fn parse_wire_format(s: &str) -> Result<WireFormat, Box<dyn std::error::Error>> {
+fn parse_log_format(s: &str) -> Result<LogFormat, Box<dyn std::error::Error>> {
+    match s {
+        "text" => Ok(LogFormat::Text),
+        "json" => Ok(LogFormat::Json),
+        _ => Err(format!("unknown log format '{}'. available: text, json", s).into()),
+    }
+}
+
+pub fn parse_wire_format(s: &str) -> Result<WireFormat, Box<dyn std::error::Error>> {
     match s {
         "text" => Ok(WireFormat::Text),
         "binary" => Ok(WireFormat::Binary),
-        _ => Err(format!("unknown wire format '{}'. available: text, binary", s).into()),
+        "sbe" => Ok(WireFormat::Sbe),
+        "protobuf" => Ok(WireFormat::Protobuf),
+        "flatbuffers" => Ok(WireFormat::FlatBuffers),
+        _ => Err(format!(
+            "unknown wire format '{}'. available: text, binary, sbe, protobuf, flatbuffers",
+            s
+        )
+        .into()),
+    }
+}
+
+fn parse_sender(s: &str) -> Result<SenderBackend, Box<dyn std::error::Error>> {
+    match s {
+        "socket" => Ok(SenderBackend::Socket),
+        "io_uring" | "io-uring" => Ok(SenderBackend::IoUring),
+        _ => Err(format!("unknown sender '{}'. available: socket, io_uring", s).into()),
+    }
+}
+
+fn parse_book_view(s: &str) -> Result<BookView, Box<dyn std::error::Error>> {
+    match s {
+        "mbo" => Ok(BookView::Mbo),
+        "mbp" => Ok(BookView::Mbp),
+        "both" => Ok(BookView::Both),
+        _ => Err(format!("unknown book view '{}'. available: mbo, mbp, both", s).into()),
+    }
+}
+
+fn parse_price_model(s: &str) -> Result<PriceModelKind, Box<dyn std::error::Error>> {
+    match s {
+        "gbm" => Ok(PriceModelKind::Gbm),
+        "jump-diffusion" => Ok(PriceModelKind::JumpDiffusion),
+        "ou" => Ok(PriceModelKind::Ou),
+        _ => Err(format!(
+            "unknown price model '{}'. available: gbm, jump-diffusion, ou",
+            s
+        )
+        .into()),
+    }
+}
+
+fn parse_pacing(s: &str) -> Result<Pacing, Box<dyn std::error::Error>> {
+    match s {
+        "sleep" => Ok(Pacing::Sleep),
+        "spin" => Ok(Pacing::Spin),
+        "hybrid" => Ok(Pacing::Hybrid),
+        _ => Err(format!("unknown pacing '{}'. available: sleep, spin, hybrid", s).into()),
     }
 }
 
@@ -81,7 +241,8 @@ fn parse_wire_format(s: &str) -> Result<WireFormat, Box<dyn std::error::Error>>
 #[command(name = "orderflow-rs")]
 #[command(about = "Realistic order generation engine with regime-based market dynamics")]
 pub struct Cli {
-    /// Market scenario to simulate
+    /// Market scenario to simulate, or a chain of scenarios with durations in
+    /// seconds (e.g. normal:60,flash-crash:30,volatile)
     #[arg(long, value_name = "SCENARIO")]
     pub scenario: Option<String>,
 
@@ -97,6 +258,18 @@ pub struct Cli {
     #[arg(long, value_name = "PORT")]
     pub multicast_port: Option<u16>,
 
+    /// Multicast TTL: router hops the feed may cross (default: 1, local subnet)
+    #[arg(long, value_name = "HOPS")]
+    pub multicast_ttl: Option<u32>,
+
+    /// Interface to send multicast from, by name or IPv4 address
+    #[arg(long, value_name = "IFACE")]
+    pub interface: Option<String>,
+
+    /// Deliver the feed to receivers on this host too
+    #[arg(long, value_name = "BOOL")]
+    pub multicast_loop: Option<bool>,
+
     /// Initial mid-price
     #[arg(long, value_name = "PRICE")]
     pub initial_price: Option<f64>,
@@ -109,6 +282,38 @@ pub struct Cli {
     #[arg(long, value_name = "SIZE")]
     pub tick_size: Option<f64>,
 
+    /// Publish every symbol listed in this CSV on one feed (symbol,initial_price,tick_size,adv_weight[,max_rate])
+    #[arg(long, value_name = "PATH")]
+    pub symbols_file: Option<PathBuf>,
+
+    /// Generator threads to shard the --symbols-file symbols across
+    #[arg(long, value_name = "N")]
+    pub threads: Option<usize>,
+
+    /// Tick pacing: sleep, spin, hybrid
+    #[arg(long, value_name = "MODE")]
+    pub pacing: Option<String>,
+
+    /// Sim seconds per wall-clock second (default: 1.0)
+    #[arg(long, value_name = "X")]
+    pub time_scale: Option<f64>,
+
+    /// Run ticks back to back, as fast as they can be generated
+    #[arg(long)]
+    pub no_realtime: bool,
+
+    /// Pin the generator (and the sender thread) to these CPUs, e.g. 2,3
+    #[arg(long, value_name = "CPUS")]
+    pub pin_cores: Option<String>,
+
+    /// Run the generator and sender threads under SCHED_FIFO at this priority (1-99)
+    #[arg(long, value_name = "N")]
+    pub rt_priority: Option<i32>,
+
+    /// Mid-price model: gbm, jump-diffusion, ou
+    #[arg(long, value_name = "MODEL")]
+    pub price_model: Option<String>,
+
     /// Shock probability per tick
     #[arg(long, value_name = "PROB")]
     pub shock_prob: Option<f64>,
@@ -121,22 +326,98 @@ pub struct Cli {
     #[arg(long, value_name = "PATH")]
     pub log_file: Option<String>,
 
+    /// Log format: text, json (one JSON record per event)
+    #[arg(long, value_name = "FORMAT")]
+    pub log_format: Option<String>,
+
     /// Throughput multiplier applied to order generation rates (default: 1.0)
     #[arg(long, value_name = "SCALE")]
     pub throughput_scale: Option<f64>,
 
+    /// Target message rate in msgs/sec; continuously adjusts the throughput scale
+    #[arg(long, value_name = "MSGS")]
+    pub target_rate: Option<f64>,
+
     /// Console display interval in seconds (how often stats are printed)
     #[arg(long, value_name = "SECONDS")]
     pub display_interval: Option<f64>,
 
+    /// Stop after this many seconds of sim time
+    #[arg(long, value_name = "SECONDS")]
+    pub duration: Option<f64>,
+
+    /// Stop once this many messages have been sent
+    #[arg(long, value_name = "N")]
+    pub max_messages: Option<u64>,
+
+    /// Stop when mid reaches this price (from either side)
+    #[arg(long, value_name = "PRICE")]
+    pub stop_at_price: Option<f64>,
+
+    /// Sim seconds to build the book silently before publishing starts at t=0
+    #[arg(long, value_name = "SECONDS")]
+    pub warmup_seconds: Option<f64>,
+
+    /// End the warmup early once this many orders are resting
+    #[arg(long, value_name = "N")]
+    pub warmup_orders: Option<usize>,
+
     /// RNG seed for reproducible runs (random if omitted)
     #[arg(long, value_name = "SEED")]
     pub seed: Option<u64>,
 
-    /// Wire format used on multicast: text, binary
+    /// Coalesce each tick's messages into as few datagrams as possible
+    #[arg(long, value_name = "BOOL")]
+    pub batch: Option<bool>,
+
+    /// Maximum datagram size in bytes when batching
+    #[arg(long, value_name = "BYTES")]
+    pub max_packet_bytes: Option<usize>,
+
+    /// Fraction of datagrams to truncate, bit-flip or garble before sending
+    #[arg(long, value_name = "PROB")]
+    pub corrupt_prob: Option<f64>,
+
+    /// Fraction of limit orders made erroneous and published as REJECT
+    #[arg(long, value_name = "PROB")]
+    pub error_prob: Option<f64>,
+
+    /// Fraction of market orders executed off-book and published as DARK_TRADE
+    #[arg(long, value_name = "PROB")]
+    pub dark_fraction: Option<f64>,
+
+    /// Append the wall-clock send time in nanoseconds since the UNIX epoch to every message
+    #[arg(long, value_name = "BOOL")]
+    pub epoch_timestamps: Option<bool>,
+
+    /// End every binary message with a CRC32C of its bytes
+    #[arg(long, value_name = "BOOL")]
+    pub crc: Option<bool>,
+
+    /// Send from a dedicated thread through a queue of this many datagrams (0 = send inline)
+    #[arg(long, value_name = "DATAGRAMS")]
+    pub sender_queue: Option<usize>,
+
+    /// Socket send path: socket (default), io_uring (Linux, experimental)
+    #[arg(long, value_name = "BACKEND")]
+    pub sender: Option<String>,
+
+    /// Bench mode: no sleeping, run as fast as possible and report throughput
+    #[arg(long)]
+    pub bench: bool,
+
+    /// Number of ticks to run in bench mode
+    #[arg(long, value_name = "N", default_value_t = 100_000)]
+    pub bench_ticks: u64,
+
+    /// Wire format used on multicast: text, binary, sbe, protobuf, flatbuffers
     #[arg(long, value_name = "FORMAT")]
     pub wire_format: Option<String>,
 
+    /// Book messages to publish: mbo (orders), mbp (price levels), both
+    #[arg(long, value_name = "VIEW")]
+    pub book_view: Option<String>,
+
     /// Enable UDP control API on localhost (pause/resume/rate/regime/reload)
     #[arg(long, value_name = "BOOL")]
     pub control_enabled: Option<bool>,
@@ -144,9 +425,126 @@ pub struct Cli {
     /// UDP control API bind address (example: 127.0.0.1:6001)
     #[arg(long, value_name = "ADDR:PORT")]
     pub control_bind: Option<String>,
+
+    /// Reply to control commands with plain `ok`/`error` text instead of JSON
+    #[arg(long, value_name = "BOOL")]
+    pub control_legacy_replies: Option<bool>,
+
+    /// Serve Binance-style depth and aggTrade WebSocket streams
+    #[arg(long, value_name = "BOOL")]
+    pub crypto_feed: Option<bool>,
+
+    /// Crypto feed bind address (example: 127.0.0.1:9443)
+    #[arg(long, value_name = "ADDR:PORT")]
+    pub crypto_feed_bind: Option<String>,
+
+    /// Publish an option chain on the underlying as OPTION_QUOTE messages
+    #[arg(long, value_name = "BOOL")]
+    pub options: Option<bool>,
+
+    /// Echo OUCH-style Accepted / Canceled messages on a multicast stream of their own
+    #[arg(long, value_name = "BOOL")]
+    pub ouch: Option<bool>,
+
+    /// Multicast port of the OUCH echo stream
+    #[arg(long, value_name = "PORT")]
+    pub ouch_port: Option<u16>,
+
+    /// On exit, cancel every resting order before SESSION_END
+    #[arg(long)]
+    pub drain_on_exit: bool,
+
+    /// Write the end-of-run report as JSON to this path
+    #[arg(long, value_name = "PATH")]
+    pub report: Option<PathBuf>,
+
+    /// Write every order, cancel and trade as a CSV row to this path
+    #[arg(long, value_name = "PATH")]
+    pub csv_out: Option<PathBuf>,
+
+    /// Write every UDP datagram sent to this pcap file
+    #[arg(long, value_name = "PATH")]
+    pub pcap_out: Option<PathBuf>,
+
+    /// Write every order, cancel and trade to this Parquet file (requires the `parquet` feature)
+    #[arg(long, value_name = "PATH")]
+    pub parquet_out: Option<PathBuf>,
+
+    /// Maximum cancels per second while draining (unlimited if omitted)
+    #[arg(long, value_name = "CANCELS")]
+    pub drain_rate: Option<f64>,
+
+    /// Periodically save a checkpoint of the run to this path (and once more on exit)
+    #[arg(long, value_name = "PATH")]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Sim seconds between checkpoints
+    #[arg(long, value_name = "SECONDS", default_value_t = 60.0)]
+    pub checkpoint_interval: f64,
+
+    /// Continue the run saved in this checkpoint (use the same config and build)
+    #[arg(long, value_name = "PATH")]
+    pub resume: Option<PathBuf>,
+
+    /// Run a tool instead of the simulator
+    #[command(subcommand)]
+    pub command: Option<Command>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Print published messages in human-readable form
+    Decode(DecodeArgs),
+    /// Subscribe to the feed and report what a consumer receives
+    Listen(ListenArgs),
+    /// Write the canonical protocol test vectors to stdout
+    Vectors(VectorsArgs),
+    /// Run the generator twice with one seed and check both streams are byte-identical
+    Verify(VerifyArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct DecodeArgs {
+    /// pcap capture, binary dump or text messages; stdin when omitted or `-`
+    #[arg(value_name = "FILE", conflicts_with = "multicast")]
+    pub input: Option<PathBuf>,
+
+    /// Join the configured multicast group and decode live traffic
+    #[arg(long)]
+    pub multicast: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ListenArgs {
+    /// Seconds between statistics lines
+    #[arg(long, value_name = "SECONDS", default_value_t = 1.0)]
+    pub interval: f64,
+
+    /// Print the summary and exit after the first SESSION_END
+    #[arg(long)]
+    pub until_end: bool,
+
+    /// Join this `[[venues]]` entry's group instead of the primary venue's
+    #[arg(long, value_name = "NAME")]
+    pub venue: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct VectorsArgs {
+    /// Encoding: text (one message per line), binary or sbe (frames back to back),
+    /// protobuf (one `Datagram`), flatbuffers (size-prefixed, back to back)
+    #[arg(long, value_name = "FORMAT", default_value = "binary")]
+    pub format: String,
+}
+
+#[derive(Debug, Args)]
+pub struct VerifyArgs {
+    /// Sim seconds to compare when `--duration` is not set
+    #[arg(long, value_name = "SECONDS", default_value_t = 60.0)]
+    pub span: f64,
+}
+
+#[derive(Debug, Default, Deserialize)]
 pub struct FileConfig {
     #[serde(default)]
     pub simulation: SimulationConfig,
@@ -165,28 +563,154 @@ pub struct FileConfig {
 
     #[serde(default)]
     pub control: ControlConfig,
+
+    #[serde(default)]
+    pub feed: FeedConfig,
+
+    #[serde(default)]
+    pub crypto_feed: CryptoFeedConfig,
+
+    #[serde(default)]
+    pub options: OptionsConfig,
+
+    #[serde(default)]
+    pub ouch: OuchConfig,
+
+    #[serde(default)]
+    pub participants: ParticipantConfig,
+
+    #[serde(default)]
+    pub agents: AgentConfig,
+
+    #[serde(default)]
+    pub events: EventsConfig,
+
+    #[serde(default)]
+    pub stops: StopConfig,
+
+    #[serde(default)]
+    pub book: BookConfig,
+
+    #[serde(default)]
+    pub ratios: RatioConfig,
+
+    #[serde(default)]
+    pub jumps: JumpConfig,
+
+    #[serde(default)]
+    pub ou: OuConfig,
+
+    #[serde(default)]
+    pub garch: GarchConfig,
+
+    #[serde(default)]
+    pub fundamental: FundamentalConfig,
+
+    #[serde(default)]
+    pub regime_feedback: RegimeFeedbackConfig,
+
+    #[serde(default)]
+    pub spreads: SpreadConfig,
+
+    #[serde(default)]
+    pub news: NewsConfig,
+
+    #[serde(default)]
+    pub drought: DroughtConfig,
+
+    #[serde(default)]
+    pub squeeze: SqueezeConfig,
+
+    #[serde(default)]
+    pub custom_scenario: CustomScenarioConfig,
+
+    #[serde(default)]
+    pub seasonality: SeasonalityConfig,
+
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+
+    #[serde(default)]
+    pub price_bands: PriceBandConfig,
+
+    #[serde(default)]
+    pub rejects: RejectConfig,
+
+    #[serde(default)]
+    pub session: SessionConfig,
+
+    #[serde(default)]
+    pub venues: Vec<VenueConfig>,
+
+    #[serde(default)]
+    pub lead_lag: LeadLagConfig,
+
+    #[serde(default)]
+    pub basis: BasisConfig,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(default)]
 pub struct SimulationConfig {
     pub scenario: Scenario,
+    /// Scenarios run back to back; replaces `scenario` when non-empty.
+    pub scenario_chain: Vec<ScenarioStep>,
+    pub symbol: String,
+    /// ISIN-like identifier; derived from the symbol when omitted.
+    pub isin: Option<String>,
     pub initial_price: f64,
     pub tick_interval: f64,
     pub tick_size: f64,
+    /// Minimum size increment, published in SESSION_START.
+    pub lot_size: u32,
+    pub pacing: Pacing,
+    /// Sim seconds per wall-clock second.
+    pub time_scale: f64,
+    /// Pace ticks against the wall clock; `false` runs them back to back.
+    pub realtime: bool,
+    /// CPUs to pin the generator and then the sender thread to.
+    pub pin_cores: Vec<usize>,
+    /// SCHED_FIFO priority for the generator and sender threads.
+    pub rt_priority: Option<i32>,
+    pub price_model: PriceModelKind,
     pub throughput_scale: f64,
+    pub target_rate: Option<f64>,
     pub seed: Option<u64>,
+    /// Sim seconds run silently before t=0 to populate the book (0 = no warmup).
+    pub warmup_seconds: f64,
+    /// End the warmup early once the book holds this many resting orders.
+    pub warmup_orders: Option<usize>,
+    /// Run end conditions; the first one met stops the engine.
+    pub duration: Option<f64>,
+    pub max_messages: Option<u64>,
+    pub stop_at_price: Option<f64>,
 }
 
 impl Default for SimulationConfig {
     fn default() -> Self {
         Self {
             scenario: Scenario::Normal,
+            scenario_chain: Vec::new(),
+            symbol: "SIM".to_string(),
+            isin: None,
             initial_price: 100.0,
             tick_interval: 0.1,
             tick_size: 0.01,
+            lot_size: 1,
+            pacing: Pacing::Sleep,
+            time_scale: 1.0,
+            realtime: true,
+            pin_cores: Vec::new(),
+            rt_priority: None,
+            price_model: PriceModelKind::Gbm,
             throughput_scale: 1.0,
+            target_rate: None,
             seed: None,
+            warmup_seconds: 0.0,
+            warmup_orders: None,
+            duration: None,
+            max_messages: None,
+            stop_at_price: None,
         }
     }
 }
@@ -195,7 +719,14 @@ impl Default for SimulationConfig {
 #[serde(default)]
 pub struct OutputConfig {
     pub mode: OutputMode,
+    pub format: LogFormat,
     pub log_file: String,
+    /// Rotate the log file once it reaches this size.
+    pub max_size_mb: Option<f64>,
+    /// Rotated log files kept (`<log_file>.1` .. `<log_file>.N`).
+    pub max_files: usize,
+    /// Also rotate when the (UTC) date changes.
+    pub rotate_daily: bool,
     pub display_interval: f64,
 }
 
@@ -203,7 +734,11 @@ impl Default for OutputConfig {
     fn default() -> Self {
         Self {
             mode: OutputMode::Console,
+            format: LogFormat::Text,
             log_file: "orderflow.log".to_string(),
+            max_size_mb: None,
+            max_files: 5,
+            rotate_daily: false,
             display_interval: 1.0,
         }
     }
@@ -214,7 +749,31 @@ impl Default for OutputConfig {
 pub struct NetworkConfig {
     pub multicast_group: String,
     pub multicast_port: u16,
+    /// Router hops datagrams may cross; 1 keeps them on the local subnet.
+    pub multicast_ttl: u32,
+    /// Interface to send from, by name or IPv4 address; the OS picks if unset.
+    pub interface: Option<String>,
+    /// Loop datagrams back to receivers on this host.
+    pub multicast_loop: bool,
     pub wire_format: WireFormat,
+    pub batch: bool,
+    pub max_packet_bytes: usize,
+    /// Send market orders with the 999999/0 sentinel price instead of MKT / price 0.
+    pub legacy_market_price: bool,
+    /// Probability that a datagram is deliberately corrupted (consumer fuzzing).
+    pub corrupt_prob: f64,
+    /// Append `sent_ns`, nanoseconds since the UNIX epoch at send, to every message.
+    pub epoch_timestamps: bool,
+    /// End every binary message with a CRC32C of its bytes.
+    pub crc: bool,
+    /// Binary layout of ORDER and CANCEL: 2, or 1 for consumers written
+    /// against the original layout.
+    pub wire_version: u8,
+    /// Datagrams the sender thread's queue holds; 0 sends from the
+    /// generating thread.
+    pub sender_queue: usize,
+    /// Send path: the socket itself, or an io_uring (experimental).
+    pub sender: SenderBackend,
 }
 
 impl Default for NetworkConfig {
@@ -222,27 +781,152 @@ impl Default for NetworkConfig {
         Self {
             multicast_group: "239.255.0.1".to_string(),
             multicast_port: 5555,
+            multicast_ttl: 1,
+            interface: None,
+            multicast_loop: true,
             wire_format: WireFormat::Text,
+            batch: false,
+            max_packet_bytes: 1472,
+            legacy_market_price: false,
+            corrupt_prob: 0.0,
+            epoch_timestamps: false,
+            crc: false,
+            wire_version: wire::VERSION,
+            sender_queue: 0,
+            sender: SenderBackend::Socket,
+        }
+    }
+}
+
+/// `[[venues]]`: another venue publishing the same instrument on its own
+/// multicast group and port.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct VenueConfig {
+    pub name: String,
+    pub multicast_group: String,
+    pub multicast_port: u16,
+    /// Wall-clock delay added to every datagram, in milliseconds.
+    pub latency_ms: f64,
+    /// Offset of every price from the primary venue, in basis points.
+    pub price_skew_bps: f64,
+    /// Order flow relative to the primary venue.
+    pub liquidity: f64,
+    /// Constant offset of the venue's timestamps from the primary's, in milliseconds.
+    pub clock_offset_ms: f64,
+    /// How fast the venue's clock runs ahead (negative: behind), in parts per million.
+    pub clock_drift_ppm: f64,
+}
+
+impl Default for VenueConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            multicast_group: String::new(),
+            multicast_port: 0,
+            latency_ms: 0.0,
+            price_skew_bps: 0.0,
+            liquidity: 1.0,
+            clock_offset_ms: 0.0,
+            clock_drift_ppm: 0.0,
+        }
+    }
+}
+
+/// A validated `[[venues]]` entry.
+#[derive(Debug, Clone)]
+pub struct Venue {
+    /// Position in `[[venues]]`, from 1; the primary venue is 0.
+    pub index: u64,
+    pub name: String,
+    pub multicast_group: Ipv4Addr,
+    pub multicast_port: u16,
+    pub latency: Duration,
+    pub price_skew_bps: f64,
+    pub liquidity: f64,
+    /// Seconds added to every timestamp the venue publishes.
+    pub clock_offset: f64,
+    /// Extra seconds per second of sim time on the venue's timestamps.
+    pub clock_drift: f64,
+}
+
+/// `[lead_lag]`: market orders in a leader symbol draw market orders on the
+/// same side in every other `--symbols-file` symbol after a delay.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct LeadLagConfig {
+    /// Symbol whose market orders lead; empty leaves the symbols uncoupled.
+    pub leader: String,
+    /// Chance each leader market order draws one in each follower.
+    pub probability: f64,
+    /// Sim seconds from the leader's market order to the followers'.
+    pub delay: f64,
+}
+
+impl Default for LeadLagConfig {
+    fn default() -> Self {
+        Self {
+            leader: String::new(),
+            probability: 0.3,
+            delay: 0.05,
         }
     }
 }
 
+/// A validated `[lead_lag]`, its leader resolved to a directory index.
+#[derive(Debug, Clone, Copy)]
+pub struct LeadLag {
+    pub leader: u16,
+    pub probability: f64,
+    pub delay: f64,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(default)]
 pub struct OrderConfig {
+    pub size_distribution: SizeDistributionKind,
     pub size_mean_log: f64,
     pub size_std_log: f64,
+    /// Minimum size and tail exponent for `size_distribution = "pareto"`.
+    pub pareto_scale: f64,
+    pub pareto_shape: f64,
+    /// `[size, weight]` pairs for `size_distribution = "lots"`.
+    pub round_lots: Vec<(u32, f64)>,
     pub ttl_min: f64,
     pub ttl_max: f64,
+    /// Probability that a generated limit order is an iceberg.
+    pub iceberg_prob: f64,
+    /// Hidden total as a multiple of the display size, drawn uniformly.
+    pub iceberg_min_mult: u32,
+    pub iceberg_max_mult: u32,
+    /// Regime-driven cancels skip orders that have rested less than this
+    /// many seconds. 0 disables the check.
+    pub min_resting_time: f64,
+    pub id_scheme: IdScheme,
+    /// Seconds before a cancelled order's ID is reused under `id_scheme = "recycled"`.
+    pub id_recycle_delay: f64,
+    /// Give every order a per-participant client order ID (`cl_ord_id`).
+    pub client_order_ids: bool,
 }
 
 impl Default for OrderConfig {
     fn default() -> Self {
         Self {
+            size_distribution: SizeDistributionKind::Lognormal,
             size_mean_log: 3.0,
             size_std_log: 1.0,
+            pareto_scale: 10.0,
+            pareto_shape: 1.5,
+            round_lots: vec![(100, 0.4), (200, 0.3), (500, 0.2), (1000, 0.1)],
             ttl_min: 1.0,
             ttl_max: 30.0,
+            iceberg_prob: 0.0,
+            iceberg_min_mult: 5,
+            iceberg_max_mult: 20,
+            min_resting_time: 0.0,
+            id_scheme: IdScheme::Monotonic,
+            id_recycle_delay: 5.0,
+            client_order_ids: false,
         }
     }
 }
@@ -253,6 +937,9 @@ pub struct ShockConfig {
     pub probability: f64,
     pub min_pct: f64,
     pub max_pct: f64,
+    /// Probability per tick of a shock that pushes every `--symbols-file`
+    /// symbol into CRASH together.
+    pub systemic_probability: f64,
 }
 
 impl Default for ShockConfig {
@@ -261,6 +948,7 @@ impl Default for ShockConfig {
             probability: 0.0003,
             min_pct: 0.02,
             max_pct: 0.06,
+            systemic_probability: 0.0,
         }
     }
 }
@@ -270,6 +958,8 @@ impl Default for ShockConfig {
 pub struct ControlConfig {
     pub enabled: bool,
     pub bind: String,
+    /// Reply `ok` / usage text instead of a JSON object per command.
+    pub legacy_replies: bool,
 }
 
 impl Default for ControlConfig {
@@ -277,56 +967,315 @@ impl Default for ControlConfig {
         Self {
             enabled: true,
             bind: "127.0.0.1:6001".to_string(),
+            legacy_replies: false,
         }
     }
 }
 
-impl Default for FileConfig {
+/// Binance-style WebSocket depth and aggTrade streams derived from the book.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CryptoFeedConfig {
+    pub enabled: bool,
+    pub bind: String,
+    /// Exchange symbol; `<simulation.symbol>USDT` when empty.
+    pub symbol: String,
+    /// Sim seconds between depthUpdate events.
+    pub depth_interval: f64,
+}
+
+impl Default for CryptoFeedConfig {
     fn default() -> Self {
         Self {
-            simulation: SimulationConfig::default(),
-            network: NetworkConfig::default(),
-            orders: OrderConfig::default(),
-            shocks: ShockConfig::default(),
-            output: OutputConfig::default(),
-            control: ControlConfig::default(),
+            enabled: false,
+            bind: "127.0.0.1:9443".to_string(),
+            symbol: String::new(),
+            depth_interval: 0.1,
+        }
+    }
+}
+
+/// OUCH-style Accepted / Canceled acknowledgements of the order flow.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct OuchConfig {
+    pub enabled: bool,
+    pub multicast_group: String,
+    pub multicast_port: u16,
+}
+
+impl Default for OuchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            multicast_group: "239.255.0.1".to_string(),
+            multicast_port: 5560,
+        }
+    }
+}
+
+/// Optional derived message streams published alongside the order flow.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct FeedConfig {
+    pub bars: bool,
+    pub bar_interval: f64,
+    pub publish_quotes: bool,
+    pub depth_levels: usize,
+    pub depth_interval: f64,
+    pub book_view: BookView,
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        Self {
+            bars: false,
+            bar_interval: 1.0,
+            publish_quotes: false,
+            depth_levels: 0,
+            depth_interval: 1.0,
+            book_view: BookView::Mbo,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ParticipantConfig {
+    pub count: usize,
+    pub first_id: u32,
+    pub zipf_exponent: f64,
+    pub weights: Vec<f64>,
+}
+
+impl Default for ParticipantConfig {
+    fn default() -> Self {
+        Self {
+            count: 16,
+            first_id: 1,
+            zipf_exponent: 1.0,
+            weights: Vec::new(),
+        }
+    }
+}
+
+/// Internal order book behaviour.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct BookConfig {
+    /// Match market orders, and limits priced through the opposite touch,
+    /// against resting liquidity and publish TRADE messages. When off, such
+    /// limits rest one tick behind the touch.
+    pub matching: bool,
+    /// How strongly top-of-book imbalance skews market order side and count.
+    /// 0 disables the feedback.
+    pub imbalance_sensitivity: f64,
+    /// What a taker does on meeting its own participant's resting order.
+    pub self_match: SelfMatchMode,
+    /// Share of market orders executed off-book against hidden liquidity,
+    /// published as DARK_TRADE with no ORDER.
+    pub dark_fraction: f64,
+}
+
+/// `[ratios]`: per-regime message ratio targets. Each display interval the
+/// engine compares the measured ratios with these and rescales the regime
+/// cancel and market order intensities towards them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RatioConfig {
+    pub enabled: bool,
+    /// Orders per trade.
+    pub order_to_trade: RegimeValues,
+    /// Cancels per order added.
+    pub cancel_to_add: RegimeValues,
+}
+
+impl Default for RatioConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            order_to_trade: RegimeValues {
+                calm: 15.0,
+                volatile: 8.0,
+                crash: 2.0,
+                rally: 2.5,
+                recovery: 12.0,
+            },
+            cancel_to_add: RegimeValues {
+                calm: 0.85,
+                volatile: 0.8,
+                crash: 0.2,
+                rally: 0.35,
+                recovery: 0.8,
+            },
         }
     }
 }
 
 /// Resolved configuration after merging TOML file + CLI overrides.
+#[derive(Clone)]
 pub struct AppConfig {
     pub config_path: Option<PathBuf>,
-    pub scenario: Scenario,
+    /// Scenarios in run order; at least one.
+    pub scenarios: Vec<ScenarioStep>,
     pub initial_price: f64,
     pub tick_interval: f64,
     pub tick_size: f64,
+    pub lot_size: u32,
+    /// Instruments announced in the symbol directory.
+    pub instruments: Vec<Instrument>,
+    /// Directory index of the simulated instrument, carried by its ORDERs,
+    /// TRADEs and QUOTEs.
+    pub instrument_index: u16,
+    pub pacing: Pacing,
+    pub time_scale: f64,
+    pub realtime: bool,
+    pub pin_cores: Vec<usize>,
+    pub rt_priority: Option<i32>,
+    pub warmup_seconds: f64,
+    pub warmup_orders: Option<usize>,
     pub multicast_group: Ipv4Addr,
     pub multicast_port: u16,
+    /// TTL, interface and loopback of every multicast socket sent from.
+    pub multicast_options: MulticastOptions,
     pub wire_format: WireFormat,
+    /// Datagram size limit when batching is enabled.
+    pub max_packet_bytes: Option<usize>,
+    pub legacy_market_price: bool,
+    pub corrupt_prob: f64,
+    pub epoch_timestamps: bool,
+    pub crc: bool,
+    pub wire_version: u8,
+    /// Queue capacity in datagrams when sending from a dedicated thread.
+    pub sender_queue: Option<usize>,
+    pub sender: SenderBackend,
+    pub size_distribution: SizeDistributionKind,
     pub size_mean_log: f64,
     pub size_std_log: f64,
+    pub pareto_scale: f64,
+    pub pareto_shape: f64,
+    pub round_lots: Vec<(u32, f64)>,
     pub ttl_min: f64,
     pub ttl_max: f64,
+    pub iceberg_prob: f64,
+    pub iceberg_min_mult: u32,
+    pub iceberg_max_mult: u32,
+    pub min_resting_time: f64,
+    pub id_scheme: IdScheme,
+    pub id_recycle_delay: f64,
+    pub client_order_ids: bool,
     pub shock_prob: f64,
     pub shock_min_pct: f64,
     pub shock_max_pct: f64,
+    pub systemic_shock_prob: f64,
     pub output_mode: OutputMode,
+    pub log_format: LogFormat,
     pub log_file: String,
+    pub log_rotation: Rotation,
     pub display_interval: f64,
     pub throughput_scale: f64,
+    pub target_rate: Option<f64>,
     pub seed: u64,
+    pub duration: Option<f64>,
+    pub max_messages: Option<u64>,
+    pub stop_at_price: Option<f64>,
     pub control_enabled: bool,
     pub control_bind: String,
+    pub control_legacy_replies: bool,
+    /// Binance-style feed settings when `[crypto_feed] enabled = true`.
+    pub crypto_feed: Option<CryptoFeedConfig>,
+    /// Option chain settings when `[options] enabled = true`.
+    pub options: Option<OptionsConfig>,
+    /// Group and port of the OUCH echo stream when `[ouch] enabled = true`.
+    pub ouch: Option<(Ipv4Addr, u16)>,
+    pub bench: bool,
+    pub bench_ticks: u64,
+    pub drain_on_exit: bool,
+    pub drain_rate: Option<f64>,
+    pub report_path: Option<PathBuf>,
+    pub checkpoint_path: Option<PathBuf>,
+    pub checkpoint_interval: f64,
+    /// Checkpoint to replay up to before publishing (`--resume`).
+    pub resume: Option<Checkpoint>,
+    pub csv_path: Option<PathBuf>,
+    pub pcap_path: Option<PathBuf>,
+    /// Always `None` without the `parquet` feature (rejected in `resolve`).
+    #[cfg_attr(not(feature = "parquet"), allow(dead_code))]
+    pub parquet_path: Option<PathBuf>,
+    /// OHLCV bar interval in seconds when bar publishing is enabled.
+    pub bar_interval: Option<f64>,
+    pub publish_quotes: bool,
+    /// Levels per side in depth snapshots (0 disables them).
+    pub depth_levels: usize,
+    pub depth_interval: f64,
+    pub book_view: BookView,
+    pub participant_count: usize,
+    pub participant_first_id: u32,
+    pub participant_zipf_exponent: f64,
+    pub participant_weights: Vec<f64>,
+    /// Agent layer settings when `[agents] enabled = true`.
+    pub agents: Option<AgentConfig>,
+    pub events: EventsConfig,
+    pub stops: StopConfig,
+    pub matching: bool,
+    pub imbalance_sensitivity: f64,
+    pub self_match: SelfMatchMode,
+    pub dark_fraction: f64,
+    /// Ratio targets when `[ratios] enabled = true`.
+    pub ratios: Option<RatioConfig>,
+    pub price_model: PriceModelKind,
+    pub jumps: JumpConfig,
+    pub ou: OuConfig,
+    pub garch: GarchConfig,
+    pub fundamental: FundamentalConfig,
+    pub regime_feedback: RegimeFeedbackConfig,
+    pub spreads: SpreadConfig,
+    pub news: NewsConfig,
+    pub drought: DroughtConfig,
+    pub squeeze: SqueezeConfig,
+    pub custom_scenario: CustomScenarioConfig,
+    pub seasonality: SeasonalityConfig,
+    pub circuit_breaker: CircuitBreakerConfig,
+    pub price_bands: PriceBandConfig,
+    pub rejects: RejectConfig,
+    pub session: SessionConfig,
+    pub schedule: Option<Schedule>,
+    /// Extra venues to publish alongside this one.
+    pub venues: Vec<Venue>,
+    /// Set when this instance publishes one of the extra venues.
+    pub venue: Option<Venue>,
+    /// `--symbols-file` rows, in directory order; empty without one.
+    pub universe: Vec<UniverseRow>,
+    /// Threads generating the universe's symbols (`--threads`).
+    pub threads: usize,
+    pub lead_lag: Option<LeadLag>,
+    /// The second symbol's basis over the first (`[basis]`).
+    pub basis: Option<BasisConfig>,
+}
+
+/// The contents of the config file at `path`.
+pub fn read_config(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read config file {}: {}", path.display(), e))
 }
 
 impl AppConfig {
     /// Build the final config: TOML defaults -> file values -> CLI overrides.
+    /// With `--symbols-file` the result publishes its first symbol; see
+    /// `symbol` for the others.
     pub fn resolve(cli: &Cli) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut file_cfg = if let Some(path) = &cli.config {
-            let contents = std::fs::read_to_string(path)
-                .map_err(|e| format!("failed to read config file {}: {}", path.display(), e))?;
-            toml::from_str::<FileConfig>(&contents)
+        let contents = cli.config.as_deref().map(read_config).transpose()?;
+        Self::resolve_from(cli, contents.as_deref())
+    }
+
+    /// `resolve` with the config file's `contents` already read, as a
+    /// replayed `reload` has them.
+    pub fn resolve_from(
+        cli: &Cli,
+        contents: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut file_cfg = if let Some(contents) = contents {
+            toml::from_str::<FileConfig>(contents)
                 .map_err(|e| format!("failed to parse config file: {}", e))?
         } else {
             FileConfig::default()
@@ -334,7 +1283,7 @@ impl AppConfig {
 
         // CLI overrides
         if let Some(s) = &cli.scenario {
-            file_cfg.simulation.scenario = parse_scenario(s)?;
+            file_cfg.simulation.scenario_chain = parse_scenario_chain(s)?;
         }
         if let Some(v) = cli.initial_price {
             file_cfg.simulation.initial_price = v;
@@ -345,15 +1294,78 @@ impl AppConfig {
         if let Some(v) = cli.tick_size {
             file_cfg.simulation.tick_size = v;
         }
+        if let Some(ref p) = cli.pacing {
+            file_cfg.simulation.pacing = parse_pacing(p)?;
+        }
+        if let Some(v) = cli.time_scale {
+            file_cfg.simulation.time_scale = v;
+        }
+        if cli.no_realtime {
+            file_cfg.simulation.realtime = false;
+        }
+        if let Some(ref s) = cli.pin_cores {
+            file_cfg.simulation.pin_cores = parse_cpu_list(s)?;
+        }
+        if let Some(v) = cli.rt_priority {
+            file_cfg.simulation.rt_priority = Some(v);
+        }
+        if let Some(v) = cli.warmup_seconds {
+            file_cfg.simulation.warmup_seconds = v;
+        }
+        if let Some(n) = cli.warmup_orders {
+            file_cfg.simulation.warmup_orders = Some(n);
+        }
+        if let Some(ref m) = cli.price_model {
+            file_cfg.simulation.price_model = parse_price_model(m)?;
+        }
         if let Some(g) = &cli.multicast_group {
             file_cfg.network.multicast_group = g.clone();
         }
         if let Some(p) = cli.multicast_port {
             file_cfg.network.multicast_port = p;
         }
+        if let Some(v) = cli.multicast_ttl {
+            file_cfg.network.multicast_ttl = v;
+        }
+        if let Some(ref s) = cli.interface {
+            file_cfg.network.interface = Some(s.clone());
+        }
+        if let Some(v) = cli.multicast_loop {
+            file_cfg.network.multicast_loop = v;
+        }
         if let Some(ref f) = cli.wire_format {
             file_cfg.network.wire_format = parse_wire_format(f)?;
         }
+        if let Some(ref v) = cli.book_view {
+            file_cfg.feed.book_view = parse_book_view(v)?;
+        }
+        if let Some(v) = cli.batch {
+            file_cfg.network.batch = v;
+        }
+        if let Some(v) = cli.corrupt_prob {
+            file_cfg.network.corrupt_prob = v;
+        }
+        if let Some(v) = cli.error_prob {
+            file_cfg.rejects.error_prob = v;
+        }
+        if let Some(v) = cli.dark_fraction {
+            file_cfg.book.dark_fraction = v;
+        }
+        if let Some(v) = cli.epoch_timestamps {
+            file_cfg.network.epoch_timestamps = v;
+        }
+        if let Some(v) = cli.crc {
+            file_cfg.network.crc = v;
+        }
+        if let Some(v) = cli.max_packet_bytes {
+            file_cfg.network.max_packet_bytes = v;
+        }
+        if let Some(v) = cli.sender_queue {
+            file_cfg.network.sender_queue = v;
+        }
+        if let Some(ref s) = cli.sender {
+            file_cfg.network.sender = parse_sender(s)?;
+        }
         if let Some(v) = cli.shock_prob {
             file_cfg.shocks.probability = v;
         }
@@ -363,69 +1375,1239 @@ impl AppConfig {
         if let Some(ref p) = cli.log_file {
             file_cfg.output.log_file = p.clone();
         }
+        if let Some(ref f) = cli.log_format {
+            file_cfg.output.format = parse_log_format(f)?;
+        }
         if let Some(v) = cli.throughput_scale {
             file_cfg.simulation.throughput_scale = v;
         }
+        if let Some(v) = cli.target_rate {
+            file_cfg.simulation.target_rate = Some(v);
+        }
         if let Some(v) = cli.display_interval {
             file_cfg.output.display_interval = v;
         }
         if let Some(v) = cli.seed {
             file_cfg.simulation.seed = Some(v);
         }
+        let resume = cli.resume.as_deref().map(Checkpoint::load).transpose()?;
+        if let Some(checkpoint) = &resume {
+            let seed = checkpoint.seed()?;
+            if cli.seed.is_some_and(|s| s != seed) {
+                return Err(format!("--seed conflicts with the checkpoint's seed {}", seed).into());
+            }
+            file_cfg.simulation.seed = Some(seed);
+        }
+        if !(cli.checkpoint_interval > 0.0 && cli.checkpoint_interval.is_finite()) {
+            return Err(format!(
+                "checkpoint interval must be positive, got {}",
+                cli.checkpoint_interval
+            )
+            .into());
+        }
+        if let Some(v) = cli.duration {
+            file_cfg.simulation.duration = Some(v);
+        }
+        if let Some(v) = cli.max_messages {
+            file_cfg.simulation.max_messages = Some(v);
+        }
+        if let Some(v) = cli.stop_at_price {
+            file_cfg.simulation.stop_at_price = Some(v);
+        }
         if let Some(v) = cli.control_enabled {
             file_cfg.control.enabled = v;
         }
         if let Some(ref v) = cli.control_bind {
             file_cfg.control.bind = v.clone();
         }
+        if let Some(v) = cli.control_legacy_replies {
+            file_cfg.control.legacy_replies = v;
+        }
+        if let Some(v) = cli.crypto_feed {
+            file_cfg.crypto_feed.enabled = v;
+        }
+        if let Some(ref v) = cli.crypto_feed_bind {
+            file_cfg.crypto_feed.bind = v.clone();
+        }
+        if let Some(v) = cli.options {
+            file_cfg.options.enabled = v;
+        }
+        if let Some(v) = cli.ouch {
+            file_cfg.ouch.enabled = v;
+        }
+        if let Some(v) = cli.ouch_port {
+            file_cfg.ouch.multicast_port = v;
+        }
+
+        let universe = match &cli.symbols_file {
+            Some(path) => refdata::load_universe(path)?,
+            None => Vec::new(),
+        };
+        if let Some(row) = universe.first() {
+            // The configured rates are those of a symbol of average weight
+            let sim = &mut file_cfg.simulation;
+            let mean_weight =
+                universe.iter().map(|r| r.adv_weight).sum::<f64>() / universe.len() as f64;
+            rescale(
+                &mut file_cfg.ou,
+                &mut sim.throughput_scale,
+                &mut sim.target_rate,
+                row.initial_price / sim.initial_price,
+                row.adv_weight / mean_weight,
+            );
+            sim.symbol = row.symbol.clone();
+            sim.isin = None;
+            sim.initial_price = row.initial_price;
+            sim.tick_size = row.tick_size;
+        }
+
+        if let Some(r) = file_cfg.simulation.target_rate {
+            if r.is_nan() || r <= 0.0 {
+                return Err(format!("target rate must be positive, got {}", r).into());
+            }
+        }
 
-        let seed = file_cfg
-            .simulation
-            .seed
-            .unwrap_or_else(|| rand::random());
+        let time_scale = file_cfg.simulation.time_scale;
+        if !(time_scale > 0.0 && time_scale.is_finite()) {
+            return Err(format!("time_scale must be positive, got {}", time_scale).into());
+        }
+
+        match file_cfg.simulation.pin_cores.len() {
+            0 | 1 => {}
+            2 if file_cfg.network.sender_queue > 0 => {}
+            2 => {
+                return Err("pin_cores has a second CPU for the sender thread, but \
+                            sender_queue is 0"
+                    .into())
+            }
+            n => {
+                return Err(format!(
+                    "pin_cores takes the generator's CPU and the sender thread's, got {}",
+                    n
+                )
+                .into())
+            }
+        }
+        if let Some(p) = file_cfg.simulation.rt_priority {
+            if !(1..=99).contains(&p) {
+                return Err(format!("rt_priority must be between 1 and 99, got {}", p).into());
+            }
+        }
+
+        let warmup_seconds = file_cfg.simulation.warmup_seconds;
+        if !(warmup_seconds >= 0.0 && warmup_seconds.is_finite()) {
+            return Err(format!(
+                "warmup_seconds must be non-negative, got {}",
+                warmup_seconds
+            )
+            .into());
+        }
+        if file_cfg.simulation.warmup_orders.is_some() && warmup_seconds == 0.0 {
+            return Err(
+                "warmup_orders needs warmup_seconds as the longest the warmup may run".into(),
+            );
+        }
+
+        if let Some(d) = file_cfg.simulation.duration {
+            if d.is_nan() || d <= 0.0 {
+                return Err(format!("duration must be positive, got {}", d).into());
+            }
+        }
+        if let Some(p) = file_cfg.simulation.stop_at_price {
+            if p.is_nan() || p <= 0.0 {
+                return Err(format!("stop_at_price must be positive, got {}", p).into());
+            }
+        }
+
+        if let Some(m) = file_cfg.output.max_size_mb {
+            if m.is_nan() || m <= 0.0 {
+                return Err(format!("output max_size_mb must be positive, got {}", m).into());
+            }
+        }
+        if file_cfg.output.max_files == 0 {
+            return Err("output max_files must be at least 1".into());
+        }
+
+        if cli.parquet_out.is_some() && !cfg!(feature = "parquet") {
+            return Err("--parquet-out requires a build with `--features parquet`".into());
+        }
 
-        let multicast_group: Ipv4Addr = file_cfg
-            .network
-            .multicast_group
-            .parse()
-            .map_err(|e| format!("invalid multicast group '{}': {}", file_cfg.network.multicast_group, e))?;
+        if let Some(r) = cli.drain_rate {
+            if r.is_nan() || r <= 0.0 {
+                return Err(format!("drain rate must be positive, got {}", r).into());
+            }
+        }
+
+        if file_cfg.network.batch && !(64..=65507).contains(&file_cfg.network.max_packet_bytes) {
+            return Err(format!(
+                "max_packet_bytes must be between 64 and 65507, got {}",
+                file_cfg.network.max_packet_bytes
+            )
+            .into());
+        }
+
+        if file_cfg.network.sender == SenderBackend::IoUring && !cfg!(feature = "io-uring") {
+            return Err("sender = \"io_uring\" requires a build with `--features io-uring`".into());
+        }
+
+        let flatbuffers = file_cfg.network.wire_format == WireFormat::FlatBuffers;
+        if flatbuffers && !cfg!(feature = "flatbuffers") {
+            return Err("flatbuffers requires a build with `--features flatbuffers`".into());
+        }
+
+        if file_cfg.network.crc && file_cfg.network.wire_format != WireFormat::Binary {
+            return Err("crc needs wire_format = \"binary\"".into());
+        }
+
+        if file_cfg.network.epoch_timestamps
+            && matches!(
+                file_cfg.network.wire_format,
+                WireFormat::Sbe | WireFormat::Protobuf | WireFormat::FlatBuffers
+            )
+        {
+            return Err("epoch_timestamps needs wire_format = \"text\" or \"binary\"".into());
+        }
+
+        match file_cfg.network.wire_version {
+            wire::VERSION => {}
+            wire::VERSION_1 => {
+                let net = &file_cfg.network;
+                if net.wire_format != WireFormat::Binary {
+                    return Err("wire_version = 1 needs wire_format = \"binary\"".into());
+                }
+                if net.batch || net.epoch_timestamps || net.crc {
+                    return Err(
+                        "wire_version = 1 has one bare message per datagram; turn off batch, epoch_timestamps and crc".into(),
+                    );
+                }
+            }
+            v => return Err(format!("wire_version must be 1 or 2, got {}", v).into()),
+        }
+
+        if !(0.0..=1.0).contains(&file_cfg.network.corrupt_prob) {
+            return Err(format!(
+                "corrupt_prob must be between 0 and 1, got {}",
+                file_cfg.network.corrupt_prob
+            )
+            .into());
+        }
+
+        let j = &file_cfg.jumps;
+        let intensities = [
+            j.intensity.calm,
+            j.intensity.volatile,
+            j.intensity.crash,
+            j.intensity.rally,
+            j.intensity.recovery,
+        ];
+        if intensities.iter().any(|&l| !(l >= 0.0 && l.is_finite())) {
+            return Err("jump intensities must be non-negative".into());
+        }
+        if !(j.std >= 0.0 && j.std.is_finite() && j.mean.is_finite()) {
+            return Err(format!(
+                "jump size mean must be finite and std non-negative, got mean={} std={}",
+                j.mean, j.std
+            )
+            .into());
+        }
+
+        let ou = &file_cfg.ou;
+        if !(ou.speed >= 0.0 && ou.speed.is_finite()) {
+            return Err(format!("ou speed must be non-negative, got {}", ou.speed).into());
+        }
+        if !(ou.volatility >= 0.0 && ou.volatility.is_finite()) {
+            return Err(
+                format!("ou volatility must be non-negative, got {}", ou.volatility).into(),
+            );
+        }
+        if ou.mean.is_some_and(|m| !(m > 0.0 && m.is_finite())) {
+            return Err("ou mean must be positive".into());
+        }
+
+        let g = &file_cfg.garch;
+        if g.enabled {
+            if !(g.alpha >= 0.0 && g.beta >= 0.0 && g.alpha + g.beta < 1.0) {
+                return Err(format!(
+                    "garch alpha and beta must be non-negative with alpha + beta < 1, got alpha={} beta={}",
+                    g.alpha, g.beta
+                )
+                .into());
+            }
+            if g.omega.is_some_and(|w| !(w > 0.0 && w.is_finite())) {
+                return Err("garch omega must be positive".into());
+            }
+        }
+
+        let fv = &file_cfg.fundamental;
+        if fv.enabled
+            && !(fv.sigma >= 0.0
+                && fv.strength >= 0.0
+                && fv.sigma.is_finite()
+                && fv.strength.is_finite())
+        {
+            return Err(format!(
+                "fundamental sigma and strength must be non-negative, got sigma={} strength={}",
+                fv.sigma, fv.strength
+            )
+            .into());
+        }
+
+        let spreads = &file_cfg.spreads;
+        for r in Regime::ALL {
+            let (half, offset) = (spreads.half_spread_bps.get(r), spreads.offset_bps.get(r));
+            if !(half >= 0.0 && half.is_finite() && offset > 0.0 && offset.is_finite()) {
+                return Err(format!(
+                    "spreads for {} need half_spread_bps >= 0 and offset_bps > 0, got {} and {}",
+                    r, half, offset
+                )
+                .into());
+            }
+        }
+
+        let scenarios = if file_cfg.simulation.scenario_chain.is_empty() {
+            vec![ScenarioStep {
+                scenario: file_cfg.simulation.scenario,
+                duration: None,
+            }]
+        } else {
+            file_cfg.simulation.scenario_chain.clone()
+        };
+        for (i, step) in scenarios.iter().enumerate() {
+            match step.duration {
+                Some(d) if !(d > 0.0 && d.is_finite()) => {
+                    return Err(format!(
+                        "scenario '{}' duration must be positive, got {}",
+                        step.scenario, d
+                    )
+                    .into());
+                }
+                None if i + 1 < scenarios.len() => {
+                    return Err(format!(
+                        "scenario '{}' needs a duration: only the last scenario of a chain may omit it",
+                        step.scenario
+                    )
+                    .into());
+                }
+                _ => {}
+            }
+        }
+
+        let news = &file_cfg.news;
+        if !(news.at > 0.0 && news.pre_window >= 0.0 && news.window >= 0.0) {
+            return Err(format!(
+                "news needs at > 0 and non-negative windows, got at={} pre_window={} window={}",
+                news.at, news.pre_window, news.window
+            )
+            .into());
+        }
+        if !(0.0..=1.0).contains(&news.cancel_fraction) {
+            return Err(format!(
+                "news cancel_fraction must be between 0 and 1, got {}",
+                news.cancel_fraction
+            )
+            .into());
+        }
+        if !(news.spread_mult > 0.0 && news.sigma_mult >= 0.0 && news.market_mult >= 0.0) {
+            return Err(
+                "news spread_mult must be positive and sigma_mult/market_mult non-negative".into(),
+            );
+        }
+
+        let drought = &file_cfg.drought;
+        if !(drought.at >= 0.0 && drought.duration >= 0.0) {
+            return Err(format!(
+                "drought needs non-negative at and duration, got at={} duration={}",
+                drought.at, drought.duration
+            )
+            .into());
+        }
+        if !(drought.limit_mult >= 0.0 && drought.cancel_mult >= 0.0) {
+            return Err("drought limit_mult and cancel_mult must be non-negative".into());
+        }
+
+        let sq = &file_cfg.squeeze;
+        if !(sq.at > 0.0 && sq.shock_interval >= 0.0 && sq.shock_pct >= 0.0) {
+            return Err(format!(
+                "squeeze needs at > 0 and non-negative shock_interval/shock_pct, got at={} shock_interval={} shock_pct={}",
+                sq.at, sq.shock_interval, sq.shock_pct
+            )
+            .into());
+        }
+        if !(sq.duration > 0.0 && sq.duration.is_finite()) {
+            return Err(format!("squeeze duration must be positive, got {}", sq.duration).into());
+        }
+        if !(sq.buy_prob_ramp >= 0.0 && sq.size_ramp >= 0.0) {
+            return Err("squeeze buy_prob_ramp and size_ramp must be non-negative".into());
+        }
+        if !(sq.max_buy_prob > 0.0 && sq.max_buy_prob < 1.0 && sq.max_size_mult > 0.0) {
+            return Err(format!(
+                "squeeze max_buy_prob must be between 0 and 1 and max_size_mult positive, got {} and {}",
+                sq.max_buy_prob, sq.max_size_mult
+            )
+            .into());
+        }
+
+        let custom = &file_cfg.custom_scenario;
+        if !(custom.stop_rate >= 0.0 && custom.stop_rate.is_finite()) {
+            return Err("custom_scenario stop_rate must be non-negative".into());
+        }
+        for ev in &custom.events {
+            if !(ev.at >= 0.0
+                && ev.at.is_finite()
+                && ev.shock_pct > -1.0
+                && ev.shock_pct.is_finite())
+            {
+                return Err(format!(
+                    "custom_scenario event needs a non-negative time and shock_pct > -1, got at={} shock_pct={}",
+                    ev.at, ev.shock_pct
+                )
+                .into());
+            }
+        }
+        for w in &custom.windows {
+            let m = &w.mult;
+            if !(w.start >= 0.0 && w.end >= w.start) {
+                return Err(format!(
+                    "custom_scenario window '{}' needs 0 <= start <= end, got {}..{}",
+                    w.label, w.start, w.end
+                )
+                .into());
+            }
+            if !(m.sigma >= 0.0
+                && m.limit_rate >= 0.0
+                && m.market_rate >= 0.0
+                && m.cancel_rate >= 0.0
+                && m.spread > 0.0)
+            {
+                return Err(format!(
+                    "custom_scenario window '{}' needs non-negative multipliers and a positive spread",
+                    w.label
+                )
+                .into());
+            }
+            if !(0.0..=1.0).contains(&w.cancel_wave) {
+                return Err(format!(
+                    "custom_scenario window '{}' cancel_wave must be between 0 and 1, got {}",
+                    w.label, w.cancel_wave
+                )
+                .into());
+            }
+        }
+        for r in Regime::ALL {
+            if !custom.params.get(r).apply(regime::params(r)).is_valid() {
+                return Err(format!(
+                    "custom_scenario.params.{} has an invalid value: rates, sigma and half_spread must be \
+                     non-negative, buy_prob in 0..=1, offset_lambda and size_mult positive",
+                    r.to_string().to_lowercase()
+                )
+                .into());
+            }
+        }
+
+        let fb = &file_cfg.regime_feedback;
+        if fb.enabled {
+            if !(fb.drawdown_pct > 0.0 && fb.drawdown_pct < 1.0 && fb.rebound_pct > 0.0) {
+                return Err(format!(
+                    "regime_feedback needs 0 < drawdown_pct < 1 and rebound_pct > 0, got {} and {}",
+                    fb.drawdown_pct, fb.rebound_pct
+                )
+                .into());
+            }
+            if !(fb.crash_boost >= 0.0 && fb.recovery_boost >= 0.0) {
+                return Err("regime_feedback boosts must be non-negative".into());
+            }
+        }
+
+        let season = &file_cfg.seasonality;
+        if season.enabled {
+            if !(season.session_length > 0.0 && season.session_length.is_finite()) {
+                return Err(format!(
+                    "seasonality session_length must be positive, got {}",
+                    season.session_length
+                )
+                .into());
+            }
+            if season.curve.is_empty() {
+                return Err("seasonality curve needs at least one point".into());
+            }
+            if season
+                .curve
+                .iter()
+                .any(|&(x, m)| !((0.0..=1.0).contains(&x) && m >= 0.0 && m.is_finite()))
+            {
+                return Err(
+                    "seasonality curve points must be [fraction in 0..=1, multiplier >= 0]".into(),
+                );
+            }
+            if season.curve.windows(2).any(|w| w[1].0 < w[0].0) {
+                return Err("seasonality curve fractions must be ascending".into());
+            }
+        }
+
+        if file_cfg.feed.bars && file_cfg.feed.bar_interval <= 0.0 {
+            return Err(format!(
+                "bar_interval must be positive, got {}",
+                file_cfg.feed.bar_interval
+            )
+            .into());
+        }
+
+        if file_cfg.feed.depth_levels > 255 {
+            return Err(format!(
+                "depth_levels must be at most 255, got {}",
+                file_cfg.feed.depth_levels
+            )
+            .into());
+        }
+        if file_cfg.feed.depth_levels > 0 && file_cfg.feed.depth_interval <= 0.0 {
+            return Err(format!(
+                "depth_interval must be positive, got {}",
+                file_cfg.feed.depth_interval
+            )
+            .into());
+        }
+
+        let orders = &file_cfg.orders;
+        if !(0.0..=1.0).contains(&orders.iceberg_prob) {
+            return Err(format!(
+                "iceberg_prob must be between 0 and 1, got {}",
+                orders.iceberg_prob
+            )
+            .into());
+        }
+        if !(orders.min_resting_time >= 0.0 && orders.min_resting_time.is_finite()) {
+            return Err(format!(
+                "min_resting_time must be non-negative, got {}",
+                orders.min_resting_time
+            )
+            .into());
+        }
+        if !(orders.id_recycle_delay >= 0.0 && orders.id_recycle_delay.is_finite()) {
+            return Err(format!(
+                "id_recycle_delay must be non-negative, got {}",
+                orders.id_recycle_delay
+            )
+            .into());
+        }
+        match orders.size_distribution {
+            SizeDistributionKind::Lognormal => {}
+            SizeDistributionKind::Pareto => {
+                if !(orders.pareto_scale > 0.0 && orders.pareto_shape > 0.0) {
+                    return Err(format!(
+                        "pareto_scale and pareto_shape must be positive, got {} and {}",
+                        orders.pareto_scale, orders.pareto_shape
+                    )
+                    .into());
+                }
+            }
+            SizeDistributionKind::Lots => {
+                if orders.round_lots.is_empty() {
+                    return Err("round_lots needs at least one [size, weight] entry".into());
+                }
+                if orders
+                    .round_lots
+                    .iter()
+                    .any(|&(size, w)| size == 0 || !(w >= 0.0 && w.is_finite()))
+                {
+                    return Err("round_lots entries must be [size >= 1, weight >= 0]".into());
+                }
+                if orders.round_lots.iter().all(|&(_, w)| w == 0.0) {
+                    return Err("round_lots weights must not all be zero".into());
+                }
+            }
+        }
+        if orders.iceberg_min_mult < 2
+            || orders.iceberg_max_mult < orders.iceberg_min_mult
+            || orders.iceberg_max_mult > MAX_ICEBERG_MULT
+        {
+            return Err(format!(
+                "iceberg multiples must satisfy 2 <= min <= max <= {}, got {}..{}",
+                MAX_ICEBERG_MULT, orders.iceberg_min_mult, orders.iceberg_max_mult
+            )
+            .into());
+        }
+        if !(orders.ttl_min >= 0.0 && orders.ttl_min < orders.ttl_max && orders.ttl_max.is_finite())
+        {
+            return Err(format!(
+                "order ttl must satisfy 0 <= ttl_min < ttl_max, got {}..{}",
+                orders.ttl_min, orders.ttl_max
+            )
+            .into());
+        }
+
+        let cb = &file_cfg.circuit_breaker;
+        if cb.enabled && (cb.move_pct <= 0.0 || cb.window <= 0.0) {
+            return Err(format!(
+                "circuit_breaker move_pct and window must be positive, got {} and {}",
+                cb.move_pct, cb.window
+            )
+            .into());
+        }
+
+        let pb = &file_cfg.price_bands;
+        if pb.enabled && (pb.band_pct <= 0.0 || pb.reference_window <= 0.0) {
+            return Err(format!(
+                "price_bands band_pct and reference_window must be positive, got {} and {}",
+                pb.band_pct, pb.reference_window
+            )
+            .into());
+        }
+
+        let ff = &file_cfg.events.fat_finger;
+        if ff.rate < 0.0 || ff.min_multiplier < 1.0 || ff.max_multiplier < ff.min_multiplier {
+            return Err(format!(
+                "events.fat_finger needs rate >= 0 and 1 <= min_multiplier <= max_multiplier, got {}, {} and {}",
+                ff.rate, ff.min_multiplier, ff.max_multiplier
+            )
+            .into());
+        }
+        if !(0.0..1.0).contains(&ff.aggression_pct) || !(0.0..1.0).contains(&ff.impact_pct) {
+            return Err(format!(
+                "events.fat_finger aggression_pct and impact_pct must be between 0 and 1, got {} and {}",
+                ff.aggression_pct, ff.impact_pct
+            )
+            .into());
+        }
+
+        let st = &file_cfg.stops;
+        if !(0.0 <= st.min_distance && st.min_distance <= st.max_distance && st.max_distance < 1.0)
+        {
+            return Err(format!(
+                "stops distances must satisfy 0 <= min_distance <= max_distance < 1, got {}..{}",
+                st.min_distance, st.max_distance
+            )
+            .into());
+        }
+        if !(0.0..=1.0).contains(&st.sell_fraction) {
+            return Err(format!(
+                "stops sell_fraction must be between 0 and 1, got {}",
+                st.sell_fraction
+            )
+            .into());
+        }
+        let non_negative = |x: f64| x >= 0.0 && x.is_finite();
+        if !non_negative(st.rate) || !non_negative(st.impact_per_unit) {
+            return Err(format!(
+                "stops rate and impact_per_unit must be non-negative, got {} and {}",
+                st.rate, st.impact_per_unit
+            )
+            .into());
+        }
+        if !(st.ttl >= 0.0 && st.ttl.is_finite()) {
+            return Err(
+                format!("stops ttl must be non-negative (0 = never), got {}", st.ttl).into(),
+            );
+        }
+
+        let ag = &file_cfg.agents;
+        if ag.spoof_layers == 0 || ag.spoof_layer_size == 0 || ag.mm_quote_size == 0 {
+            return Err(format!(
+                "agents spoof_layers, spoof_layer_size and mm_quote_size must be at least 1, got {}, {} and {}",
+                ag.spoof_layers, ag.spoof_layer_size, ag.mm_quote_size
+            )
+            .into());
+        }
+        if !(ag.spoof_interval > 0.0 && ag.spoof_interval.is_finite()) {
+            return Err(format!(
+                "agents spoof_interval must be positive, got {}",
+                ag.spoof_interval
+            )
+            .into());
+        }
+        if ![
+            ag.momentum_rate,
+            ag.noise_rate,
+            ag.spoof_hold,
+            ag.momentum_lookback,
+        ]
+        .into_iter()
+        .all(non_negative)
+        {
+            return Err(format!(
+                "agents momentum_rate, noise_rate, spoof_hold and momentum_lookback must be non-negative, got {}, {}, {} and {}",
+                ag.momentum_rate, ag.noise_rate, ag.spoof_hold, ag.momentum_lookback
+            )
+            .into());
+        }
+
+        let rj = &file_cfg.rejects;
+        if !(0.0..=1.0).contains(&rj.error_prob) || !(0.0..=1.0).contains(&rj.zero_size_share) {
+            return Err(format!(
+                "rejects error_prob and zero_size_share must be between 0 and 1, got {} and {}",
+                rj.error_prob, rj.zero_size_share
+            )
+            .into());
+        }
+        if rj.collar_pct <= 0.0 || rj.collar_pct >= 0.5 {
+            return Err(format!(
+                "rejects collar_pct must be between 0 and 0.5, got {}",
+                rj.collar_pct
+            )
+            .into());
+        }
+
+        if file_cfg.simulation.lot_size == 0 {
+            return Err("lot_size must be at least 1, got 0".into());
+        }
+
+        let sim = &file_cfg.simulation;
+        if sim.symbol.is_empty() || sim.symbol.len() > refdata::SYMBOL_LEN || !sim.symbol.is_ascii()
+        {
+            return Err(format!(
+                "symbol must be 1-{} ASCII characters, got '{}'",
+                refdata::SYMBOL_LEN,
+                sim.symbol
+            )
+            .into());
+        }
+        if let Some(isin) = &sim.isin {
+            if isin.len() != refdata::ISIN_LEN || !isin.is_ascii() {
+                return Err(format!(
+                    "isin must be {} ASCII characters, got '{}'",
+                    refdata::ISIN_LEN,
+                    isin
+                )
+                .into());
+            }
+        }
+
+        let crypto = &mut file_cfg.crypto_feed;
+        if crypto.symbol.is_empty() {
+            crypto.symbol = format!("{}USDT", file_cfg.simulation.symbol);
+        }
+        if !crypto.symbol.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            return Err(format!(
+                "crypto_feed symbol must be ASCII letters and digits, got '{}'",
+                crypto.symbol
+            )
+            .into());
+        }
+        if crypto.depth_interval <= 0.0 {
+            return Err(format!(
+                "crypto_feed depth_interval must be positive, got {}",
+                crypto.depth_interval
+            )
+            .into());
+        }
+
+        let options = &file_cfg.options;
+        if options.enabled {
+            if options.interval <= 0.0 {
+                return Err(format!(
+                    "options interval must be positive, got {}",
+                    options.interval
+                )
+                .into());
+            }
+            if !(options.strike_step_pct > 0.0 && options.strike_step_pct < 1.0) {
+                return Err(format!(
+                    "options strike_step_pct must be between 0 and 1, got {}",
+                    options.strike_step_pct
+                )
+                .into());
+            }
+            if options.expiries.is_empty() || options.expiries.contains(&0) {
+                return Err(
+                    "options expiries must be a non-empty list of positive trading days".into(),
+                );
+            }
+            for r in Regime::ALL {
+                if options.atm_vol.get(r) <= 0.0 {
+                    return Err(format!(
+                        "options atm_vol must be positive, got {} for {}",
+                        options.atm_vol.get(r),
+                        r
+                    )
+                    .into());
+                }
+            }
+            if !(options.long_vol > 0.0 && options.term_days > 0.0) {
+                return Err(format!(
+                    "options long_vol and term_days must be positive, got {} and {}",
+                    options.long_vol, options.term_days
+                )
+                .into());
+            }
+            if !(options.spread_pct >= 0.0 && options.tick_size > 0.0) {
+                return Err(format!(
+                    "options spread_pct must be non-negative and tick_size positive, got {} and {}",
+                    options.spread_pct, options.tick_size
+                )
+                .into());
+            }
+        }
+
+        let sensitivity = file_cfg.book.imbalance_sensitivity;
+        if !(sensitivity >= 0.0 && sensitivity.is_finite()) {
+            return Err(format!(
+                "book imbalance_sensitivity must be non-negative, got {}",
+                sensitivity
+            )
+            .into());
+        }
+        let dark = file_cfg.book.dark_fraction;
+        if !(0.0..=1.0).contains(&dark) {
+            return Err(format!("book dark_fraction must be between 0 and 1, got {}", dark).into());
+        }
+
+        let ratios = &file_cfg.ratios;
+        for r in Regime::ALL {
+            let (otr, cta) = (ratios.order_to_trade.get(r), ratios.cancel_to_add.get(r));
+            if !(otr > 0.0 && otr.is_finite() && cta > 0.0 && cta.is_finite()) {
+                return Err(format!(
+                    "ratios for {} must be positive, got order_to_trade={} cancel_to_add={}",
+                    r, otr, cta
+                )
+                .into());
+            }
+        }
+
+        let session = &file_cfg.session;
+        if session.imbalance_interval <= 0.0 {
+            return Err(format!(
+                "imbalance_interval must be positive, got {}",
+                session.imbalance_interval
+            )
+            .into());
+        }
+        let schedule = Schedule::from_config(session)?;
+        if schedule.is_some() && session.close_at.is_some() {
+            return Err("session close_at cannot be combined with open/close hours".into());
+        }
+        if session.days == Some(0) {
+            return Err("session days must be at least 1".into());
+        }
+        let overnight = &session.overnight;
+        if !(overnight.std >= 0.0 && overnight.std.is_finite() && overnight.mean.is_finite()) {
+            return Err(format!(
+                "overnight mean must be finite and std non-negative, got mean={} std={}",
+                overnight.mean, overnight.std
+            )
+            .into());
+        }
+
+        let seed = file_cfg.simulation.seed.unwrap_or_else(rand::random);
+
+        let multicast_group: Ipv4Addr = file_cfg.network.multicast_group.parse().map_err(|e| {
+            format!(
+                "invalid multicast group '{}': {}",
+                file_cfg.network.multicast_group, e
+            )
+        })?;
+
+        if file_cfg.network.multicast_ttl > 255 {
+            return Err(format!(
+                "multicast_ttl must be between 0 and 255, got {}",
+                file_cfg.network.multicast_ttl
+            )
+            .into());
+        }
+        let interface = match &file_cfg.network.interface {
+            Some(name) => Some(
+                multicast::interface_addr(name)
+                    .map_err(|e| format!("invalid interface '{}': {}", name, e))?,
+            ),
+            None => None,
+        };
+        let multicast_options = MulticastOptions {
+            ttl: file_cfg.network.multicast_ttl,
+            interface,
+            loopback: file_cfg.network.multicast_loop,
+        };
+
+        let mut venues: Vec<Venue> = Vec::new();
+        for (i, v) in file_cfg.venues.iter().enumerate() {
+            if v.name.is_empty() || venues.iter().any(|other| other.name == v.name) {
+                return Err(format!("venue #{} needs a unique, non-empty name", i + 1).into());
+            }
+            let group: Ipv4Addr = v.multicast_group.parse().map_err(|e| {
+                format!(
+                    "venue '{}' has an invalid multicast group '{}': {}",
+                    v.name, v.multicast_group, e
+                )
+            })?;
+            let taken = (group, v.multicast_port)
+                == (multicast_group, file_cfg.network.multicast_port)
+                || venues.iter().any(|other| {
+                    (other.multicast_group, other.multicast_port) == (group, v.multicast_port)
+                });
+            if v.multicast_port == 0 || taken {
+                return Err(format!(
+                    "venue '{}' needs a multicast group/port of its own, got {}:{}",
+                    v.name, group, v.multicast_port
+                )
+                .into());
+            }
+            if !(v.latency_ms >= 0.0 && v.latency_ms.is_finite()) {
+                return Err(format!(
+                    "venue '{}' latency_ms must be non-negative, got {}",
+                    v.name, v.latency_ms
+                )
+                .into());
+            }
+            if !(v.price_skew_bps > -10_000.0 && v.price_skew_bps.is_finite()) {
+                return Err(format!(
+                    "venue '{}' price_skew_bps must be > -10000, got {}",
+                    v.name, v.price_skew_bps
+                )
+                .into());
+            }
+            if !(v.liquidity > 0.0 && v.liquidity.is_finite()) {
+                return Err(format!(
+                    "venue '{}' liquidity must be positive, got {}",
+                    v.name, v.liquidity
+                )
+                .into());
+            }
+            if !(v.clock_offset_ms.is_finite()
+                && v.clock_drift_ppm > -1e6
+                && v.clock_drift_ppm.is_finite())
+            {
+                return Err(format!(
+                    "venue '{}' needs a finite clock_offset_ms and clock_drift_ppm > -1000000",
+                    v.name
+                )
+                .into());
+            }
+            venues.push(Venue {
+                index: i as u64 + 1,
+                name: v.name.clone(),
+                multicast_group: group,
+                multicast_port: v.multicast_port,
+                latency: Duration::from_secs_f64(v.latency_ms / 1000.0),
+                price_skew_bps: v.price_skew_bps,
+                liquidity: v.liquidity,
+                clock_offset: v.clock_offset_ms / 1000.0,
+                clock_drift: v.clock_drift_ppm / 1e6,
+            });
+        }
+        if !venues.is_empty() && (cli.checkpoint.is_some() || resume.is_some()) {
+            return Err("--checkpoint and --resume do not cover [[venues]]".into());
+        }
+        let systemic = file_cfg.shocks.systemic_probability;
+        if !(0.0..=1.0).contains(&systemic) {
+            return Err(format!(
+                "shocks.systemic_probability must be in [0, 1], got {}",
+                systemic
+            )
+            .into());
+        }
+        let ll = &file_cfg.lead_lag;
+        let lead_lag = if ll.leader.is_empty() {
+            None
+        } else {
+            let leader = universe
+                .iter()
+                .position(|r| r.symbol == ll.leader)
+                .filter(|_| universe.len() > 1)
+                .ok_or_else(|| {
+                    format!(
+                        "lead_lag leader '{}' is not one of several --symbols-file symbols",
+                        ll.leader
+                    )
+                })?;
+            if !((0.0..=1.0).contains(&ll.probability) && ll.delay >= 0.0 && ll.delay.is_finite()) {
+                return Err(format!(
+                    "lead_lag needs probability in [0, 1] and delay >= 0, got probability={} delay={}",
+                    ll.probability, ll.delay
+                )
+                .into());
+            }
+            Some(LeadLag {
+                leader: leader as u16,
+                probability: ll.probability,
+                delay: ll.delay,
+            })
+        };
+        let basis = &file_cfg.basis;
+        if basis.enabled {
+            if universe.len() < 2 {
+                return Err("[basis] needs a --symbols-file with the spot and the future".into());
+            }
+            if !(basis.speed >= 0.0
+                && basis.volatility >= 0.0
+                && basis.mean.is_finite()
+                && basis.speed.is_finite()
+                && basis.volatility.is_finite())
+            {
+                return Err(format!(
+                    "basis needs a finite mean and non-negative speed/volatility, got mean={} speed={} volatility={}",
+                    basis.mean, basis.speed, basis.volatility
+                )
+                .into());
+            }
+        }
+        if cli.threads == Some(0) {
+            return Err("--threads must be at least 1".into());
+        }
+        if universe.len() > 1 {
+            if !venues.is_empty() {
+                return Err("--symbols-file and [[venues]] cannot be combined".into());
+            }
+            if cli.checkpoint.is_some() || resume.is_some() {
+                return Err("--checkpoint and --resume do not cover --symbols-file".into());
+            }
+            if file_cfg.network.wire_version == wire::VERSION_1 {
+                return Err(
+                    "--symbols-file needs wire_version = 2: v1 cancels carry no instrument".into(),
+                );
+            }
+        }
+
+        let ouch = &file_cfg.ouch;
+        let ouch = if ouch.enabled {
+            let group: Ipv4Addr = ouch.multicast_group.parse().map_err(|e| {
+                format!(
+                    "invalid OUCH multicast group '{}': {}",
+                    ouch.multicast_group, e
+                )
+            })?;
+            let taken = (group, ouch.multicast_port)
+                == (multicast_group, file_cfg.network.multicast_port)
+                || venues
+                    .iter()
+                    .any(|v| (v.multicast_group, v.multicast_port) == (group, ouch.multicast_port));
+            if ouch.multicast_port == 0 || taken {
+                return Err(format!(
+                    "the OUCH stream needs a multicast group/port of its own, got {}:{}",
+                    group, ouch.multicast_port
+                )
+                .into());
+            }
+            Some((group, ouch.multicast_port))
+        } else {
+            None
+        };
 
         Ok(Self {
             config_path: cli.config.clone(),
-            scenario: file_cfg.simulation.scenario,
+            scenarios,
             initial_price: file_cfg.simulation.initial_price,
             tick_interval: file_cfg.simulation.tick_interval,
             tick_size: file_cfg.simulation.tick_size,
+            lot_size: file_cfg.simulation.lot_size,
+            instruments: if universe.is_empty() {
+                vec![Instrument::new(
+                    &file_cfg.simulation.symbol,
+                    file_cfg.simulation.isin.as_deref(),
+                    file_cfg.simulation.tick_size,
+                    file_cfg.simulation.lot_size,
+                )]
+            } else {
+                universe
+                    .iter()
+                    .map(|r| {
+                        Instrument::new(&r.symbol, None, r.tick_size, file_cfg.simulation.lot_size)
+                    })
+                    .collect()
+            },
+            instrument_index: 0,
+            pacing: file_cfg.simulation.pacing,
+            time_scale,
+            realtime: file_cfg.simulation.realtime,
+            pin_cores: file_cfg.simulation.pin_cores,
+            rt_priority: file_cfg.simulation.rt_priority,
+            warmup_seconds,
+            warmup_orders: file_cfg.simulation.warmup_orders,
             multicast_group,
             multicast_port: file_cfg.network.multicast_port,
+            multicast_options,
             wire_format: file_cfg.network.wire_format,
+            max_packet_bytes: file_cfg
+                .network
+                .batch
+                .then_some(file_cfg.network.max_packet_bytes),
+            legacy_market_price: file_cfg.network.legacy_market_price,
+            corrupt_prob: file_cfg.network.corrupt_prob,
+            epoch_timestamps: file_cfg.network.epoch_timestamps,
+            crc: file_cfg.network.crc,
+            wire_version: file_cfg.network.wire_version,
+            sender_queue: (file_cfg.network.sender_queue > 0)
+                .then_some(file_cfg.network.sender_queue),
+            sender: file_cfg.network.sender,
+            size_distribution: file_cfg.orders.size_distribution,
             size_mean_log: file_cfg.orders.size_mean_log,
             size_std_log: file_cfg.orders.size_std_log,
+            pareto_scale: file_cfg.orders.pareto_scale,
+            pareto_shape: file_cfg.orders.pareto_shape,
+            round_lots: file_cfg.orders.round_lots,
             ttl_min: file_cfg.orders.ttl_min,
             ttl_max: file_cfg.orders.ttl_max,
+            iceberg_prob: file_cfg.orders.iceberg_prob,
+            iceberg_min_mult: file_cfg.orders.iceberg_min_mult,
+            iceberg_max_mult: file_cfg.orders.iceberg_max_mult,
+            min_resting_time: file_cfg.orders.min_resting_time,
+            id_scheme: file_cfg.orders.id_scheme,
+            id_recycle_delay: file_cfg.orders.id_recycle_delay,
+            client_order_ids: file_cfg.orders.client_order_ids,
             shock_prob: file_cfg.shocks.probability,
             shock_min_pct: file_cfg.shocks.min_pct,
             shock_max_pct: file_cfg.shocks.max_pct,
+            systemic_shock_prob: file_cfg.shocks.systemic_probability,
             output_mode: file_cfg.output.mode,
+            log_format: file_cfg.output.format,
             log_file: file_cfg.output.log_file,
+            log_rotation: Rotation {
+                max_bytes: file_cfg
+                    .output
+                    .max_size_mb
+                    .map(|m| (m * 1024.0 * 1024.0) as u64),
+                max_files: file_cfg.output.max_files,
+                daily: file_cfg.output.rotate_daily,
+            },
             display_interval: file_cfg.output.display_interval,
             throughput_scale: file_cfg.simulation.throughput_scale,
+            target_rate: file_cfg.simulation.target_rate,
             seed,
+            duration: file_cfg.simulation.duration,
+            max_messages: file_cfg.simulation.max_messages,
+            stop_at_price: file_cfg.simulation.stop_at_price,
             control_enabled: file_cfg.control.enabled,
             control_bind: file_cfg.control.bind,
+            control_legacy_replies: file_cfg.control.legacy_replies,
+            crypto_feed: file_cfg.crypto_feed.enabled.then_some(file_cfg.crypto_feed),
+            options: file_cfg.options.enabled.then_some(file_cfg.options),
+            ouch,
+            bench: cli.bench,
+            bench_ticks: cli.bench_ticks,
+            drain_on_exit: cli.drain_on_exit,
+            drain_rate: cli.drain_rate,
+            report_path: cli.report.clone(),
+            checkpoint_path: cli.checkpoint.clone(),
+            checkpoint_interval: cli.checkpoint_interval,
+            resume,
+            csv_path: cli.csv_out.clone(),
+            pcap_path: cli.pcap_out.clone(),
+            parquet_path: cli.parquet_out.clone(),
+            bar_interval: file_cfg.feed.bars.then_some(file_cfg.feed.bar_interval),
+            publish_quotes: file_cfg.feed.publish_quotes,
+            depth_levels: file_cfg.feed.depth_levels,
+            depth_interval: file_cfg.feed.depth_interval,
+            book_view: file_cfg.feed.book_view,
+            participant_count: file_cfg.participants.count,
+            participant_first_id: file_cfg.participants.first_id,
+            participant_zipf_exponent: file_cfg.participants.zipf_exponent,
+            participant_weights: file_cfg.participants.weights,
+            agents: file_cfg.agents.enabled.then_some(file_cfg.agents),
+            events: file_cfg.events,
+            stops: file_cfg.stops,
+            matching: file_cfg.book.matching,
+            imbalance_sensitivity: file_cfg.book.imbalance_sensitivity,
+            self_match: file_cfg.book.self_match,
+            dark_fraction: file_cfg.book.dark_fraction,
+            ratios: file_cfg.ratios.enabled.then_some(file_cfg.ratios),
+            price_model: file_cfg.simulation.price_model,
+            jumps: file_cfg.jumps,
+            ou: file_cfg.ou,
+            garch: file_cfg.garch,
+            fundamental: file_cfg.fundamental,
+            regime_feedback: file_cfg.regime_feedback,
+            spreads: file_cfg.spreads,
+            news: file_cfg.news,
+            drought: file_cfg.drought,
+            squeeze: file_cfg.squeeze,
+            custom_scenario: file_cfg.custom_scenario,
+            seasonality: file_cfg.seasonality,
+            circuit_breaker: file_cfg.circuit_breaker,
+            price_bands: file_cfg.price_bands,
+            rejects: file_cfg.rejects,
+            session: file_cfg.session,
+            schedule,
+            venues,
+            venue: None,
+            universe,
+            threads: cli.threads.unwrap_or(1),
+            lead_lag,
+            basis: file_cfg.basis.enabled.then_some(file_cfg.basis),
         })
     }
+
+    /// The `index`th `--symbols-file` symbol, derived from this config of
+    /// the first: its price, tick size and ADV-weighted rates, on a seed of
+    /// its own.
+    pub fn symbol(&self, index: usize) -> Self {
+        let (first, row) = (&self.universe[0], &self.universe[index]);
+        let mut cfg = self.clone();
+        rescale(
+            &mut cfg.ou,
+            &mut cfg.throughput_scale,
+            &mut cfg.target_rate,
+            row.initial_price / first.initial_price,
+            row.adv_weight / first.adv_weight,
+        );
+        cfg.seed = self.seed.wrapping_add(index as u64);
+        cfg.initial_price = row.initial_price;
+        cfg.tick_size = row.tick_size;
+        cfg.instrument_index = index as u16;
+        cfg
+    }
+}
+
+/// Move a symbol's price process and rates onto another's: the OU mean and
+/// noise scale with the start price, the rates with the ADV weight.
+fn rescale(
+    ou: &mut OuConfig,
+    throughput_scale: &mut f64,
+    target_rate: &mut Option<f64>,
+    price_ratio: f64,
+    weight: f64,
+) {
+    if let Some(mean) = ou.mean.as_mut() {
+        *mean *= price_ratio;
+    }
+    ou.volatility *= price_ratio;
+    *throughput_scale *= weight;
+    if let Some(rate) = target_rate.as_mut() {
+        *rate *= weight;
+    }
+}
+
+/// Comma-separated CPU numbers, e.g. `2,3`.
+fn parse_cpu_list(s: &str) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+    s.split(',')
+        .map(|cpu| {
+            cpu.trim()
+                .parse()
+                .map_err(|_| format!("invalid CPU '{}' in '{}'", cpu, s).into())
+        })
+        .collect()
+}
+
+/// `name` or `name:seconds[,name:seconds...]`.
+fn parse_scenario_chain(s: &str) -> Result<Vec<ScenarioStep>, Box<dyn std::error::Error>> {
+    s.split(',')
+        .map(|part| {
+            let (name, duration) = match part.trim().split_once(':') {
+                Some((name, secs)) => {
+                    let d = secs.parse::<f64>().map_err(|_| {
+                        format!("invalid duration '{}' for scenario '{}'", secs, name)
+                    })?;
+                    (name, Some(d))
+                }
+                None => (part.trim(), None),
+            };
+            Ok(ScenarioStep {
+                scenario: parse_scenario(name)?,
+                duration,
+            })
+        })
+        .collect()
 }
 
-fn parse_scenario(s: &str) -> Result<Scenario, Box<dyn std::error::Error>> {
+pub fn parse_scenario(s: &str) -> Result<Scenario, Box<dyn std::error::Error>> {
     match s {
         "normal" => Ok(Scenario::Normal),
         "crash" => Ok(Scenario::Crash),
         "volatile" => Ok(Scenario::Volatile),
         "flash-crash" => Ok(Scenario::FlashCrash),
         "rally" => Ok(Scenario::Rally),
+        "stop-cascade" => Ok(Scenario::StopCascade),
+        "news-event" => Ok(Scenario::NewsEvent),
+        "drought" => Ok(Scenario::Drought),
+        "squeeze" => Ok(Scenario::Squeeze),
+        "custom" => Ok(Scenario::Custom),
         _ => Err(format!(
-            "unknown scenario '{}'. available: normal, crash, volatile, flash-crash, rally",
+            "unknown scenario '{}'. available: normal, crash, volatile, flash-crash, rally, stop-cascade, news-event, drought, squeeze, custom",
             s
         )
         .into()),
@@ -0,0 +1,101 @@
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use std::borrow::Cow;
+use std::fmt;
+
+/// Longest run of bytes overwritten by one garble.
+const MAX_GARBLE: usize = 16;
+
+/// How a datagram was damaged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corruption {
+    /// Cut short at a random length (possibly empty).
+    Truncate,
+    /// One to three random bits inverted.
+    BitFlip,
+    /// A run of up to 16 bytes overwritten with random bytes.
+    Garble,
+}
+
+impl fmt::Display for Corruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Corruption::Truncate => write!(f, "truncate"),
+            Corruption::BitFlip => write!(f, "bitflip"),
+            Corruption::Garble => write!(f, "garble"),
+        }
+    }
+}
+
+/// One damaged datagram, for the event log.
+#[derive(Debug, Clone)]
+pub struct Corrupted {
+    /// 1-based sequence number of the datagram among all sent this run
+    /// (also its frame number in a `--pcap-out` capture).
+    pub datagram: u64,
+    pub kind: Corruption,
+    pub len: usize,
+    pub sent_len: usize,
+}
+
+/// Damages a random fraction of outgoing datagrams so consumers can be
+/// checked for graceful rejection of bad input. Has its own RNG so turning it
+/// on does not change the generated order flow.
+pub struct Corruptor {
+    prob: f64,
+    rng: StdRng,
+    log: Vec<Corrupted>,
+}
+
+impl Corruptor {
+    pub fn new(prob: f64, seed: u64) -> Self {
+        Self {
+            prob,
+            rng: StdRng::seed_from_u64(seed ^ 0xC0DE_BAD5),
+            log: Vec::new(),
+        }
+    }
+
+    pub fn set_prob(&mut self, prob: f64) {
+        self.prob = prob;
+    }
+
+    /// The datagram to send in place of `packet`, damaged with probability `prob`.
+    pub fn apply<'a>(&mut self, datagram: u64, packet: &'a [u8]) -> Cow<'a, [u8]> {
+        if packet.is_empty() || self.rng.gen::<f64>() >= self.prob {
+            return Cow::Borrowed(packet);
+        }
+        let mut out = packet.to_vec();
+        let kind = match self.rng.gen_range(0..3) {
+            0 => Corruption::Truncate,
+            1 => Corruption::BitFlip,
+            _ => Corruption::Garble,
+        };
+        match kind {
+            Corruption::Truncate => out.truncate(self.rng.gen_range(0..packet.len())),
+            Corruption::BitFlip => {
+                for _ in 0..self.rng.gen_range(1..=3) {
+                    let bit = self.rng.gen_range(0..packet.len() * 8);
+                    out[bit / 8] ^= 1 << (bit % 8);
+                }
+            }
+            Corruption::Garble => {
+                let start = self.rng.gen_range(0..packet.len());
+                let len = self.rng.gen_range(1..=MAX_GARBLE.min(packet.len() - start));
+                self.rng.fill_bytes(&mut out[start..start + len]);
+            }
+        }
+        self.log.push(Corrupted {
+            datagram,
+            kind,
+            len: packet.len(),
+            sent_len: out.len(),
+        });
+        Cow::Owned(out)
+    }
+
+    /// Datagrams damaged since the last call.
+    pub fn take_log(&mut self) -> Vec<Corrupted> {
+        std::mem::take(&mut self.log)
+    }
+}
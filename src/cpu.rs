@@ -0,0 +1,58 @@
+//! Thread placement for latency-sensitive runs (`pin_cores`, `rt_priority`):
+//! pinning a thread to one CPU and moving it to the SCHED_FIFO real-time
+//! class. Linux only; elsewhere both report `Unsupported`.
+
+use std::io;
+
+/// Pin the calling thread to `core`. Threads it spawns afterwards inherit
+/// the mask.
+#[cfg(target_os = "linux")]
+pub fn pin_current(core: usize) -> io::Result<()> {
+    if core >= libc::CPU_SETSIZE as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("CPU {} is out of range", core),
+        ));
+    }
+    // SAFETY: cpu_set_t is a plain bitmask; all-zero is the empty set, and
+    // `core` was checked against its size.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(core, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Move the calling thread to SCHED_FIFO at `priority` (1-99). Without
+/// CAP_SYS_NICE or an RLIMIT_RTPRIO allowance this fails with
+/// `PermissionDenied`.
+#[cfg(target_os = "linux")]
+pub fn set_fifo(priority: i32) -> io::Result<()> {
+    let param = libc::sched_param {
+        sched_priority: priority,
+    };
+    // SAFETY: `param` outlives the call; pid 0 is the calling thread.
+    if unsafe { libc::sched_setscheduler(0, libc::SCHED_FIFO, &param) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin_current(_core: usize) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "CPU pinning is only supported on Linux",
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_fifo(_priority: i32) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "real-time scheduling is only supported on Linux",
+    ))
+}
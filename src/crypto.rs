@@ -0,0 +1,423 @@
+//! Binance-style market data (`[crypto_feed]`): the `<symbol>@depth` diff
+//! stream and `<symbol>@aggTrade` stream over WebSocket, plus the
+//! `/api/v3/depth` REST snapshot that depth consumers sync against, all
+//! derived from the simulated book.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{self, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tungstenite::Message as WsMessage;
+
+use crate::book::Book;
+use crate::jsonlog::Record;
+use crate::order::Side;
+
+/// Messages queued per client before it is dropped as too slow.
+const CLIENT_QUEUE: usize = 4096;
+/// Largest HTTP request head read before the upgrade.
+const MAX_REQUEST: usize = 8192;
+/// `limit` of a depth snapshot request when none is given, and its maximum.
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 5000;
+
+/// Depth as last published on the diff stream; the REST snapshot serves it.
+#[derive(Default)]
+struct Published {
+    last_update_id: u64,
+    bids: BTreeMap<i64, u64>,
+    asks: BTreeMap<i64, u64>,
+}
+
+struct Client {
+    depth: bool,
+    trades: bool,
+    /// Combined stream: wrap each event as `{"stream":..,"data":..}`.
+    combined: bool,
+    tx: SyncSender<String>,
+}
+
+/// State shared with the listener thread.
+struct Shared {
+    /// Lowercase stream name prefix, e.g. `simusdt`.
+    stream: String,
+    symbol: String,
+    tick_size: f64,
+    decimals: usize,
+    published: Mutex<Published>,
+    clients: Mutex<Vec<Client>>,
+}
+
+impl Shared {
+    fn price(&self, ticks: i64) -> String {
+        format!("{:.*}", self.decimals, ticks as f64 * self.tick_size)
+    }
+
+    /// `[["price","qty"],...]` in the order given.
+    fn levels<'a>(&self, levels: impl Iterator<Item = (&'a i64, &'a u64)>) -> String {
+        let body = levels
+            .map(|(&t, q)| format!("[\"{}\",\"{}\"]", self.price(t), q))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("[{}]", body)
+    }
+}
+
+/// Consecutive fills of one taker at one price, published as one aggTrade.
+struct AggTrade {
+    /// `None` for an auction uncross, which never aggregates.
+    taker: Option<u64>,
+    buyer_maker: bool,
+    ticks: i64,
+    qty: u64,
+    first_id: u64,
+    last_id: u64,
+    time: f64,
+}
+
+pub struct CryptoFeed {
+    shared: Arc<Shared>,
+    interval: f64,
+    next_depth_at: f64,
+    /// Wall-clock milliseconds at sim time 0.
+    epoch_ms: u64,
+    next_trade_id: u64,
+    next_agg_id: u64,
+    pending: Option<AggTrade>,
+    /// Publish nothing while set (warmup, replaying a checkpoint).
+    muted: bool,
+}
+
+impl CryptoFeed {
+    /// Bind `bind` and serve clients from a background thread.
+    pub fn start(
+        bind: &str,
+        symbol: &str,
+        tick_size: f64,
+        interval: f64,
+    ) -> Result<Self, Box<dyn Error>> {
+        let listener = TcpListener::bind(bind)
+            .map_err(|e| format!("crypto feed could not bind {}: {}", bind, e))?;
+        let shared = Arc::new(Shared {
+            stream: symbol.to_ascii_lowercase(),
+            symbol: symbol.to_ascii_uppercase(),
+            tick_size,
+            decimals: decimals(tick_size),
+            published: Mutex::new(Published::default()),
+            clients: Mutex::new(Vec::new()),
+        });
+        let accept = Arc::clone(&shared);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let shared = Arc::clone(&accept);
+                thread::spawn(move || {
+                    let _ = serve(stream, &shared);
+                });
+            }
+        });
+        let epoch_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        Ok(Self {
+            shared,
+            interval,
+            next_depth_at: 0.0,
+            epoch_ms,
+            next_trade_id: 1,
+            next_agg_id: 1,
+            pending: None,
+            muted: false,
+        })
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    /// Record a fill of `taker`. Consecutive fills of the same taker at the
+    /// same price are published as one aggTrade.
+    pub fn fill(&mut self, taker: u64, aggressor: Side, price: f64, size: u32, time: f64) {
+        let buyer_maker = aggressor == Side::Sell;
+        self.record(Some(taker), buyer_maker, price, size as u64, time);
+    }
+
+    /// Record an auction uncross as one aggTrade of its whole volume.
+    pub fn uncross(&mut self, price: f64, volume: u64, time: f64) {
+        self.record(None, false, price, volume, time);
+    }
+
+    fn record(&mut self, taker: Option<u64>, buyer_maker: bool, price: f64, qty: u64, time: f64) {
+        if self.muted {
+            return;
+        }
+        let ticks = (price / self.shared.tick_size).round() as i64;
+        let id = self.next_trade_id;
+        self.next_trade_id += 1;
+        if let Some(p) = self.pending.as_mut() {
+            if taker.is_some() && p.taker == taker && p.ticks == ticks {
+                p.qty += qty;
+                p.last_id = id;
+                return;
+            }
+        }
+        self.flush_trade();
+        self.pending = Some(AggTrade {
+            taker,
+            buyer_maker,
+            ticks,
+            qty,
+            first_id: id,
+            last_id: id,
+            time,
+        });
+    }
+
+    fn flush_trade(&mut self) {
+        let Some(t) = self.pending.take() else {
+            return;
+        };
+        let ms = self.ms(t.time);
+        let json = Record::object()
+            .str("e", "aggTrade")
+            .count("E", ms)
+            .str("s", &self.shared.symbol)
+            .count("a", self.next_agg_id)
+            .str("p", self.shared.price(t.ticks))
+            .str("q", t.qty)
+            .count("f", t.first_id)
+            .count("l", t.last_id)
+            .count("T", ms)
+            .bool("m", t.buyer_maker)
+            .bool("M", true)
+            .to_json();
+        self.next_agg_id += 1;
+        self.broadcast("aggTrade", json);
+    }
+
+    /// End of tick: publish the pending aggTrade and, every `interval`, a
+    /// depthUpdate of the levels changed since the last one.
+    pub fn tick(&mut self, book: &Book, time: f64) {
+        if self.muted {
+            return;
+        }
+        self.flush_trade();
+        // (epsilon absorbs float drift in the accumulated sim clock)
+        if time + 1e-9 < self.next_depth_at {
+            return;
+        }
+        self.next_depth_at = (self.next_depth_at + self.interval).max(time);
+
+        let bids = side_levels(book, Side::Buy, self.shared.tick_size);
+        let asks = side_levels(book, Side::Sell, self.shared.tick_size);
+        let mut published = self.shared.published.lock().unwrap();
+        let bid_changes = diff(&mut published.bids, bids);
+        let ask_changes = diff(&mut published.asks, asks);
+        let changed = (bid_changes.len() + ask_changes.len()) as u64;
+        if changed == 0 {
+            return;
+        }
+        let first = published.last_update_id + 1;
+        published.last_update_id += changed;
+        let json = Record::object()
+            .str("e", "depthUpdate")
+            .count("E", self.ms(time))
+            .str("s", &self.shared.symbol)
+            .count("U", first)
+            .count("u", published.last_update_id)
+            .raw("b", self.shared.levels(bid_changes.iter().rev()))
+            .raw("a", self.shared.levels(ask_changes.iter()))
+            .to_json();
+        drop(published);
+        self.broadcast("depth", json);
+    }
+
+    fn ms(&self, time: f64) -> u64 {
+        self.epoch_ms + (time.max(0.0) * 1000.0).round() as u64
+    }
+
+    /// Queue `json` for every client subscribed to `kind`, dropping clients
+    /// that have gone away or fallen too far behind.
+    fn broadcast(&self, kind: &str, json: String) {
+        let mut clients = self.shared.clients.lock().unwrap();
+        clients.retain(|c| {
+            let wanted = match kind {
+                "depth" => c.depth,
+                _ => c.trades,
+            };
+            if !wanted {
+                return true;
+            }
+            let msg = if c.combined {
+                format!(
+                    "{{\"stream\":\"{}@{}\",\"data\":{}}}",
+                    self.shared.stream, kind, json
+                )
+            } else {
+                json.clone()
+            };
+            match c.tx.try_send(msg) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => false,
+            }
+        });
+    }
+}
+
+/// Decimal places of `tick_size`, so prices print like the exchange's.
+fn decimals(tick_size: f64) -> usize {
+    (0..=8)
+        .find(|&d| {
+            let scaled = tick_size * 10f64.powi(d as i32);
+            (scaled - scaled.round()).abs() < 1e-9
+        })
+        .unwrap_or(8)
+}
+
+fn side_levels(book: &Book, side: Side, tick_size: f64) -> BTreeMap<i64, u64> {
+    book.top(side, usize::MAX)
+        .into_iter()
+        .map(|(p, l)| ((p / tick_size).round() as i64, l.size))
+        .collect()
+}
+
+/// Replace `published` with `current`, returning the levels that changed;
+/// removed levels have quantity 0.
+fn diff(published: &mut BTreeMap<i64, u64>, current: BTreeMap<i64, u64>) -> BTreeMap<i64, u64> {
+    let mut changes: BTreeMap<i64, u64> = current
+        .iter()
+        .filter(|(t, q)| published.get(t) != Some(q))
+        .map(|(&t, &q)| (t, q))
+        .collect();
+    for t in published.keys() {
+        if !current.contains_key(t) {
+            changes.insert(*t, 0);
+        }
+    }
+    *published = current;
+    changes
+}
+
+/// Answer one connection: a WebSocket upgrade for the streams named in its
+/// path, or a REST depth snapshot.
+fn serve(mut stream: TcpStream, shared: &Shared) -> Result<(), Box<dyn Error>> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let head = peek_head(&stream)?;
+    let path = head.split_whitespace().nth(1).unwrap_or("/").to_string();
+    let upgrade = head.to_ascii_lowercase().contains("upgrade: websocket");
+    if !upgrade {
+        let (status, body) = rest(&path, shared);
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )?;
+        return Ok(());
+    }
+
+    let Some((depth, trades, combined)) = subscription(&path, &shared.stream) else {
+        write!(
+            stream,
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        )?;
+        return Ok(());
+    };
+    stream.set_read_timeout(None)?;
+    let mut ws = tungstenite::accept(stream)?;
+    let (tx, rx) = mpsc::sync_channel(CLIENT_QUEUE);
+    shared.clients.lock().unwrap().push(Client {
+        depth,
+        trades,
+        combined,
+        tx,
+    });
+    for msg in rx {
+        ws.send(WsMessage::Text(msg))?;
+    }
+    Ok(())
+}
+
+/// The request head, left unread for the WebSocket handshake.
+fn peek_head(stream: &TcpStream) -> io::Result<String> {
+    let mut buf = vec![0u8; MAX_REQUEST];
+    let mut seen = 0;
+    loop {
+        let n = stream.peek(&mut buf)?;
+        let head = &buf[..n];
+        if n == 0 || n == buf.len() || head.windows(4).any(|w| w == b"\r\n\r\n") {
+            return Ok(String::from_utf8_lossy(head).into_owned());
+        }
+        if n == seen {
+            thread::sleep(Duration::from_millis(1));
+        }
+        seen = n;
+    }
+}
+
+/// Streams requested by `/ws/<name>[/<name>...]` (raw events) or
+/// `/stream?streams=<name>/<name>` (combined), as (depth, trades, combined).
+/// A bare `/ws` subscribes to both.
+fn subscription(path: &str, stream: &str) -> Option<(bool, bool, bool)> {
+    let (names, combined) = if let Some(rest) = path.strip_prefix("/stream?streams=") {
+        (rest, true)
+    } else if path == "/ws" || path == "/ws/" {
+        return Some((true, true, false));
+    } else {
+        (path.strip_prefix("/ws/")?, false)
+    };
+    let (mut depth, mut trades) = (false, false);
+    for name in names.split('/') {
+        let (symbol, kind) = name.split_once('@')?;
+        if !symbol.eq_ignore_ascii_case(stream) {
+            return None;
+        }
+        match kind {
+            "depth" | "depth@100ms" | "depth@1000ms" => depth = true,
+            "aggTrade" => trades = true,
+            _ => return None,
+        }
+    }
+    Some((depth, trades, combined))
+}
+
+/// `GET /api/v3/depth?symbol=<SYMBOL>[&limit=<N>]`: status line and body.
+fn rest(path: &str, shared: &Shared) -> (&'static str, String) {
+    let (route, query) = path.split_once('?').unwrap_or((path, ""));
+    if route != "/api/v3/depth" {
+        return ("404 Not Found", error_body(-1000, "Unknown endpoint."));
+    }
+    let param = |key: &str| {
+        query
+            .split('&')
+            .filter_map(|kv| kv.split_once('='))
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v)
+    };
+    if !param("symbol").is_some_and(|s| s.eq_ignore_ascii_case(&shared.symbol)) {
+        return ("400 Bad Request", error_body(-1121, "Invalid symbol."));
+    }
+    let limit = match param("limit").map(str::parse::<usize>) {
+        None => DEFAULT_LIMIT,
+        Some(Ok(n)) if (1..=MAX_LIMIT).contains(&n) => n,
+        Some(_) => return ("400 Bad Request", error_body(-1100, "Illegal limit.")),
+    };
+    let published = shared.published.lock().unwrap();
+    let bids = shared.levels(published.bids.iter().rev().take(limit));
+    let asks = shared.levels(published.asks.iter().take(limit));
+    let body = Record::object()
+        .count("lastUpdateId", published.last_update_id)
+        .raw("bids", bids)
+        .raw("asks", asks)
+        .to_json();
+    ("200 OK", body)
+}
+
+fn error_body(code: i64, msg: &str) -> String {
+    format!("{{\"code\":{},\"msg\":\"{}\"}}", code, msg)
+}
@@ -1,25 +1,267 @@
-use rand::seq::SliceRandom;
-use rand::{Rng, SeedableRng};
+mod control;
+mod flow;
+mod lifecycle;
+mod output;
+mod universe;
+
 use rand::rngs::StdRng;
-use rand_distr::{Exp, LogNormal, Poisson, Uniform};
-use std::collections::HashMap;
-use std::fs::OpenOptions;
-use std::io::Write;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_distr::{Exp, Poisson, StandardNormal, Uniform};
+use std::collections::VecDeque;
 use std::net::UdpSocket;
-use std::sync::mpsc::{self, Receiver};
-use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::agents::AgentLayer;
+use crate::alloc;
+use crate::auction::{self, Imbalance, Session, SessionEvent, UncrossFill};
+use crate::bands::{ErrorInjector, PriceBands, Reject, RejectReason};
+use crate::bars::BarAggregator;
+use crate::book::{Book, Depth, Fill, Quote, SelfMatchMode};
+use crate::checkpoint::{LoggedCommand, LoggedReload};
+use crate::config::{AppConfig, BookView, Cli, RatioConfig, SenderBackend};
+use crate::corrupt::Corruptor;
+use crate::cpu;
+use crate::crypto::CryptoFeed;
+use crate::events::{FatFinger, QuoteStuffer};
+use crate::export::CsvExport;
+#[cfg(feature = "parquet")]
+use crate::export::ParquetExport;
+use crate::ids::{IdScheme, OrderIds};
+use crate::jsonlog::Record;
+use crate::multicast::{MulticastOptions, MulticastSender};
+use crate::options::OptionChain;
+use crate::order::{DarkTrade, MassCancel, Order, OrderType, Side};
+use crate::ouch::OuchEcho;
+use crate::pacing::Pacer;
+use crate::participants::ParticipantPool;
+use crate::pcap::PcapWriter;
+use crate::price::{self, Basis, Fundamental, PriceModel, PriceModelKind};
+use crate::refdata::SymbolDirectory;
+use crate::regime::{self, Regime, RegimeFeedback, RegimeParams, RegimeState, SpreadUnits};
+use crate::report::RunReport;
+use crate::rngs::RngStreams;
+use crate::scenario::{
+    Multipliers, RegimeOverrides, Scenario, ScenarioConfig, ScenarioStep, SqueezeConfig, Timeline,
+    TimelineEvent,
+};
+use crate::seasonality::Seasonality;
+use crate::sizes::{SizeDist, SizeDistributionKind};
+use crate::stops::StopBook;
+use crate::symbols;
+use crate::timing::LatencyHistogram;
+use crate::venue::{
+    Admin, CircuitBreaker, ReferencePrice, SessionStart, Severity, TradingState, VenueState,
+    ADMIN_CORRUPTION, ADMIN_SCENARIO,
+};
+use crate::venues;
+use crate::wire;
+
+use control::{spawn_control_listener, ControlRequest, RuntimeTunables};
+use output::Output;
+pub use universe::simulate_universe;
+
+/// Price at which a market order on `side` is assumed to execute: the touch
+/// implied by the current regime's half-spread.
+fn touch_price(mid: f64, half_spread: f64, side: Side, tick_size: f64) -> f64 {
+    let raw = match side {
+        Side::Buy => mid + half_spread,
+        Side::Sell => mid - half_spread,
+    };
+    ((raw / tick_size).round() * tick_size).max(tick_size)
+}
+
+/// Route a marketable order. With matching enabled it executes against the
+/// book, publishing each TRADE, SELF_MATCH and iceberg refresh; otherwise it
+/// is assumed to fill at `touch`: a market order in full, a limit order up
+/// to the displayed quantity at or through its price. Fills feed the bar aggregator and crypto
+/// feed either way. Returns the quantity the order lost: filled, plus any
+/// decremented by self-match prevention.
+#[allow(clippy::too_many_arguments)]
+fn take_liquidity(
+    order: &Order,
+    matching: bool,
+    self_match: SelfMatchMode,
+    touch: f64,
+    time: f64,
+    book: &mut Book,
+    sender: &mut MulticastSender,
+    bars: &mut Option<BarAggregator>,
+    crypto: &mut Option<CryptoFeed>,
+    stats: &mut TickStats,
+) -> u32 {
+    if !matching {
+        let size = match order.order_type {
+            OrderType::Market => order.size,
+            _ => book.reachable(order).min(order.size as u64) as u32,
+        };
+        if size == 0 {
+            return 0;
+        }
+        stats.trades += 1;
+        if let Some(bars) = bars.as_mut() {
+            bars.record(touch, size);
+        }
+        if let Some(crypto) = crypto.as_mut() {
+            crypto.fill(order.id, order.side, touch, size, time);
+        }
+        return size;
+    }
+    let mut filled = 0;
+    for exec in book.execute(order, time, self_match) {
+        match exec.fill {
+            Fill::Trade(t) => {
+                filled += t.size;
+                let _ = sender.send_trade(&t);
+                stats.trades += 1;
+                if let Some(bars) = bars.as_mut() {
+                    bars.record(t.price, t.size);
+                }
+                if let Some(crypto) = crypto.as_mut() {
+                    crypto.fill(t.taker_id, t.aggressor, t.price, t.size, time);
+                }
+            }
+            Fill::SelfMatch(sm) => {
+                if sm.mode == SelfMatchMode::Decrement {
+                    filled += sm.size;
+                }
+                let _ = sender.send_self_match(&sm);
+                stats.self_matches += 1;
+            }
+        }
+        stats.messages_sent += 1;
+        if let Some(refreshed) = exec.replenished {
+            let _ = sender.send_order(&refreshed);
+            stats.messages_sent += 1;
+        }
+    }
+    filled
+}
+
+/// Entry checks for an order about to be published: a simulated erroneous
+/// copy (`[errors]`) or a price outside the LULD band is published as a
+/// REJECT instead. Returns whether `order` passed and may go out.
+#[allow(clippy::too_many_arguments)]
+fn admit(
+    order: &Order,
+    errors: &ErrorInjector,
+    band_limits: Option<(f64, f64)>,
+    mid: f64,
+    time: f64,
+    rng: &mut StdRng,
+    sender: &mut MulticastSender,
+    stats: &mut TickStats,
+) -> bool {
+    let reject = errors.inject(order, mid, time, rng).or_else(|| {
+        // Priced orders outside the LULD band are rejected at entry.
+        let (lower, upper) = band_limits?;
+        (order.is_resting() && !(lower..=upper).contains(&order.price)).then(|| Reject {
+            order: order.clone(),
+            reason: RejectReason::PriceBand,
+            lower,
+            upper,
+            time,
+        })
+    });
+    let Some(reject) = reject else {
+        return true;
+    };
+    let _ = sender.send(&reject);
+    stats.messages_sent += 1;
+    stats.rejects += 1;
+    false
+}
+
+/// With matching enabled, trade the part of a limit or iceberg order priced
+/// through the opposite touch before it rests, refreshing an iceberg's
+/// display from its reserve as it is consumed. Returns what is left to
+/// insert, or `None` once nothing is. A remainder whose reserve changed is
+/// republished so the feed carries its new total.
+#[allow(clippy::too_many_arguments)]
+fn cross_resting(
+    order: &Order,
+    self_match: SelfMatchMode,
+    time: f64,
+    book: &mut Book,
+    sender: &mut MulticastSender,
+    bars: &mut Option<BarAggregator>,
+    crypto: &mut Option<CryptoFeed>,
+    stats: &mut TickStats,
+) -> Option<Order> {
+    if !book.marketable(order) {
+        return Some(order.clone());
+    }
+    let mut rest = order.clone();
+    while book.marketable(&rest) {
+        let filled = take_liquidity(
+            &rest, true, self_match, rest.price, time, book, sender, bars, crypto, stats,
+        );
+        rest.size -= filled.min(rest.size);
+        if rest.size == 0 {
+            if rest.reserve == 0 {
+                return None;
+            }
+            let slice = order.size.min(rest.reserve);
+            rest.size = slice;
+            rest.reserve -= slice;
+        }
+    }
+    // An iceberg rests showing a full slice while its reserve lasts.
+    let top_up = (order.size - rest.size).min(rest.reserve);
+    rest.size += top_up;
+    rest.reserve -= top_up;
+    if rest.reserve != order.reserve {
+        let _ = sender.send_order(&rest);
+        stats.messages_sent += 1;
+    }
+    Some(rest)
+}
+
+/// The item of `items`, which has `n` of them, that `SliceRandom::choose`
+/// would pick from them collected, making the same draw from `rng`; picks
+/// from the book without collecting its order IDs.
+fn choose_nth<T>(mut items: impl Iterator<Item = T>, n: usize, rng: &mut impl Rng) -> Option<T> {
+    if n == 0 {
+        return None;
+    }
+    items.nth(rng.gen_range(0..n as u32) as usize)
+}
 
-use crate::config::{AppConfig, FileConfig, OutputMode};
-use crate::multicast::MulticastSender;
-use crate::order::{Order, OrderType, Side};
-use crate::regime::{self, Regime, RegimeState};
-use crate::scenario::{Scenario, ScenarioConfig};
+/// Headline counters attributed to the regime active when they were generated.
+#[derive(Debug, Default, Clone, Copy)]
+struct RegimeCounts {
+    orders: u64,
+    cancels: u64,
+    trades: u64,
+    messages: u64,
+}
+
+impl RegimeCounts {
+    /// `{"CALM": {"orders": .., ...}, ...}` over all regimes.
+    fn json_by_regime(counts: &[RegimeCounts; 5]) -> String {
+        let body = Regime::ALL
+            .iter()
+            .map(|r| {
+                let c = &counts[r.index()];
+                format!(
+                    "\"{}\":{{\"orders\":{},\"cancels\":{},\"trades\":{},\"messages\":{}}}",
+                    r, c.orders, c.cancels, c.trades, c.messages
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("{{{}}}", body)
+    }
 
-/// GBM dt: tick interval expressed in years.
-fn dt_years(tick_interval: f64) -> f64 {
-    1.0 / (252.0 * 6.5 * 3600.0) * tick_interval / 0.1 * 0.1
+    fn add(&mut self, other: &RegimeCounts) {
+        self.orders += other.orders;
+        self.cancels += other.cancels;
+        self.trades += other.trades;
+        self.messages += other.messages;
+    }
 }
 
 /// Per-interval statistics for display/logging.
@@ -28,7 +270,23 @@ struct TickStats {
     markets_generated: u64,
     cancels_expired: u64,
     cancels_regime: u64,
+    cancels_agent: u64,
+    cancels_mass: u64,
+    /// Cancels of everything left resting at exit (`drain_on_exit`).
+    cancels_drain: u64,
+    stuffing_pairs: u64,
+    stops_placed: u64,
+    stops_triggered: u64,
+    trades: u64,
+    rejects: u64,
+    self_matches: u64,
+    dark_trades: u64,
+    /// Deepest the sender thread's queue got (`sender_queue`).
+    queue_peak: u64,
+    /// Datagrams dropped on a full sender queue.
+    queue_drops: u64,
     messages_sent: u64,
+    by_regime: [RegimeCounts; 5],
 }
 
 impl TickStats {
@@ -38,7 +296,20 @@ impl TickStats {
             markets_generated: 0,
             cancels_expired: 0,
             cancels_regime: 0,
+            cancels_agent: 0,
+            cancels_mass: 0,
+            cancels_drain: 0,
+            stuffing_pairs: 0,
+            stops_placed: 0,
+            stops_triggered: 0,
+            trades: 0,
+            rejects: 0,
+            self_matches: 0,
+            dark_trades: 0,
+            queue_peak: 0,
+            queue_drops: 0,
             messages_sent: 0,
+            by_regime: [RegimeCounts::default(); 5],
         }
     }
 
@@ -47,7 +318,40 @@ impl TickStats {
         self.markets_generated = 0;
         self.cancels_expired = 0;
         self.cancels_regime = 0;
+        self.cancels_agent = 0;
+        self.cancels_mass = 0;
+        self.cancels_drain = 0;
+        self.stuffing_pairs = 0;
+        self.stops_placed = 0;
+        self.stops_triggered = 0;
+        self.trades = 0;
+        self.rejects = 0;
+        self.self_matches = 0;
+        self.dark_trades = 0;
+        self.queue_peak = 0;
+        self.queue_drops = 0;
         self.messages_sent = 0;
+        self.by_regime = [RegimeCounts::default(); 5];
+    }
+
+    fn counts(&self) -> RegimeCounts {
+        RegimeCounts {
+            orders: self.total_orders(),
+            cancels: self.total_cancels(),
+            trades: self.trades,
+            messages: self.messages_sent,
+        }
+    }
+
+    /// Credit everything counted since `start` to `regime`.
+    fn attribute(&mut self, regime: Regime, start: &RegimeCounts) {
+        let now = self.counts();
+        self.by_regime[regime.index()].add(&RegimeCounts {
+            orders: now.orders - start.orders,
+            cancels: now.cancels - start.cancels,
+            trades: now.trades - start.trades,
+            messages: now.messages - start.messages,
+        });
     }
 
     fn total_orders(&self) -> u64 {
@@ -55,18 +359,276 @@ impl TickStats {
     }
 
     fn total_cancels(&self) -> u64 {
-        self.cancels_expired + self.cancels_regime
+        self.cancels_expired
+            + self.cancels_regime
+            + self.cancels_agent
+            + self.cancels_mass
+            + self.cancels_drain
     }
 }
 
-const BOX_W: usize = 50;
+/// Sampling distributions for one regime at a given throughput scale.
+/// `None` means the per-tick arrival rate is zero.
+#[derive(Clone, Copy)]
+struct RegimeDists {
+    limits: Option<Poisson<f64>>,
+    markets: Option<Poisson<f64>>,
+    cancels: Option<Poisson<f64>>,
+    offset: Exp<f64>,
+}
 
-fn box_line(content: &str) -> String {
-    let pad = if content.len() < BOX_W {
-        BOX_W - content.len()
+impl RegimeDists {
+    fn new(p: &RegimeParams, throughput_scale: f64, dt_seconds: f64) -> Self {
+        Self {
+            limits: per_tick_poisson(p.limit_rate * throughput_scale * dt_seconds),
+            markets: per_tick_poisson(p.market_rate * throughput_scale * dt_seconds),
+            cancels: per_tick_poisson(p.cancel_rate * throughput_scale * dt_seconds),
+            offset: Exp::new(p.offset_lambda).unwrap(),
+        }
+    }
+}
+
+fn per_tick_poisson(lambda: f64) -> Option<Poisson<f64>> {
+    if lambda > 0.0 {
+        Poisson::new(lambda).ok()
+    } else {
+        None
+    }
+}
+
+fn sample_count(dist: &Option<Poisson<f64>>, rng: &mut impl Rng) -> u64 {
+    match dist {
+        Some(d) => rng.sample(d) as u64,
+        None => 0,
+    }
+}
+
+/// Per-regime distribution cache. Rebuilt only when the throughput scale changes,
+/// so the hot loop doesn't construct distributions every tick.
+struct DistCache {
+    params: [RegimeParams; 5],
+    throughput_scale: f64,
+    dt_seconds: f64,
+    table: Vec<RegimeDists>,
+}
+
+impl DistCache {
+    fn new(params: [RegimeParams; 5], throughput_scale: f64, dt_seconds: f64) -> Self {
+        let mut cache = Self {
+            params,
+            throughput_scale,
+            dt_seconds,
+            table: Vec::with_capacity(Regime::ALL.len()),
+        };
+        cache.rebuild();
+        cache
+    }
+
+    fn rebuild(&mut self) {
+        self.table = Regime::ALL
+            .iter()
+            .map(|&r| {
+                RegimeDists::new(
+                    &self.params[r.index()],
+                    self.throughput_scale,
+                    self.dt_seconds,
+                )
+            })
+            .collect();
+    }
+
+    fn set_params(&mut self, params: [RegimeParams; 5]) {
+        self.params = params;
+        self.rebuild();
+    }
+
+    fn get(&mut self, regime: Regime, throughput_scale: f64) -> &RegimeDists {
+        if throughput_scale != self.throughput_scale {
+            self.throughput_scale = throughput_scale;
+            self.rebuild();
+        }
+        &self.table[regime.index()]
+    }
+}
+
+/// The running scenario and its progress. Every step of a scenario chain, and
+/// every `scenario` control command, starts a fresh one; scenario times are
+/// measured from `started_at`.
+struct ActiveScenario<'a> {
+    scenario: Scenario,
+    cfg: ScenarioConfig,
+    started_at: f64,
+    ends_at: Option<f64>,
+    timeline: Timeline,
+    dists: DistCache,
+    stop_arrivals: Option<Poisson<f64>>,
+    forced_event_fired: bool,
+    next_event: usize,
+    squeeze: Option<&'a SqueezeConfig>,
+    next_squeeze_shock: f64,
+    /// Buy market orders owed by shorts covering after an up-shock.
+    cover_burst: u64,
+}
+
+impl<'a> ActiveScenario<'a> {
+    fn new(
+        step: &ScenarioStep,
+        app: &'a AppConfig,
+        started_at: f64,
+        overrides: &RegimeOverrides,
+    ) -> Self {
+        let mut cfg = ScenarioConfig::from_scenario(step.scenario, app);
+        for r in Regime::ALL {
+            cfg.regime_params[r.index()] = overrides.get(r).apply(cfg.params(r));
+        }
+        let dt_seconds = app.tick_interval;
+        Self {
+            scenario: step.scenario,
+            timeline: Timeline::new(cfg.windows.clone()),
+            dists: DistCache::new(cfg.regime_params.clone(), app.throughput_scale, dt_seconds),
+            stop_arrivals: per_tick_poisson(app.stops.rate.max(cfg.stop_rate) * dt_seconds),
+            cfg,
+            started_at,
+            ends_at: step.duration.map(|d| started_at + d),
+            forced_event_fired: false,
+            next_event: 0,
+            squeeze: (step.scenario == Scenario::Squeeze).then_some(&app.squeeze),
+            next_squeeze_shock: app.squeeze.at + app.squeeze.shock_interval,
+            cover_burst: 0,
+        }
+    }
+
+    /// Whether the scenario's duration is up at sim time `now`.
+    /// (epsilon absorbs float drift in the accumulated sim clock)
+    fn finished(&self, now: f64) -> bool {
+        self.ends_at.is_some_and(|t| now + 1e-9 >= t)
+    }
+
+    /// Squeeze settings while the squeeze is under way at sim time `now`.
+    fn squeezing(&self, now: f64) -> Option<&'a SqueezeConfig> {
+        self.squeeze
+            .filter(|sq| self.forced_event_fired && now - self.started_at < sq.at + sq.duration)
+    }
+}
+
+/// Move `mid` by `shock`, a signed fraction of price, and from CALM or
+/// RECOVERY force the regime into CRASH (down) or RALLY (up). During a
+/// squeeze an up-shock also sends shorts covering. Random shocks and the
+/// `shock` control command both come through here; returns the regime the
+/// shock forced, if any.
+#[allow(clippy::too_many_arguments)]
+fn apply_shock(
+    shock: f64,
+    mid: &mut f64,
+    tick_size: f64,
+    state: &mut RegimeState,
+    rng: &mut impl Rng,
+    active: &mut ActiveScenario,
+    now: f64,
+    run_report: &mut RunReport,
+) -> Option<Regime> {
+    *mid = (*mid * (1.0 + shock)).max(tick_size);
+    run_report.record_shock();
+    if shock > 0.0 {
+        active.cover_burst += active.squeezing(now).map_or(0, |sq| sq.cover_orders);
+    }
+    if shock == 0.0 || !matches!(state.current, Regime::Calm | Regime::Recovery) {
+        return None;
+    }
+    let next = if shock < 0.0 {
+        Regime::Crash
     } else {
-        0
+        Regime::Rally
     };
+    state.transition_to(next, rng);
+    Some(next)
+}
+
+/// Feedback controller that steers the throughput scale toward a target
+/// message rate, measured over each display interval.
+struct RateController {
+    target: f64,
+}
+
+impl RateController {
+    const MIN_SCALE: f64 = 1e-3;
+    const MAX_SCALE: f64 = 1e4;
+
+    fn new(target: f64) -> Self {
+        Self { target }
+    }
+
+    /// Return the adjusted scale given the rate observed at `current_scale`.
+    /// The correction is damped (square root of the error ratio) and bounded
+    /// per step so regime switches don't cause the scale to oscillate.
+    fn update(&self, current_scale: f64, measured_rate: f64) -> f64 {
+        let ratio = if measured_rate > 0.0 {
+            (self.target / measured_rate).clamp(0.25, 4.0)
+        } else {
+            4.0
+        };
+        (current_scale * ratio.sqrt()).clamp(Self::MIN_SCALE, Self::MAX_SCALE)
+    }
+}
+
+/// Feedback controller that steers each regime's order-to-trade and
+/// cancel-to-add ratios towards `[ratios]` by rescaling its market order and
+/// regime cancel intensities, measured over each display interval.
+struct RatioController {
+    cfg: RatioConfig,
+    market_mult: [f64; 5],
+    cancel_mult: [f64; 5],
+    /// Regimes whose cancels emptied the book this interval, so a higher
+    /// cancel rate would not have produced more cancels.
+    cancels_capped: [bool; 5],
+}
+
+impl RatioController {
+    const MIN_MULT: f64 = 1e-2;
+    const MAX_MULT: f64 = 1e2;
+
+    fn new(cfg: &RatioConfig) -> Self {
+        Self {
+            cfg: cfg.clone(),
+            market_mult: [1.0; 5],
+            cancel_mult: [1.0; 5],
+            cancels_capped: [false; 5],
+        }
+    }
+
+    /// Adjust the multipliers of every regime that generated orders in the
+    /// interval. Damped and bounded per step like `RateController`.
+    fn update(&mut self, counts: &[RegimeCounts; 5]) {
+        for r in Regime::ALL {
+            let (c, i) = (&counts[r.index()], r.index());
+            if c.orders == 0 {
+                continue;
+            }
+            // Too many orders per trade: more market orders.
+            let otr = c.orders as f64 / c.trades.max(1) as f64;
+            let ratio = (otr / self.cfg.order_to_trade.get(r)).clamp(0.25, 4.0);
+            self.market_mult[i] =
+                (self.market_mult[i] * ratio.sqrt()).clamp(Self::MIN_MULT, Self::MAX_MULT);
+            let cta = c.cancels as f64 / c.orders as f64;
+            let ratio = if cta > 0.0 {
+                (self.cfg.cancel_to_add.get(r) / cta).clamp(0.25, 4.0)
+            } else {
+                4.0
+            };
+            if ratio < 1.0 || !self.cancels_capped[i] {
+                self.cancel_mult[i] =
+                    (self.cancel_mult[i] * ratio.sqrt()).clamp(Self::MIN_MULT, Self::MAX_MULT);
+            }
+        }
+        self.cancels_capped = [false; 5];
+    }
+}
+
+const BOX_W: usize = 50;
+
+fn box_line(content: &str) -> String {
+    let width = content.chars().count();
+    let pad = BOX_W.saturating_sub(width);
     format!("│ {}{} │", content, " ".repeat(pad))
 }
 
@@ -82,557 +644,1743 @@ fn box_bottom() -> String {
     format!("└─{}─┘", "─".repeat(BOX_W))
 }
 
-enum ControlCommand {
-    Pause,
-    Resume,
-    Throughput(f64),
-    DisplayInterval(f64),
-    Regime(Regime),
-    Reload,
-    Stats,
+fn warning(t: f64, message: impl ToString) -> Record {
+    Record::new("warning", t).str("message", message)
 }
 
-struct RuntimeTunables {
-    throughput_scale: f64,
-    display_interval: f64,
-    shock_prob: f64,
-    paused: bool,
+/// Open the session: SESSION_START, the symbol directory (once per feed, from
+/// its first instrument), the AUCTION state
+/// when trading starts with an opening auction, and an ADMIN warning when
+/// datagrams are being corrupted.
+fn start_session(
+    cfg: &AppConfig,
+    session: &Session,
+    sender: &mut MulticastSender,
+    out: &mut Output,
+    stats: &mut TickStats,
+    t: f64,
+) {
+    let _ = sender.send(&SessionStart {
+        tick_size: cfg.tick_size,
+        lot_size: cfg.lot_size,
+        initial_price: cfg.initial_price,
+        instrument: cfg.instrument_index,
+        time: t,
+    });
+    stats.messages_sent += 1;
+    let instruments = match cfg.instrument_index {
+        0 => &cfg.instruments[..],
+        _ => &[],
+    };
+    for (index, instrument) in (0..).zip(instruments) {
+        let _ = sender.send(&SymbolDirectory {
+            index,
+            instrument,
+            time: t,
+        });
+        stats.messages_sent += 1;
+    }
+
+    if session.in_auction() {
+        let _ = sender.send(&VenueState {
+            state: TradingState::Auction,
+            instrument: cfg.instrument_index,
+            time: t,
+        });
+        stats.messages_sent += 1;
+        out.event(
+            &format!("  ▶ AUCTION  opening auction  t={:.1}s", t),
+            Record::new("auction", t).str("phase", "opening"),
+        );
+    }
+    if cfg.corrupt_prob > 0.0 {
+        let text = format!("corrupting {}% of datagrams", cfg.corrupt_prob * 100.0);
+        admin(
+            sender,
+            stats,
+            Severity::Warning,
+            ADMIN_CORRUPTION,
+            &text,
+            cfg.instrument_index,
+            t,
+        );
+    }
 }
 
-fn parse_regime(s: &str) -> Option<Regime> {
-    match s {
-        "calm" => Some(Regime::Calm),
-        "volatile" => Some(Regime::Volatile),
-        "crash" => Some(Regime::Crash),
-        "rally" => Some(Regime::Rally),
-        "recovery" => Some(Regime::Recovery),
-        _ => None,
+/// Publish an ADMIN message for consumers to log alongside the market data.
+fn admin(
+    sender: &mut MulticastSender,
+    stats: &mut TickStats,
+    severity: Severity,
+    code: u16,
+    text: &str,
+    instrument: u16,
+    t: f64,
+) {
+    let _ = sender.send(&Admin::new(severity, code, text, instrument, t));
+    stats.messages_sent += 1;
+}
+
+/// LEVEL messages for the levels changed since the last call (none unless
+/// the book tracks them), and the order messages the sender withheld taken
+/// back off the count. Runs before every flush.
+fn publish_levels(book: &mut Book, sender: &mut MulticastSender, stats: &mut TickStats, t: f64) {
+    for level in book.take_level_changes(t) {
+        let _ = sender.send(&level);
+        stats.messages_sent += 1;
     }
+    stats.messages_sent -= sender.take_withheld();
 }
 
-fn parse_control_command(input: &str) -> Option<ControlCommand> {
-    let trimmed = input.trim();
-    if trimmed.is_empty() {
-        return None;
+/// One event per datagram damaged by `--corrupt-prob` since the last call.
+fn log_corruptions(out: &mut Output, sender: &mut MulticastSender, t: f64) {
+    for c in sender.take_corruptions() {
+        out.event(
+            &format!(
+                "  ✗ CORRUPT datagram #{} {} ({} -> {} bytes)  t={:.1}s",
+                c.datagram, c.kind, c.len, c.sent_len, t
+            ),
+            Record::new("corrupt", t)
+                .count("datagram", c.datagram)
+                .str("mode", c.kind)
+                .count("bytes", c.len)
+                .count("sent_bytes", c.sent_len),
+        );
     }
-    let mut parts = trimmed.split_whitespace();
-    let cmd = parts.next()?.to_ascii_lowercase();
-    match cmd.as_str() {
-        "pause" => Some(ControlCommand::Pause),
-        "resume" => Some(ControlCommand::Resume),
-        "reload" => Some(ControlCommand::Reload),
-        "stats" => Some(ControlCommand::Stats),
-        "rate" | "throughput" => {
-            let v = parts.next()?.parse::<f64>().ok()?;
-            Some(ControlCommand::Throughput(v))
-        }
-        "display" => {
-            let v = parts.next()?.parse::<f64>().ok()?;
-            Some(ControlCommand::DisplayInterval(v))
-        }
-        "regime" => {
-            let r = parse_regime(&parts.next()?.to_ascii_lowercase())?;
-            Some(ControlCommand::Regime(r))
-        }
-        _ => None,
-    }
-}
-
-fn spawn_control_listener(bind: &str) -> std::io::Result<Receiver<ControlCommand>> {
-    let socket = UdpSocket::bind(bind)?;
-    socket.set_read_timeout(Some(Duration::from_millis(500)))?;
-
-    let (tx, rx) = mpsc::channel::<ControlCommand>();
-    std::thread::spawn(move || {
-        let mut buf = [0u8; 1024];
-        loop {
-            match socket.recv_from(&mut buf) {
-                Ok((n, peer)) => {
-                    let cmd_text = String::from_utf8_lossy(&buf[..n]).trim().to_string();
-                    if let Some(cmd) = parse_control_command(&cmd_text) {
-                        let _ = tx.send(cmd);
-                        let _ = socket.send_to(b"ok\n", peer);
-                    } else {
-                        let _ = socket.send_to(
-                            b"error: commands are pause|resume|rate <x>|display <sec>|regime <name>|reload|stats\n",
-                            peer,
-                        );
-                    }
-                }
-                Err(e)
-                    if e.kind() == std::io::ErrorKind::WouldBlock
-                        || e.kind() == std::io::ErrorKind::TimedOut => {}
-                Err(_) => break,
-            }
+}
+
+pub fn run(cfg: &AppConfig, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let running = Arc::new(AtomicBool::new(true));
+    {
+        let running = Arc::clone(&running);
+        ctrlc::set_handler(move || {
+            running.store(false, Ordering::SeqCst);
+        })?;
+    }
+    if cfg.bench {
+        simulate(cfg, cli, &running, None)
+    } else if cfg.instruments.len() > 1 {
+        symbols::run(cfg, cli, &running)
+    } else if !cfg.venues.is_empty() {
+        venues::run(cfg, cli, &running)
+    } else {
+        simulate(cfg, cli, &running, None)
+    }
+}
+
+/// Run the generator until it ends or `running` is cleared. With `tap`, every
+/// datagram goes to the channel instead of the network and ticks are unpaced.
+pub fn simulate(
+    cfg: &AppConfig,
+    cli: &Cli,
+    running: &AtomicBool,
+    tap: Option<SyncSender<Vec<u8>>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut sim = Sim::new(cfg, cli, Feed::Own(tap))?;
+    let bench_start = Instant::now();
+    let allocs_start = alloc::snapshot();
+    sim.start();
+
+    loop {
+        if sim.warmup_done() {
+            sim.open_after_warmup();
+        }
+        sim.check_resumed()?;
+
+        if cfg.bench && sim.ticks >= cfg.bench_ticks {
+            break;
+        }
+        let end_reason = sim.end_reason(running);
+        sim.checkpoint(end_reason.is_some());
+        if let Some(reason) = end_reason {
+            sim.log_end(&reason);
+            break;
         }
-    });
 
-    Ok(rx)
+        sim.run_commands();
+        if sim.runtime.paused {
+            std::thread::sleep(Duration::from_secs_f64(cfg.tick_interval));
+            sim.pacer.reset();
+            continue;
+        }
+        sim.tick();
+    }
+
+    sim.finish(bench_start, allocs_start)
 }
 
-/// Output sink that respects the configured output mode.
-struct Output {
-    mode: OutputMode,
-    file: Option<std::fs::File>,
+/// Where a `Sim` publishes.
+enum Feed {
+    /// A feed of its own; with a tap, every datagram goes to the channel
+    /// instead of the network.
+    Own(Option<SyncSender<Vec<u8>>>),
+    /// Another `Sim`'s feed, which forwards what this one collects.
+    Shared,
 }
 
-impl Output {
-    fn new(cfg: &AppConfig) -> Result<Self, Box<dyn std::error::Error>> {
-        let file = match cfg.output_mode {
-            OutputMode::File | OutputMode::Both => {
-                let f = OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open(&cfg.log_file)
-                    .map_err(|e| format!("failed to open log file '{}': {}", cfg.log_file, e))?;
-                Some(f)
-            }
-            _ => None,
-        };
-        Ok(Self {
-            mode: cfg.output_mode,
-            file,
-        })
+/// The sender of a feed of its own with its exports, capture and send path,
+/// and why io_uring couldn't be used if it was asked for.
+fn open_feed(
+    cfg: &AppConfig,
+    tap: Option<SyncSender<Vec<u8>>>,
+) -> Result<(MulticastSender, Option<std::io::Error>), Box<dyn std::error::Error>> {
+    let mut sender = MulticastSender::new(
+        cfg.multicast_group,
+        cfg.multicast_port,
+        cfg.wire_format,
+        cfg.max_packet_bytes,
+        cfg.legacy_market_price,
+        &cfg.multicast_options,
+    )?;
+    if let Some(venue) = &cfg.venue {
+        if !venue.latency.is_zero() {
+            sender.delay_by(venue.latency)?;
+        }
+        if venue.clock_offset != 0.0 || venue.clock_drift != 0.0 {
+            sender.skew_clock(venue.clock_offset, venue.clock_drift);
+        }
     }
-
-    fn to_console(&self) -> bool {
-        matches!(self.mode, OutputMode::Console | OutputMode::Both)
+    if let Some(tap) = tap {
+        sender.tap(tap);
     }
-
-    fn to_file(&self) -> bool {
-        self.file.is_some()
+    let symbol = cfg.instruments.first().map_or("", |i| i.symbol.as_str());
+    if let Some(path) = &cfg.csv_path {
+        let csv = CsvExport::create(path, symbol)
+            .map_err(|e| format!("failed to create CSV file '{}': {}", path.display(), e))?;
+        sender.add_export(Box::new(csv));
+    }
+    if let Some(path) = &cfg.pcap_path {
+        let mut pcap = PcapWriter::create(path, cfg.multicast_group, cfg.multicast_port)
+            .map_err(|e| format!("failed to create pcap file '{}': {}", path.display(), e))?;
+        if !cfg.realtime {
+            pcap.use_sim_time();
+        }
+        // (multicast_ttl is validated to fit in a u8)
+        pcap.set_ttl(cfg.multicast_options.ttl as u8);
+        sender.capture_pcap(pcap);
+    }
+    if cfg.epoch_timestamps {
+        sender.stamp_sent_ns();
+    }
+    if cfg.crc {
+        sender.append_crc();
+    }
+    if cfg.wire_version == wire::VERSION_1 {
+        sender.encode_v1();
+    }
+    if cfg.corrupt_prob > 0.0 {
+        sender.corrupt_with(Corruptor::new(cfg.corrupt_prob, cfg.seed));
+    }
+    // Where io_uring can't be set up, sends stay on the socket path
+    let uring_error = match cfg.sender {
+        SenderBackend::IoUring => sender.use_io_uring().err(),
+        SenderBackend::Socket => None,
+    };
+    if let Some(capacity) = cfg.sender_queue {
+        sender
+            .send_from_thread(capacity, cfg.pin_cores.get(1).copied(), cfg.rt_priority)
+            .map_err(|e| format!("failed to start the sender thread: {}", e))?;
     }
+    if let Some((group, port)) = cfg.ouch {
+        let echo = OuchEcho::new(group, port, symbol, &cfg.multicast_options)
+            .map_err(|e| format!("failed to open the OUCH stream {}:{}: {}", group, port, e))?;
+        sender.add_export(Box::new(echo));
+    }
+    #[cfg(feature = "parquet")]
+    if let Some(path) = &cfg.parquet_path {
+        let pq = ParquetExport::create(path, symbol)
+            .map_err(|e| format!("failed to create Parquet file '{}': {}", path.display(), e))?;
+        sender.add_export(Box::new(pq));
+    }
+    Ok((sender, uring_error))
+}
 
-    fn print(&mut self, msg: &str) {
-        if self.to_console() {
-            println!("{}", msg);
+/// Everything the generator carries from one tick to the next.
+struct Sim<'a> {
+    cfg: &'a AppConfig,
+    cli: &'a Cli,
+    /// Ticks run back to back rather than paced to the clock.
+    unpaced: bool,
+    rngs: RngStreams,
+    runtime: RuntimeTunables,
+    active: ActiveScenario<'a>,
+    next_scenarios: VecDeque<&'a ScenarioStep>,
+    state: RegimeState,
+    last_printed_regime: Regime,
+
+    sender: MulticastSender,
+    out: Output,
+    control_api: Option<(Receiver<ControlRequest>, UdpSocket)>,
+    crypto: Option<CryptoFeed>,
+
+    participants: ParticipantPool,
+    agents: Option<AgentLayer>,
+    statistical_flow: bool,
+    price_model: Box<dyn PriceModel>,
+    size_dist: SizeDist,
+    ttl_dist: Uniform<f64>,
+    order_ids: OrderIds,
+    /// Orders from the `order` command, sent with the next tick's flow.
+    injected_orders: Vec<Order>,
+    /// Market orders sent, buys then sells, since the universe driver last
+    /// took them to pass on to the followers (`[lead_lag]`).
+    lead_markets: [u32; 2],
+    /// Follow-on market orders due after the leader's, in time order.
+    follow_ons: VecDeque<(f64, Side)>,
+    /// `at` commands waiting for their sim time, in time order
+    scheduled_commands: Vec<(f64, ControlRequest)>,
+
+    mid: f64,
+    book: Book,
+    stop_book: StopBook,
+    last_quote: Option<Quote>,
+    next_depth_at: f64,
+    options: Option<OptionChain>,
+    stuffer: QuoteStuffer,
+    fat_finger: FatFinger,
+    breaker: CircuitBreaker,
+    bands: PriceBands,
+    errors: ErrorInjector,
+    session: Session,
+    seasonality: Option<Seasonality>,
+    feedback: Option<RegimeFeedback>,
+    fundamental: Option<Fundamental>,
+    /// The futures leg's basis over the spot (`[basis]`, second symbol only).
+    basis: Option<Basis>,
+    rate_ctl: Option<RateController>,
+    ratio_ctl: Option<RatioController>,
+    bars: Option<BarAggregator>,
+
+    current_time: f64,
+    ticks: u64,
+    stats: TickStats,
+    time_since_display: f64,
+    total_messages: u64,
+    regime_totals: [RegimeCounts; 5],
+    run_report: RunReport,
+    /// Wall-clock time each tick is paced to
+    tick_budget: f64,
+    pacer: Pacer,
+    tick_times: LatencyHistogram,
+
+    // Per-tick buffers, cleared and refilled every tick so the steady state
+    // doesn't allocate
+    tick_orders: Vec<Order>,
+    dark_trades: Vec<DarkTrade>,
+    agent_cancels: Vec<u64>,
+    expired: Vec<u64>,
+    cancellable: Vec<u64>,
+
+    // Checkpoints: commands to log, and with --resume a silent replay up to the saved tick
+    command_log: Vec<LoggedCommand>,
+    reload_log: Vec<LoggedReload>,
+    next_checkpoint_at: f64,
+    replay: VecDeque<LoggedCommand>,
+    replay_reloads: VecDeque<LoggedReload>,
+    replaying: bool,
+    warming: bool,
+}
+
+/// What the phases of a tick share once the price has moved and the venue
+/// has updated.
+struct TickContext {
+    /// The regime's parameters after seasonality, windows, bps spreads and
+    /// any squeeze.
+    params: RegimeParams,
+    dists: RegimeDists,
+    /// Per-tick arrival count per unit of per-second rate.
+    tick_scale: f64,
+    /// Continuous trading: the breaker and session are open (or warming up).
+    trading: bool,
+    in_auction: bool,
+    band_limits: Option<(f64, f64)>,
+}
+
+impl<'a> Sim<'a> {
+    /// Open the feed, its exports and the control API, set up the state for
+    /// tick 0 and print the startup banner.
+    fn new(
+        cfg: &'a AppConfig,
+        cli: &'a Cli,
+        feed: Feed,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let unpaced = cfg.bench || !cfg.realtime || !matches!(feed, Feed::Own(None));
+        let mut rngs = RngStreams::new(cfg.seed, cfg.venue.as_ref().map_or(0, |v| v.index));
+        let runtime = RuntimeTunables::new(cfg);
+        let active = ActiveScenario::new(&cfg.scenarios[0], cfg, 0.0, &runtime.params);
+        let state = RegimeState::new(active.cfg.starting_regime, &mut rngs.regimes);
+
+        let (mut sender, uring_error) = match feed {
+            Feed::Own(tap) => open_feed(cfg, tap)?,
+            Feed::Shared => (
+                MulticastSender::collecting(cfg.wire_format, cfg.legacy_market_price)?,
+                None,
+            ),
+        };
+        let mut out = Output::new(cfg)?;
+        if let Some(e) = &uring_error {
+            let msg = format!("io_uring unavailable, sending through the socket: {}", e);
+            out.event(&format!("  ⚠ {}", msg), warning(0.0, msg));
         }
-        if let Some(ref mut f) = self.file {
-            let _ = writeln!(f, "{}", msg);
+
+        let control_api = if cfg.control_enabled {
+            match spawn_control_listener(&cfg.control_bind, cfg.control_legacy_replies) {
+                Ok(listener) => {
+                    out.event(
+                        &format!("  ▶ CONTROL API listening on udp://{}", cfg.control_bind),
+                        Record::new("control_listening", 0.0).str("bind", &cfg.control_bind),
+                    );
+                    out.push_socket = listener.1.try_clone().ok();
+                    Some(listener)
+                }
+                Err(e) => {
+                    out.event(
+                        &format!("  ⚠ control API disabled: {}", e),
+                        warning(0.0, format!("control API disabled: {}", e)),
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let crypto = match &cfg.crypto_feed {
+            Some(c) => {
+                let feed = CryptoFeed::start(&c.bind, &c.symbol, cfg.tick_size, c.depth_interval)?;
+                out.event(
+                    &format!(
+                        "  ▶ CRYPTO FEED listening on ws://{}/ws/{}@depth",
+                        c.bind,
+                        c.symbol.to_ascii_lowercase()
+                    ),
+                    Record::new("crypto_feed_listening", 0.0)
+                        .str("bind", &c.bind)
+                        .str("symbol", &c.symbol),
+                );
+                Some(feed)
+            }
+            None => None,
+        };
+
+        let participants = ParticipantPool::new(
+            cfg.participant_first_id,
+            cfg.participant_count,
+            &cfg.participant_weights,
+            cfg.participant_zipf_exponent,
+        )?;
+        let price_model = price::build(
+            cfg.price_model,
+            &cfg.jumps,
+            &cfg.ou,
+            &cfg.garch,
+            cfg.initial_price,
+        )?;
+        let size_dist = SizeDist::new(
+            cfg.size_distribution,
+            cfg.size_mean_log,
+            cfg.size_std_log,
+            cfg.pareto_scale,
+            cfg.pareto_shape,
+            &cfg.round_lots,
+        )?;
+        let mut book = Book::new(cfg.tick_size, cfg.instrument_index);
+        if cfg.book_view != BookView::Mbo {
+            book.track_levels();
         }
-    }
+        if cfg.book_view == BookView::Mbp {
+            sender.withhold_orders();
+        }
+        let tick_budget = cfg.tick_interval / cfg.time_scale;
+
+        let mut sim = Self {
+            cfg,
+            cli,
+            unpaced,
+            rngs,
+            next_scenarios: cfg.scenarios.iter().skip(1).collect(),
+            last_printed_regime: state.current,
+            runtime,
+            active,
+            state,
+            sender,
+            out,
+            control_api,
+            crypto,
+            participants,
+            agents: cfg
+                .agents
+                .as_ref()
+                .map(|a| AgentLayer::new(a, cfg.tick_interval)),
+            statistical_flow: cfg.agents.as_ref().is_none_or(|a| a.statistical_flow),
+            price_model,
+            size_dist,
+            ttl_dist: Uniform::new(cfg.ttl_min, cfg.ttl_max),
+            order_ids: OrderIds::new(
+                cfg.id_scheme,
+                cfg.id_recycle_delay,
+                cfg.client_order_ids,
+                cfg.seed,
+            ),
+            injected_orders: Vec::new(),
+            lead_markets: [0; 2],
+            follow_ons: VecDeque::new(),
+            scheduled_commands: Vec::new(),
+            mid: cfg.initial_price,
+            book,
+            stop_book: StopBook::default(),
+            last_quote: None,
+            next_depth_at: 0.0,
+            options: cfg
+                .options
+                .clone()
+                .map(|c| OptionChain::new(c, cfg.initial_price, cfg.instrument_index)),
+            stuffer: QuoteStuffer::new(&cfg.events.quote_stuffing),
+            fat_finger: FatFinger::new(&cfg.events.fat_finger),
+            breaker: CircuitBreaker::new(&cfg.circuit_breaker),
+            bands: PriceBands::new(&cfg.price_bands),
+            errors: ErrorInjector::new(&cfg.rejects, cfg.tick_size),
+            session: Session::new(&cfg.session, cfg.schedule.clone()),
+            seasonality: cfg
+                .seasonality
+                .enabled
+                .then(|| Seasonality::new(&cfg.seasonality)),
+            feedback: cfg
+                .regime_feedback
+                .enabled
+                .then(|| RegimeFeedback::new(&cfg.regime_feedback, cfg.initial_price)),
+            fundamental: cfg
+                .fundamental
+                .enabled
+                .then(|| Fundamental::new(&cfg.fundamental, cfg.initial_price)),
+            basis: cfg
+                .basis
+                .as_ref()
+                .filter(|_| cfg.instrument_index == 1)
+                .map(|b| Basis::new(b, cfg.universe[0].initial_price, cfg.initial_price)),
+            rate_ctl: cfg.target_rate.map(RateController::new),
+            ratio_ctl: cfg.ratios.as_ref().map(RatioController::new),
+            bars: cfg
+                .bar_interval
+                .map(|i| BarAggregator::new(i, cfg.instrument_index)),
+            current_time: 0.0,
+            ticks: 0,
+            stats: TickStats::new(),
+            time_since_display: 0.0,
+            total_messages: 0,
+            regime_totals: [RegimeCounts::default(); 5],
+            run_report: RunReport::new(),
+            tick_budget,
+            pacer: Pacer::new(cfg.pacing, tick_budget),
+            tick_times: LatencyHistogram::new(),
+            tick_orders: Vec::new(),
+            dark_trades: Vec::new(),
+            agent_cancels: Vec::new(),
+            expired: Vec::new(),
+            cancellable: Vec::new(),
+            command_log: Vec::new(),
+            reload_log: Vec::new(),
+            next_checkpoint_at: cfg.checkpoint_interval,
+            replay: VecDeque::new(),
+            replay_reloads: VecDeque::new(),
+            replaying: false,
+            warming: false,
+        };
+        let backend = match uring_error {
+            Some(_) => SenderBackend::Socket,
+            None => cfg.sender,
+        };
+        sim.print_banner(backend);
 
-    fn event(&mut self, msg: &str) {
-        self.print(msg);
+        // Pinned only now, so the helper threads started above keep every CPU
+        if let Some(&core) = cfg.pin_cores.first() {
+            cpu::pin_current(core).map_err(|e| format!("failed to pin to CPU {}: {}", core, e))?;
+        }
+        if let Some(priority) = cfg.rt_priority {
+            if let Err(e) = cpu::set_fifo(priority) {
+                let msg = format!("SCHED_FIFO not granted, running at normal priority: {}", e);
+                sim.out.event(&format!("  ⚠ {}", msg), warning(0.0, msg));
+            }
+        }
+        Ok(sim)
     }
 
-    /// Print the periodic summary block.
-    fn summary(
-        &mut self,
-        elapsed: f64,
-        mid: f64,
-        regime: Regime,
-        active_orders: usize,
-        stats: &TickStats,
-        interval_secs: f64,
-    ) {
-        let orders_per_sec = stats.total_orders() as f64 / interval_secs;
-        let cancels_per_sec = stats.total_cancels() as f64 / interval_secs;
-        let msgs_per_sec = stats.messages_sent as f64 / interval_secs;
-
-        if self.to_console() {
-            println!("{}", box_top());
-            println!("{}", box_line(&format!(
-                "t={:.1}s  mid={:.4}  regime={}",
-                elapsed, mid, regime
-            )));
-            println!("{}", box_line(&format!(
-                "orders: {} ({:.0}/s)  limits: {}  mkt: {}",
-                stats.total_orders(), orders_per_sec,
-                stats.limits_generated, stats.markets_generated
+    fn print_banner(&mut self, backend: SenderBackend) {
+        let cfg = self.cfg;
+        let out = &mut self.out;
+        out.print(&box_top());
+        out.print(&box_line("Order Generation Engine"));
+        out.print(&box_mid());
+        let scenario_names = cfg
+            .scenarios
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        out.print(&box_line(&format!("scenario:    {}", scenario_names)));
+        out.print(&box_line(&format!("regime:      {}", self.state.current)));
+        out.print(&box_line(&format!("mid price:   {}", cfg.initial_price)));
+        let pacing = if !cfg.realtime {
+            "not realtime".to_string()
+        } else if cfg.time_scale != 1.0 {
+            format!("{}, {}x", cfg.pacing, cfg.time_scale)
+        } else {
+            cfg.pacing.to_string()
+        };
+        out.print(&box_line(&format!(
+            "tick:        {}s ({})",
+            cfg.tick_interval, pacing
+        )));
+        out.print(&box_line(&format!("seed:        {}", cfg.seed)));
+        if !cfg.pin_cores.is_empty() || cfg.rt_priority.is_some() {
+            let mut placement = match cfg.pin_cores.as_slice() {
+                [] => "unpinned".to_string(),
+                [gen] => format!("cpu {}", gen),
+                [gen, send, ..] => format!("cpu {}, sender cpu {}", gen, send),
+            };
+            if let Some(p) = cfg.rt_priority {
+                placement.push_str(&format!(", SCHED_FIFO {}", p));
+            }
+            out.print(&box_line(&format!("threads:     {}", placement)));
+        }
+        if cfg.price_model != PriceModelKind::Gbm {
+            out.print(&box_line(&format!("price model: {}", cfg.price_model)));
+        }
+        if cfg.id_scheme != IdScheme::Monotonic {
+            out.print(&box_line(&format!("order ids:   {}", cfg.id_scheme)));
+        }
+        if cfg.spreads.units == SpreadUnits::Bps {
+            out.print(&box_line("spreads:     bps of mid"));
+        }
+        if cfg.size_distribution != SizeDistributionKind::Lognormal {
+            out.print(&box_line(&format!(
+                "sizes:       {}",
+                cfg.size_distribution
             )));
-            println!("{}", box_line(&format!(
-                "cancels: {} ({:.0}/s)  expired: {}  regime: {}",
-                stats.total_cancels(), cancels_per_sec,
-                stats.cancels_expired, stats.cancels_regime
+        }
+        if let Some(schedule) = &cfg.schedule {
+            out.print(&box_line(&format!("hours:       {}", schedule)));
+        }
+        if cfg.seasonality.enabled {
+            out.print(&box_line(&format!(
+                "seasonality: {}s day",
+                cfg.seasonality.session_length
             )));
-            println!("{}", box_line(&format!(
-                "active: {}  msgs/s: {:.0}",
-                active_orders, msgs_per_sec
+        }
+        out.print(&box_line(&format!(
+            "throughput:  {}x",
+            self.runtime.throughput_scale
+        )));
+        if let Some(r) = cfg.target_rate {
+            out.print(&box_line(&format!("target rate: {} msgs/s", r)));
+        }
+        out.print(&box_line(&format!("output:      {}", cfg.output_mode)));
+        out.print(&box_line(&format!("wire fmt:    {}", cfg.wire_format)));
+        if cfg.book_view != BookView::Mbo {
+            out.print(&box_line(&format!("book view:   {}", cfg.book_view)));
+        }
+        out.print(&box_line(&format!(
+            "traders:     {}",
+            self.participants.len()
+        )));
+        if cfg.matching && cfg.self_match != SelfMatchMode::Allow {
+            out.print(&box_line(&format!("self match:  {}", cfg.self_match)));
+        }
+        if cfg.dark_fraction > 0.0 {
+            out.print(&box_line(&format!(
+                "dark:        {:.1}% of market orders",
+                cfg.dark_fraction * 100.0
             )));
-            println!("{}", box_bottom());
-        }
-
-        if self.to_file() {
-            if let Some(ref mut f) = self.file {
-                let _ = writeln!(
-                    f,
-                    "SUMMARY|t={:.1}|mid={:.4}|regime={}|active={}|limits={}|markets={}|cancels_exp={}|cancels_reg={}|msgs={}",
-                    elapsed, mid, regime, active_orders,
-                    stats.limits_generated, stats.markets_generated,
-                    stats.cancels_expired, stats.cancels_regime,
-                    stats.messages_sent
-                );
+        }
+        if let Some(layer) = &self.agents {
+            out.print(&box_line(&format!("agents:      {}", layer.len())));
+        }
+        if out.to_file() {
+            out.print(&box_line(&format!("log file:    {}", cfg.log_file)));
+        }
+        out.print(&box_line(&format!(
+            "multicast:   {}:{}",
+            cfg.multicast_group, cfg.multicast_port
+        )));
+        let mcast = &cfg.multicast_options;
+        if *mcast != MulticastOptions::default() {
+            let mut line = format!("ttl {}", mcast.ttl);
+            if let Some(addr) = mcast.interface {
+                line.push_str(&format!(", via {}", addr));
+            }
+            if !mcast.loopback {
+                line.push_str(", no loopback");
             }
+            out.print(&box_line(&format!("             {}", line)));
+        }
+        match (cfg.sender_queue, backend) {
+            (Some(capacity), backend) => out.print(&box_line(&format!(
+                "sender:      {} thread, queue of {}",
+                backend, capacity
+            ))),
+            (None, SenderBackend::IoUring) => out.print(&box_line("sender:      io_uring")),
+            (None, SenderBackend::Socket) => {}
+        }
+        for v in &cfg.venues {
+            out.print(&box_line(&format!(
+                "venue:       {} {}:{}",
+                v.name, v.multicast_group, v.multicast_port
+            )));
+        }
+        if cfg.control_enabled {
+            out.print(&box_line(&format!(
+                "control:     udp://{}",
+                cfg.control_bind
+            )));
+        }
+        if let Some((group, port)) = cfg.ouch {
+            out.print(&box_line(&format!("ouch echo:   {}:{}", group, port)));
+        }
+        if let Some(c) = &cfg.crypto_feed {
+            out.print(&box_line(&format!(
+                "crypto feed: ws://{} {}",
+                c.bind, c.symbol
+            )));
+        }
+        if let Some(c) = &cfg.options {
+            out.print(&box_line(&format!(
+                "options:     {} expiries x {} strikes",
+                c.expiries.len(),
+                2 * c.strikes + 1
+            )));
         }
+        out.print(&box_bottom());
+        out.record(
+            Record::new("start", 0.0)
+                .str("scenario", &scenario_names)
+                .str("regime", self.state.current)
+                .num("mid", cfg.initial_price)
+                .num("tick_interval", cfg.tick_interval)
+                .str("pacing", cfg.pacing)
+                .num("time_scale", cfg.time_scale)
+                .bool("realtime", cfg.realtime)
+                .count("seed", cfg.seed)
+                .str("price_model", cfg.price_model)
+                .str("size_distribution", cfg.size_distribution)
+                .str("id_scheme", cfg.id_scheme)
+                .num("throughput", self.runtime.throughput_scale)
+                .str("wire_format", cfg.wire_format)
+                .str("book_view", cfg.book_view)
+                .str("self_match", cfg.self_match)
+                .num("dark_fraction", cfg.dark_fraction)
+                .count("participants", self.participants.len())
+                .str(
+                    "multicast",
+                    format!("{}:{}", cfg.multicast_group, cfg.multicast_port),
+                )
+                .strs(
+                    "venues",
+                    &cfg.venues
+                        .iter()
+                        .map(|v| v.name.as_str())
+                        .collect::<Vec<_>>(),
+                ),
+        );
     }
-}
 
-pub fn run(cfg: &AppConfig) -> Result<(), Box<dyn std::error::Error>> {
-    let mut rng = StdRng::seed_from_u64(cfg.seed);
+    /// One tick: scenario events, the price step, venue events, the order
+    /// flow, market data and the periodic display, then the clock moves on.
+    fn tick(&mut self) {
+        let tick_started = Instant::now();
+        self.sender.set_sim_time(self.current_time);
+        let tick_start_mid = self.mid;
+        let tick_start_counts = self.stats.counts();
+        let dt_seconds = self.cfg.tick_interval;
+
+        self.advance_scenario();
+        let squeezing = self.active.squeezing(self.current_time);
+        self.random_shock();
+        self.order_ids.set_time(self.current_time);
+        let scenario_time = self.current_time - self.active.started_at;
+        let window = self.poll_timeline(scenario_time);
+
+        // --- Time-of-day seasonality scales the arrival rates and sigma ---
+        let season = self
+            .seasonality
+            .as_ref()
+            .map_or(1.0, |s| s.multiplier(self.current_time));
+        let base_params = self.active.cfg.params(self.state.current);
+        let params = if season != 1.0 || window != Multipliers::NONE {
+            Multipliers {
+                sigma: window.sigma * season,
+                ..window
+            }
+            .apply(base_params)
+        } else {
+            base_params.clone()
+        };
+        // Windows that change arrival rates need their own distributions.
+        let scale = self.runtime.throughput_scale * season;
+        let mut dists = if window.changes_rates() {
+            RegimeDists::new(&params, scale, dt_seconds)
+        } else {
+            *self.active.dists.get(self.state.current, scale)
+        };
 
-    let scenario_cfg = ScenarioConfig::from_scenario(cfg.scenario);
-    let mut state = RegimeState::new(scenario_cfg.starting_regime, &mut rng);
-    let mut forced_event_fired = false;
+        // --- Mid-price update: GBM (plus Poisson jumps under jump-diffusion) or OU ---
+        let (diffusion_return, sigma) = self.move_price(&params, squeezing);
 
-    let sender = MulticastSender::new(cfg.multicast_group, cfg.multicast_port, cfg.wire_format)?;
-    let mut out = Output::new(cfg)?;
+        // --- Spreads quoted in basis points follow the new mid ---
+        let bps_params = self.cfg.spreads.at(&params, self.state.current, self.mid);
+        let bps = bps_params.is_some();
+        let params = bps_params.unwrap_or(params);
 
-    let mut runtime = RuntimeTunables {
-        throughput_scale: cfg.throughput_scale,
-        display_interval: cfg.display_interval,
-        shock_prob: cfg.shock_prob,
-        paused: false,
-    };
+        // --- Squeeze: buying pressure and market order size build up ---
+        let params = match squeezing {
+            Some(sq) => sq.escalate(&params, scenario_time - sq.at),
+            None => params,
+        };
+        if bps {
+            dists.offset = Exp::new(params.offset_lambda).unwrap();
+        }
 
-    let control_rx = if cfg.control_enabled {
-        match spawn_control_listener(&cfg.control_bind) {
-            Ok(rx) => {
-                out.event(&format!("  ▶ CONTROL API listening on udp://{}", cfg.control_bind));
-                Some(rx)
-            }
-            Err(e) => {
-                out.event(&format!("  ⚠ control API disabled: {}", e));
-                None
-            }
+        if self.venue_events() {
+            // Nothing trades after the close: the end conditions pick it up at
+            // the top of the loop, on the tick after this one
+            self.ticks += 1;
+            return;
         }
-    } else {
-        None
-    };
+        // The warmup trades continuously whatever the session starts with
+        let in_auction = self.session.in_auction() && !self.warming;
+        if in_auction && self.session.imbalance_due(self.current_time) {
+            let _ = self.sender.send(&Imbalance {
+                indicative: auction::indicative(&self.book, self.session.markets(), self.mid),
+                instrument: self.cfg.instrument_index,
+                time: self.current_time,
+            });
+            self.stats.messages_sent += 1;
+        }
+        let trading = self.breaker.is_open() && (self.session.is_continuous() || self.warming);
+        self.bands.update(self.current_time, self.mid);
+        let ctx = TickContext {
+            params,
+            dists,
+            tick_scale: scale * dt_seconds,
+            trading,
+            in_auction,
+            band_limits: self.bands.limits(),
+        };
 
-    let running = Arc::new(AtomicBool::new(true));
-    {
-        let running = Arc::clone(&running);
-        ctrlc::set_handler(move || {
-            running.store(false, Ordering::SeqCst);
-        })?;
-    }
+        self.log_regime_change();
 
-    // --- Startup banner ---
-    out.print(&box_top());
-    out.print(&box_line("Order Generation Engine"));
-    out.print(&box_mid());
-    out.print(&box_line(&format!("scenario:    {}", cfg.scenario)));
-    out.print(&box_line(&format!("regime:      {}", state.current)));
-    out.print(&box_line(&format!("mid price:   {}", cfg.initial_price)));
-    out.print(&box_line(&format!("tick:        {}s", cfg.tick_interval)));
-    out.print(&box_line(&format!("seed:        {}", cfg.seed)));
-    out.print(&box_line(&format!("throughput:  {}x", runtime.throughput_scale)));
-    out.print(&box_line(&format!("output:      {}", cfg.output_mode)));
-    out.print(&box_line(&format!("wire fmt:    {}", cfg.wire_format)));
-    if out.to_file() {
-        out.print(&box_line(&format!("log file:    {}", cfg.log_file)));
-    }
-    out.print(&box_line(&format!(
-        "multicast:   {}:{}",
-        cfg.multicast_group, cfg.multicast_port
-    )));
-    if cfg.control_enabled {
-        out.print(&box_line(&format!("control:     udp://{}", cfg.control_bind)));
-    }
-    out.print(&box_bottom());
-
-    let dt = dt_years(cfg.tick_interval);
-    let dt_seconds = cfg.tick_interval;
-    let size_dist = LogNormal::new(cfg.size_mean_log, cfg.size_std_log)?;
-    let ttl_dist = Uniform::new(cfg.ttl_min, cfg.ttl_max);
-
-    let mut mid = cfg.initial_price;
-    let mut next_id: u64 = 0;
-    let mut active_orders: HashMap<u64, Order> = HashMap::new();
-    let mut current_time: f64 = 0.0;
-    let mut last_printed_regime = state.current;
-
-    let mut stats = TickStats::new();
-    let mut time_since_display: f64 = 0.0;
-
-    while running.load(Ordering::Relaxed) {
-        if let Some(rx) = &control_rx {
-            while let Ok(cmd) = rx.try_recv() {
-                match cmd {
-                    ControlCommand::Pause => {
-                        runtime.paused = true;
-                        out.event("  ▶ CONTROL pause");
-                    }
-                    ControlCommand::Resume => {
-                        runtime.paused = false;
-                        out.event("  ▶ CONTROL resume");
-                    }
-                    ControlCommand::Throughput(v) if v >= 0.0 => {
-                        runtime.throughput_scale = v;
-                        out.event(&format!("  ▶ CONTROL throughput={}x", v));
-                    }
-                    ControlCommand::DisplayInterval(v) if v > 0.0 => {
-                        runtime.display_interval = v;
-                        out.event(&format!("  ▶ CONTROL display_interval={}s", v));
-                    }
-                    ControlCommand::Regime(next) => {
-                        state.transition_to(next, &mut rng);
-                        out.event(&format!("  ▶ CONTROL regime -> {}", state.current));
-                    }
-                    ControlCommand::Reload => {
-                        if let Some(path) = &cfg.config_path {
-                            match std::fs::read_to_string(path) {
-                                Ok(contents) => match toml::from_str::<FileConfig>(&contents) {
-                                    Ok(file_cfg) => {
-                                        runtime.throughput_scale = file_cfg.simulation.throughput_scale;
-                                        runtime.display_interval = file_cfg.output.display_interval;
-                                        runtime.shock_prob = file_cfg.shocks.probability;
-                                        out.event(&format!(
-                                            "  ▶ CONTROL reload OK throughput={}x display={}s shock_prob={}",
-                                            runtime.throughput_scale,
-                                            runtime.display_interval,
-                                            runtime.shock_prob
-                                        ));
-                                    }
-                                    Err(e) => out.event(&format!("  ⚠ reload parse failed: {}", e)),
-                                },
-                                Err(e) => out.event(&format!("  ⚠ reload read failed: {}", e)),
-                            }
-                        } else {
-                            out.event("  ⚠ reload unavailable (run with -c/--config)");
-                        }
-                    }
-                    ControlCommand::Stats => {
-                        out.event(&format!(
-                            "  ▶ CONTROL stats t={:.1}s mid={:.4} regime={} active={} paused={} throughput={}x",
-                            current_time,
-                            mid,
-                            state.current,
-                            active_orders.len(),
-                            runtime.paused,
-                            runtime.throughput_scale
-                        ));
-                    }
-                    _ => out.event("  ⚠ invalid control value"),
-                }
-            }
+        // --- Publish completed OHLCV bar ---
+        if let Some(bar) = self.bars.as_mut().and_then(|b| b.roll(self.current_time)) {
+            let _ = self.sender.send(&bar);
+            self.stats.messages_sent += 1;
         }
 
-        if runtime.paused {
-            std::thread::sleep(Duration::from_secs_f64(cfg.tick_interval));
-            continue;
+        self.order_flow(&ctx);
+        self.publish_market_data();
+        self.stats.attribute(self.state.current, &tick_start_counts);
+        self.display();
+
+        self.run_report.record_tick(
+            self.state.current,
+            dt_seconds,
+            (self.mid / tick_start_mid).ln(),
+            diffusion_return,
+            sigma,
+        );
+        self.transition_regime();
+
+        self.current_time += dt_seconds;
+        // --- Outside trading hours: skip straight to the next open ---
+        if let Some(resume) = self.session.resume_at() {
+            let resume = self.cfg.duration.map_or(resume, |d| resume.min(d));
+            self.current_time = self.current_time.max(resume);
+        }
+        self.ticks += 1;
+        self.tick_times.record(tick_started.elapsed());
+        if !self.unpaced && !self.replaying && !self.warming {
+            self.pacer.wait();
+        }
+    }
+
+    /// The scenario's own events: the next step of a chain, the forced event,
+    /// scheduled events and a squeeze's periodic up-shocks.
+    fn advance_scenario(&mut self) {
+        let cfg = self.cfg;
+        let now = self.current_time;
+        // --- Scenario chain: next scenario, keeping price and book ---
+        if let Some(step) = self
+            .next_scenarios
+            .front()
+            .copied()
+            .filter(|_| self.active.finished(now))
+        {
+            self.next_scenarios.pop_front();
+            self.active = ActiveScenario::new(step, cfg, now, &self.runtime.params);
+            self.state
+                .transition_to(self.active.cfg.starting_regime, &mut self.rngs.regimes);
+            let step_no = cfg.scenarios.len() - self.next_scenarios.len();
+            let text = format!(
+                "scenario {} ({}/{})",
+                step.scenario,
+                step_no,
+                cfg.scenarios.len()
+            );
+            admin(
+                &mut self.sender,
+                &mut self.stats,
+                Severity::Info,
+                ADMIN_SCENARIO,
+                &text,
+                self.cfg.instrument_index,
+                now,
+            );
+            self.out.event(
+                &format!(
+                    "  ▶ SCENARIO  {} ({}/{})  regime -> {}  t={:.1}s",
+                    step,
+                    step_no,
+                    cfg.scenarios.len(),
+                    self.state.current,
+                    now
+                ),
+                Record::new("scenario", now)
+                    .str("scenario", step.scenario)
+                    .count("step", step_no)
+                    .str("regime", self.state.current),
+            );
         }
+        let scenario_time = now - self.active.started_at;
+        let active = &mut self.active;
 
         // --- Forced scenario event ---
-        if !forced_event_fired
-            && scenario_cfg.forced_event_time > 0.0
-            && current_time >= scenario_cfg.forced_event_time
+        if !active.forced_event_fired
+            && active.cfg.forced_event_time > 0.0
+            && scenario_time >= active.cfg.forced_event_time
         {
-            forced_event_fired = true;
-            state.transition_to(scenario_cfg.forced_regime, &mut rng);
+            active.forced_event_fired = true;
+            self.state
+                .transition_to(active.cfg.forced_regime, &mut self.rngs.regimes);
 
             // Flash crash: short duration override
-            if cfg.scenario == Scenario::FlashCrash {
-                state.regime_duration = 3.0 + rng.gen::<f64>() * 4.0;
+            if active.scenario == Scenario::FlashCrash {
+                self.state.regime_duration = 3.0 + self.rngs.regimes.gen::<f64>() * 4.0;
+            }
+            // Squeeze: the RALLY lasts as long as the squeeze
+            if let Some(sq) = active.squeeze {
+                self.state.regime_duration = sq.duration;
             }
 
-            out.event(&format!(
-                "  ▶ FORCED EVENT  regime -> {}  t={:.1}s",
-                state.current, current_time
-            ));
+            self.out.event(
+                &format!(
+                    "  ▶ FORCED EVENT  regime -> {}  t={:.1}s",
+                    self.state.current, now
+                ),
+                Record::new("forced_event", now).str("regime", self.state.current),
+            );
+
+            if active.cfg.forced_shock_pct != 0.0 {
+                self.mid *= 1.0 + active.cfg.forced_shock_pct;
+                self.mid = self.mid.max(cfg.tick_size);
+                self.out.event(
+                    &format!(
+                        "  ⚡ FORCED SHOCK  {:+.2}% -> mid={:.4}  t={:.1}s",
+                        active.cfg.forced_shock_pct * 100.0,
+                        self.mid,
+                        now
+                    ),
+                    Record::new("shock", now)
+                        .num("pct", active.cfg.forced_shock_pct * 100.0)
+                        .num("mid", self.mid)
+                        .bool("forced", true),
+                );
+                if active.cfg.forced_shock_pct > 0.0 {
+                    active.cover_burst += active.squeezing(now).map_or(0, |sq| sq.cover_orders);
+                }
+            }
         }
 
-        // --- Shock event ---
-        if rng.gen::<f64>() < runtime.shock_prob {
-            let shock_pct =
-                cfg.shock_min_pct + rng.gen::<f64>() * (cfg.shock_max_pct - cfg.shock_min_pct);
-            let direction: f64 = if rng.gen::<f64>() < 0.5 { 1.0 } else { -1.0 };
-            mid *= 1.0 + direction * shock_pct;
-            mid = mid.max(cfg.tick_size);
+        // --- Scheduled scenario events ---
+        while let Some(ev) = active
+            .cfg
+            .events
+            .get(active.next_event)
+            .filter(|ev| scenario_time >= ev.at)
+        {
+            active.next_event += 1;
+            if let Some(regime) = ev.regime {
+                self.state.transition_to(regime, &mut self.rngs.regimes);
+                self.out.event(
+                    &format!("  ▶ SCENARIO EVENT  regime -> {}  t={:.1}s", regime, now),
+                    Record::new("forced_event", now).str("regime", regime),
+                );
+            }
+            if ev.shock_pct != 0.0 {
+                self.mid *= 1.0 + ev.shock_pct;
+                self.mid = self.mid.max(cfg.tick_size);
+                self.out.event(
+                    &format!(
+                        "  ⚡ FORCED SHOCK  {:+.2}% -> mid={:.4}  t={:.1}s",
+                        ev.shock_pct * 100.0,
+                        self.mid,
+                        now
+                    ),
+                    Record::new("shock", now)
+                        .num("pct", ev.shock_pct * 100.0)
+                        .num("mid", self.mid)
+                        .bool("forced", true),
+                );
+            }
+        }
 
-            let sign = if direction > 0.0 { "+" } else { "" };
-            out.event(&format!(
+        // --- Squeeze: further up-shocks at a fixed interval ---
+        if let Some(sq) = active.squeezing(now).filter(|sq| sq.shock_interval > 0.0) {
+            if scenario_time >= active.next_squeeze_shock {
+                active.next_squeeze_shock += sq.shock_interval;
+                self.mid *= 1.0 + sq.shock_pct;
+                active.cover_burst += sq.cover_orders;
+                self.out.event(
+                    &format!(
+                        "  ⚡ SQUEEZE SHOCK  {:+.2}% -> mid={:.4}  t={:.1}s",
+                        sq.shock_pct * 100.0,
+                        self.mid,
+                        now
+                    ),
+                    Record::new("shock", now)
+                        .num("pct", sq.shock_pct * 100.0)
+                        .num("mid", self.mid)
+                        .bool("forced", true),
+                );
+            }
+        }
+    }
+
+    /// A random shock with the `[shocks]` probability.
+    fn random_shock(&mut self) {
+        let runtime = &self.runtime;
+        if self.rngs.shocks.gen::<f64>() >= runtime.shock_prob {
+            return;
+        }
+        let shock_pct = runtime.shock_min_pct
+            + self.rngs.shocks.gen::<f64>() * (runtime.shock_max_pct - runtime.shock_min_pct);
+        let direction: f64 = if self.rngs.shocks.gen::<f64>() < 0.5 {
+            1.0
+        } else {
+            -1.0
+        };
+        let now = self.current_time;
+        let forced = apply_shock(
+            direction * shock_pct,
+            &mut self.mid,
+            self.cfg.tick_size,
+            &mut self.state,
+            &mut self.rngs.regimes,
+            &mut self.active,
+            now,
+            &mut self.run_report,
+        );
+
+        let sign = if direction > 0.0 { "+" } else { "" };
+        self.out.event(
+            &format!(
                 "  ⚡ SHOCK  {}{:.2}% -> mid={:.4}  t={:.1}s",
                 sign,
                 shock_pct * 100.0 * direction,
-                mid,
-                current_time
-            ));
+                self.mid,
+                now
+            ),
+            Record::new("shock", now)
+                .num("pct", shock_pct * 100.0 * direction)
+                .num("mid", self.mid)
+                .bool("forced", false),
+        );
+        if let Some(regime) = forced {
+            self.out.event(
+                &format!("  ⚡ SHOCK triggered regime -> {}", regime),
+                Record::new("shock_regime", now).str("regime", regime),
+            );
+        }
+    }
 
-            if state.current == Regime::Calm || state.current == Regime::Recovery {
-                let next = if direction < 0.0 {
-                    Regime::Crash
-                } else {
-                    Regime::Rally
-                };
-                state.transition_to(next, &mut rng);
-                out.event(&format!(
-                    "  ⚡ SHOCK triggered regime -> {}",
-                    state.current
-                ));
-            }
+    /// Whether a systemic shock hits the universe this tick, and the fall it
+    /// brings (`--symbols-file`). The first symbol draws it for every symbol.
+    fn draw_systemic_shock(&mut self) -> Option<f64> {
+        let runtime = &self.runtime;
+        if self.rngs.systemic.gen::<f64>() >= runtime.systemic_shock_prob {
+            return None;
         }
+        let shock_pct = runtime.shock_min_pct
+            + self.rngs.systemic.gen::<f64>() * (runtime.shock_max_pct - runtime.shock_min_pct);
+        Some(-shock_pct)
+    }
 
-        let params = regime::params(state.current);
+    /// The spot's mid for a futures leg to price off (`[basis]`).
+    fn set_spot(&mut self, spot: f64) {
+        if let Some(b) = self.basis.as_mut() {
+            b.set_spot(spot);
+        }
+    }
 
-        // --- GBM mid-price update (mu is per-second, sigma is annualized) ---
-        let drift_term = params.mu * dt_seconds;
-        let z: f64 = {
-            let u1: f64 = rng.gen::<f64>().max(1e-15);
-            let u2: f64 = rng.gen();
-            (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
-        };
-        let diffusion_term = params.sigma * dt.sqrt() * z;
-        mid *= (drift_term + diffusion_term).exp();
-        mid = mid.max(cfg.tick_size);
-
-        // --- Print regime changes ---
-        if state.current != last_printed_regime {
-            let p = regime::params(state.current);
-            out.event(&format!(
-                "  ↔ REGIME  {} -> {}  (σ={} μ={} buy_prob={})  t={:.1}s",
-                last_printed_regime, state.current, p.sigma, p.mu, p.buy_prob, current_time
-            ));
-            last_printed_regime = state.current;
+    /// Take a systemic shock: the mid falls by `shock` and the symbol goes
+    /// into CRASH whatever its regime.
+    fn systemic_shock(&mut self, shock: f64) {
+        let now = self.current_time;
+        apply_shock(
+            shock,
+            &mut self.mid,
+            self.cfg.tick_size,
+            &mut self.state,
+            &mut self.rngs.regimes,
+            &mut self.active,
+            now,
+            &mut self.run_report,
+        );
+        if self.state.current != Regime::Crash {
+            self.state
+                .transition_to(Regime::Crash, &mut self.rngs.regimes);
         }
+        self.out.event(
+            &format!(
+                "  ⚡ SYSTEMIC {:.2}% -> mid={:.4}  t={:.1}s",
+                shock * 100.0,
+                self.mid,
+                now
+            ),
+            Record::new("systemic_shock", now)
+                .num("pct", shock * 100.0)
+                .num("mid", self.mid),
+        );
+    }
 
-        // --- Generate orders for this tick (with throughput scaling) ---
-        let mut tick_orders: Vec<Order> = Vec::new();
+    /// Open and close the scenario's scheduled windows, some opening with a
+    /// cancel wave; returns the multipliers of the windows now open.
+    fn poll_timeline(&mut self, scenario_time: f64) -> Multipliers {
+        let now = self.current_time;
+        for event in self.active.timeline.poll(scenario_time) {
+            match event {
+                TimelineEvent::Open(w) => {
+                    let ids = self.book.order_ids();
+                    let n = (ids.len() as f64 * w.cancel_wave).round() as usize;
+                    let doomed: Vec<u64> = ids
+                        .choose_multiple(&mut self.rngs.events, n)
+                        .copied()
+                        .collect();
+                    for &id in &doomed {
+                        self.book.remove(id);
+                        let _ = self.sender.send_cancel(id, self.cfg.instrument_index, now);
+                        self.order_ids.release(id);
+                        self.stats.messages_sent += 1;
+                        self.stats.cancels_regime += 1;
+                    }
+                    self.out.event(
+                        &format!(
+                            "  ▶ WINDOW  {} begins  cancelled={}  t={:.1}s",
+                            w.label,
+                            doomed.len(),
+                            now
+                        ),
+                        Record::new("window", now)
+                            .str("label", &w.label)
+                            .str("phase", "begin")
+                            .count("cancelled", doomed.len()),
+                    );
+                }
+                TimelineEvent::Close(w) => {
+                    self.out.event(
+                        &format!("  ▶ WINDOW  {} ends  t={:.1}s", w.label, now),
+                        Record::new("window", now)
+                            .str("label", &w.label)
+                            .str("phase", "end"),
+                    );
+                }
+            }
+        }
+        self.active.timeline.multipliers()
+    }
 
-        let limit_lambda = params.limit_rate * runtime.throughput_scale * dt_seconds;
-        let num_limits: u64 = if limit_lambda > 0.0 {
-            rng.sample(Poisson::new(limit_lambda).unwrap()) as u64
-        } else {
-            0
+    /// Step the price model (and the fundamental value pulling on it), or
+    /// for a futures leg the basis over the spot's mid. Returns the model's own log return, jumps aside, and the sigma it ran
+    /// at, for the run report.
+    fn move_price(
+        &mut self,
+        params: &RegimeParams,
+        squeezing: Option<&SqueezeConfig>,
+    ) -> (f64, f64) {
+        let dt_seconds = self.cfg.tick_interval;
+        let tick_size = self.cfg.tick_size;
+        let step = match self.basis.as_mut() {
+            Some(basis) => basis.step(dt_seconds, &mut self.rngs.price),
+            None => self.price_model.step(
+                self.mid,
+                dt_seconds,
+                self.state.current,
+                params,
+                &mut self.rngs.price,
+            ),
         };
+        // The run report sets the model's own moves apart from jumps, shocks and the rest
+        let diffusion_return = (step.mid / self.mid).ln() - step.jump_return;
+        self.mid = step.mid.max(tick_size);
+        if let Some(f) = self.fundamental.as_mut().filter(|_| self.basis.is_none()) {
+            self.mid = f
+                .step(self.mid, dt_seconds, &mut self.rngs.price)
+                .max(tick_size);
+        }
+        if step.jumps > 0 {
+            let pct = (step.jump_return.exp() - 1.0) * 100.0;
+            self.out.event(
+                &format!(
+                    "  ⚡ JUMP  {:+.2}% ({} jump{}) -> mid={:.4}  t={:.1}s",
+                    pct,
+                    step.jumps,
+                    if step.jumps == 1 { "" } else { "s" },
+                    self.mid,
+                    self.current_time
+                ),
+                Record::new("jump", self.current_time)
+                    .count("jumps", step.jumps)
+                    .num("pct", pct)
+                    .num("mid", self.mid),
+            );
+            if step.jump_return > 0.0 {
+                self.active.cover_burst += squeezing.map_or(0, |sq| sq.cover_orders);
+            }
+        }
+        (diffusion_return, step.sigma)
+    }
 
-        let offset_dist = Exp::new(params.offset_lambda).unwrap();
-
-        for _ in 0..num_limits {
-            let side = if rng.gen::<f64>() < params.buy_prob {
-                Side::Buy
-            } else {
-                Side::Sell
-            };
-            let offset = params.half_spread + rng.sample::<f64, _>(offset_dist);
-            let raw_price = match side {
-                Side::Buy => mid - offset,
-                Side::Sell => mid + offset,
-            };
-            let price = (raw_price / cfg.tick_size).round() * cfg.tick_size;
-            let size = (rng.sample::<f64, _>(size_dist).round() as u32).max(1);
-
-            tick_orders.push(Order {
-                id: next_id,
-                side,
-                order_type: OrderType::Limit,
-                price,
-                size,
-                created_at: current_time,
-                ttl: rng.sample(ttl_dist),
+    /// The circuit breaker and the trading session: halts, auctions and their
+    /// uncross, breaks and openings. Returns whether the session just closed.
+    fn venue_events(&mut self) -> bool {
+        let cfg = self.cfg;
+        let now = self.current_time;
+        // --- Circuit breaker: no new orders while halted or in the reopening auction ---
+        if let Some(venue_state) = self.breaker.update(now, self.mid) {
+            let _ = self.sender.send(&VenueState {
+                state: venue_state,
+                instrument: cfg.instrument_index,
+                time: now,
             });
-            next_id += 1;
+            self.stats.messages_sent += 1;
+            let note = match venue_state {
+                TradingState::Halted => {
+                    format!("  move > {}%", cfg.circuit_breaker.move_pct * 100.0)
+                }
+                _ => String::new(),
+            };
+            self.out.event(
+                &format!(
+                    "  ▶ {}  mid={:.4}{}  t={:.1}s",
+                    venue_state, self.mid, note, now
+                ),
+                Record::new("venue_state", now)
+                    .str("state", venue_state)
+                    .num("mid", self.mid),
+            );
         }
-        stats.limits_generated += num_limits;
-
-        let market_lambda = params.market_rate * runtime.throughput_scale * dt_seconds;
-        let num_markets: u64 = if market_lambda > 0.0 {
-            rng.sample(Poisson::new(market_lambda).unwrap()) as u64
-        } else {
-            0
-        };
+        // --- Session: auctions collect orders, then uncross at a single price ---
+        match self.session.poll(now) {
+            Some(SessionEvent::AuctionStart) => {
+                let _ = self.sender.send(&VenueState {
+                    state: TradingState::Auction,
+                    instrument: cfg.instrument_index,
+                    time: now,
+                });
+                self.stats.messages_sent += 1;
+                self.out.event(
+                    &format!("  ▶ AUCTION  closing auction  t={:.1}s", now),
+                    Record::new("auction", now).str("phase", "closing"),
+                );
+            }
+            Some(SessionEvent::Uncross) => {
+                let markets = self.session.take_markets();
+                let (result, fills) = auction::uncross(&mut self.book, &markets, self.mid, now);
+                let _ = self.sender.send(&result);
+                self.stats.messages_sent += 1;
+                for fill in &fills {
+                    match fill {
+                        UncrossFill::Trade(t) => {
+                            let _ = self.sender.send_trade(t);
+                            self.stats.trades += 1;
+                        }
+                        UncrossFill::Refresh(order) => {
+                            let _ = self.sender.send_order(order);
+                        }
+                    }
+                    self.stats.messages_sent += 1;
+                }
+                if result.volume > 0 {
+                    self.mid = result.price;
+                    if let Some(bars) = self.bars.as_mut() {
+                        bars.record(result.price, result.volume.min(u32::MAX as u64) as u32);
+                    }
+                    if let Some(crypto) = self.crypto.as_mut() {
+                        crypto.uncross(result.price, result.volume, now);
+                    }
+                }
+                let next_state = if self.session.is_closed() || self.session.resume_at().is_some() {
+                    TradingState::Closed
+                } else {
+                    TradingState::Open
+                };
+                let _ = self.sender.send(&VenueState {
+                    state: next_state,
+                    instrument: cfg.instrument_index,
+                    time: now,
+                });
+                self.stats.messages_sent += 1;
+                self.out.event(
+                    &format!(
+                        "  ▶ UNCROSS  price={:.2} volume={} -> {}  t={:.1}s",
+                        result.price, result.volume, next_state, now
+                    ),
+                    Record::new("uncross", now)
+                        .num("price", result.price)
+                        .count("volume", result.volume)
+                        .str("state", next_state),
+                );
+                if self.session.is_closed() {
+                    publish_levels(&mut self.book, &mut self.sender, &mut self.stats, now);
+                    let _ = self.sender.flush();
+                    return true;
+                }
+            }
+            Some(SessionEvent::Break) => {
+                let _ = self.sender.send(&VenueState {
+                    state: TradingState::Closed,
+                    instrument: cfg.instrument_index,
+                    time: now,
+                });
+                self.stats.messages_sent += 1;
+                self.out.event(
+                    &format!("  ▶ CLOSE  lunch break  t={:.1}s", now),
+                    Record::new("break", now).str("phase", "lunch"),
+                );
+            }
+            Some(SessionEvent::Open { new_day }) => {
+                // --- Overnight gap: reopen away from the close, announced with a reference price ---
+                let mut gap_note = String::new();
+                if new_day {
+                    let prev_close = self.mid;
+                    let o = &cfg.session.overnight;
+                    let gap = o.mean + o.std * self.rngs.price.sample::<f64, _>(StandardNormal);
+                    self.mid = (self.mid * gap.exp()).max(cfg.tick_size);
+                    let _ = self.sender.send(&ReferencePrice {
+                        price: self.mid,
+                        prev_close,
+                        instrument: cfg.instrument_index,
+                        time: now,
+                    });
+                    self.stats.messages_sent += 1;
+                    if let Some(f) = self.feedback.as_mut() {
+                        f.reset(self.mid);
+                    }
+                    gap_note = format!(
+                        "  gap={:+.2}% ref={:.2}",
+                        (self.mid / prev_close - 1.0) * 100.0,
+                        self.mid
+                    );
+                }
+                let (state, phase) = match (self.session.in_auction(), new_day) {
+                    (true, true) => (TradingState::Auction, "opening auction"),
+                    (true, false) => (TradingState::Auction, "reopening auction"),
+                    (false, _) => (TradingState::Open, "continuous"),
+                };
+                let _ = self.sender.send(&VenueState {
+                    state,
+                    instrument: cfg.instrument_index,
+                    time: now,
+                });
+                self.stats.messages_sent += 1;
+                self.out.event(
+                    &format!(
+                        "  ▶ {}  day {} {}{}  t={:.1}s",
+                        state,
+                        self.session.day() + 1,
+                        phase,
+                        gap_note,
+                        now
+                    ),
+                    Record::new("open", now)
+                        .count("day", self.session.day() + 1)
+                        .str("phase", phase)
+                        .num("mid", self.mid),
+                );
+            }
+            None => {}
+        }
+        false
+    }
 
-        for _ in 0..num_markets {
-            let side = if rng.gen::<f64>() < params.buy_prob {
-                Side::Buy
-            } else {
-                Side::Sell
+    /// Log a regime change since the last tick; a CRASH may set off a purge.
+    fn log_regime_change(&mut self) {
+        if self.state.current == self.last_printed_regime {
+            return;
+        }
+        let now = self.current_time;
+        let p = self.active.cfg.params(self.state.current);
+        self.out.event(
+            &format!(
+                "  ↔ REGIME  {} -> {}  (σ={} μ={} buy_prob={})  t={:.1}s",
+                self.last_printed_regime, self.state.current, p.sigma, p.mu, p.buy_prob, now
+            ),
+            Record::new("regime", now)
+                .str("from", self.last_printed_regime)
+                .str("to", self.state.current)
+                .num("sigma", p.sigma)
+                .num("mu", p.mu)
+                .num("buy_prob", p.buy_prob),
+        );
+        self.last_printed_regime = self.state.current;
+
+        // --- Purge: participants pull everything with one MASS_CANCEL each ---
+        let purge = &self.cfg.events.mass_cancel;
+        if self.state.current != Regime::Crash || self.rngs.events.gen::<f64>() >= purge.crash_prob
+        {
+            return;
+        }
+        for _ in 0..purge.participants {
+            let Some(participant_id) =
+                choose_nth(self.book.orders(), self.book.len(), &mut self.rngs.events)
+                    .map(|o| o.participant_id)
+            else {
+                break;
             };
-            let price = match side {
-                Side::Buy => 999_999.0,
-                Side::Sell => 0.0,
+            let side = (self.rngs.events.gen::<f64>() < purge.one_side_prob).then(|| {
+                if self.rngs.events.gen::<bool>() {
+                    Side::Buy
+                } else {
+                    Side::Sell
+                }
+            });
+            let doomed: Vec<u64> = self
+                .book
+                .orders()
+                .filter(|o| o.participant_id == participant_id && side.is_none_or(|s| o.side == s))
+                .map(|o| o.id)
+                .collect();
+            if doomed.is_empty() {
+                continue;
+            }
+            for id in &doomed {
+                self.book.remove(*id);
+                self.order_ids.release(*id);
+            }
+            let mass = MassCancel {
+                participant_id,
+                side,
+                instrument: self.cfg.instrument_index,
+                time: now,
             };
-            let raw_size = rng.sample::<f64, _>(size_dist) * 0.5 * params.size_mult;
-            let size = (raw_size.round() as u32).max(1);
+            let _ = self.sender.send_mass_cancel(&mass, &doomed);
+            self.stats.messages_sent += 1;
+            self.stats.cancels_mass += doomed.len() as u64;
+            let side = side.map_or("ALL".to_string(), |s| s.to_string());
+            self.out.event(
+                &format!(
+                    "  ⚑ MASS_CANCEL participant={} side={} orders={}  t={:.1}s",
+                    participant_id,
+                    side,
+                    doomed.len(),
+                    now
+                ),
+                Record::new("mass_cancel", now)
+                    .count("participant", participant_id)
+                    .str("side", side)
+                    .count("orders", doomed.len()),
+            );
+        }
+    }
 
-            tick_orders.push(Order {
-                id: next_id,
-                side,
-                order_type: OrderType::Market,
-                price,
-                size,
-                created_at: current_time,
-                ttl: 0.0,
-            });
-            next_id += 1;
+    /// Price levels, the top-of-book quote, depth snapshots, the option chain
+    /// and the crypto feed's view of the book, then the tick's flush.
+    fn publish_market_data(&mut self) {
+        let cfg = self.cfg;
+        let now = self.current_time;
+        // --- Price levels changed this tick (book_view = "mbp" / "both") ---
+        publish_levels(&mut self.book, &mut self.sender, &mut self.stats, now);
+
+        // --- Top-of-book quote ---
+        if cfg.publish_quotes {
+            let quote = Quote::from_book(&self.book, now);
+            if self.last_quote.as_ref().is_none_or(|q| !q.same_bbo(&quote)) {
+                let _ = self.sender.send(&quote);
+                self.stats.messages_sent += 1;
+                self.last_quote = Some(quote);
+            }
         }
-        stats.markets_generated += num_markets;
 
-        tick_orders.shuffle(&mut rng);
+        // --- L2 depth snapshot ---
+        // (epsilon absorbs float drift in the accumulated sim clock)
+        if cfg.depth_levels > 0 && now + 1e-9 >= self.next_depth_at {
+            let depth = Depth::from_book(&self.book, cfg.depth_levels, now);
+            let _ = self.sender.send(&depth);
+            self.stats.messages_sent += 1;
+            self.next_depth_at = (self.next_depth_at + cfg.depth_interval).max(now);
+        }
 
-        // --- Send orders ---
-        for order in &tick_orders {
-            let _ = sender.send_order(order);
-            stats.messages_sent += 1;
-            if order.order_type == OrderType::Limit {
-                active_orders.insert(order.id, order.clone());
+        // --- Option chain, repriced off the mid ---
+        if let Some(chain) = self.options.as_mut() {
+            for quote in chain.quotes(self.mid, self.state.current, now) {
+                let _ = self.sender.send(&quote);
+                self.stats.messages_sent += 1;
             }
         }
+        if let Some(crypto) = self.crypto.as_mut() {
+            crypto.tick(&self.book, now);
+        }
 
-        // --- Cancel expired orders ---
-        let expired: Vec<u64> = active_orders
-            .iter()
-            .filter(|(_, o)| o.ttl > 0.0 && (current_time - o.created_at) >= o.ttl)
-            .map(|(&id, _)| id)
-            .collect();
+        let _ = self.sender.flush();
+        if let Some((peak, dropped)) = self.sender.take_queue_stats() {
+            self.stats.queue_peak = self.stats.queue_peak.max(peak as u64);
+            self.stats.queue_drops += dropped;
+        }
+        log_corruptions(&mut self.out, &mut self.sender, now);
+    }
 
-        for id in &expired {
-            let _ = sender.send_cancel(*id, current_time);
-            active_orders.remove(id);
-            stats.messages_sent += 1;
+    /// Every display interval: the summary and timing boxes, the ratio and
+    /// rate controllers' updates, and the interval rolled into the run totals.
+    fn display(&mut self) {
+        let now = self.current_time;
+        self.time_since_display += self.cfg.tick_interval;
+        if self.time_since_display < self.runtime.display_interval {
+            return;
+        }
+        self.out.summary(
+            now,
+            self.mid,
+            self.state.current,
+            self.book.len(),
+            &self.stats,
+            self.time_since_display,
+        );
+        if let Some(f) = &self.fundamental {
+            self.out.event(
+                &format!(
+                    "  ▶ FUNDAMENTAL  value={:.4}  mid {:+.2}% away",
+                    f.value(),
+                    (self.mid / f.value() - 1.0) * 100.0
+                ),
+                Record::new("fundamental", now)
+                    .num("value", f.value())
+                    .num("mid", self.mid),
+            );
+        }
+        self.out.timing(
+            now,
+            &self.tick_times,
+            self.sender.send_gaps(),
+            Duration::from_secs_f64(self.tick_budget),
+        );
+        self.tick_times.reset();
+        self.sender.send_gaps().reset();
+        let regime = self.state.current;
+        if let Some(ctl) = self.ratio_ctl.as_mut() {
+            ctl.update(&self.stats.by_regime);
+            let c = &self.stats.by_regime[regime.index()];
+            let otr = c.orders as f64 / c.trades.max(1) as f64;
+            let cta = c.cancels as f64 / c.orders.max(1) as f64;
+            let i = regime.index();
+            self.out.event(
+                &format!(
+                    "  ▶ RATIOS  {}  o/t={:.1} ({})  c/a={:.2} ({})  mkt x{:.2}  cxl x{:.2}",
+                    regime,
+                    otr,
+                    ctl.cfg.order_to_trade.get(regime),
+                    cta,
+                    ctl.cfg.cancel_to_add.get(regime),
+                    ctl.market_mult[i],
+                    ctl.cancel_mult[i]
+                ),
+                Record::new("ratios", now)
+                    .str("regime", regime)
+                    .num("order_to_trade", otr)
+                    .num("cancel_to_add", cta)
+                    .num("market_mult", ctl.market_mult[i])
+                    .num("cancel_mult", ctl.cancel_mult[i]),
+            );
+        }
+        if let Some(ctl) = &self.rate_ctl {
+            let measured = self.stats.messages_sent as f64 / self.time_since_display;
+            self.runtime.throughput_scale = ctl.update(self.runtime.throughput_scale, measured);
         }
-        stats.cancels_expired += expired.len() as u64;
+        self.run_report.record_interval(
+            self.stats.total_orders(),
+            self.stats.total_cancels(),
+            self.stats.trades,
+            self.stats.messages_sent,
+            self.time_since_display,
+        );
+        self.total_messages += self.stats.messages_sent;
+        for (total, interval) in self.regime_totals.iter_mut().zip(&self.stats.by_regime) {
+            total.add(interval);
+        }
+        self.stats.reset();
+        self.time_since_display = 0.0;
+    }
 
-        // --- Regime-driven cancellations (with throughput scaling) ---
-        let cancel_lambda = params.cancel_rate * runtime.throughput_scale * dt_seconds;
-        let num_cancels: u64 = if cancel_lambda > 0.0 {
-            rng.sample(Poisson::new(cancel_lambda).unwrap()) as u64
-        } else {
-            0
+    /// The regime's clock, and a transition when it is due (or when drawdown
+    /// feedback favours one).
+    fn transition_regime(&mut self) {
+        self.state.time_in_regime += self.cfg.tick_interval;
+        let mid = self.mid;
+        let boost = self.feedback.as_mut().and_then(|f| {
+            f.update(mid);
+            f.boost(mid, self.state.current)
+        });
+        let next = regime::try_transition(
+            &self.state,
+            self.active.cfg.allow_transitions,
+            boost,
+            &mut self.rngs.regimes,
+        );
+        if next == self.state.current {
+            return;
+        }
+        if boost.is_some_and(|(target, _)| target == next) {
+            let cause = if next == Regime::Crash {
+                "drawdown"
+            } else {
+                "rebound"
+            };
+            self.out.event(
+                &format!(
+                    "  ↔ FEEDBACK  {} favoured {}  mid={:.4}  t={:.1}s",
+                    cause, next, mid, self.current_time
+                ),
+                Record::new("regime_feedback", self.current_time)
+                    .str("cause", cause)
+                    .str("to", next)
+                    .num("mid", mid),
+            );
+        }
+        self.state.transition_to(next, &mut self.rngs.regimes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price::dt_years;
+    use clap::Parser;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn test_config() -> AppConfig {
+        AppConfig::resolve(&Cli::parse_from(["orderflow-rs", "--seed", "7"])).unwrap()
+    }
+
+    #[test]
+    fn shocks_force_a_regime_from_calm_only() {
+        let cfg = test_config();
+        let step = ScenarioStep {
+            scenario: Scenario::Normal,
+            duration: None,
         };
+        let mut active = ActiveScenario::new(&step, &cfg, 0.0, &RegimeOverrides::default());
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut report = RunReport::new();
+        let mut state = RegimeState::new(Regime::Calm, &mut rng);
+        let mut mid = 100.0;
+
+        let forced = apply_shock(
+            -0.05,
+            &mut mid,
+            0.01,
+            &mut state,
+            &mut rng,
+            &mut active,
+            1.0,
+            &mut report,
+        );
+        assert!((mid - 95.0).abs() < 1e-9);
+        assert_eq!(forced, Some(Regime::Crash));
+        assert_eq!(state.current, Regime::Crash);
+
+        // Already in CRASH: the price moves, the regime stays
+        let forced = apply_shock(
+            0.02,
+            &mut mid,
+            0.01,
+            &mut state,
+            &mut rng,
+            &mut active,
+            2.0,
+            &mut report,
+        );
+        assert!((mid - 96.9).abs() < 1e-9);
+        assert_eq!(forced, None);
+        assert_eq!(state.current, Regime::Crash);
+        assert!(report.lines(dt_years(0.1))[1].contains("shocks: 2"));
+    }
 
-        if num_cancels > 0 && !active_orders.is_empty() {
-            let count = num_cancels.min(active_orders.len() as u64);
-            for _ in 0..count {
-                if active_orders.is_empty() {
-                    break;
-                }
-                let keys: Vec<u64> = active_orders.keys().copied().collect();
-                let &pick = keys.choose(&mut rng).unwrap();
-                let _ = sender.send_cancel(pick, current_time);
-                active_orders.remove(&pick);
-                stats.messages_sent += 1;
-                stats.cancels_regime += 1;
+    #[test]
+    fn the_rate_controller_settles_on_its_target_across_regimes() {
+        let ctl = RateController::new(50_000.0);
+        let mut scale = 1.0;
+        // Messages per second per unit of scale: a quiet regime, then a busy one
+        for gain in [20_000.0, 180_000.0] {
+            for _ in 0..20 {
+                let next = ctl.update(scale, gain * scale);
+                // Damped: no step moves the scale more than 2x
+                assert!(
+                    (0.5..=2.0).contains(&(next / scale)),
+                    "{} -> {}",
+                    scale,
+                    next
+                );
+                scale = next;
             }
+            assert!((gain * scale / 50_000.0 - 1.0).abs() < 1e-3, "{}", scale);
         }
+        // A silent interval raises the scale, within its bound
+        assert_eq!(ctl.update(1.0, 0.0), 2.0);
+        let max = RateController::MAX_SCALE;
+        assert_eq!(ctl.update(max, 0.0), max);
+    }
 
-        // --- Periodic display ---
-        time_since_display += dt_seconds;
-        if time_since_display >= runtime.display_interval {
-            out.summary(
-                current_time,
-                mid,
-                state.current,
-                active_orders.len(),
-                &stats,
-                time_since_display,
-            );
-            stats.reset();
-            time_since_display = 0.0;
+    #[test]
+    fn the_ratio_controller_settles_on_each_regimes_targets() {
+        let cfg = RatioConfig::default();
+        let mut ctl = RatioController::new(&cfg);
+        // Trades and cancels per order rise with the multipliers
+        let measure = |ctl: &RatioController| {
+            let mut counts = [RegimeCounts::default(); 5];
+            for (i, c) in counts.iter_mut().enumerate() {
+                c.orders = 1_000_000;
+                c.trades = (10_000.0 * ctl.market_mult[i]).round() as u64;
+                c.cancels = (500_000.0 * ctl.cancel_mult[i]).round() as u64;
+            }
+            counts
+        };
+        for _ in 0..30 {
+            let counts = measure(&ctl);
+            ctl.update(&counts);
         }
-
-        // --- Regime transition ---
-        state.time_in_regime += dt_seconds;
-        let next = regime::try_transition(&state, scenario_cfg.allow_transitions, &mut rng);
-        if next != state.current {
-            state.transition_to(next, &mut rng);
+        let counts = measure(&ctl);
+        for r in Regime::ALL {
+            let c = &counts[r.index()];
+            let otr = c.orders as f64 / c.trades as f64;
+            let cta = c.cancels as f64 / c.orders as f64;
+            assert!(
+                (otr / cfg.order_to_trade.get(r) - 1.0).abs() < 1e-3,
+                "{} {}",
+                r,
+                otr
+            );
+            assert!(
+                (cta / cfg.cancel_to_add.get(r) - 1.0).abs() < 1e-3,
+                "{} {}",
+                r,
+                cta
+            );
         }
 
-        current_time += dt_seconds;
-        std::thread::sleep(Duration::from_secs_f64(dt_seconds));
+        // Cancels that emptied the book don't push the cancel rate up further
+        let calm = Regime::Calm.index();
+        let before = ctl.cancel_mult[calm];
+        let mut counts = measure(&ctl);
+        counts[calm].cancels /= 2;
+        ctl.cancels_capped[calm] = true;
+        ctl.update(&counts);
+        assert_eq!(ctl.cancel_mult[calm], before);
     }
-
-    out.event("Shutting down...");
-    Ok(())
 }
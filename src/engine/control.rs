@@ -0,0 +1,1161 @@
+//! The UDP control API: parsing commands, the listener thread and applying
+//! commands to a running simulation.
+
+use rand_distr::Uniform;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use super::{admin, apply_shock, warning, ActiveScenario, RegimeCounts, Sim};
+use crate::checkpoint::{LoggedCommand, LoggedReload};
+use crate::config::{self, AppConfig};
+use crate::jsonlog::Record;
+use crate::order::{Order, OrderType, Side};
+use crate::refdata::SymbolDirectory;
+use crate::regime::Regime;
+use crate::scenario::{RegimeOverrides, Scenario, ScenarioConfig, ScenarioStep};
+use crate::sizes::SizeDist;
+use crate::venue::{
+    Severity, ADMIN_CORRUPTION, ADMIN_QUOTE_STUFFING, ADMIN_SCENARIO, ADMIN_THROUGHPUT,
+};
+
+enum ControlCommand {
+    Pause,
+    Resume,
+    Throughput(f64),
+    DisplayInterval(f64),
+    Regime(Regime),
+    Reload,
+    Stats,
+    StatsDetail,
+    QuoteStuffing(Option<f64>),
+    RefData,
+    Scenario(Scenario),
+    /// Immediate move of mid, as a signed fraction of price (typed in percent).
+    Shock(f64),
+    /// Random shock probability per tick and magnitude range, as fractions
+    /// of price like `[shocks]` (typed in percent, like `shock`).
+    ShockConfig {
+        prob: f64,
+        min_pct: f64,
+        max_pct: f64,
+    },
+    /// Limit order TTL range in seconds.
+    Ttl {
+        min: f64,
+        max: f64,
+    },
+    Param {
+        regime: Regime,
+        field: String,
+        value: f64,
+    },
+    /// One order to send on the next tick; `price` is ignored for market orders.
+    Order {
+        side: Side,
+        order_type: OrderType,
+        price: f64,
+        size: u32,
+    },
+    /// Push selected events to the sender until it unsubscribes.
+    Subscribe,
+    Unsubscribe,
+    /// Run `cmd` (typed as `text`) once sim time reaches `at`.
+    At {
+        at: f64,
+        cmd: Box<ControlCommand>,
+        text: String,
+    },
+}
+
+impl ControlCommand {
+    /// Whether the command changes the simulation, so that resuming from a
+    /// checkpoint has to apply it again. Pausing only holds the clock.
+    fn replayable(&self) -> bool {
+        !matches!(
+            self,
+            ControlCommand::Pause
+                | ControlCommand::Resume
+                | ControlCommand::Stats
+                | ControlCommand::StatsDetail
+                | ControlCommand::Subscribe
+                | ControlCommand::Unsubscribe
+        )
+    }
+}
+
+pub(super) struct RuntimeTunables {
+    pub(super) throughput_scale: f64,
+    pub(super) display_interval: f64,
+    pub(super) shock_prob: f64,
+    pub(super) shock_min_pct: f64,
+    pub(super) shock_max_pct: f64,
+    pub(super) systemic_shock_prob: f64,
+    pub(super) iceberg_prob: f64,
+    pub(super) iceberg_min_mult: u32,
+    pub(super) iceberg_max_mult: u32,
+    pub(super) min_resting_time: f64,
+    pub(super) corrupt_prob: f64,
+    /// Regime parameters set with the `param` command; they outlast scenario switches.
+    pub(super) params: RegimeOverrides,
+    pub(super) paused: bool,
+}
+
+impl RuntimeTunables {
+    /// The tunables as configured, before any command changes them.
+    pub(super) fn new(cfg: &AppConfig) -> Self {
+        RuntimeTunables {
+            throughput_scale: cfg.throughput_scale,
+            display_interval: cfg.display_interval,
+            shock_prob: cfg.shock_prob,
+            shock_min_pct: cfg.shock_min_pct,
+            shock_max_pct: cfg.shock_max_pct,
+            systemic_shock_prob: cfg.systemic_shock_prob,
+            iceberg_prob: cfg.iceberg_prob,
+            iceberg_min_mult: cfg.iceberg_min_mult,
+            iceberg_max_mult: cfg.iceberg_max_mult,
+            min_resting_time: cfg.min_resting_time,
+            corrupt_prob: cfg.corrupt_prob,
+            params: RegimeOverrides::default(),
+            paused: false,
+        }
+    }
+}
+
+/// Startup-only settings that differ between `old` and `new`, named as in
+/// the config file; `reload` reports these instead of applying them.
+fn startup_only_changes(old: &AppConfig, new: &AppConfig) -> Vec<&'static str> {
+    startup_settings(old)
+        .into_iter()
+        .zip(startup_settings(new))
+        .filter(|((_, a), (_, b))| a != b)
+        .map(|((name, _), _)| name)
+        .collect()
+}
+
+/// Settings only read at startup, rendered for comparison. The seed is left
+/// out: without one, each resolve draws a fresh random seed.
+fn startup_settings(c: &AppConfig) -> Vec<(&'static str, String)> {
+    let custom = &c.custom_scenario;
+    vec![
+        ("simulation.scenario", format!("{:?}", c.scenarios)),
+        ("simulation.initial_price", format!("{:?}", c.initial_price)),
+        ("simulation.tick_interval", format!("{:?}", c.tick_interval)),
+        ("simulation.tick_size", format!("{:?}", c.tick_size)),
+        ("simulation.lot_size", format!("{:?}", c.lot_size)),
+        ("simulation.symbol", format!("{:?}", c.instruments)),
+        ("simulation.pacing", format!("{:?}", c.pacing)),
+        ("simulation.time_scale", format!("{:?}", c.time_scale)),
+        ("simulation.realtime", format!("{:?}", c.realtime)),
+        (
+            "simulation.warmup_seconds",
+            format!("{:?}", c.warmup_seconds),
+        ),
+        ("simulation.warmup_orders", format!("{:?}", c.warmup_orders)),
+        ("simulation.price_model", format!("{:?}", c.price_model)),
+        ("simulation.target_rate", format!("{:?}", c.target_rate)),
+        ("simulation.pin_cores", format!("{:?}", c.pin_cores)),
+        ("simulation.rt_priority", format!("{:?}", c.rt_priority)),
+        ("simulation.duration", format!("{:?}", c.duration)),
+        ("simulation.max_messages", format!("{:?}", c.max_messages)),
+        ("simulation.stop_at_price", format!("{:?}", c.stop_at_price)),
+        (
+            "network.multicast_group",
+            format!("{:?}", c.multicast_group),
+        ),
+        ("network.multicast_port", format!("{:?}", c.multicast_port)),
+        (
+            "network.multicast_ttl",
+            format!("{:?}", c.multicast_options.ttl),
+        ),
+        (
+            "network.interface",
+            format!("{:?}", c.multicast_options.interface),
+        ),
+        (
+            "network.multicast_loop",
+            format!("{:?}", c.multicast_options.loopback),
+        ),
+        ("network.wire_format", format!("{:?}", c.wire_format)),
+        ("network.batch", format!("{:?}", c.max_packet_bytes)),
+        (
+            "network.legacy_market_price",
+            format!("{:?}", c.legacy_market_price),
+        ),
+        (
+            "network.epoch_timestamps",
+            format!("{:?}", c.epoch_timestamps),
+        ),
+        ("network.crc", format!("{:?}", c.crc)),
+        ("network.wire_version", c.wire_version.to_string()),
+        ("network.sender_queue", format!("{:?}", c.sender_queue)),
+        ("network.sender", format!("{:?}", c.sender)),
+        ("orders.id_scheme", format!("{:?}", c.id_scheme)),
+        (
+            "orders.id_recycle_delay",
+            format!("{:?}", c.id_recycle_delay),
+        ),
+        (
+            "orders.client_order_ids",
+            format!("{:?}", c.client_order_ids),
+        ),
+        ("output.mode", format!("{:?}", c.output_mode)),
+        ("output.format", format!("{:?}", c.log_format)),
+        ("output.log_file", format!("{:?}", c.log_file)),
+        ("output.rotation", format!("{:?}", c.log_rotation)),
+        ("control.enabled", format!("{:?}", c.control_enabled)),
+        ("control.bind", format!("{:?}", c.control_bind)),
+        (
+            "control.legacy_replies",
+            format!("{:?}", c.control_legacy_replies),
+        ),
+        ("feed.bars", format!("{:?}", c.bar_interval)),
+        ("feed.publish_quotes", format!("{:?}", c.publish_quotes)),
+        ("feed.depth_levels", format!("{:?}", c.depth_levels)),
+        ("feed.depth_interval", format!("{:?}", c.depth_interval)),
+        ("feed.book_view", format!("{:?}", c.book_view)),
+        ("crypto_feed", format!("{:?}", c.crypto_feed)),
+        ("options", format!("{:?}", c.options)),
+        ("ouch", format!("{:?}", c.ouch)),
+        ("participants.count", format!("{:?}", c.participant_count)),
+        (
+            "participants.first_id",
+            format!("{:?}", c.participant_first_id),
+        ),
+        (
+            "participants.zipf_exponent",
+            format!("{:?}", c.participant_zipf_exponent),
+        ),
+        (
+            "participants.weights",
+            format!("{:?}", c.participant_weights),
+        ),
+        ("agents", format!("{:?}", c.agents)),
+        ("events", format!("{:?}", c.events)),
+        ("stops", format!("{:?}", c.stops)),
+        ("book.matching", format!("{:?}", c.matching)),
+        ("book.self_match", format!("{:?}", c.self_match)),
+        ("book.dark_fraction", format!("{:?}", c.dark_fraction)),
+        (
+            "book.imbalance_sensitivity",
+            format!("{:?}", c.imbalance_sensitivity),
+        ),
+        ("ratios", format!("{:?}", c.ratios)),
+        ("jumps", format!("{:?}", c.jumps)),
+        ("ou", format!("{:?}", c.ou)),
+        ("garch", format!("{:?}", c.garch)),
+        ("fundamental", format!("{:?}", c.fundamental)),
+        ("regime_feedback", format!("{:?}", c.regime_feedback)),
+        ("spreads", format!("{:?}", c.spreads)),
+        ("news", format!("{:?}", c.news)),
+        ("drought", format!("{:?}", c.drought)),
+        ("squeeze", format!("{:?}", c.squeeze)),
+        ("lead_lag", format!("{:?}", c.lead_lag)),
+        ("basis", format!("{:?}", c.basis)),
+        // `params` is handled by `reload` itself
+        (
+            "custom_scenario.starting_regime",
+            format!("{:?}", custom.starting_regime),
+        ),
+        (
+            "custom_scenario.allow_transitions",
+            format!("{:?}", custom.allow_transitions),
+        ),
+        (
+            "custom_scenario.stop_rate",
+            format!("{:?}", custom.stop_rate),
+        ),
+        ("custom_scenario.events", format!("{:?}", custom.events)),
+        ("custom_scenario.windows", format!("{:?}", custom.windows)),
+        ("seasonality", format!("{:?}", c.seasonality)),
+        ("circuit_breaker", format!("{:?}", c.circuit_breaker)),
+        ("price_bands", format!("{:?}", c.price_bands)),
+        ("rejects", format!("{:?}", c.rejects)),
+        ("session", format!("{:?}", c.session)),
+    ]
+}
+
+fn parse_regime(s: &str) -> Option<Regime> {
+    match s {
+        "calm" => Some(Regime::Calm),
+        "volatile" => Some(Regime::Volatile),
+        "crash" => Some(Regime::Crash),
+        "rally" => Some(Regime::Rally),
+        "recovery" => Some(Regime::Recovery),
+        _ => None,
+    }
+}
+
+fn parse_control_command(input: &str) -> Option<ControlCommand> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    let mut parts = trimmed.split_whitespace();
+    let cmd = parts.next()?.to_ascii_lowercase();
+    match cmd.as_str() {
+        "pause" => Some(ControlCommand::Pause),
+        "resume" => Some(ControlCommand::Resume),
+        "reload" => Some(ControlCommand::Reload),
+        "stats" => match parts.next() {
+            None => Some(ControlCommand::Stats),
+            Some("detail") => Some(ControlCommand::StatsDetail),
+            Some(_) => None,
+        },
+        "refdata" => Some(ControlCommand::RefData),
+        "subscribe" => Some(ControlCommand::Subscribe),
+        "unsubscribe" => Some(ControlCommand::Unsubscribe),
+        "at" => {
+            let at = parts.next()?.parse::<f64>().ok()?;
+            let text = parts.collect::<Vec<_>>().join(" ");
+            match parse_control_command(&text)? {
+                ControlCommand::At { .. } => None,
+                cmd => Some(ControlCommand::At {
+                    at,
+                    cmd: Box::new(cmd),
+                    text,
+                }),
+            }
+        }
+        "rate" | "throughput" => {
+            let v = parts.next()?.parse::<f64>().ok()?;
+            Some(ControlCommand::Throughput(v))
+        }
+        "display" => {
+            let v = parts.next()?.parse::<f64>().ok()?;
+            Some(ControlCommand::DisplayInterval(v))
+        }
+        "regime" => {
+            let r = parse_regime(&parts.next()?.to_ascii_lowercase())?;
+            Some(ControlCommand::Regime(r))
+        }
+        "order" => {
+            let side = match parts.next()?.to_ascii_lowercase().as_str() {
+                "buy" => Side::Buy,
+                "sell" => Side::Sell,
+                _ => return None,
+            };
+            let order_type = match parts.next()?.to_ascii_lowercase().as_str() {
+                "limit" => OrderType::Limit,
+                "market" => OrderType::Market,
+                _ => return None,
+            };
+            let price = parts.next()?.parse::<f64>().ok()?;
+            let size = parts.next()?.parse::<u32>().ok()?;
+            Some(ControlCommand::Order {
+                side,
+                order_type,
+                price,
+                size,
+            })
+        }
+        "shockcfg" => {
+            let prob = parts.next()?.parse::<f64>().ok()?;
+            let min_pct = parts.next()?.parse::<f64>().ok()?;
+            let max_pct = parts.next()?.parse::<f64>().ok()?;
+            Some(ControlCommand::ShockConfig {
+                prob,
+                min_pct: min_pct / 100.0,
+                max_pct: max_pct / 100.0,
+            })
+        }
+        "param" => {
+            let regime = parse_regime(&parts.next()?.to_ascii_lowercase())?;
+            let field = parts.next()?.to_ascii_lowercase();
+            let value = parts.next()?.parse::<f64>().ok()?;
+            Some(ControlCommand::Param {
+                regime,
+                field,
+                value,
+            })
+        }
+        "ttl" => {
+            let min = parts.next()?.parse::<f64>().ok()?;
+            let max = parts.next()?.parse::<f64>().ok()?;
+            Some(ControlCommand::Ttl { min, max })
+        }
+        "shock" => {
+            let v = parts.next()?.parse::<f64>().ok()?;
+            Some(ControlCommand::Shock(v / 100.0))
+        }
+        "scenario" => {
+            let s = config::parse_scenario(&parts.next()?.to_ascii_lowercase()).ok()?;
+            Some(ControlCommand::Scenario(s))
+        }
+        "stuff" => match parts.next() {
+            Some(v) => Some(ControlCommand::QuoteStuffing(Some(v.parse::<f64>().ok()?))),
+            None => Some(ControlCommand::QuoteStuffing(None)),
+        },
+        _ => None,
+    }
+}
+
+const CONTROL_USAGE: &str = "pause|resume|at <t> <command>|rate <x>|display <sec>|regime <name>|scenario <name>|shock <pct>|shockcfg <prob> <min pct> <max pct>|ttl <min> <max>|param <regime> <field> <value>|order <side> <type> <price> <size>|stuff [sec]|reload|stats|subscribe|unsubscribe";
+
+/// A parsed control command and where to send its reply (none for
+/// commands scheduled with `at`).
+pub(super) struct ControlRequest {
+    cmd: ControlCommand,
+    text: String,
+    peer: Option<SocketAddr>,
+}
+
+/// Starts the UDP listener thread. Commands that fail to parse are answered
+/// by the thread itself; the returned socket lets the engine answer the rest
+/// once they have been applied. With `legacy` replies the thread answers every
+/// datagram with plain `ok` / usage text instead.
+pub(super) fn spawn_control_listener(
+    bind: &str,
+    legacy: bool,
+) -> std::io::Result<(Receiver<ControlRequest>, UdpSocket)> {
+    let socket = UdpSocket::bind(bind)?;
+    socket.set_read_timeout(Some(Duration::from_millis(500)))?;
+    let reply_socket = socket.try_clone()?;
+
+    let (tx, rx) = mpsc::channel::<ControlRequest>();
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 1024];
+        loop {
+            match socket.recv_from(&mut buf) {
+                Ok((n, peer)) => {
+                    let cmd_text = String::from_utf8_lossy(&buf[..n]).trim().to_string();
+                    let parsed = parse_control_command(&cmd_text);
+                    let reply = match (&parsed, legacy) {
+                        (Some(_), true) => Some("ok\n".to_string()),
+                        (Some(_), false) => None,
+                        (None, true) => Some(format!("error: commands are {}\n", CONTROL_USAGE)),
+                        (None, false) => Some(format!(
+                            "{}\n",
+                            Record::object()
+                                .str("status", "error")
+                                .str("command", &cmd_text)
+                                .str("error", "unknown command or malformed arguments")
+                                .str("usage", CONTROL_USAGE)
+                                .to_json()
+                        )),
+                    };
+                    if let Some(cmd) = parsed {
+                        let _ = tx.send(ControlRequest {
+                            cmd,
+                            text: cmd_text,
+                            peer: Some(peer),
+                        });
+                    }
+                    if let Some(reply) = reply {
+                        let _ = socket.send_to(reply.as_bytes(), peer);
+                    }
+                }
+                Err(e)
+                    if e.kind() == std::io::ErrorKind::WouldBlock
+                        || e.kind() == std::io::ErrorKind::TimedOut => {}
+                Err(_) => break,
+            }
+        }
+    });
+
+    Ok((rx, reply_socket))
+}
+
+fn control(t: f64, command: &str) -> Record {
+    Record::new("control", t).str("command", command)
+}
+
+impl Sim<'_> {
+    /// Apply the control commands due this tick: `at` commands whose time
+    /// has come, then those received since the last tick (or, replaying a
+    /// checkpoint, the logged ones), answering each sender.
+    pub(super) fn run_commands(&mut self) {
+        // Commands scheduled with `at` that are now due run before newly received ones
+        let due = self
+            .scheduled_commands
+            .partition_point(|(at, _)| *at <= self.current_time);
+        let mut requests: Vec<ControlRequest> = self
+            .scheduled_commands
+            .drain(..due)
+            .map(|(_, req)| req)
+            .collect();
+        // While replaying, the logged commands stand in for the control socket
+        let incoming: Vec<ControlRequest> = if self.replaying {
+            let due = self
+                .replay
+                .iter()
+                .take_while(|c| c.tick <= self.ticks)
+                .count();
+            self.replay
+                .drain(..due)
+                .filter_map(|c| {
+                    parse_control_command(&c.command).map(|cmd| ControlRequest {
+                        cmd,
+                        text: c.command,
+                        peer: None,
+                    })
+                })
+                .collect()
+        } else {
+            self.control_api
+                .as_ref()
+                .map_or_else(Vec::new, |(rx, _)| rx.try_iter().collect())
+        };
+        for req in incoming.iter().filter(|req| req.cmd.replayable()) {
+            self.command_log.push(LoggedCommand {
+                tick: self.ticks,
+                command: req.text.clone(),
+            });
+        }
+        requests.extend(incoming);
+        for req in requests {
+            let wants_state =
+                matches!(req.cmd, ControlCommand::Stats | ControlCommand::StatsDetail);
+            let text = req.text.clone();
+            let peer = req.peer;
+            let result = self.apply_command(req);
+            if !self.cfg.control_legacy_replies {
+                self.reply(&text, peer, result, wants_state);
+            }
+        }
+    }
+
+    /// Apply one command. Returns its result for the reply, if it has one,
+    /// or why it was refused.
+    fn apply_command(&mut self, req: ControlRequest) -> Result<Option<Record>, String> {
+        let now = self.current_time;
+        match req.cmd {
+            ControlCommand::Pause => {
+                self.runtime.paused = true;
+                self.out.event("  ▶ CONTROL pause", control(now, "pause"));
+            }
+            ControlCommand::Resume => {
+                self.runtime.paused = false;
+                self.out.event("  ▶ CONTROL resume", control(now, "resume"));
+            }
+            ControlCommand::Throughput(v) if v >= 0.0 => {
+                self.runtime.throughput_scale = v;
+                let text = format!("throughput {}x", v);
+                self.admin_message(Severity::Info, ADMIN_THROUGHPUT, &text);
+                self.out.event(
+                    &format!("  ▶ CONTROL throughput={}x", v),
+                    control(now, "rate").num("throughput", v),
+                );
+            }
+            ControlCommand::DisplayInterval(v) if v > 0.0 => {
+                self.runtime.display_interval = v;
+                self.out.event(
+                    &format!("  ▶ CONTROL display_interval={}s", v),
+                    control(now, "display").num("display_interval", v),
+                );
+            }
+            ControlCommand::Regime(next) => {
+                self.state.transition_to(next, &mut self.rngs.regimes);
+                self.out.event(
+                    &format!("  ▶ CONTROL regime -> {}", self.state.current),
+                    control(now, "regime").str("regime", self.state.current),
+                );
+            }
+            ControlCommand::Shock(shock) if shock > -1.0 && shock.is_finite() => {
+                let forced = apply_shock(
+                    shock,
+                    &mut self.mid,
+                    self.cfg.tick_size,
+                    &mut self.state,
+                    &mut self.rngs.regimes,
+                    &mut self.active,
+                    now,
+                    &mut self.run_report,
+                );
+                self.out.event(
+                    &format!(
+                        "  ▶ CONTROL shock {:+.2}% -> mid={:.4}",
+                        shock * 100.0,
+                        self.mid
+                    ),
+                    control(now, "shock")
+                        .num("pct", shock * 100.0)
+                        .num("mid", self.mid),
+                );
+                if let Some(regime) = forced {
+                    self.out.event(
+                        &format!("  ⚡ SHOCK triggered regime -> {}", regime),
+                        Record::new("shock_regime", now).str("regime", regime),
+                    );
+                }
+            }
+            ControlCommand::Order {
+                side,
+                order_type,
+                price,
+                size,
+            } if size > 0
+                && (order_type == OrderType::Market || price > 0.0 && price.is_finite()) =>
+            {
+                self.inject_order(side, order_type, price, size);
+            }
+            ControlCommand::ShockConfig {
+                prob,
+                min_pct,
+                max_pct,
+            } if (0.0..=1.0).contains(&prob)
+                && 0.0 <= min_pct
+                && min_pct <= max_pct
+                && max_pct < 1.0 =>
+            {
+                self.runtime.shock_prob = prob;
+                self.runtime.shock_min_pct = min_pct;
+                self.runtime.shock_max_pct = max_pct;
+                self.out.event(
+                    &format!(
+                        "  ▶ CONTROL shocks prob={} range={}%..{}%",
+                        prob,
+                        min_pct * 100.0,
+                        max_pct * 100.0
+                    ),
+                    control(now, "shockcfg")
+                        .num("shock_prob", prob)
+                        .num("min_pct", min_pct * 100.0)
+                        .num("max_pct", max_pct * 100.0),
+                );
+            }
+            ControlCommand::Ttl { min, max } if min >= 0.0 && min < max && max.is_finite() => {
+                self.ttl_dist = Uniform::new(min, max);
+                self.out.event(
+                    &format!("  ▶ CONTROL ttl={}..{}s", min, max),
+                    control(now, "ttl").num("ttl_min", min).num("ttl_max", max),
+                );
+            }
+            ControlCommand::Param {
+                regime,
+                field,
+                value,
+            } => {
+                let mut o = self.runtime.params.get(regime).clone();
+                let updated = o
+                    .set(&field, value)
+                    .then(|| o.apply(self.active.cfg.params(regime)));
+                let Some(p) = updated.filter(|p| p.is_valid()) else {
+                    return Err(self.invalid());
+                };
+                *self.runtime.params.get_mut(regime) = o;
+                self.active.cfg.regime_params[regime.index()] = p;
+                self.active
+                    .dists
+                    .set_params(self.active.cfg.regime_params.clone());
+                self.out.event(
+                    &format!("  ▶ CONTROL param {} {}={}", regime, field, value),
+                    control(now, "param")
+                        .str("regime", regime)
+                        .str("field", &field)
+                        .num("value", value),
+                );
+            }
+            ControlCommand::Scenario(scenario) => {
+                // Runs for the rest of the session; the rest of a chain is dropped.
+                let step = ScenarioStep {
+                    scenario,
+                    duration: None,
+                };
+                self.active = ActiveScenario::new(&step, self.cfg, now, &self.runtime.params);
+                self.next_scenarios.clear();
+                self.state
+                    .transition_to(self.active.cfg.starting_regime, &mut self.rngs.regimes);
+                let text = format!("scenario {}", scenario);
+                self.admin_message(Severity::Info, ADMIN_SCENARIO, &text);
+                self.out.event(
+                    &format!(
+                        "  ▶ CONTROL scenario -> {}  regime -> {}",
+                        scenario, self.state.current
+                    ),
+                    control(now, "scenario")
+                        .str("scenario", scenario)
+                        .str("regime", self.state.current),
+                );
+            }
+            ControlCommand::Reload => match self.reload() {
+                Ok(result) => return Ok(Some(result)),
+                Err(msg) => {
+                    self.out.event(&format!("  ⚠ {}", msg), warning(now, &msg));
+                    return Err(msg);
+                }
+            },
+            ControlCommand::QuoteStuffing(d) if d.is_none_or(|d| d > 0.0) => {
+                let d = self.stuffer.trigger(now, d);
+                let text = format!("quote stuffing for {}s", d);
+                self.admin_message(Severity::Warning, ADMIN_QUOTE_STUFFING, &text);
+                self.out.event(
+                    &format!("  ▶ CONTROL quote stuffing for {}s", d),
+                    control(now, "stuff").num("duration", d),
+                );
+            }
+            ControlCommand::StatsDetail => {
+                let totals = self.run_totals();
+                let mut text = format!(
+                    "  ▶ CONTROL stats detail t={:.1}s (run totals by regime)",
+                    now
+                );
+                for r in Regime::ALL {
+                    let c = &totals[r.index()];
+                    text.push_str(&format!(
+                        "\n      {:<9} orders={} cancels={} trades={} msgs={}",
+                        r.to_string(),
+                        c.orders,
+                        c.cancels,
+                        c.trades,
+                        c.messages
+                    ));
+                }
+                self.out.event(
+                    &text,
+                    control(now, "stats_detail")
+                        .raw("by_regime", RegimeCounts::json_by_regime(&totals)),
+                );
+            }
+            ControlCommand::RefData => {
+                for (index, instrument) in (0..).zip(&self.cfg.instruments) {
+                    let _ = self.sender.send(&SymbolDirectory {
+                        index,
+                        instrument,
+                        time: now,
+                    });
+                    self.stats.messages_sent += 1;
+                }
+                self.out.event(
+                    &format!(
+                        "  ▶ CONTROL refdata ({} symbols)",
+                        self.cfg.instruments.len()
+                    ),
+                    control(now, "refdata").count("symbols", self.cfg.instruments.len()),
+                );
+            }
+            ControlCommand::Stats => {
+                self.out.event(
+                    &format!(
+                        "  ▶ CONTROL stats t={:.1}s mid={:.4} regime={} active={} paused={} throughput={}x",
+                        now,
+                        self.mid,
+                        self.state.current,
+                        self.book.len(),
+                        self.runtime.paused,
+                        self.runtime.throughput_scale
+                    ),
+                    control(now, "stats")
+                        .num("mid", self.mid)
+                        .str("regime", self.state.current)
+                        .count("active", self.book.len())
+                        .bool("paused", self.runtime.paused)
+                        .num("throughput", self.runtime.throughput_scale),
+                );
+            }
+            ControlCommand::Subscribe if req.peer.is_some() => {
+                let peer = req.peer.unwrap();
+                if !self.out.subscribers.contains(&peer) {
+                    self.out.subscribers.push(peer);
+                }
+                self.out.event(
+                    &format!("  ▶ CONTROL subscribe {}", peer),
+                    control(now, "subscribe").str("peer", peer),
+                );
+            }
+            ControlCommand::Unsubscribe if req.peer.is_some() => {
+                let peer = req.peer.unwrap();
+                self.out.subscribers.retain(|p| *p != peer);
+                self.out.event(
+                    &format!("  ▶ CONTROL unsubscribe {}", peer),
+                    control(now, "unsubscribe").str("peer", peer),
+                );
+            }
+            ControlCommand::At { at, cmd, text } if at > now && at.is_finite() => {
+                self.out.event(
+                    &format!("  ▶ CONTROL at t={}s: {}", at, text),
+                    control(now, "at").num("at", at).str("scheduled", &text),
+                );
+                let i = self.scheduled_commands.partition_point(|(t, _)| *t <= at);
+                let req = ControlRequest {
+                    cmd: *cmd,
+                    text,
+                    peer: None,
+                };
+                self.scheduled_commands.insert(i, (at, req));
+            }
+            _ => return Err(self.invalid()),
+        }
+        Ok(None)
+    }
+
+    /// Log a command refused for its values; returns the reply's error.
+    fn invalid(&mut self) -> String {
+        self.out.event(
+            "  ⚠ invalid control value",
+            warning(self.current_time, "invalid control value"),
+        );
+        "invalid control value".to_string()
+    }
+
+    fn admin_message(&mut self, severity: Severity, code: u16, text: &str) {
+        admin(
+            &mut self.sender,
+            &mut self.stats,
+            severity,
+            code,
+            text,
+            self.cfg.instrument_index,
+            self.current_time,
+        );
+    }
+
+    /// Queue the `order` command's order for the next tick's flow.
+    fn inject_order(&mut self, side: Side, order_type: OrderType, price: f64, size: u32) {
+        let now = self.current_time;
+        let order = Order {
+            id: self.order_ids.next(),
+            side,
+            order_type,
+            price: if order_type == OrderType::Market {
+                0.0
+            } else {
+                price
+            },
+            size,
+            reserve: 0,
+            participant_id: self.participants.sample(&mut self.rngs.orders),
+            cl_ord_id: String::new(),
+            instrument: self.cfg.instrument_index,
+            created_at: now,
+            ttl: 0.0,
+        };
+        match order_type {
+            OrderType::Market => self.stats.markets_generated += 1,
+            _ => self.stats.limits_generated += 1,
+        }
+        let at = match order_type {
+            OrderType::Market => "MKT".to_string(),
+            _ => order.price.to_string(),
+        };
+        self.out.event(
+            &format!(
+                "  ▶ CONTROL order {} {} {}@{}  id={}",
+                side, order_type, size, at, order.id
+            ),
+            control(now, "order")
+                .str("side", side)
+                .str("order_type", order_type)
+                .num("price", order.price)
+                .count("size", size)
+                .count("id", order.id),
+        );
+        self.injected_orders.push(order);
+    }
+
+    /// Re-resolve the whole config (CLI flags still win), apply what is safe
+    /// mid-run and report the rest as skipped. Returns the applied and
+    /// skipped settings.
+    fn reload(&mut self) -> Result<Record, String> {
+        let cfg = self.cfg;
+        let Some(path) = &cfg.config_path else {
+            return Err("reload unavailable (run with -c/--config)".to_string());
+        };
+        // A replay takes the file as the run read it, then
+        let read = match self.replay_reloads.pop_front() {
+            Some(logged) if self.replaying => logged
+                .contents
+                .ok_or_else(|| format!("failed to read config file {}", path.display())),
+            _ => config::read_config(path),
+        };
+        self.reload_log.push(LoggedReload {
+            contents: read.as_ref().ok().cloned(),
+        });
+        let contents = read.map_err(|e| format!("reload failed: {}", e))?;
+        let (new, sizes) = AppConfig::resolve_from(self.cli, Some(&contents))
+            .and_then(|new| {
+                let sizes = SizeDist::new(
+                    new.size_distribution,
+                    new.size_mean_log,
+                    new.size_std_log,
+                    new.pareto_scale,
+                    new.pareto_shape,
+                    &new.round_lots,
+                )?;
+                Ok((new, sizes))
+            })
+            .map_err(|e| format!("reload failed: {}", e))?;
+        let now = self.current_time;
+        if new.throughput_scale != self.runtime.throughput_scale {
+            let text = format!("throughput {}x", new.throughput_scale);
+            self.admin_message(Severity::Info, ADMIN_THROUGHPUT, &text);
+        }
+        if new.corrupt_prob != self.runtime.corrupt_prob {
+            let (severity, text) = match new.corrupt_prob {
+                p if p > 0.0 => (
+                    Severity::Warning,
+                    format!("corrupting {}% of datagrams", p * 100.0),
+                ),
+                _ => (Severity::Info, "corruption off".to_string()),
+            };
+            self.admin_message(severity, ADMIN_CORRUPTION, &text);
+        }
+        let runtime = &mut self.runtime;
+        runtime.throughput_scale = new.throughput_scale;
+        runtime.display_interval = new.display_interval;
+        runtime.shock_prob = new.shock_prob;
+        runtime.shock_min_pct = new.shock_min_pct;
+        runtime.shock_max_pct = new.shock_max_pct;
+        runtime.systemic_shock_prob = new.systemic_shock_prob;
+        runtime.iceberg_prob = new.iceberg_prob;
+        runtime.iceberg_min_mult = new.iceberg_min_mult;
+        runtime.iceberg_max_mult = new.iceberg_max_mult;
+        runtime.min_resting_time = new.min_resting_time;
+        runtime.corrupt_prob = new.corrupt_prob;
+        self.size_dist = sizes;
+        self.ttl_dist = Uniform::new(new.ttl_min, new.ttl_max);
+        self.sender.set_corrupt_prob(new.corrupt_prob, cfg.seed);
+        let mut applied = vec![
+            "simulation.throughput_scale",
+            "output.display_interval",
+            "shocks",
+            "orders.sizes",
+            "orders.ttl",
+            "orders.iceberg",
+            "orders.min_resting_time",
+            "network.corrupt_prob",
+        ];
+        let mut skipped = startup_only_changes(cfg, &new);
+        if self.active.scenario == Scenario::Custom {
+            let mut custom = ScenarioConfig::from_scenario(Scenario::Custom, &new);
+            for r in Regime::ALL {
+                custom.regime_params[r.index()] =
+                    self.runtime.params.get(r).apply(custom.params(r));
+            }
+            self.active.cfg.regime_params = custom.regime_params;
+            self.active
+                .dists
+                .set_params(self.active.cfg.regime_params.clone());
+            applied.push("custom_scenario.params");
+        } else if format!("{:?}", cfg.custom_scenario.params)
+            != format!("{:?}", new.custom_scenario.params)
+        {
+            skipped.push("custom_scenario.params");
+        }
+        let runtime = &self.runtime;
+        self.out.event(
+            &format!(
+                "  ▶ CONTROL reload OK throughput={}x display={}s shock_prob={}  skipped: {}",
+                runtime.throughput_scale,
+                runtime.display_interval,
+                runtime.shock_prob,
+                if skipped.is_empty() {
+                    "none".to_string()
+                } else {
+                    skipped.join(", ")
+                }
+            ),
+            control(now, "reload")
+                .num("throughput", runtime.throughput_scale)
+                .num("display_interval", runtime.display_interval)
+                .num("shock_prob", runtime.shock_prob)
+                .strs("applied", &applied)
+                .strs("skipped", &skipped),
+        );
+        Ok(Record::object()
+            .strs("applied", &applied)
+            .strs("skipped", &skipped))
+    }
+
+    /// Counts by regime over the run so far, the current interval included.
+    fn run_totals(&self) -> [RegimeCounts; 5] {
+        let mut totals = self.regime_totals;
+        for (total, interval) in totals.iter_mut().zip(&self.stats.by_regime) {
+            total.add(interval);
+        }
+        totals
+    }
+
+    /// Answer a command's sender with its status, result and, for `stats`,
+    /// the engine's state.
+    fn reply(
+        &self,
+        command: &str,
+        peer: Option<SocketAddr>,
+        result: Result<Option<Record>, String>,
+        wants_state: bool,
+    ) {
+        let (Some(peer), Some((_, reply_socket))) = (peer, &self.control_api) else {
+            return;
+        };
+        let mut reply = Record::object()
+            .str("status", if result.is_err() { "error" } else { "ok" })
+            .str("command", command)
+            .num("t", self.current_time);
+        match result {
+            Err(e) => reply = reply.str("error", e),
+            Ok(Some(r)) => reply = reply.raw("result", r.to_json()),
+            Ok(None) => {}
+        }
+        if wants_state {
+            let totals = self.run_totals();
+            let runtime = &self.runtime;
+            let state_json = Record::object()
+                .num("mid", self.mid)
+                .str("regime", self.state.current)
+                .str("scenario", self.active.scenario)
+                .num("scenario_time", self.current_time - self.active.started_at)
+                .count("active", self.book.len())
+                .bool("paused", runtime.paused)
+                .num("throughput", runtime.throughput_scale)
+                .num("display_interval", runtime.display_interval)
+                .num("shock_prob", runtime.shock_prob)
+                .num("shock_min_pct", runtime.shock_min_pct * 100.0)
+                .num("shock_max_pct", runtime.shock_max_pct * 100.0)
+                .count("scheduled", self.scheduled_commands.len())
+                .count("orders", totals.iter().map(|c| c.orders).sum::<u64>())
+                .count("cancels", totals.iter().map(|c| c.cancels).sum::<u64>())
+                .count("trades", totals.iter().map(|c| c.trades).sum::<u64>())
+                .count("messages", self.total_messages + self.stats.messages_sent)
+                .raw("by_regime", RegimeCounts::json_by_regime(&totals))
+                .to_json();
+            reply = reply.raw("state", state_json);
+        }
+        let _ = reply_socket.send_to(format!("{}\n", reply.to_json()).as_bytes(), peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Cli;
+    use crate::engine::Feed;
+    use clap::Parser;
+    use std::sync::mpsc::sync_channel;
+
+    #[test]
+    fn shock_commands_take_percent() {
+        match parse_control_command("shock -2.5") {
+            Some(ControlCommand::Shock(s)) => assert!((s + 0.025).abs() < 1e-12),
+            _ => panic!("shock didn't parse"),
+        }
+        match parse_control_command("shockcfg 0.001 1 3") {
+            Some(ControlCommand::ShockConfig {
+                prob,
+                min_pct,
+                max_pct,
+            }) => {
+                assert_eq!(prob, 0.001);
+                assert!((min_pct - 0.01).abs() < 1e-12);
+                assert!((max_pct - 0.03).abs() < 1e-12);
+            }
+            _ => panic!("shockcfg didn't parse"),
+        }
+    }
+
+    #[test]
+    fn shock_commands_move_the_mid_in_percent() {
+        let cli = Cli::parse_from(["orderflow-rs", "--seed", "7", "--control-enabled", "false"]);
+        let cfg = AppConfig::resolve(&cli).unwrap();
+        let (tx, _rx) = sync_channel(1024);
+        let mut sim = Sim::new(&cfg, &cli, Feed::Own(Some(tx))).unwrap();
+        let apply = |sim: &mut Sim, text: &str| {
+            let cmd = parse_control_command(text).unwrap();
+            let text = text.to_string();
+            sim.apply_command(ControlRequest {
+                cmd,
+                text,
+                peer: None,
+            })
+            .unwrap();
+        };
+
+        let mid = sim.mid;
+        apply(&mut sim, "shock -2.5");
+        assert!(
+            (sim.mid / mid - 0.975).abs() < 1e-9,
+            "{} -> {}",
+            mid,
+            sim.mid
+        );
+
+        // Random shocks every tick, each moving the mid by 1-3% either way
+        apply(&mut sim, "shockcfg 1 1 3");
+        for _ in 0..50 {
+            let mid = sim.mid;
+            sim.random_shock();
+            let pct = (sim.mid / mid - 1.0).abs() * 100.0;
+            assert!((1.0 - 1e-9..=3.0 + 1e-9).contains(&pct), "{}", pct);
+        }
+    }
+
+    #[test]
+    fn reload_applies_tunables_and_skips_startup_settings() {
+        let path = std::env::temp_dir().join(format!("reload-{}.toml", std::process::id()));
+        let write = |throughput: f64, port: u16| {
+            let toml = format!(
+                "[simulation]\nthroughput_scale = {}\n\n[network]\nmulticast_port = {}\n\n[output]\nmode = \"quiet\"\n",
+                throughput, port
+            );
+            std::fs::write(&path, toml).unwrap();
+        };
+        write(1.0, 5555);
+        let cli = Cli::parse_from([
+            "orderflow-rs",
+            "-c",
+            path.to_str().unwrap(),
+            "--seed",
+            "7",
+            "--control-enabled",
+            "false",
+        ]);
+        let cfg = AppConfig::resolve(&cli).unwrap();
+        let (tx, _rx) = sync_channel(1024);
+        let mut sim = Sim::new(&cfg, &cli, Feed::Own(Some(tx))).unwrap();
+
+        write(2.5, 6000);
+        let result = sim.reload();
+        std::fs::remove_file(&path).unwrap();
+        let json = result.unwrap().to_json();
+
+        assert_eq!(sim.runtime.throughput_scale, 2.5);
+        assert!(json.contains("simulation.throughput_scale"));
+        assert!(json.contains(r#""skipped":["network.multicast_port"]"#));
+        // The feed keeps the port it was opened on
+        assert_eq!(sim.cfg.multicast_port, 5555);
+    }
+
+    #[test]
+    fn a_replayed_reload_applies_the_file_the_run_read() {
+        let path = std::env::temp_dir().join(format!("replay-{}.toml", std::process::id()));
+        let write = |throughput: f64| {
+            let toml = format!(
+                "[simulation]\nthroughput_scale = {}\n\n[output]\nmode = \"quiet\"\n",
+                throughput
+            );
+            std::fs::write(&path, toml).unwrap();
+        };
+        write(1.0);
+        let cli = Cli::parse_from([
+            "orderflow-rs",
+            "-c",
+            path.to_str().unwrap(),
+            "--seed",
+            "7",
+            "--control-enabled",
+            "false",
+        ]);
+        let cfg = AppConfig::resolve(&cli).unwrap();
+        let (tx, _rx) = sync_channel(1024);
+        let mut sim = Sim::new(&cfg, &cli, Feed::Own(Some(tx.clone()))).unwrap();
+        write(2.5);
+        sim.reload().unwrap();
+
+        // The file has changed again by the time the run resumes
+        write(4.0);
+        let mut resumed = Sim::new(&cfg, &cli, Feed::Own(Some(tx))).unwrap();
+        resumed.replaying = true;
+        resumed
+            .replay_reloads
+            .extend(sim.reload_log.iter().cloned());
+        resumed.reload().unwrap();
+        assert_eq!(resumed.runtime.throughput_scale, 2.5);
+
+        // Once the replay is done, reload reads the file again
+        resumed.replaying = false;
+        resumed.reload().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(resumed.runtime.throughput_scale, 4.0);
+        assert_eq!(resumed.reload_log.len(), 2);
+    }
+}
@@ -0,0 +1,788 @@
+//! The tick's order flow: statistical, squeeze and agent orders through the
+//! entry checks to the book, dark prints, fat fingers, stops and cancels.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use super::{
+    admit, cross_resting, per_tick_poisson, sample_count, take_liquidity, touch_price, Sim,
+    TickContext,
+};
+use crate::agents::{AgentAction, AgentContext};
+use crate::jsonlog::Record;
+use crate::order::{DarkTrade, Order, OrderType, Side};
+use crate::venue::{Severity, ADMIN_QUOTE_STUFFING};
+
+impl Sim<'_> {
+    /// Generate and send this tick's orders, then cancel what is due.
+    pub(super) fn order_flow(&mut self, ctx: &TickContext) {
+        self.tick_orders.clear();
+        self.dark_trades.clear();
+        self.limit_orders(ctx);
+        self.market_orders(ctx);
+        self.cover_orders(ctx);
+        self.follow_on_orders(ctx);
+        self.agent_flow(ctx);
+        self.send_orders(ctx);
+        self.fat_finger(ctx);
+        self.stop_orders(ctx);
+        self.expire_orders();
+        self.regime_cancels(ctx);
+        self.quote_stuffing(ctx);
+    }
+
+    /// Limit and iceberg orders around the mid, at the regime's arrival rate.
+    fn limit_orders(&mut self, ctx: &TickContext) {
+        let cfg = self.cfg;
+        let params = &ctx.params;
+        let num_limits = if self.statistical_flow && (ctx.trading || ctx.in_auction) {
+            sample_count(&ctx.dists.limits, &mut self.rngs.orders)
+        } else {
+            0
+        };
+        let runtime = &self.runtime;
+        let rng = &mut self.rngs.orders;
+        for _ in 0..num_limits {
+            let side = if rng.gen::<f64>() < params.buy_prob {
+                Side::Buy
+            } else {
+                Side::Sell
+            };
+            let offset = params.half_spread + rng.sample::<f64, _>(ctx.dists.offset);
+            let raw_price = match side {
+                Side::Buy => self.mid - offset,
+                Side::Sell => self.mid + offset,
+            };
+            let price = (raw_price / cfg.tick_size).round() * cfg.tick_size;
+            let size = self.size_dist.sample(1.0, rng);
+            let (order_type, reserve) = if rng.gen::<f64>() < runtime.iceberg_prob {
+                let mult = rng.gen_range(runtime.iceberg_min_mult..=runtime.iceberg_max_mult);
+                // The total has to fit the u32 size fields on the wire.
+                let reserve = size.saturating_mul(mult - 1).min(u32::MAX - size);
+                (OrderType::Iceberg, reserve)
+            } else {
+                (OrderType::Limit, 0)
+            };
+
+            self.tick_orders.push(Order {
+                id: self.order_ids.next(),
+                side,
+                order_type,
+                price,
+                size,
+                reserve,
+                participant_id: self.participants.sample(rng),
+                cl_ord_id: String::new(),
+                instrument: cfg.instrument_index,
+                created_at: self.current_time,
+                ttl: rng.sample(self.ttl_dist),
+            });
+        }
+        self.stats.limits_generated += num_limits;
+    }
+
+    /// Market orders, tilted by book imbalance; with `dark_fraction` some
+    /// execute off the lit book instead.
+    fn market_orders(&mut self, ctx: &TickContext) {
+        let cfg = self.cfg;
+        let params = &ctx.params;
+        let flowing = self.statistical_flow && (ctx.trading || ctx.in_auction);
+        let rng = &mut self.rngs.orders;
+        let mut num_markets = if flowing {
+            match &self.ratio_ctl {
+                Some(ctl) => {
+                    let lambda = params.market_rate * ctl.market_mult[self.state.current.index()];
+                    sample_count(&per_tick_poisson(lambda * ctx.tick_scale), rng)
+                }
+                None => sample_count(&ctx.dists.markets, rng),
+            }
+        } else {
+            0
+        };
+
+        // Top-of-book imbalance tilts aggression towards the heavier side's
+        // direction and adds extra market orders as it grows.
+        let mut buy_prob = params.buy_prob;
+        if cfg.imbalance_sensitivity > 0.0 && flowing {
+            let tilt = cfg.imbalance_sensitivity * self.book.imbalance();
+            if tilt != 0.0 {
+                let logit = (buy_prob / (1.0 - buy_prob)).ln() + tilt;
+                buy_prob = 1.0 / (1.0 + (-logit).exp());
+                let extra = params.market_rate * ctx.tick_scale * tilt.abs();
+                num_markets += sample_count(&per_tick_poisson(extra), rng);
+            }
+        }
+
+        for _ in 0..num_markets {
+            let side = if rng.gen::<f64>() < buy_prob {
+                Side::Buy
+            } else {
+                Side::Sell
+            };
+            let size = self.size_dist.sample(0.5 * params.size_mult, rng);
+            let participant_id = self.participants.sample(rng);
+
+            // Hidden liquidity: the order never reaches the lit book and
+            // takes no order ID, so only its print is published.
+            if cfg.dark_fraction > 0.0
+                && ctx.trading
+                && !self.warming
+                && self.rngs.dark.gen_bool(cfg.dark_fraction)
+            {
+                self.dark_trades.push(DarkTrade {
+                    aggressor: side,
+                    price: (self.mid / cfg.tick_size).round() * cfg.tick_size,
+                    size,
+                    participant_id,
+                    instrument: cfg.instrument_index,
+                    time: self.current_time,
+                });
+                continue;
+            }
+
+            self.tick_orders.push(Order {
+                id: self.order_ids.next(),
+                side,
+                order_type: OrderType::Market,
+                price: 0.0,
+                size,
+                reserve: 0,
+                participant_id,
+                cl_ord_id: String::new(),
+                instrument: cfg.instrument_index,
+                created_at: self.current_time,
+                ttl: 0.0,
+            });
+        }
+        // Diverted orders never reach the feed; they count as dark prints
+        self.stats.markets_generated += num_markets - self.dark_trades.len() as u64;
+    }
+
+    /// Squeeze: shorts forced to cover after an up-shock.
+    fn cover_orders(&mut self, ctx: &TickContext) {
+        let burst = self.active.cover_burst;
+        if burst == 0 || !ctx.trading {
+            return;
+        }
+        for _ in 0..burst {
+            self.tick_orders.push(Order {
+                id: self.order_ids.next(),
+                side: Side::Buy,
+                order_type: OrderType::Market,
+                price: 0.0,
+                size: self
+                    .size_dist
+                    .sample(0.5 * ctx.params.size_mult, &mut self.rngs.orders),
+                reserve: 0,
+                participant_id: self.participants.sample(&mut self.rngs.orders),
+                cl_ord_id: String::new(),
+                instrument: self.cfg.instrument_index,
+                created_at: self.current_time,
+                ttl: 0.0,
+            });
+        }
+        self.stats.markets_generated += burst;
+        self.out.event(
+            &format!(
+                "  ⚡ COVER  {} buy market orders  t={:.1}s",
+                burst, self.current_time
+            ),
+            Record::new("cover", self.current_time).count("orders", burst),
+        );
+        self.active.cover_burst = 0;
+    }
+
+    /// Queue a follow-on market order for each of the leader's, sent at
+    /// `at`, that draws one (`[lead_lag]`).
+    pub(super) fn follow(&mut self, markets: [u32; 2], at: f64) {
+        let cfg = self.cfg;
+        let Some(lead_lag) = cfg.lead_lag.filter(|l| l.leader != cfg.instrument_index) else {
+            return;
+        };
+        for (side, n) in [Side::Buy, Side::Sell].into_iter().zip(markets) {
+            for _ in 0..n {
+                if self.rngs.lead_lag.gen::<f64>() < lead_lag.probability {
+                    self.follow_ons.push_back((at + lead_lag.delay, side));
+                }
+            }
+        }
+    }
+
+    /// The market orders sent since the last call, buys then sells, if this
+    /// symbol leads (`[lead_lag]`).
+    pub(super) fn take_lead_markets(&mut self) -> [u32; 2] {
+        let markets = std::mem::take(&mut self.lead_markets);
+        let cfg = self.cfg;
+        match cfg.lead_lag {
+            Some(l) if l.leader == cfg.instrument_index => markets,
+            _ => [0; 2],
+        }
+    }
+
+    /// Follow-on market orders now due; those falling due while the venue
+    /// isn't trading are dropped.
+    fn follow_on_orders(&mut self, ctx: &TickContext) {
+        let now = self.current_time;
+        let mut count = 0;
+        // (epsilon absorbs float drift in the accumulated sim clock)
+        while let Some(&(_, side)) = self.follow_ons.front().filter(|f| f.0 <= now + 1e-9) {
+            self.follow_ons.pop_front();
+            if !ctx.trading {
+                continue;
+            }
+            let rng = &mut self.rngs.lead_lag;
+            self.tick_orders.push(Order {
+                id: self.order_ids.next(),
+                side,
+                order_type: OrderType::Market,
+                price: 0.0,
+                size: self.size_dist.sample(0.5 * ctx.params.size_mult, rng),
+                reserve: 0,
+                participant_id: self.participants.sample(rng),
+                cl_ord_id: String::new(),
+                instrument: self.cfg.instrument_index,
+                created_at: now,
+                ttl: 0.0,
+            });
+            count += 1;
+        }
+        self.stats.markets_generated += count;
+    }
+
+    /// Orders and cancels from the `[agents]` layer.
+    fn agent_flow(&mut self, ctx: &TickContext) {
+        let now = self.current_time;
+        if let Some(layer) = self.agents.as_mut().filter(|_| ctx.trading) {
+            let agent_ctx = AgentContext {
+                mid: self.mid,
+                time: now,
+                tick_size: self.cfg.tick_size,
+                params: &ctx.params,
+                book: &self.book,
+                size_dist: &self.size_dist,
+                ttl_dist: &self.ttl_dist,
+            };
+            for action in layer.step(&agent_ctx, &mut self.order_ids, &mut self.rngs.agents) {
+                match action {
+                    AgentAction::Submit(order) => {
+                        match order.order_type {
+                            OrderType::Limit | OrderType::Iceberg => {
+                                self.stats.limits_generated += 1
+                            }
+                            OrderType::Market => self.stats.markets_generated += 1,
+                            OrderType::Stop => self.stats.stops_placed += 1,
+                        }
+                        self.tick_orders.push(order);
+                    }
+                    AgentAction::Cancel(id) => self.agent_cancels.push(id),
+                    AgentAction::Label(label) => {
+                        self.out.event(
+                            &format!("  ⚑ {}  t={:.1}s", label, now),
+                            Record::new("label", now).str("label", &label),
+                        );
+                    }
+                }
+            }
+        }
+
+        for id in self.agent_cancels.drain(..) {
+            if self.book.remove(id).is_some() {
+                let _ = self.sender.send_cancel(id, self.cfg.instrument_index, now);
+                self.order_ids.release(id);
+                self.stats.messages_sent += 1;
+                self.stats.cancels_agent += 1;
+            }
+        }
+    }
+
+    /// Shuffle the tick's orders together and send each through the entry
+    /// checks to the book, the auction or the opposite side; then the dark
+    /// prints.
+    fn send_orders(&mut self, ctx: &TickContext) {
+        let cfg = self.cfg;
+        let now = self.current_time;
+        // Orders injected through the control API go out with this tick's
+        // flow, and are held while the venue is halted or closed.
+        if ctx.trading || ctx.in_auction {
+            self.tick_orders.append(&mut self.injected_orders);
+        }
+
+        for order in self.tick_orders.iter_mut() {
+            self.order_ids.stamp(order);
+        }
+        self.tick_orders.shuffle(&mut self.rngs.orders);
+
+        // --- Send orders ---
+        for order in self.tick_orders.iter_mut() {
+            self.run_report.record_size(order.size);
+            // Without matching, nothing would trade a limit priced through the
+            // opposite touch, so it rests one tick behind it instead.
+            if order.is_resting() && !cfg.matching && !ctx.in_auction {
+                order.price = self.book.passive_price(order);
+            }
+            if !admit(
+                order,
+                &self.errors,
+                ctx.band_limits,
+                self.mid,
+                now,
+                &mut self.rngs.errors,
+                &mut self.sender,
+                &mut self.stats,
+            ) {
+                continue;
+            }
+            let _ = self.sender.send_order(order);
+            self.stats.messages_sent += 1;
+            match (order.order_type, order.side) {
+                (OrderType::Market, Side::Buy) => self.lead_markets[0] += 1,
+                (OrderType::Market, Side::Sell) => self.lead_markets[1] += 1,
+                _ => {}
+            }
+            if order.is_resting() && cfg.matching && !ctx.in_auction {
+                let rest = cross_resting(
+                    order,
+                    cfg.self_match,
+                    now,
+                    &mut self.book,
+                    &mut self.sender,
+                    &mut self.bars,
+                    &mut self.crypto,
+                    &mut self.stats,
+                );
+                if let Some(rest) = rest {
+                    self.book.insert(rest);
+                }
+            } else if order.is_resting() {
+                self.book.insert(order.clone());
+            } else if order.order_type == OrderType::Market && ctx.in_auction {
+                self.session.collect(order.clone());
+            } else if order.order_type == OrderType::Market {
+                take_liquidity(
+                    order,
+                    cfg.matching,
+                    cfg.self_match,
+                    touch_price(self.mid, ctx.params.half_spread, order.side, cfg.tick_size),
+                    now,
+                    &mut self.book,
+                    &mut self.sender,
+                    &mut self.bars,
+                    &mut self.crypto,
+                    &mut self.stats,
+                );
+            }
+        }
+
+        // --- Dark prints: executed at the mid, off the lit book ---
+        for dark in &self.dark_trades {
+            let _ = self.sender.send(dark);
+            self.stats.messages_sent += 1;
+            self.stats.dark_trades += 1;
+            if let Some(bars) = self.bars.as_mut() {
+                bars.record(dark.price, dark.size);
+            }
+        }
+    }
+
+    /// Fat finger: one huge order through the market, unfilled rest cancelled.
+    fn fat_finger(&mut self, ctx: &TickContext) {
+        let cfg = self.cfg;
+        let now = self.current_time;
+        if !ctx.trading
+            || self.warming
+            || !self
+                .fat_finger
+                .poll(now, cfg.tick_interval, &mut self.rngs.events)
+        {
+            return;
+        }
+        let side = if self.rngs.events.gen::<bool>() {
+            Side::Buy
+        } else {
+            Side::Sell
+        };
+        let scale = self.fat_finger.multiplier(&mut self.rngs.events);
+        let mut order = Order {
+            id: self.order_ids.next(),
+            side,
+            order_type: OrderType::Limit,
+            price: self.fat_finger.limit_price(self.mid, side, cfg.tick_size),
+            size: self.size_dist.sample(scale, &mut self.rngs.events),
+            reserve: 0,
+            participant_id: self.participants.sample(&mut self.rngs.events),
+            cl_ord_id: String::new(),
+            instrument: cfg.instrument_index,
+            created_at: now,
+            ttl: 0.0,
+        };
+        self.order_ids.stamp(&mut order);
+        self.stats.limits_generated += 1;
+        self.run_report.record_size(order.size);
+        // Like any other order it can be refused at entry, which spares
+        // the market its impact
+        let admitted = admit(
+            &order,
+            &self.errors,
+            ctx.band_limits,
+            self.mid,
+            now,
+            &mut self.rngs.errors,
+            &mut self.sender,
+            &mut self.stats,
+        );
+        if !admitted {
+            self.order_ids.release(order.id);
+            self.out.event(
+                &format!(
+                    "  ▶ FAT FINGER  {} {}@{:.2} rejected  t={:.1}s",
+                    side, order.size, order.price, now
+                ),
+                Record::new("fat_finger", now)
+                    .str("side", side)
+                    .num("price", order.price)
+                    .count("size", order.size)
+                    .bool("rejected", true),
+            );
+            return;
+        }
+        let _ = self.sender.send_order(&order);
+        self.stats.messages_sent += 1;
+        let filled = take_liquidity(
+            &order,
+            cfg.matching,
+            cfg.self_match,
+            touch_price(self.mid, ctx.params.half_spread, side, cfg.tick_size),
+            now,
+            &mut self.book,
+            &mut self.sender,
+            &mut self.bars,
+            &mut self.crypto,
+            &mut self.stats,
+        );
+        if filled < order.size {
+            let _ = self.sender.send_cancel(order.id, order.instrument, now);
+            self.stats.messages_sent += 1;
+        }
+        self.order_ids.release(order.id);
+        self.mid = self.fat_finger.impact(self.mid, side).max(cfg.tick_size);
+        self.out.event(
+            &format!(
+                "  ▶ FAT FINGER  {} {}@{:.2} filled {}  mid -> {:.4}  t={:.1}s",
+                side, order.size, order.price, filled, self.mid, now
+            ),
+            Record::new("fat_finger", now)
+                .str("side", side)
+                .num("price", order.price)
+                .count("size", order.size)
+                .count("filled", filled)
+                .num("mid", self.mid),
+        );
+    }
+
+    /// Stop orders: placement, triggers and cascade impact, and expiry.
+    fn stop_orders(&mut self, ctx: &TickContext) {
+        let cfg = self.cfg;
+        let now = self.current_time;
+        let num_stops = if ctx.trading {
+            sample_count(&self.active.stop_arrivals, &mut self.rngs.stops)
+        } else {
+            0
+        };
+        let rng = &mut self.rngs.stops;
+        for _ in 0..num_stops {
+            let side = if rng.gen::<f64>() < cfg.stops.sell_fraction {
+                Side::Sell
+            } else {
+                Side::Buy
+            };
+            let distance = rng.gen_range(cfg.stops.min_distance..=cfg.stops.max_distance);
+            let raw_trigger = match side {
+                Side::Sell => self.mid * (1.0 - distance),
+                Side::Buy => self.mid * (1.0 + distance),
+            };
+            let mut stop = Order {
+                id: self.order_ids.next(),
+                side,
+                order_type: OrderType::Stop,
+                price: ((raw_trigger / cfg.tick_size).round() * cfg.tick_size).max(cfg.tick_size),
+                size: self.size_dist.sample(1.0, rng),
+                reserve: 0,
+                participant_id: self.participants.sample(rng),
+                cl_ord_id: String::new(),
+                instrument: cfg.instrument_index,
+                created_at: now,
+                ttl: cfg.stops.ttl,
+            };
+            self.order_ids.stamp(&mut stop);
+            let _ = self.sender.send_order(&stop);
+            self.stats.messages_sent += 1;
+            self.stats.stops_placed += 1;
+            self.stop_book.insert(stop);
+        }
+
+        // Each triggered stop is pulled and becomes a market order whose
+        // impact moves mid, which can trigger further stops on the next tick.
+        let max_triggers = if ctx.trading {
+            cfg.stops.max_triggers_per_tick
+        } else {
+            0
+        };
+        let fired = self.stop_book.trigger(self.mid, max_triggers);
+        for stop in fired {
+            let _ = self.sender.send_cancel(stop.id, stop.instrument, now);
+            self.order_ids.release(stop.id);
+            self.stats.messages_sent += 1;
+            let order = Order {
+                id: self.order_ids.next(),
+                side: stop.side,
+                order_type: OrderType::Market,
+                price: 0.0,
+                size: stop.size,
+                reserve: 0,
+                participant_id: stop.participant_id,
+                cl_ord_id: stop.cl_ord_id.clone(),
+                instrument: stop.instrument,
+                created_at: now,
+                ttl: 0.0,
+            };
+            let _ = self.sender.send_order(&order);
+            self.stats.messages_sent += 1;
+            self.stats.markets_generated += 1;
+            self.stats.stops_triggered += 1;
+            take_liquidity(
+                &order,
+                cfg.matching,
+                cfg.self_match,
+                touch_price(self.mid, ctx.params.half_spread, order.side, cfg.tick_size),
+                now,
+                &mut self.book,
+                &mut self.sender,
+                &mut self.bars,
+                &mut self.crypto,
+                &mut self.stats,
+            );
+            let impact = cfg.stops.impact_per_unit * order.size as f64;
+            self.mid *= match order.side {
+                Side::Sell => 1.0 - impact,
+                Side::Buy => 1.0 + impact,
+            };
+            self.mid = self.mid.max(cfg.tick_size);
+        }
+        let triggered = fired.len();
+        if triggered > 0 {
+            self.out.event(
+                &format!(
+                    "  ⇅ STOPS triggered={} -> mid={:.4}  resting={}  t={:.1}s",
+                    triggered,
+                    self.mid,
+                    self.stop_book.len(),
+                    now
+                ),
+                Record::new("stops", now)
+                    .count("triggered", triggered)
+                    .num("mid", self.mid)
+                    .count("resting", self.stop_book.len()),
+            );
+        }
+
+        for &id in self.stop_book.expire(now) {
+            let _ = self.sender.send_cancel(id, self.cfg.instrument_index, now);
+            self.order_ids.release(id);
+            self.stats.messages_sent += 1;
+            self.stats.cancels_expired += 1;
+        }
+    }
+
+    /// Cancel resting orders whose TTL is up.
+    fn expire_orders(&mut self) {
+        let now = self.current_time;
+        self.expired.clear();
+        self.expired.extend(
+            self.book
+                .orders()
+                .filter(|o| o.ttl > 0.0 && (now - o.created_at) >= o.ttl)
+                .map(|o| o.id),
+        );
+
+        for &id in &self.expired {
+            let _ = self.sender.send_cancel(id, self.cfg.instrument_index, now);
+            self.book.remove(id);
+            self.order_ids.release(id);
+            self.stats.messages_sent += 1;
+        }
+        self.stats.cancels_expired += self.expired.len() as u64;
+    }
+
+    /// Regime-driven cancellations (with throughput scaling).
+    fn regime_cancels(&mut self, ctx: &TickContext) {
+        let now = self.current_time;
+        let regime = self.state.current;
+        let num_cancels = if self.statistical_flow {
+            match &self.ratio_ctl {
+                Some(ctl) => {
+                    let lambda = ctx.params.cancel_rate * ctl.cancel_mult[regime.index()];
+                    sample_count(
+                        &per_tick_poisson(lambda * ctx.tick_scale),
+                        &mut self.rngs.cancels,
+                    )
+                }
+                None => sample_count(&ctx.dists.cancels, &mut self.rngs.cancels),
+            }
+        } else {
+            0
+        };
+
+        if let Some(ctl) = self
+            .ratio_ctl
+            .as_mut()
+            .filter(|_| num_cancels > self.book.len() as u64)
+        {
+            ctl.cancels_capped[regime.index()] = true;
+        }
+        if num_cancels == 0 || self.book.is_empty() {
+            return;
+        }
+        // Orders old enough to cancel, gathered once for the whole tick;
+        // they stay in ID order, so each pick is the one `choose_nth`
+        // would make from the book
+        let min_resting_time = self.runtime.min_resting_time;
+        self.cancellable.clear();
+        self.cancellable.extend(
+            self.book
+                .orders()
+                .filter(|o| min_resting_time <= 0.0 || now - o.created_at >= min_resting_time)
+                .map(|o| o.id),
+        );
+        for _ in 0..num_cancels.min(self.book.len() as u64) {
+            if self.cancellable.is_empty() {
+                break;
+            }
+            let pick = self.cancellable.remove(
+                self.rngs
+                    .cancels
+                    .gen_range(0..self.cancellable.len() as u32) as usize,
+            );
+            let _ = self
+                .sender
+                .send_cancel(pick, self.cfg.instrument_index, now);
+            self.book.remove(pick);
+            self.order_ids.release(pick);
+            self.stats.messages_sent += 1;
+            self.stats.cancels_regime += 1;
+        }
+    }
+
+    /// Quote stuffing burst: add/cancel pairs at the touch.
+    fn quote_stuffing(&mut self, ctx: &TickContext) {
+        let cfg = self.cfg;
+        let now = self.current_time;
+        if let Some(d) = self.stuffer.poll_schedule(now) {
+            let text = format!("quote stuffing for {}s", d);
+            super::admin(
+                &mut self.sender,
+                &mut self.stats,
+                Severity::Warning,
+                ADMIN_QUOTE_STUFFING,
+                &text,
+                self.cfg.instrument_index,
+                now,
+            );
+            self.out.event(
+                &format!("  ▶ QUOTE STUFFING  {}s  t={:.1}s", d, now),
+                Record::new("quote_stuffing", now).num("duration", d),
+            );
+        }
+        let stuffing_pairs = if ctx.trading {
+            self.stuffer.pairs_this_tick(now, cfg.tick_interval)
+        } else {
+            0
+        };
+        for _ in 0..stuffing_pairs {
+            let side = if self.rngs.events.gen::<bool>() {
+                Side::Buy
+            } else {
+                Side::Sell
+            };
+            let touch = match side {
+                Side::Buy => self.book.best_bid(),
+                Side::Sell => self.book.best_ask(),
+            };
+            // Join the resting touch; on an empty side use the implied one.
+            let half_spread = ctx.params.half_spread;
+            let price = match (touch, side) {
+                (Some((p, _)), _) => p,
+                (None, Side::Buy) => touch_price(self.mid, half_spread, Side::Sell, cfg.tick_size),
+                (None, Side::Sell) => touch_price(self.mid, half_spread, Side::Buy, cfg.tick_size),
+            };
+            let mut order = Order {
+                id: self.order_ids.next(),
+                side,
+                order_type: OrderType::Limit,
+                price,
+                size: self.rngs.events.gen_range(1..=100),
+                reserve: 0,
+                participant_id: self.stuffer.participant_id(),
+                cl_ord_id: String::new(),
+                instrument: cfg.instrument_index,
+                created_at: now,
+                ttl: 0.0,
+            };
+            self.order_ids.stamp(&mut order);
+            let _ = self.sender.send_order(&order);
+            let _ = self.sender.send_cancel(order.id, order.instrument, now);
+            self.order_ids.release(order.id);
+            self.stats.messages_sent += 2;
+            self.stats.stuffing_pairs += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{AppConfig, Cli};
+    use crate::engine::{Feed, Sim};
+    use crate::order::OrderType;
+    use crate::wire::decode::{decode_datagram, Message};
+    use clap::Parser;
+    use std::sync::mpsc::sync_channel;
+
+    #[test]
+    fn dark_prints_are_counted_apart_from_lit_market_orders() {
+        let cli = Cli::parse_from([
+            "orderflow-rs",
+            "--seed",
+            "11",
+            "--dark-fraction",
+            "0.5",
+            "--display-interval",
+            "1000",
+            "--no-realtime",
+            "--control-enabled",
+            "false",
+        ]);
+        let cfg = AppConfig::resolve(&cli).unwrap();
+        let (tx, rx) = sync_channel(1 << 16);
+        let mut sim = Sim::new(&cfg, &cli, Feed::Own(Some(tx))).unwrap();
+        sim.start();
+        if sim.warming {
+            sim.open_after_warmup();
+        }
+        for _ in 0..500 {
+            sim.tick();
+        }
+        let stats = &sim.stats;
+        let (mut markets, mut darks) = (0, 0);
+        for m in rx.try_iter().flat_map(|d| decode_datagram(&d).unwrap()) {
+            match m {
+                Message::Order(o) if o.order_type == OrderType::Market => markets += 1,
+                Message::DarkTrade(_) => darks += 1,
+                _ => {}
+            }
+        }
+
+        // Each diverted order is one print and no lit market order
+        assert!(darks > 0 && markets > 0, "{} {}", darks, markets);
+        assert_eq!(stats.dark_trades, darks);
+        assert_eq!(stats.markets_generated, markets);
+    }
+}
@@ -0,0 +1,500 @@
+//! How a run starts and ends: warmup and its snapshot, resuming from a
+//! checkpoint, end conditions and checkpoints, the exit drain and the reports.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use super::{
+    box_bottom, box_line, box_mid, box_top, publish_levels, start_session, warning, RegimeCounts,
+    Sim, TickStats,
+};
+use crate::alloc;
+use crate::bars::BarAggregator;
+use crate::checkpoint::Checkpoint;
+use crate::config::BookView;
+use crate::jsonlog::Record;
+use crate::price::dt_years;
+use crate::report::RunReport;
+use crate::venue::{CircuitBreaker, SessionEnd, SnapshotEnd, SnapshotStart};
+
+impl Sim<'_> {
+    /// Before the first tick: start replaying a checkpoint, or a warmup, or
+    /// else open the session right away.
+    pub(super) fn start(&mut self) {
+        let cfg = self.cfg;
+        if let Some(checkpoint) = &cfg.resume {
+            self.out.event(
+                &format!(
+                    "  ▶ RESUME  replaying {} ticks (t={:.1}s) without publishing",
+                    checkpoint.ticks, checkpoint.sim_time
+                ),
+                Record::new("resume", 0.0)
+                    .count("ticks", checkpoint.ticks)
+                    .num("sim_time", checkpoint.sim_time),
+            );
+            self.replay.extend(checkpoint.commands.iter().cloned());
+            self.replay_reloads
+                .extend(checkpoint.reloads.iter().cloned());
+            self.replaying = true;
+            self.set_muted(true);
+        }
+
+        // --- Warmup: run silently from t=-warmup_seconds, then publish the book at t=0 ---
+        self.warming = cfg.warmup_seconds > 0.0;
+        if self.warming {
+            self.current_time = -cfg.warmup_seconds;
+            self.out.event(
+                &format!(
+                    "  ▶ WARMUP  building the book for up to {}s before publishing",
+                    cfg.warmup_seconds
+                ),
+                Record::new("warmup", self.current_time).num("seconds", cfg.warmup_seconds),
+            );
+            self.set_muted(true);
+        } else {
+            start_session(
+                cfg,
+                &self.session,
+                &mut self.sender,
+                &mut self.out,
+                &mut self.stats,
+                self.current_time,
+            );
+        }
+    }
+
+    /// Hold back (or let through) the feed, the crypto feed and the log.
+    fn set_muted(&mut self, muted: bool) {
+        self.sender.set_muted(muted);
+        if let Some(crypto) = self.crypto.as_mut() {
+            crypto.set_muted(muted);
+        }
+        self.out.muted = muted;
+    }
+
+    /// Whether the warmup has run its time or built its book.
+    /// (epsilon absorbs float drift in the accumulated sim clock)
+    pub(super) fn warmup_done(&self) -> bool {
+        self.warming
+            && (self.current_time + 1e-9 >= 0.0
+                || self.cfg.warmup_orders.is_some_and(|n| self.book.len() >= n))
+    }
+
+    /// Warmup over: open the session with a snapshot of the book it built.
+    pub(super) fn open_after_warmup(&mut self) {
+        let cfg = self.cfg;
+        self.warming = false;
+        let warmed_for = self.current_time + cfg.warmup_seconds;
+        self.current_time = 0.0;
+        let now = self.current_time;
+        // Nothing from the warmup reaches consumers or the run's statistics
+        self.breaker = CircuitBreaker::new(&cfg.circuit_breaker);
+        self.stats = TickStats::new();
+        self.total_messages = 0;
+        self.regime_totals = [RegimeCounts::default(); 5];
+        self.run_report = RunReport::new();
+        self.time_since_display = 0.0;
+        self.tick_times.reset();
+        self.last_quote = None;
+        self.follow_ons.clear();
+        self.bars = cfg
+            .bar_interval
+            .map(|i| BarAggregator::new(i, cfg.instrument_index));
+        if !self.replaying {
+            self.set_muted(false);
+            self.pacer.reset();
+        }
+
+        start_session(
+            cfg,
+            &self.session,
+            &mut self.sender,
+            &mut self.out,
+            &mut self.stats,
+            now,
+        );
+        let resting = self.book.snapshot();
+        let orders = match cfg.book_view {
+            BookView::Mbp => 0,
+            _ => resting.len() + self.stop_book.len(),
+        };
+        let levels = match cfg.book_view {
+            BookView::Mbo => Vec::new(),
+            _ => self.book.level_snapshot(now),
+        };
+        let _ = self.sender.send(&SnapshotStart {
+            orders: (orders + levels.len()) as u64,
+            instrument: cfg.instrument_index,
+            time: now,
+        });
+        for order in resting.into_iter().chain(self.stop_book.orders()) {
+            let _ = self.sender.send_order(order);
+        }
+        for level in &levels {
+            let _ = self.sender.send(level);
+        }
+        let _ = self.sender.send(&SnapshotEnd {
+            instrument: cfg.instrument_index,
+            time: now,
+        });
+        self.stats.messages_sent +=
+            2 + (self.book.len() + self.stop_book.len() + levels.len()) as u64;
+        self.stats.messages_sent -= self.sender.take_withheld();
+        self.out.event(
+            &format!(
+                "  ▶ WARMUP done after {:.1}s  {} resting orders, {} stops  mid={:.4}  regime={}",
+                warmed_for,
+                self.book.len(),
+                self.stop_book.len(),
+                self.mid,
+                self.state.current
+            ),
+            Record::new("warmup_done", now)
+                .num("seconds", warmed_for)
+                .count("orders", self.book.len())
+                .count("stops", self.stop_book.len())
+                .num("mid", self.mid)
+                .str("regime", self.state.current),
+        );
+    }
+
+    /// Once a replay reaches the checkpoint, check it landed on the saved
+    /// state and start publishing.
+    pub(super) fn check_resumed(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(checkpoint) = self
+            .cfg
+            .resume
+            .as_ref()
+            .filter(|c| self.replaying && self.ticks == c.ticks)
+        else {
+            return Ok(());
+        };
+        let now = self.current_time;
+        let messages = self.total_messages + self.stats.messages_sent;
+        let mismatch = [
+            ("sim time", checkpoint.sim_time.to_string(), now.to_string()),
+            ("mid", checkpoint.mid.to_string(), self.mid.to_string()),
+            (
+                "regime",
+                checkpoint.regime.clone(),
+                self.state.current.to_string(),
+            ),
+            (
+                "next order ID",
+                checkpoint.next_id.to_string(),
+                self.order_ids.next_fresh().to_string(),
+            ),
+            (
+                "resting orders",
+                checkpoint.active_orders.to_string(),
+                self.book.len().to_string(),
+            ),
+            (
+                "messages",
+                checkpoint.messages.to_string(),
+                messages.to_string(),
+            ),
+        ]
+        .into_iter()
+        .find(|(_, saved, replayed)| saved != replayed);
+        if let Some((what, saved, replayed)) = mismatch {
+            return Err(format!(
+                "replay diverged from the checkpoint at tick {}: {} is {}, expected {} (resume needs the same config and build)",
+                self.ticks, what, replayed, saved
+            )
+            .into());
+        }
+        self.replaying = false;
+        self.set_muted(false);
+        self.pacer.reset();
+        self.out.event(
+            &format!(
+                "  ▶ RESUMED  t={:.1}s  mid={:.4}  regime={}  messages={}",
+                now, self.mid, self.state.current, messages
+            ),
+            Record::new("resumed", now)
+                .count("ticks", self.ticks)
+                .num("mid", self.mid)
+                .str("regime", self.state.current)
+                .count("messages", messages),
+        );
+        Ok(())
+    }
+
+    /// Why the run ends now, if it does.
+    /// (epsilon absorbs float drift in the accumulated sim clock)
+    pub(super) fn end_reason(&self, running: &AtomicBool) -> Option<String> {
+        let cfg = self.cfg;
+        let messages = self.total_messages + self.stats.messages_sent;
+        if !running.load(Ordering::Relaxed) {
+            Some("interrupted".to_string())
+        } else if self.warming {
+            None
+        } else if let Some(d) = cfg.duration.filter(|&d| self.current_time + 1e-9 >= d) {
+            Some(format!("duration {}s reached", d))
+        } else if cfg.max_messages.is_some_and(|n| messages >= n) {
+            Some(format!("{} messages sent", messages))
+        } else if cfg
+            .stop_at_price
+            .is_some_and(|p| (cfg.initial_price - p) * (self.mid - p) <= 0.0)
+        {
+            Some(format!("mid {:.4} reached stop price", self.mid))
+        } else if self.next_scenarios.is_empty() && self.active.finished(self.current_time) {
+            Some("scenario chain finished".to_string())
+        } else if self.session.is_closed() {
+            Some("session closed".to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Write a checkpoint every interval and on the way out (`ending`).
+    pub(super) fn checkpoint(&mut self, ending: bool) {
+        let Some(path) = self
+            .cfg
+            .checkpoint_path
+            .as_ref()
+            .filter(|_| !self.replaying && !self.warming)
+        else {
+            return;
+        };
+        let now = self.current_time;
+        if now + 1e-9 < self.next_checkpoint_at && !ending {
+            return;
+        }
+        self.next_checkpoint_at = now + self.cfg.checkpoint_interval;
+        let checkpoint = Checkpoint {
+            seed: self.cfg.seed.to_string(),
+            ticks: self.ticks,
+            sim_time: now,
+            mid: self.mid,
+            regime: self.state.current.to_string(),
+            next_id: self.order_ids.next_fresh(),
+            active_orders: self.book.len(),
+            messages: self.total_messages + self.stats.messages_sent,
+            commands: self.command_log.clone(),
+            reloads: self.reload_log.clone(),
+        };
+        if let Err(e) = checkpoint.save(path) {
+            let msg = format!("checkpoint write failed: {}", e);
+            self.out.event(&format!("  ⚠ {}", msg), warning(now, msg));
+        }
+    }
+
+    pub(super) fn log_end(&mut self, reason: &str) {
+        if self.warming && !self.replaying {
+            // Interrupted before the session started: log the end, publish nothing
+            self.out.muted = false;
+        }
+        self.out.event(
+            &format!("  ▶ END  {}  t={:.1}s", reason, self.current_time),
+            Record::new("end", self.current_time).str("reason", reason),
+        );
+    }
+
+    /// After the last tick: the drain, SESSION_END and the exports, then the
+    /// bench results and the run report.
+    pub(super) fn finish(
+        &mut self,
+        bench_start: Instant,
+        allocs_start: Option<(u64, u64)>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cfg = self.cfg;
+        if let Some(checkpoint) = cfg.resume.as_ref().filter(|_| self.replaying) {
+            return Err(format!(
+                "run ended at tick {} before reaching the checkpoint at tick {}",
+                self.ticks, checkpoint.ticks
+            )
+            .into());
+        }
+        let now = self.current_time;
+        self.sender.set_sim_time(now);
+
+        if cfg.drain_on_exit {
+            self.drain();
+        }
+
+        publish_levels(&mut self.book, &mut self.sender, &mut self.stats, now);
+        let _ = self.sender.send(&SessionEnd {
+            messages: self.total_messages + self.stats.messages_sent,
+            instrument: cfg.instrument_index,
+            time: now,
+        });
+        self.stats.messages_sent += 1;
+        let _ = self.sender.flush();
+        super::log_corruptions(&mut self.out, &mut self.sender, now);
+        if let Err(e) = self.sender.finish_exports() {
+            let msg = format!("export failed: {}", e);
+            self.out.event(&format!("  ⚠ {}", msg), warning(now, msg));
+        }
+
+        if cfg.bench {
+            self.bench_report(bench_start, allocs_start);
+        }
+        self.run_report();
+        self.out
+            .event("Shutting down...", Record::new("shutdown", now));
+        Ok(())
+    }
+
+    /// Cancel everything still resting so consumers end with an empty book.
+    fn drain(&mut self) {
+        let cfg = self.cfg;
+        let now = self.current_time;
+        let mut ids = self.book.order_ids();
+        ids.sort_unstable();
+        ids.extend(self.stop_book.drain());
+        // Rate-limited drains go out one tick's worth of cancels at a time.
+        let per_tick = cfg
+            .drain_rate
+            .map_or(usize::MAX, |r| ((r * cfg.tick_interval) as usize).max(1));
+        for chunk in ids.chunks(per_tick) {
+            for &id in chunk {
+                self.book.remove(id);
+                let _ = self.sender.send_cancel(id, self.cfg.instrument_index, now);
+                self.stats.messages_sent += 1;
+                self.stats.cancels_drain += 1;
+            }
+            publish_levels(&mut self.book, &mut self.sender, &mut self.stats, now);
+            let _ = self.sender.flush();
+            if cfg.drain_rate.is_some() && !self.unpaced {
+                std::thread::sleep(Duration::from_secs_f64(self.tick_budget));
+            }
+        }
+        self.out.event(
+            &format!("  ▶ DRAIN cancelled {} resting orders", ids.len()),
+            Record::new("drain", now).count("orders", ids.len()),
+        );
+    }
+
+    fn bench_report(&mut self, bench_start: Instant, allocs_start: Option<(u64, u64)>) {
+        let now = self.current_time;
+        self.total_messages += self.stats.messages_sent;
+        let total_messages = self.total_messages;
+        let wall = bench_start.elapsed().as_secs_f64();
+        let mut lines = vec![
+            box_top(),
+            box_line("Bench results"),
+            box_mid(),
+            box_line(&format!("ticks:       {}  (sim {:.1}s)", self.ticks, now)),
+            box_line(&format!("wall time:   {:.3}s", wall)),
+            box_line(&format!("messages:    {}", total_messages)),
+            box_line(&format!("msgs/s:      {:.0}", total_messages as f64 / wall)),
+        ];
+        let mut record = Record::new("bench", now)
+            .count("ticks", self.ticks)
+            .num("wall_seconds", wall)
+            .count("messages", total_messages)
+            .num("msgs_per_sec", total_messages as f64 / wall);
+        match allocs_start.zip(alloc::snapshot()) {
+            Some(((allocs_start, bytes_start), (allocs_end, bytes_end))) => {
+                let allocs = allocs_end - allocs_start;
+                let bytes = bytes_end - bytes_start;
+                let per_msg = if total_messages > 0 {
+                    allocs as f64 / total_messages as f64
+                } else {
+                    0.0
+                };
+                lines.push(box_line(&format!(
+                    "allocations: {}  ({:.2}/msg)",
+                    allocs, per_msg
+                )));
+                lines.push(box_line(&format!("alloc bytes: {}", bytes)));
+                record = record
+                    .count("allocations", allocs)
+                    .count("alloc_bytes", bytes);
+            }
+            None => lines.push(box_line("allocations: n/a (needs --features bench)")),
+        }
+        lines.push(box_bottom());
+        self.out.report(&lines, record);
+    }
+
+    /// The end-of-run report, on the console and with `--report` as JSON.
+    fn run_report(&mut self) {
+        let cfg = self.cfg;
+        let now = self.current_time;
+        let dt = dt_years(cfg.tick_interval);
+        // The last interval is partial (and includes any drain), so it adds
+        // counts but no peak-rate sample.
+        self.run_report.record_interval(
+            self.stats.total_orders(),
+            self.stats.total_cancels(),
+            self.stats.trades,
+            self.stats.messages_sent,
+            0.0,
+        );
+        let out = &mut self.out;
+        out.print(&box_top());
+        out.print(&box_line("Run report"));
+        out.print(&box_mid());
+        out.print(&box_line(&format!(
+            "sim time: {:.1}s  seed: {}",
+            now, cfg.seed
+        )));
+        for line in self.run_report.lines(dt) {
+            out.print(&box_line(&line));
+        }
+        out.print(&box_bottom());
+        let report_json = self.run_report.to_json(dt, now, cfg.seed);
+        out.record(
+            Record::new("run_report", now).raw("report", report_json.trim_end().to_string()),
+        );
+        if let Some(path) = &cfg.report_path {
+            match std::fs::write(path, &report_json) {
+                Ok(()) => out.event(
+                    &format!("  ▶ report written to {}", path.display()),
+                    Record::new("report_written", now).str("path", path.display()),
+                ),
+                Err(e) => {
+                    let msg = format!("report write failed: {}", e);
+                    out.event(&format!("  ⚠ {}", msg), warning(now, msg));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{AppConfig, Cli};
+    use clap::Parser;
+    use std::path::Path;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::mpsc::sync_channel;
+
+    /// Every datagram of a run with `args` on top of a fixed base.
+    fn feed(args: &[&str]) -> Vec<Vec<u8>> {
+        let base = [
+            "orderflow-rs",
+            "--no-realtime",
+            "--control-enabled",
+            "false",
+        ];
+        let cli = Cli::parse_from(base.iter().chain(args));
+        let cfg = AppConfig::resolve(&cli).unwrap();
+        let (tx, rx) = sync_channel(1024);
+        std::thread::scope(|s| {
+            let received = s.spawn(move || rx.iter().collect());
+            super::super::simulate(&cfg, &cli, &AtomicBool::new(true), Some(tx)).unwrap();
+            received.join().unwrap()
+        })
+    }
+
+    #[test]
+    fn a_resumed_run_carries_on_the_stream_it_checkpointed() {
+        let path = std::env::temp_dir().join(format!("run-{}.ckpt", std::process::id()));
+        let path = path.to_str().unwrap();
+        let whole = feed(&["--seed", "5", "--duration", "4"]);
+        feed(&["--seed", "5", "--duration", "2", "--checkpoint", path]);
+        let checkpoint = crate::checkpoint::Checkpoint::load(Path::new(path)).unwrap();
+        assert_eq!(checkpoint.seed().unwrap(), 5);
+        assert!(checkpoint.ticks > 0);
+
+        // The seed comes from the checkpoint
+        let resumed = feed(&["--duration", "4", "--resume", path]);
+        std::fs::remove_file(path).unwrap();
+        assert!(!resumed.is_empty() && resumed.len() < whole.len());
+        assert_eq!(resumed, whole[whole.len() - resumed.len()..]);
+    }
+}
@@ -0,0 +1,324 @@
+//! Console, log file and control-push output for the engine's events.
+
+use std::fmt::{self, Write as _};
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use super::{RegimeCounts, TickStats, BOX_W};
+use crate::config::{AppConfig, LogFormat, OutputMode};
+use crate::jsonlog::Record;
+use crate::logfile::RotatingLog;
+use crate::regime::Regime;
+use crate::timing::{self, LatencyHistogram};
+
+/// Events pushed as JSON datagrams to control clients that sent `subscribe`.
+const PUSHED_EVENTS: &[&str] = &[
+    "regime",
+    "shock",
+    "jump",
+    "venue_state",
+    "scenario",
+    "control",
+    "summary",
+    "end",
+];
+
+/// Output sink that respects the configured output mode.
+pub(super) struct Output {
+    mode: OutputMode,
+    format: LogFormat,
+    file: Option<RotatingLog>,
+    /// Control socket to push from, and the clients subscribed to pushes.
+    pub(super) push_socket: Option<UdpSocket>,
+    pub(super) subscribers: Vec<SocketAddr>,
+    /// Log nothing while set (replaying a checkpoint).
+    pub(super) muted: bool,
+    /// Reused for the periodic console boxes, so they don't allocate.
+    line: String,
+}
+
+impl Output {
+    pub(super) fn new(cfg: &AppConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = match cfg.output_mode {
+            OutputMode::File | OutputMode::Both => {
+                let f = RotatingLog::open(&cfg.log_file, cfg.log_rotation.clone())
+                    .map_err(|e| format!("failed to open log file '{}': {}", cfg.log_file, e))?;
+                Some(f)
+            }
+            _ => None,
+        };
+        Ok(Self {
+            mode: cfg.output_mode,
+            format: cfg.log_format,
+            file,
+            push_socket: None,
+            subscribers: Vec::new(),
+            muted: false,
+            line: String::new(),
+        })
+    }
+
+    /// Print one console box line from `args`, padded like `box_line`.
+    pub(super) fn print_box_line(&mut self, args: fmt::Arguments) {
+        self.line.clear();
+        self.line.push_str("│ ");
+        let _ = self.line.write_fmt(args);
+        let pad = BOX_W.saturating_sub(self.line.chars().count() - 2);
+        self.line.extend(std::iter::repeat_n(' ', pad));
+        self.line.push_str(" │");
+        println!("{}", self.line);
+    }
+
+    /// Print a console box border: `left`, the rule, then `right`.
+    pub(super) fn print_box_rule(&mut self, left: char, right: char) {
+        self.line.clear();
+        self.line.push(left);
+        self.line.extend(std::iter::repeat_n('─', BOX_W + 2));
+        self.line.push(right);
+        println!("{}", self.line);
+    }
+
+    pub(super) fn to_console(&self) -> bool {
+        !self.muted && matches!(self.mode, OutputMode::Console | OutputMode::Both)
+    }
+
+    pub(super) fn to_file(&self) -> bool {
+        !self.muted && self.file.is_some()
+    }
+
+    pub(super) fn json(&self) -> bool {
+        self.format == LogFormat::Json
+    }
+
+    fn write_line(&mut self, line: &str, force_console: bool) {
+        if self.muted {
+            return;
+        }
+        if force_console || self.to_console() {
+            println!("{}", line);
+        }
+        self.write_file(line);
+    }
+
+    fn write_file(&mut self, line: &str) {
+        if self.muted {
+            return;
+        }
+        if let Some(ref mut f) = self.file {
+            let _ = f.write_line(line);
+        }
+    }
+
+    /// Decorated text (banners, boxes); suppressed in JSON mode, where the
+    /// same information goes out as a `record`.
+    pub(super) fn print(&mut self, msg: &str) {
+        if !self.json() {
+            self.write_line(msg, false);
+        }
+    }
+
+    /// Structured record; written only in JSON mode.
+    pub(super) fn record(&mut self, record: Record) {
+        if self.json() {
+            self.write_line(&record.to_json(), false);
+        }
+    }
+
+    /// Send `record` to every subscriber if it is one of `PUSHED_EVENTS`,
+    /// whatever the log format.
+    fn push(&self, record: &Record) {
+        let Some(socket) = &self.push_socket else {
+            return;
+        };
+        if self.subscribers.is_empty()
+            || !record.event().is_some_and(|e| PUSHED_EVENTS.contains(&e))
+        {
+            return;
+        }
+        let datagram = format!("{}\n", record.to_json());
+        for peer in &self.subscribers {
+            let _ = socket.send_to(datagram.as_bytes(), peer);
+        }
+    }
+
+    /// A log event: `msg` in text mode, `record` in JSON mode.
+    pub(super) fn event(&mut self, msg: &str, record: Record) {
+        self.push(&record);
+        if self.json() {
+            self.write_line(&record.to_json(), false);
+        } else {
+            self.write_line(msg, false);
+        }
+    }
+
+    /// Like `event`, but always reaches the console (even in quiet mode).
+    pub(super) fn report(&mut self, lines: &[String], record: Record) {
+        if self.json() {
+            self.write_line(&record.to_json(), true);
+        } else {
+            for line in lines {
+                self.write_line(line, true);
+            }
+        }
+    }
+
+    /// Print the periodic summary block.
+    pub(super) fn summary(
+        &mut self,
+        elapsed: f64,
+        mid: f64,
+        regime: Regime,
+        active_orders: usize,
+        stats: &TickStats,
+        interval_secs: f64,
+    ) {
+        let orders_per_sec = stats.total_orders() as f64 / interval_secs;
+        let cancels_per_sec = stats.total_cancels() as f64 / interval_secs;
+        let msgs_per_sec = stats.messages_sent as f64 / interval_secs;
+
+        if self.json() || !self.subscribers.is_empty() {
+            let record = Record::new("summary", elapsed)
+                .num("mid", mid)
+                .str("regime", regime)
+                .count("active", active_orders)
+                .count("limits", stats.limits_generated)
+                .count("markets", stats.markets_generated)
+                .count("cancels_expired", stats.cancels_expired)
+                .count("cancels_regime", stats.cancels_regime)
+                .count("cancels_agent", stats.cancels_agent)
+                .count("cancels_mass", stats.cancels_mass)
+                .count("stuffing_pairs", stats.stuffing_pairs)
+                .count("stops_placed", stats.stops_placed)
+                .count("stops_triggered", stats.stops_triggered)
+                .count("trades", stats.trades)
+                .count("rejects", stats.rejects)
+                .count("self_matches", stats.self_matches)
+                .count("dark_trades", stats.dark_trades)
+                .count("queue_peak", stats.queue_peak)
+                .count("queue_drops", stats.queue_drops)
+                .count("messages", stats.messages_sent)
+                .num("orders_per_sec", orders_per_sec)
+                .num("cancels_per_sec", cancels_per_sec)
+                .num("msgs_per_sec", msgs_per_sec)
+                .raw("by_regime", RegimeCounts::json_by_regime(&stats.by_regime));
+            self.push(&record);
+            if self.json() {
+                self.record(record);
+                return;
+            }
+        }
+
+        if self.to_console() {
+            self.print_box_rule('┌', '┐');
+            self.print_box_line(format_args!(
+                "t={:.1}s  mid={:.4}  regime={}",
+                elapsed, mid, regime
+            ));
+            self.print_box_line(format_args!(
+                "orders: {} ({:.0}/s)  limits: {}  mkt: {}",
+                stats.total_orders(),
+                orders_per_sec,
+                stats.limits_generated,
+                stats.markets_generated
+            ));
+            self.print_box_line(format_args!(
+                "cancels: {} ({:.0}/s)  expired: {}  regime: {}",
+                stats.total_cancels(),
+                cancels_per_sec,
+                stats.cancels_expired,
+                stats.cancels_regime
+            ));
+            self.print_box_line(format_args!(
+                "active: {}  msgs/s: {:.0}",
+                active_orders, msgs_per_sec
+            ));
+            if stats.queue_peak > 0 || stats.queue_drops > 0 {
+                self.print_box_line(format_args!(
+                    "send queue: peak {}  dropped: {}",
+                    stats.queue_peak, stats.queue_drops
+                ));
+            }
+            self.print_box_rule('└', '┘');
+        }
+
+        if self.to_file() {
+            let line = format!(
+                    "SUMMARY|t={:.1}|mid={:.4}|regime={}|active={}|limits={}|markets={}|cancels_exp={}|cancels_reg={}|cancels_agent={}|cancels_mass={}|stuffing={}|stops={}|stops_triggered={}|trades={}|rejects={}|self_matches={}|dark_trades={}|queue_peak={}|queue_drops={}|msgs={}",
+                    elapsed, mid, regime, active_orders,
+                    stats.limits_generated, stats.markets_generated,
+                    stats.cancels_expired, stats.cancels_regime,
+                    stats.cancels_agent, stats.cancels_mass, stats.stuffing_pairs,
+                    stats.stops_placed, stats.stops_triggered, stats.trades,
+                    stats.rejects, stats.self_matches, stats.dark_trades,
+                    stats.queue_peak, stats.queue_drops, stats.messages_sent
+            );
+            self.write_file(&line);
+            let mut line = format!("SUMMARY_BY_REGIME|t={:.1}", elapsed);
+            for r in Regime::ALL {
+                let c = &stats.by_regime[r.index()];
+                let name = r.to_string().to_ascii_lowercase();
+                line.push_str(&format!(
+                    "|{0}_orders={1}|{0}_cancels={2}|{0}_trades={3}|{0}_msgs={4}",
+                    name, c.orders, c.cancels, c.trades, c.messages
+                ));
+            }
+            self.write_file(&line);
+        }
+    }
+
+    /// Tick loop and send timing for the last display interval, so it shows
+    /// when the generator itself can't keep up with the configured rate.
+    pub(super) fn timing(
+        &mut self,
+        elapsed: f64,
+        tick_times: &LatencyHistogram,
+        send_gaps: &LatencyHistogram,
+        tick_interval: Duration,
+    ) {
+        let tick = tick_times.percentiles_us();
+        let gap = send_gaps.percentiles_us();
+        let overruns = tick_times.count_above(tick_interval);
+
+        if self.json() {
+            let us = |p: Option<[f64; 3]>| {
+                p.map_or("null".to_string(), |[p50, p99, p999]| {
+                    format!("{{\"p50\":{},\"p99\":{},\"p999\":{}}}", p50, p99, p999)
+                })
+            };
+            self.record(
+                Record::new("timing", elapsed)
+                    .count("ticks", tick_times.len())
+                    .count("overruns", overruns)
+                    .raw("tick_us", us(tick))
+                    .count("sends", send_gaps.len())
+                    .raw("send_gap_us", us(gap)),
+            );
+            return;
+        }
+
+        if self.to_console() {
+            self.print_box_rule('┌', '┐');
+            self.print_box_line(format_args!(
+                "tick µs p50/p99/p999: {}  overruns: {}",
+                timing::format_percentiles(tick),
+                overruns
+            ));
+            self.print_box_line(format_args!(
+                "send gap µs p50/p99/p999: {}",
+                timing::format_percentiles(gap)
+            ));
+            self.print_box_rule('└', '┘');
+        }
+
+        if self.to_file() {
+            let [t50, t99, t999] = tick.unwrap_or_default();
+            let [g50, g99, g999] = gap.unwrap_or_default();
+            let line = format!(
+                "TIMING|t={:.1}|ticks={}|tick_p50_us={:.1}|tick_p99_us={:.1}|tick_p999_us={:.1}|overruns={}|sends={}|gap_p50_us={:.1}|gap_p99_us={:.1}|gap_p999_us={:.1}",
+                elapsed, tick_times.len(), t50, t99, t999, overruns,
+                send_gaps.len(), g50, g99, g999
+            );
+            self.write_file(&line);
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! Many instruments on one feed (`--symbols-file`): one `Sim` per symbol,
+//! ticked in step with the first, which publishes what the others collect.
+//! With `--threads N` the others are dealt across N generator threads.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::time::{Duration, Instant};
+
+use super::{Feed, Sim};
+use crate::alloc;
+use crate::config::{AppConfig, Cli};
+use crate::multicast::Outbox;
+use crate::schedule::FairScheduler;
+
+/// Run `cfg`'s symbol and one per config in `others` on `cfg`'s feed until
+/// the first ends or `running` is cleared. Every tick, each of the others
+/// ticks first, then the first symbol's flush sends every symbol's messages
+/// interleaved by `adv_weight` and within each symbol's `max_rate`. A
+/// systemic shock the first symbol draws hits them all on the same tick, and
+/// the leader's market orders reach the followers on the next.
+/// Pausing the first pauses them all. With `tap`, as `simulate`.
+///
+/// The calling thread runs the first symbol and every `cfg.threads`th of
+/// the others; each further thread runs its own share in step with it.
+/// Every symbol draws from its own seed whichever thread runs it and the
+/// scheduler orders the feed by symbol, so the feed is the same for any
+/// number of threads.
+pub fn simulate_universe(
+    cfg: &AppConfig,
+    others: &[AppConfig],
+    cli: &Cli,
+    running: &AtomicBool,
+    tap: Option<SyncSender<Vec<u8>>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let threads = cfg.threads.clamp(1, others.len() + 1);
+    let shard = |k: usize| others.iter().skip(k).step_by(threads);
+    let mut sim = Sim::new(cfg, cli, Feed::Own(tap))?;
+    sim.sender.schedule(FairScheduler::new(
+        cfg.universe
+            .iter()
+            .map(|row| (row.adv_weight, row.max_rate)),
+    ));
+    let mut local = start_shard(shard(0), cli)?;
+    let bench_start = Instant::now();
+    let allocs_start = alloc::snapshot();
+    sim.start();
+
+    std::thread::scope(|s| {
+        let mut workers: Vec<_> = (1..threads)
+            .map(|k| {
+                let (requests, steps) = sync_channel(1);
+                let (results, replies) = sync_channel(1);
+                let configs = shard(k);
+                s.spawn(move || {
+                    let mut sims = match start_shard(configs, cli) {
+                        Ok(sims) => sims,
+                        Err(e) => {
+                            let _ = results.send(Err(e.to_string()));
+                            return;
+                        }
+                    };
+                    for (step, batch) in steps {
+                        let result = run_step(&mut sims, step, running, bench_start, allocs_start)
+                            .map(|lead| (collect(&mut sims, batch), lead));
+                        if results.send(result).is_err() {
+                            break;
+                        }
+                    }
+                });
+                Worker {
+                    requests,
+                    replies,
+                    batch: Vec::new(),
+                }
+            })
+            .collect();
+        // Runs `step` on every other symbol; returns the leader's market orders
+        let mut step =
+            |step: Step, sim: &mut Sim| -> Result<[u32; 2], Box<dyn std::error::Error>> {
+                for w in &mut workers {
+                    // A worker that failed to start has its error waiting instead
+                    let _ = w.requests.send((step, std::mem::take(&mut w.batch)));
+                }
+                let mut lead = run_step(&mut local, step, running, bench_start, allocs_start)?;
+                for other in &mut local {
+                    if let Some(outbox) = other.sender.outbox() {
+                        let _ = sim
+                            .sender
+                            .forward(other.cfg.instrument_index.into(), outbox);
+                        outbox.clear();
+                    }
+                }
+                for w in &mut workers {
+                    let (mut batch, markets) = w
+                        .replies
+                        .recv()
+                        .map_err(|_| "a generator thread stopped")??;
+                    for (lane, outbox) in &mut batch {
+                        let _ = sim.sender.forward(*lane, outbox);
+                        outbox.clear();
+                    }
+                    w.batch = batch;
+                    lead = add(lead, markets);
+                }
+                Ok(lead)
+            };
+
+        let mut lead = [0; 2];
+        let mut lead_at = 0.0;
+
+        loop {
+            // The first symbol's warmup decides when the feed opens for all
+            if sim.warmup_done() {
+                sim.open_after_warmup();
+                step(Step::Open, &mut sim)?;
+            }
+            let end_reason = sim.end_reason(running);
+            if let Some(reason) = end_reason {
+                sim.log_end(&reason);
+                break;
+            }
+
+            sim.run_commands();
+            if sim.runtime.paused {
+                std::thread::sleep(Duration::from_secs_f64(cfg.tick_interval));
+                sim.pacer.reset();
+                continue;
+            }
+            let coupling = Coupling {
+                systemic: sim.draw_systemic_shock(),
+                lead,
+                lead_at,
+                spot: sim.mid,
+            };
+            lead_at = sim.current_time;
+            coupling.apply(&mut sim);
+            lead = step(Step::Tick(coupling), &mut sim)?;
+            sim.tick();
+            lead = add(lead, sim.take_lead_markets());
+        }
+
+        step(Step::Finish, &mut sim)?;
+        // Whatever the caps still hold goes out ahead of the first symbol's end
+        sim.sender.lift_rate_caps();
+        let _ = sim.sender.flush();
+        sim.finish(bench_start, allocs_start)
+    })
+}
+
+/// What the other symbols do when the first signals them.
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    /// Open the session, the first symbol's warmup being over.
+    Open,
+    Tick(Coupling),
+    /// Drain and end the session.
+    Finish,
+}
+
+/// What the first symbol passes every symbol ahead of a tick.
+#[derive(Debug, Clone, Copy)]
+struct Coupling {
+    /// The systemic shock the first symbol drew, if any.
+    systemic: Option<f64>,
+    /// The leader's market orders on the last tick, buys then sells
+    /// (`[lead_lag]`), and that tick's time.
+    lead: [u32; 2],
+    lead_at: f64,
+    /// The first symbol's mid ahead of the tick, the futures leg's spot
+    /// (`[basis]`): the future follows it a tick behind.
+    spot: f64,
+}
+
+impl Coupling {
+    fn apply(&self, sim: &mut Sim) {
+        if let Some(shock) = self.systemic {
+            sim.systemic_shock(shock);
+        }
+        sim.follow(self.lead, self.lead_at);
+        sim.set_spot(self.spot);
+    }
+}
+
+fn add(a: [u32; 2], b: [u32; 2]) -> [u32; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+/// Collected messages per symbol: its instrument index and its outbox.
+type Batch = Vec<(usize, Outbox)>;
+
+/// A generator thread running a share of the other symbols.
+struct Worker {
+    requests: SyncSender<(Step, Batch)>,
+    replies: Receiver<Result<(Batch, [u32; 2]), String>>,
+    /// Cleared outboxes from the last reply, handed back with the next step.
+    batch: Batch,
+}
+
+/// The symbols of `configs`, each started on a collecting feed.
+fn start_shard<'a>(
+    configs: impl Iterator<Item = &'a AppConfig>,
+    cli: &'a Cli,
+) -> Result<Vec<Sim<'a>>, Box<dyn std::error::Error>> {
+    configs
+        .map(|c| {
+            let mut sim = Sim::new(c, cli, Feed::Shared)
+                .map_err(|e| format!("symbol '{}': {}", symbol(c), e))?;
+            sim.start();
+            Ok(sim)
+        })
+        .collect()
+}
+
+/// Run `step` on each of `sims`; returns the market orders of the leader
+/// among them, if it is.
+fn run_step(
+    sims: &mut [Sim],
+    step: Step,
+    running: &AtomicBool,
+    bench_start: Instant,
+    allocs_start: Option<(u64, u64)>,
+) -> Result<[u32; 2], String> {
+    let mut lead = [0; 2];
+    for sim in sims {
+        match step {
+            Step::Open if sim.warming => sim.open_after_warmup(),
+            Step::Open => {}
+            // A symbol whose session or scenarios ended early sits out the rest
+            Step::Tick(_) if sim.end_reason(running).is_some() => {}
+            Step::Tick(coupling) => {
+                coupling.apply(sim);
+                sim.tick();
+                lead = add(lead, sim.take_lead_markets());
+            }
+            Step::Finish => sim
+                .finish(bench_start, allocs_start)
+                .map_err(|e| format!("symbol '{}': {}", symbol(sim.cfg), e))?,
+        }
+    }
+    Ok(lead)
+}
+
+/// Swap what each of `sims` collected for a cleared outbox from `batch`.
+fn collect(sims: &mut [Sim], mut batch: Batch) -> Batch {
+    batch.resize_with(sims.len(), Default::default);
+    for (sim, (lane, outbox)) in sims.iter_mut().zip(&mut batch) {
+        *lane = sim.cfg.instrument_index.into();
+        if let Some(collected) = sim.sender.outbox() {
+            std::mem::swap(collected, outbox);
+        }
+    }
+    batch
+}
+
+fn symbol(cfg: &AppConfig) -> &str {
+    cfg.instruments
+        .get(cfg.instrument_index as usize)
+        .map_or("", |i| i.symbol.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LeadLag;
+    use crate::order::{OrderType, Side};
+    use crate::price::BasisConfig;
+    use crate::regime::Regime;
+    use crate::wire::decode::{decode_datagram, Message};
+    use clap::Parser;
+
+    /// A universe of three symbols run for 2s on `threads` threads.
+    fn config(threads: usize) -> (Cli, AppConfig) {
+        let path =
+            std::env::temp_dir().join(format!("one-feed-{}-{}.csv", std::process::id(), threads));
+        std::fs::write(&path, "AAA,100,0.01,1\nBBB,50,0.05,2\nCCC,400,0.1,1,500\n").unwrap();
+        let cli = Cli::parse_from([
+            "orderflow-rs",
+            "--seed",
+            "3",
+            "--duration",
+            "2",
+            "--no-realtime",
+            "--control-enabled",
+            "false",
+            "--symbols-file",
+            path.to_str().unwrap(),
+            "--threads",
+            &threads.to_string(),
+        ]);
+        let cfg = AppConfig::resolve(&cli).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        (cli, cfg)
+    }
+
+    /// Every datagram of `config(threads)`'s run.
+    fn feed(threads: usize) -> Vec<Vec<u8>> {
+        let (cli, cfg) = config(threads);
+        run(&cli, &cfg)
+    }
+
+    fn run(cli: &Cli, cfg: &AppConfig) -> Vec<Vec<u8>> {
+        let others: Vec<_> = (1..3)
+            .map(|i| crate::symbols::symbol_config(cfg, i))
+            .collect();
+
+        let (tx, rx) = sync_channel(1024);
+        std::thread::scope(|s| {
+            let received = s.spawn(move || rx.iter().collect());
+            simulate_universe(cfg, &others, cli, &AtomicBool::new(true), Some(tx)).unwrap();
+            received.join().unwrap()
+        })
+    }
+
+    #[test]
+    fn every_symbol_shares_one_feed() {
+        let datagrams = feed(1);
+        let messages: Vec<_> = datagrams
+            .iter()
+            .flat_map(|d| decode_datagram(d).unwrap())
+            .collect();
+
+        let mut starts = [0; 3];
+        let mut ends = [0; 3];
+        let mut orders = [0; 3];
+        let mut directory = 0;
+        for m in &messages {
+            match m {
+                Message::SessionStart(s) => starts[s.instrument as usize] += 1,
+                Message::SessionEnd(e) => ends[e.instrument as usize] += 1,
+                Message::Order(o) => orders[o.instrument as usize] += 1,
+                Message::Symbol { .. } => directory += 1,
+                _ => {}
+            }
+        }
+        assert_eq!((starts, ends, directory), ([1; 3], [1; 3], 3));
+        assert!(orders.iter().all(|&n| n > 0), "{:?}", orders);
+        // The primary's SESSION_END closes the feed
+        assert!(matches!(messages.last(), Some(Message::SessionEnd(e)) if e.instrument == 0));
+
+        // Sharding the symbols across threads leaves the feed as it was
+        assert_eq!(feed(2), datagrams);
+        assert_eq!(feed(3), datagrams);
+    }
+
+    #[test]
+    fn systemic_shocks_crash_every_symbol_together() {
+        let (cli, mut cfg) = config(1);
+        cfg.systemic_shock_prob = 1.0;
+        let others: Vec<_> = (1..3)
+            .map(|i| crate::symbols::symbol_config(&cfg, i))
+            .collect();
+        let (tx, _rx) = sync_channel(1);
+        let mut sim = Sim::new(&cfg, &cli, Feed::Own(Some(tx.clone()))).unwrap();
+        let mut others = start_shard(others.iter(), &cli).unwrap();
+        let rally = &mut others[1];
+        rally
+            .state
+            .transition_to(Regime::Rally, &mut rally.rngs.regimes);
+
+        let shock = sim.draw_systemic_shock().unwrap();
+        assert!((-0.06..=-0.02).contains(&shock), "{}", shock);
+        for s in std::iter::once(&mut sim).chain(&mut others) {
+            let mid = s.mid;
+            s.systemic_shock(shock);
+            assert!((s.mid - mid * (1.0 + shock)).abs() < 1e-9);
+            assert_eq!(s.state.current, Regime::Crash);
+        }
+
+        cfg.systemic_shock_prob = 0.0;
+        let mut sim = Sim::new(&cfg, &cli, Feed::Own(Some(tx))).unwrap();
+        assert_eq!(sim.draw_systemic_shock(), None);
+    }
+
+    #[test]
+    fn leader_market_orders_draw_follow_ons() {
+        let (cli, mut cfg) = config(1);
+        let markets = |datagrams: Vec<Vec<u8>>| {
+            let mut counts = [0; 3];
+            for m in datagrams.iter().flat_map(|d| decode_datagram(d).unwrap()) {
+                if let Message::Order(o) = m {
+                    counts[o.instrument as usize] += (o.order_type == OrderType::Market) as u32;
+                }
+            }
+            counts
+        };
+        let alone = markets(run(&cli, &cfg));
+        cfg.lead_lag = Some(LeadLag {
+            leader: 1,
+            probability: 1.0,
+            delay: 0.25,
+        });
+        let coupled = markets(run(&cli, &cfg));
+
+        // The leader's own flow is untouched; each follower gains one market
+        // order per leader market order due before the end
+        assert_eq!(coupled[1], alone[1]);
+        for i in [0, 2] {
+            let extra = coupled[i] - alone[i];
+            assert!(extra > 0 && extra <= alone[1], "{:?} {:?}", alone, coupled);
+        }
+
+        let (tx, _rx) = sync_channel(1);
+        let mut sim = Sim::new(&cfg, &cli, Feed::Own(Some(tx))).unwrap();
+        let others = [crate::symbols::symbol_config(&cfg, 1)];
+        let mut leader = start_shard(others.iter(), &cli).unwrap();
+        leader[0].follow([2, 1], 1.0);
+        sim.follow([2, 1], 1.0);
+        assert!(leader[0].follow_ons.is_empty());
+        assert_eq!(
+            sim.follow_ons,
+            [(1.25, Side::Buy), (1.25, Side::Buy), (1.25, Side::Sell)]
+        );
+    }
+
+    #[test]
+    fn the_future_prices_off_the_spot_plus_its_basis() {
+        let (cli, mut cfg) = config(1);
+        cfg.basis = Some(BasisConfig {
+            enabled: true,
+            mean: 0.5,
+            speed: 5.0,
+            volatility: 0.0,
+        });
+        let legs = [
+            crate::symbols::symbol_config(&cfg, 0),
+            crate::symbols::symbol_config(&cfg, 1),
+        ];
+        let mut legs = start_shard(legs.iter(), &cli).unwrap();
+        assert!(legs[0].basis.is_none() && legs[1].basis.is_some());
+
+        for sim in &mut legs {
+            sim.open_after_warmup();
+        }
+        let dt = legs[1].cfg.tick_interval;
+        for n in 1..=(3.0 / dt) as i32 {
+            let spot = legs[0].mid;
+            for sim in &mut legs {
+                sim.set_spot(spot);
+                sim.tick();
+            }
+            // Without noise the basis decays from the legs' initial price gap
+            // to its mean; only the tick's own flow moves the future off it
+            let basis = 0.5 + (50.0 - 100.0 - 0.5) * (-5.0 * dt * n as f64).exp();
+            assert!(
+                (legs[1].mid / (spot + basis) - 1.0).abs() < 0.01,
+                "{} {} {}",
+                legs[1].mid,
+                spot,
+                basis
+            );
+        }
+    }
+}
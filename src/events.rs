@@ -0,0 +1,202 @@
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::order::Side;
+
+/// Injected market events, configured under `[events.*]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EventsConfig {
+    pub quote_stuffing: QuoteStuffingConfig,
+    pub mass_cancel: MassCancelConfig,
+    pub fat_finger: FatFingerConfig,
+}
+
+/// Purges on entering CRASH: participants pull all their resting orders
+/// with a single MASS_CANCEL instead of individual cancels.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MassCancelConfig {
+    /// Probability that a transition into CRASH triggers purges.
+    pub crash_prob: f64,
+    /// Participants purged per triggering transition.
+    pub participants: usize,
+    /// Probability that a purge only covers one side of the book.
+    pub one_side_prob: f64,
+}
+
+impl Default for MassCancelConfig {
+    fn default() -> Self {
+        Self {
+            crash_prob: 0.0,
+            participants: 3,
+            one_side_prob: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct QuoteStuffingConfig {
+    /// Add/cancel pairs per second during a burst.
+    pub rate: f64,
+    /// Burst length in seconds when not given explicitly.
+    pub duration: f64,
+    /// Sim times at which bursts start automatically.
+    pub schedule: Vec<f64>,
+    pub participant_id: u32,
+}
+
+impl Default for QuoteStuffingConfig {
+    fn default() -> Self {
+        Self {
+            rate: 5000.0,
+            duration: 2.0,
+            schedule: Vec::new(),
+            participant_id: 99_999,
+        }
+    }
+}
+
+/// Quote-stuffing burst state: while active, the engine emits `rate` add/cancel
+/// pairs per second at the top of the book.
+pub struct QuoteStuffer {
+    cfg: QuoteStuffingConfig,
+    active_until: f64,
+    next_scheduled: usize,
+    /// Fractional pairs carried between ticks so low rates still average out.
+    carry: f64,
+}
+
+impl QuoteStuffer {
+    pub fn new(cfg: &QuoteStuffingConfig) -> Self {
+        let mut cfg = cfg.clone();
+        cfg.schedule.sort_by(f64::total_cmp);
+        Self {
+            cfg,
+            active_until: f64::NEG_INFINITY,
+            next_scheduled: 0,
+            carry: 0.0,
+        }
+    }
+
+    pub fn participant_id(&self) -> u32 {
+        self.cfg.participant_id
+    }
+
+    /// Start (or extend) a burst. `None` uses the configured duration.
+    pub fn trigger(&mut self, now: f64, duration: Option<f64>) -> f64 {
+        let d = duration.unwrap_or(self.cfg.duration);
+        self.active_until = self.active_until.max(now + d);
+        d
+    }
+
+    /// Fire any scheduled bursts that are due. Returns their duration if one started.
+    pub fn poll_schedule(&mut self, now: f64) -> Option<f64> {
+        let mut started = None;
+        while self.next_scheduled < self.cfg.schedule.len()
+            && now >= self.cfg.schedule[self.next_scheduled]
+        {
+            self.next_scheduled += 1;
+            started = Some(self.trigger(now, None));
+        }
+        started
+    }
+
+    /// Number of add/cancel pairs to emit this tick (0 when no burst is active).
+    pub fn pairs_this_tick(&mut self, now: f64, dt_seconds: f64) -> u64 {
+        if now >= self.active_until {
+            self.carry = 0.0;
+            return 0;
+        }
+        let exact = self.cfg.rate * dt_seconds + self.carry;
+        let n = exact.floor();
+        self.carry = exact - n;
+        n as u64
+    }
+}
+
+/// A single order far larger than normal, priced through the market.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FatFingerConfig {
+    /// Sim times at which a fat-finger order is sent.
+    pub schedule: Vec<f64>,
+    /// Random fat-finger orders per sim second, on top of the schedule.
+    pub rate: f64,
+    /// Size range as a multiple of a normal order size.
+    pub min_multiplier: f64,
+    pub max_multiplier: f64,
+    /// How far through mid the limit price sits, as a fraction of mid.
+    pub aggression_pct: f64,
+    /// Fraction mid moves in the order's direction afterwards.
+    pub impact_pct: f64,
+}
+
+impl Default for FatFingerConfig {
+    fn default() -> Self {
+        Self {
+            schedule: Vec::new(),
+            rate: 0.0,
+            min_multiplier: 100.0,
+            max_multiplier: 1000.0,
+            aggression_pct: 0.02,
+            impact_pct: 0.005,
+        }
+    }
+}
+
+/// Decides when fat-finger orders fire and how they look.
+pub struct FatFinger {
+    cfg: FatFingerConfig,
+    next_scheduled: usize,
+}
+
+impl FatFinger {
+    pub fn new(cfg: &FatFingerConfig) -> Self {
+        let mut cfg = cfg.clone();
+        cfg.schedule.sort_by(f64::total_cmp);
+        Self {
+            cfg,
+            next_scheduled: 0,
+        }
+    }
+
+    /// Whether an order is due this tick: a scheduled time has passed or a
+    /// random one fired. Several due at once still make a single order.
+    pub fn poll(&mut self, now: f64, dt_seconds: f64, rng: &mut impl Rng) -> bool {
+        let mut due = false;
+        while self.next_scheduled < self.cfg.schedule.len()
+            && now >= self.cfg.schedule[self.next_scheduled]
+        {
+            self.next_scheduled += 1;
+            due = true;
+        }
+        if self.cfg.rate > 0.0 {
+            due |= rng.gen_bool((self.cfg.rate * dt_seconds).min(1.0));
+        }
+        due
+    }
+
+    /// Size multiple of a normal order for the next fat-finger order.
+    pub fn multiplier(&self, rng: &mut impl Rng) -> f64 {
+        rng.gen_range(self.cfg.min_multiplier..=self.cfg.max_multiplier)
+    }
+
+    /// Limit price `aggression_pct` through `mid` on `side`, on the tick grid.
+    pub fn limit_price(&self, mid: f64, side: Side, tick_size: f64) -> f64 {
+        let raw = match side {
+            Side::Buy => mid * (1.0 + self.cfg.aggression_pct),
+            Side::Sell => mid * (1.0 - self.cfg.aggression_pct),
+        };
+        ((raw / tick_size).round() * tick_size).max(tick_size)
+    }
+
+    /// Mid after the order's impact.
+    pub fn impact(&self, mid: f64, side: Side) -> f64 {
+        match side {
+            Side::Buy => mid * (1.0 + self.cfg.impact_pct),
+            Side::Sell => mid * (1.0 - self.cfg.impact_pct),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::book::SelfMatch;
+use crate::order::{MassCancel, Order, OrderType, Trade};
+use crate::wire::Price;
+
+#[cfg(feature = "parquet")]
+mod parquet;
+#[cfg(feature = "parquet")]
+pub use self::parquet::ParquetExport;
+
+/// Copy of the published order flow, independent of the wire format. The
+/// file sinks have the same columns: id, symbol, side, type, price, size,
+/// created_at, event_type, sim_time.
+pub trait Export {
+    fn order(&mut self, order: &Order) -> io::Result<()>;
+    fn cancel(&mut self, order_id: u64, time: f64) -> io::Result<()>;
+    fn trade(&mut self, trade: &Trade) -> io::Result<()>;
+    /// `ids` are the orders it removed. The file sinks have no row for it.
+    fn mass_cancel(&mut self, _mass: &MassCancel, _ids: &[u64]) -> io::Result<()> {
+        Ok(())
+    }
+    /// A fill stopped by self-match prevention. The file sinks have no row for it.
+    fn self_match(&mut self, _sm: &SelfMatch) -> io::Result<()> {
+        Ok(())
+    }
+    /// Called once per tick.
+    fn flush(&mut self) -> io::Result<()>;
+    /// Called once at shutdown; writes anything still buffered.
+    fn finish(&mut self) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+const CSV_HEADER: &str = "id,symbol,side,type,price,size,created_at,event_type,sim_time";
+
+/// One CSV row per published order, cancel and trade. CANCEL rows carry only
+/// the order ID; TRADE rows use the maker's order ID and the aggressor side.
+pub struct CsvExport {
+    out: BufWriter<File>,
+    symbol: String,
+}
+
+impl CsvExport {
+    pub fn create(path: &Path, symbol: &str) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{}", CSV_HEADER)?;
+        Ok(Self {
+            out,
+            symbol: symbol.to_string(),
+        })
+    }
+}
+
+impl Export for CsvExport {
+    fn order(&mut self, order: &Order) -> io::Result<()> {
+        // Market orders have no price.
+        let price = match order.order_type {
+            OrderType::Market => String::new(),
+            _ => Price(order.price).to_string(),
+        };
+        writeln!(
+            self.out,
+            "{},{},{},{},{},{},{:.3},ORDER,{:.3}",
+            order.id,
+            self.symbol,
+            order.side,
+            order.order_type,
+            price,
+            order.size,
+            order.created_at,
+            order.created_at
+        )
+    }
+
+    fn cancel(&mut self, order_id: u64, time: f64) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "{},{},,,,,,CANCEL,{:.3}",
+            order_id, self.symbol, time
+        )
+    }
+
+    fn trade(&mut self, trade: &Trade) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "{},{},{},,{},{},,TRADE,{:.3}",
+            trade.maker_id,
+            self.symbol,
+            trade.aggressor,
+            Price(trade.price),
+            trade.size,
+            trade.time
+        )
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
@@ -0,0 +1,561 @@
+//! Consumer-side tools that read the feed back instead of publishing it.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::book::{Book, Level, Quote, SelfMatchMode};
+use crate::config::{self, AppConfig, DecodeArgs, ListenArgs, VectorsArgs, WireFormat};
+use crate::multicast;
+use crate::order::{OrderType, Side};
+use crate::pcap;
+use crate::wire::decode::{self, Message};
+use crate::wire::{protobuf, sbe, vectors, Price, WireMessage, MAGIC};
+
+/// Largest UDP payload.
+const MAX_DATAGRAM: usize = 65_535;
+
+/// How long `listen` blocks on the socket before checking for Ctrl+C.
+const LISTEN_POLL: Duration = Duration::from_millis(100);
+
+/// `decode`: print every message from a capture, stdin or the multicast
+/// group as one text-format line. Undecodable input is reported on stderr
+/// and skipped.
+pub fn decode(args: &DecodeArgs, cfg: &AppConfig) -> Result<(), Box<dyn std::error::Error>> {
+    // Piping into `head` closes stdout early; that is not a failure.
+    match decode_all(args, cfg) {
+        Err(e)
+            if e.downcast_ref::<io::Error>()
+                .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe) =>
+        {
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+fn decode_all(args: &DecodeArgs, cfg: &AppConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let mut out = io::stdout().lock();
+    let mut failed = 0u64;
+
+    if args.multicast {
+        let socket = multicast::join(cfg.multicast_group, cfg.multicast_port)?;
+        eprintln!(
+            "Decoding {}:{} (Ctrl+C to stop)",
+            cfg.multicast_group, cfg.multicast_port
+        );
+        let mut buf = vec![0u8; MAX_DATAGRAM];
+        loop {
+            let (n, from) = socket.recv_from(&mut buf)?;
+            match decode::decode_datagram(&buf[..n]) {
+                Ok(msgs) => print_all(&mut out, &msgs)?,
+                Err(e) => eprintln!("error: datagram from {}: {}", from, e),
+            }
+        }
+    }
+
+    let mut input: Box<dyn BufRead> = match &args.input {
+        Some(path) if path.as_os_str() != "-" => {
+            Box::new(BufReader::new(File::open(path).map_err(|e| {
+                format!("failed to open {}: {}", path.display(), e)
+            })?))
+        }
+        _ => Box::new(io::stdin().lock()),
+    };
+
+    let head = input.fill_buf()?;
+    if pcap::is_pcap(head)
+        || head.starts_with(MAGIC)
+        || sbe::is_sbe(head)
+        || protobuf::is_protobuf(head)
+        || is_flatbuffers(head)
+    {
+        let mut buf = Vec::new();
+        input.read_to_end(&mut buf)?;
+        // A raw binary dump has no datagram boundaries; decode it as one stream.
+        let decoded: Vec<_> = if pcap::is_pcap(&buf) {
+            pcap::udp_payloads(&buf)?
+                .iter()
+                .map(|d| decode::decode_datagram(d))
+                .collect()
+        } else if is_flatbuffers(&buf) {
+            vec![decode::decode_datagram(&buf)]
+        } else if sbe::is_sbe(&buf) {
+            vec![decode::decode_sbe_stream(&buf)]
+        } else if protobuf::is_protobuf(&buf) {
+            vec![protobuf::decode(&buf)]
+        } else {
+            vec![decode::decode_binary_stream(&buf)]
+        };
+        for (i, result) in decoded.into_iter().enumerate() {
+            match result {
+                Ok(msgs) => print_all(&mut out, &msgs)?,
+                Err(e) => {
+                    eprintln!("error: datagram {}: {}", i + 1, e);
+                    failed += 1;
+                }
+            }
+        }
+    } else {
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match decode::decode_text(&line) {
+                Ok(msg) => print_all(&mut out, &[msg])?,
+                Err(e) => {
+                    eprintln!("error: line {}: {}", i + 1, e);
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} inputs failed to decode", failed).into());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "flatbuffers")]
+fn is_flatbuffers(buf: &[u8]) -> bool {
+    crate::wire::flatbuffers::is_flatbuffers(buf)
+}
+
+#[cfg(not(feature = "flatbuffers"))]
+fn is_flatbuffers(_buf: &[u8]) -> bool {
+    false
+}
+
+fn print_all(out: &mut impl Write, msgs: &[Message]) -> io::Result<()> {
+    for msg in msgs {
+        writeln!(out, "{}", msg)?;
+    }
+    Ok(())
+}
+
+/// `vectors`: write `wire::vectors::canonical()` in one wire format.
+/// Binary and SBE frames are self-delimiting, so they are written back to back,
+/// and protobuf `Datagram`s concatenate into one. FlatBuffers messages are
+/// size-prefixed.
+pub fn vectors(args: &VectorsArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let format = config::parse_wire_format(&args.format)?;
+    let mut out = io::stdout().lock();
+    for msg in vectors::canonical() {
+        match format {
+            WireFormat::Text => writeln!(out, "{}", msg.to_wire_text())?,
+            WireFormat::Binary => out.write_all(&msg.to_wire_binary())?,
+            WireFormat::Sbe => out.write_all(&sbe::from_binary(&msg.to_wire_binary())?)?,
+            WireFormat::Protobuf => out.write_all(&protobuf::encode(&msg, false))?,
+            #[cfg(feature = "flatbuffers")]
+            WireFormat::FlatBuffers => {
+                out.write_all(&crate::wire::flatbuffers::encode(&msg, false))?
+            }
+            #[cfg(not(feature = "flatbuffers"))]
+            WireFormat::FlatBuffers => {
+                return Err("flatbuffers requires a build with `--features flatbuffers`".into())
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// `listen`: join the configured group (or a `--venue`'s) and check the feed the way a
+/// consumer sees it, printing statistics every `--interval` seconds and a
+/// summary on Ctrl+C (or after SESSION_END with `--until-end`).
+pub fn listen(args: &ListenArgs, cfg: &AppConfig) -> Result<(), Box<dyn std::error::Error>> {
+    if args.interval <= 0.0 {
+        return Err("--interval must be > 0".into());
+    }
+    let (group, port) = match &args.venue {
+        Some(name) => cfg
+            .venues
+            .iter()
+            .find(|v| &v.name == name)
+            .map(|v| (v.multicast_group, v.multicast_port))
+            .ok_or_else(|| format!("no venue named '{}' in [[venues]]", name))?,
+        None => (cfg.multicast_group, cfg.multicast_port),
+    };
+    let socket = multicast::join(group, port)?;
+    socket.set_read_timeout(Some(LISTEN_POLL))?;
+
+    let running = Arc::new(AtomicBool::new(true));
+    {
+        let running = Arc::clone(&running);
+        ctrlc::set_handler(move || {
+            running.store(false, Ordering::SeqCst);
+        })?;
+    }
+    eprintln!("Listening on {}:{} (Ctrl+C to stop)", group, port);
+
+    let mut listener = Listener::new(cfg.tick_size);
+    let started = Instant::now();
+    let interval = Duration::from_secs_f64(args.interval);
+    let mut next_report = started + interval;
+    let mut buf = vec![0u8; MAX_DATAGRAM];
+    while running.load(Ordering::SeqCst) {
+        match socket.recv(&mut buf) {
+            Ok(n) => {
+                listener.datagram(&buf[..n]);
+                if args.until_end && listener.ended {
+                    break;
+                }
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock
+                        | io::ErrorKind::TimedOut
+                        | io::ErrorKind::Interrupted
+                ) => {}
+            Err(e) => return Err(e.into()),
+        }
+        if Instant::now() >= next_report {
+            println!("{}", listener.interval_line(started.elapsed()));
+            next_report += interval;
+        }
+    }
+    println!("{}", listener.summary(started.elapsed()));
+    Ok(())
+}
+
+/// Gaps in order IDs. The protocol has no sequence numbers, but every
+/// order ID is published exactly once as an ORDER or REJECT (iceberg
+/// refreshes repeat an ID), so a skipped ID means a lost message.
+#[derive(Default)]
+struct IdGaps {
+    next: Option<u64>,
+    /// IDs skipped over and not seen since.
+    missing: BTreeSet<u64>,
+    /// Skipped IDs that arrived later (reordered, not lost).
+    late: u64,
+}
+
+impl IdGaps {
+    fn see(&mut self, id: u64) {
+        match self.next {
+            Some(next) if id >= next => {
+                self.missing.extend(next..id);
+                self.next = Some(id + 1);
+            }
+            Some(_) => {
+                if self.missing.remove(&id) {
+                    self.late += 1;
+                }
+            }
+            None => self.next = Some(id + 1),
+        }
+    }
+}
+
+/// Consumer-side state rebuilt from the feed.
+struct Listener {
+    /// Book implied by ORDER, CANCEL, MASS_CANCEL, TRADE and UNCROSS.
+    book: Book,
+    /// Bid and ask levels by tick from LEVEL messages (`book_view = "mbp"`);
+    /// `None` until the first one.
+    levels: Option<[BTreeMap<i64, Level>; 2]>,
+    /// Resting STOP orders, held outside the book until cancelled.
+    stops: BTreeSet<u64>,
+    ids: IdGaps,
+    datagrams: u64,
+    bytes: u64,
+    messages: u64,
+    /// `messages` at the last interval line, for the rate.
+    reported: u64,
+    errors: u64,
+    by_kind: BTreeMap<&'static str, u64>,
+    /// Messages since SESSION_START; `None` when joined mid-session.
+    session_messages: Option<u64>,
+    /// `(received, sent)` from the last SESSION_END.
+    session_end: Option<(u64, u64)>,
+    ended: bool,
+    /// Between SNAPSHOT_START and SNAPSHOT_END.
+    snapshot: bool,
+    /// CANCELs and TRADEs naming an order not in the implied book.
+    unknown_refs: u64,
+    /// Shares printed by TRADE (against the lit book) and by DARK_TRADE.
+    lit_volume: u64,
+    dark_volume: u64,
+    quotes_checked: u64,
+    quote_mismatches: u64,
+}
+
+impl Listener {
+    fn new(tick_size: f64) -> Self {
+        Self {
+            book: Book::new(tick_size, 0),
+            levels: None,
+            stops: BTreeSet::new(),
+            ids: IdGaps::default(),
+            datagrams: 0,
+            bytes: 0,
+            messages: 0,
+            reported: 0,
+            errors: 0,
+            by_kind: BTreeMap::new(),
+            session_messages: None,
+            session_end: None,
+            ended: false,
+            snapshot: false,
+            unknown_refs: 0,
+            lit_volume: 0,
+            dark_volume: 0,
+            quotes_checked: 0,
+            quote_mismatches: 0,
+        }
+    }
+
+    fn datagram(&mut self, buf: &[u8]) {
+        self.datagrams += 1;
+        self.bytes += buf.len() as u64;
+        match decode::decode_datagram(buf) {
+            Ok(msgs) => msgs.into_iter().for_each(|m| self.apply(m)),
+            Err(e) => {
+                self.errors += 1;
+                eprintln!("error: datagram {}: {}", self.datagrams, e);
+            }
+        }
+    }
+
+    fn apply(&mut self, msg: Message) {
+        self.messages += 1;
+        *self.by_kind.entry(msg.kind()).or_default() += 1;
+        if let Some(n) = self.session_messages.as_mut() {
+            *n += 1;
+        }
+
+        match msg {
+            Message::SessionStart(start) => {
+                self.book = Book::new(start.tick_size, 0);
+                self.levels = None;
+                self.stops.clear();
+                self.ids = IdGaps {
+                    next: Some(0),
+                    ..IdGaps::default()
+                };
+                self.session_messages = Some(1);
+                self.session_end = None;
+                self.snapshot = false;
+            }
+            Message::SessionEnd(end) => {
+                // SESSION_END counts every message sent before it.
+                if let Some(n) = self.session_messages.take() {
+                    self.session_end = Some((n - 1, end.messages));
+                }
+                self.ended = true;
+            }
+            Message::SnapshotStart(_) => self.snapshot = true,
+            Message::SnapshotEnd(_) => {
+                // Orders that came and went during the warmup were never
+                // published: pick up gap tracking from the next live ID.
+                self.snapshot = false;
+                self.ids.next = None;
+            }
+            Message::Order(order) if self.snapshot => {
+                if order.order_type == OrderType::Stop {
+                    self.stops.insert(order.id);
+                } else if order.is_resting() {
+                    self.book.insert(order);
+                }
+            }
+            Message::Order(order) => {
+                self.ids.see(order.id);
+                if order.order_type == OrderType::Stop {
+                    self.stops.insert(order.id);
+                } else if order.is_resting() {
+                    self.book.insert(order);
+                }
+            }
+            Message::Reject(reject) => self.ids.see(reject.order.id),
+            Message::Cancel { id, .. } => {
+                let known = self.stops.remove(&id) || self.book.remove(id).is_some();
+                self.unknown_refs += u64::from(!known);
+            }
+            Message::Trade(trade) => {
+                self.lit_volume += trade.size as u64;
+                let known = self.book.reduce(trade.maker_id, trade.size);
+                // A limit taker (marketable on arrival, or resting through an
+                // auction uncross) was inserted on its ORDER
+                self.book.reduce(trade.taker_id, trade.size);
+                // With book_view = "mbp" there are no ORDERs to check against
+                if self.by_kind.contains_key("ORDER") {
+                    self.unknown_refs += u64::from(!known);
+                }
+            }
+            Message::SelfMatch(sm) => {
+                let known = match sm.mode {
+                    SelfMatchMode::CancelOldest => self.book.remove(sm.maker_id).is_some(),
+                    _ => {
+                        self.book.reduce(sm.taker_id, sm.size);
+                        self.book.reduce(sm.maker_id, sm.size)
+                    }
+                };
+                if self.by_kind.contains_key("ORDER") {
+                    self.unknown_refs += u64::from(!known);
+                }
+            }
+            // Off-book: nothing in the implied book to reconcile it against
+            Message::DarkTrade(dark) => self.dark_volume += dark.size as u64,
+            Message::MassCancel(mc) => {
+                let doomed: Vec<u64> = self
+                    .book
+                    .orders()
+                    .filter(|o| {
+                        o.participant_id == mc.participant_id && mc.side.is_none_or(|s| o.side == s)
+                    })
+                    .map(|o| o.id)
+                    .collect();
+                for id in doomed {
+                    self.book.remove(id);
+                }
+            }
+            Message::Level(l) => {
+                let t = (l.price / self.book.tick_size()).round() as i64;
+                let levels = self.levels.get_or_insert_with(Default::default);
+                let side = &mut levels[(l.side == Side::Sell) as usize];
+                if l.count == 0 {
+                    side.remove(&t);
+                } else {
+                    side.insert(
+                        t,
+                        Level {
+                            size: l.size,
+                            count: l.count,
+                        },
+                    );
+                }
+            }
+            Message::Quote(q) => {
+                self.quotes_checked += 1;
+                if !self.matches_book(&q) {
+                    self.quote_mismatches += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Best level on `side`: from the LEVELs once they are flowing,
+    /// otherwise from the implied book.
+    fn best(&self, side: Side) -> Option<(f64, Level)> {
+        let Some([bids, asks]) = &self.levels else {
+            return match side {
+                Side::Buy => self.book.best_bid(),
+                Side::Sell => self.book.best_ask(),
+            };
+        };
+        let best = match side {
+            Side::Buy => bids.last_key_value(),
+            Side::Sell => asks.first_key_value(),
+        };
+        best.map(|(&t, &level)| (t as f64 * self.book.tick_size(), level))
+    }
+
+    /// Prices are compared to the nearest tick: text quotes carry two decimals.
+    fn matches_book(&self, q: &Quote) -> bool {
+        let (bid_price, bid) = self.best(Side::Buy).unwrap_or_default();
+        let (ask_price, ask) = self.best(Side::Sell).unwrap_or_default();
+        let tick = self.book.tick_size();
+        let same_price = |a: f64, b: f64| (a - b).abs() < tick / 2.0;
+        same_price(q.bid_price, bid_price)
+            && same_price(q.ask_price, ask_price)
+            && q.bid_size == bid.size
+            && q.ask_size == ask.size
+    }
+
+    fn interval_line(&mut self, elapsed: Duration) -> String {
+        let secs = elapsed.as_secs_f64();
+        let line = format!(
+            "[{:>7.1}s] msgs={} (+{})  datagrams={}  errors={}  missing_ids={}  book: {} orders  {} | {}",
+            secs,
+            self.messages,
+            self.messages - self.reported,
+            self.datagrams,
+            self.errors,
+            self.ids.missing.len(),
+            self.book.len(),
+            fmt_level(self.best(Side::Buy)),
+            fmt_level(self.best(Side::Sell)),
+        );
+        self.reported = self.messages;
+        line
+    }
+
+    fn summary(&self, elapsed: Duration) -> String {
+        let secs = elapsed.as_secs_f64();
+        let kinds = self
+            .by_kind
+            .iter()
+            .map(|(k, n)| format!("{} {}", k, n))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let session = match (self.session_end, self.session_messages) {
+            (Some((received, sent)), _) => format!(
+                "{} of {} messages received ({} lost)",
+                received,
+                sent,
+                sent.saturating_sub(received)
+            ),
+            (None, Some(_)) => "in progress (no SESSION_END yet)".to_string(),
+            (None, None) if self.ids.next.is_some() => {
+                "joined mid-session; total loss unknown".to_string()
+            }
+            (None, None) => "no messages".to_string(),
+        };
+        let levels = match &self.levels {
+            Some([bids, asks]) => bids.len() + asks.len(),
+            None => {
+                self.book.top(Side::Buy, usize::MAX).len()
+                    + self.book.top(Side::Sell, usize::MAX).len()
+            }
+        };
+        [
+            "── listen summary ──".to_string(),
+            format!("  elapsed        {:.1}s", secs),
+            format!(
+                "  received       {} messages in {} datagrams, {} bytes ({:.0} msgs/s)",
+                self.messages,
+                self.datagrams,
+                self.bytes,
+                self.messages as f64 / secs.max(1e-9)
+            ),
+            format!("  by type        {}", kinds),
+            format!("  decode errors  {}", self.errors),
+            format!(
+                "  order ids      {} missing, {} arrived out of order",
+                self.ids.missing.len(),
+                self.ids.late
+            ),
+            format!("  session        {}", session),
+            format!(
+                "  implied book   {} orders on {} levels, {} | {}",
+                self.book.len(),
+                levels,
+                fmt_level(self.best(Side::Buy)),
+                fmt_level(self.best(Side::Sell))
+            ),
+            format!(
+                "  volume         {} lit, {} dark",
+                self.lit_volume, self.dark_volume
+            ),
+            format!(
+                "  consistency    {} cancels/trades for unknown orders, {} of {} quotes disagree with the book",
+                self.unknown_refs, self.quote_mismatches, self.quotes_checked
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+fn fmt_level(level: Option<(f64, Level)>) -> String {
+    match level {
+        Some((price, l)) => format!("{} x {}", Price(price), l.size),
+        None => "-".to_string(),
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt::Write;
+
+/// A typed field value.
+enum Value {
+    Int(u64),
+    Num(f64),
+    Bool(bool),
+    Str(String),
+    /// Pre-rendered JSON (object or array), embedded as-is.
+    Raw(String),
+}
+
+/// One structured log record, rendered as a single line of JSON for
+/// `output.format = "json"`. Every record carries `event` and sim time `t`.
+pub struct Record {
+    fields: Vec<(&'static str, Value)>,
+}
+
+impl Record {
+    pub fn new(event: &str, t: f64) -> Self {
+        Self {
+            fields: vec![
+                ("event", Value::Str(event.to_string())),
+                ("t", Value::Num(t)),
+            ],
+        }
+    }
+
+    /// A record without the `event`/`t` header, for replies and nested objects.
+    pub fn object() -> Self {
+        Self { fields: Vec::new() }
+    }
+
+    /// The `event` name, unless built with `object`.
+    pub fn event(&self) -> Option<&str> {
+        match self.fields.first() {
+            Some(("event", Value::Str(name))) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Non-negative integer field (counts, IDs, seeds).
+    pub fn count(mut self, key: &'static str, v: impl TryInto<u64>) -> Self {
+        self.fields
+            .push((key, Value::Int(v.try_into().unwrap_or(u64::MAX))));
+        self
+    }
+
+    pub fn num(mut self, key: &'static str, v: f64) -> Self {
+        self.fields.push((key, Value::Num(v)));
+        self
+    }
+
+    pub fn bool(mut self, key: &'static str, v: bool) -> Self {
+        self.fields.push((key, Value::Bool(v)));
+        self
+    }
+
+    pub fn str(mut self, key: &'static str, v: impl ToString) -> Self {
+        self.fields.push((key, Value::Str(v.to_string())));
+        self
+    }
+
+    /// Array of strings.
+    pub fn strs(self, key: &'static str, items: &[&str]) -> Self {
+        let mut json = String::from("[");
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            push_str(&mut json, item);
+        }
+        json.push(']');
+        self.raw(key, json)
+    }
+
+    pub fn raw(mut self, key: &'static str, json: String) -> Self {
+        self.fields.push((key, Value::Raw(json)));
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{");
+        for (i, (key, value)) in self.fields.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            push_str(&mut out, key);
+            out.push(':');
+            match value {
+                Value::Int(v) => {
+                    let _ = write!(out, "{}", v);
+                }
+                // JSON has no NaN/inf
+                Value::Num(v) if v.is_finite() => {
+                    let _ = write!(out, "{}", v);
+                }
+                Value::Num(_) => out.push_str("null"),
+                Value::Bool(v) => {
+                    let _ = write!(out, "{}", v);
+                }
+                Value::Str(s) => push_str(&mut out, s),
+                Value::Raw(json) => out.push_str(json),
+            }
+        }
+        out.push('}');
+        out
+    }
+}
+
+/// Append `s` as a quoted, escaped JSON string.
+fn push_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -0,0 +1,82 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// When to roll the log over to a new file.
+#[derive(Debug, Clone)]
+pub struct Rotation {
+    /// Rotate once the current file reaches this many bytes.
+    pub max_bytes: Option<u64>,
+    /// Rotated files kept as `<path>.1` (newest) .. `<path>.<max_files>`.
+    pub max_files: usize,
+    /// Also rotate when the UTC date changes.
+    pub daily: bool,
+}
+
+/// Append-only log file with size- and date-based rotation.
+pub struct RotatingLog {
+    path: String,
+    file: File,
+    written: u64,
+    day: u64,
+    rotation: Rotation,
+}
+
+impl RotatingLog {
+    pub fn open(path: &str, rotation: Rotation) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_string(),
+            file,
+            written,
+            day: utc_day(),
+            rotation,
+        })
+    }
+
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        let full = self
+            .rotation
+            .max_bytes
+            .is_some_and(|max| self.written > 0 && self.written + len > max);
+        let new_day = self.rotation.daily && utc_day() != self.day;
+        if full || new_day {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.written += len;
+        Ok(())
+    }
+
+    /// Shift `<path>.i` to `<path>.i+1` (dropping the oldest), move the
+    /// current file to `<path>.1` and start a fresh one.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let n = self.rotation.max_files;
+        let _ = fs::remove_file(format!("{}.{}", self.path, n));
+        for i in (1..n).rev() {
+            let from = format!("{}.{}", self.path, i);
+            if fs::metadata(&from).is_ok() {
+                fs::rename(&from, format!("{}.{}", self.path, i + 1))?;
+            }
+        }
+        fs::rename(&self.path, format!("{}.1", self.path))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.written = 0;
+        self.day = utc_day();
+        Ok(())
+    }
+}
+
+/// Days since the Unix epoch (UTC).
+fn utc_day() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() / 86_400)
+}
@@ -1,12 +1,51 @@
+mod agents;
+mod alloc;
+mod auction;
+mod bands;
+mod bars;
+mod book;
+mod checkpoint;
 mod config;
+mod corrupt;
+mod cpu;
+mod crypto;
 mod engine;
+mod events;
+mod export;
+mod ids;
+mod inspect;
+mod jsonlog;
+mod logfile;
 mod multicast;
+mod options;
 mod order;
+mod ouch;
+mod pacing;
+mod participants;
+mod pcap;
+mod price;
+mod refdata;
 mod regime;
+mod report;
+mod rngs;
 mod scenario;
+mod schedule;
+mod seasonality;
+mod sizes;
+mod stops;
+mod symbols;
+mod timing;
+mod venue;
+mod venues;
+mod verify;
+mod wire;
 
 use clap::Parser;
-use config::{AppConfig, Cli};
+use config::{AppConfig, Cli, Command};
+
+#[cfg(feature = "bench")]
+#[global_allocator]
+static GLOBAL: alloc::CountingAlloc = alloc::CountingAlloc;
 
 fn main() {
     let cli = Cli::parse();
@@ -19,7 +58,14 @@ fn main() {
         }
     };
 
-    if let Err(e) = engine::run(&cfg) {
+    let result = match &cli.command {
+        Some(Command::Decode(args)) => inspect::decode(args, &cfg),
+        Some(Command::Listen(args)) => inspect::listen(args, &cfg),
+        Some(Command::Vectors(args)) => inspect::vectors(args),
+        Some(Command::Verify(args)) => verify::verify(args, &cli),
+        None => engine::run(&cfg, &cli),
+    };
+    if let Err(e) = result {
         eprintln!("fatal: {}", e);
         std::process::exit(1);
     }
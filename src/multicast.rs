@@ -1,62 +1,1140 @@
 use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::borrow::Cow;
+use std::fmt;
 use std::io;
-use std::net::{Ipv4Addr, SocketAddrV4};
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use crate::book::SelfMatch;
 use crate::config::WireFormat;
-use crate::order::Order;
+use crate::corrupt::{Corrupted, Corruptor};
+use crate::cpu;
+use crate::export::Export;
+use crate::order::{self, MassCancel, Order, Trade};
+use crate::pcap::PcapWriter;
+use crate::schedule::FairScheduler;
+use crate::timing::{GapTracker, LatencyHistogram};
+use crate::wire::decode::{self, Message};
+use crate::wire::{self, protobuf, sbe, WireMessage, MAX_MESSAGE_LEN, MSG_BATCH};
+
+/// Binary batch header: magic[2], version:u8, msg_type:u8, count:u16.
+const BATCH_HEADER_LEN: usize = 6;
+/// Most queued datagrams the sender thread takes per socket call.
+const SEND_BURST: usize = 1024;
 
 pub struct MulticastSender {
     socket: Socket,
     dest: SockAddr,
     wire_format: WireFormat,
+    /// Datagram size limit when batching; `None` sends one message per datagram.
+    max_packet_bytes: Option<usize>,
+    /// Encode market orders with the old 999999/0 sentinel price.
+    legacy_market_price: bool,
+    /// Encode ORDERs and CANCELs in the v1 layout (`wire_version = 1`).
+    wire_v1: bool,
+    /// Completed datagrams waiting for `flush`.
+    packets: Vec<Vec<u8>>,
+    /// Datagram currently being packed.
+    current: Vec<u8>,
+    current_count: u16,
+    /// Sent datagrams' buffers, cleared for the next ones to be packed in.
+    spare: Vec<Vec<u8>>,
+    /// Messages are encoded here rather than in a fresh `Vec` or `String`.
+    scratch: Vec<u8>,
+    text: String,
+    /// Wall-clock gaps between socket sends (one per datagram, or per `sendmmsg` call).
+    send_gaps: GapTracker,
+    /// Sinks receiving a copy of every order, cancel and trade sent.
+    exports: Vec<Box<dyn Export>>,
+    /// Capture of every datagram sent.
+    pcap: Option<PcapWriter>,
+    /// Damages a fraction of outgoing datagrams (`--corrupt-prob`).
+    corruptor: Option<Corruptor>,
+    /// Datagrams handed to the socket so far.
+    datagrams: u64,
+    /// Drop datagrams and exports while set (replaying a checkpoint).
+    muted: bool,
+    /// Keep ORDER, CANCEL and MASS_CANCEL off the feed (`book_view = "mbp"`);
+    /// exports still see them.
+    withhold_orders: bool,
+    /// Messages withheld since the last `take_withheld`.
+    withheld: u64,
+    /// Receives every datagram in place of the socket (`verify`).
+    tap: Option<SyncSender<Vec<u8>>>,
+    /// Collects every message in place of packing it, for the sender of
+    /// the feed it is published on to `forward` (`--symbols-file`).
+    outbox: Option<Outbox>,
+    /// Interleaves this sender's messages with those forwarded to it, sent
+    /// at each flush (`--symbols-file`).
+    scheduler: Option<FairScheduler>,
+    /// Holds datagrams back before the socket sees them (a venue's latency).
+    delay: Option<Delay>,
+    /// Dedicated sender thread the socket I/O is handed to (`sender_queue`).
+    queue: Option<SendQueue>,
+    /// Offset in seconds and drift (fraction of elapsed sim time) of the
+    /// clock every timestamp is restamped with (a venue's clock skew).
+    clock: Option<(f64, f64)>,
+    /// Append the wall-clock send time to every message (`epoch_timestamps`).
+    sent_ns: bool,
+    /// End every (binary) message with its CRC32C (`crc`).
+    crc: bool,
+    /// Cleared if the kernel doesn't support `sendmmsg`.
+    #[cfg(target_os = "linux")]
+    use_sendmmsg: bool,
+    /// io_uring the socket sends go through instead (`sender = "io_uring"`).
+    ring: Option<uring::Ring>,
 }
 
 impl MulticastSender {
-    pub fn new(group: Ipv4Addr, port: u16, wire_format: WireFormat) -> io::Result<Self> {
+    pub fn new(
+        group: Ipv4Addr,
+        port: u16,
+        wire_format: WireFormat,
+        max_packet_bytes: Option<usize>,
+        legacy_market_price: bool,
+        options: &MulticastOptions,
+    ) -> io::Result<Self> {
         let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        options.apply(&socket)?;
 
-        // TTL = 1: local subnet only
-        socket.set_multicast_ttl_v4(1)?;
+        match max_packet_bytes {
+            Some(max) => eprintln!(
+                "Multicast sender ready on {}:{} ({}, batched <= {} bytes)",
+                group, port, wire_format, max
+            ),
+            None => eprintln!(
+                "Multicast sender ready on {}:{} ({})",
+                group, port, wire_format
+            ),
+        }
 
         let dest = SockAddr::from(SocketAddrV4::new(group, port));
+        Ok(Self::with_socket(
+            socket,
+            dest,
+            wire_format,
+            max_packet_bytes,
+            legacy_market_price,
+        ))
+    }
 
-        eprintln!(
-            "Multicast sender ready on {}:{} ({})",
-            group, port, wire_format
-        );
+    /// A sender that publishes nothing itself: every message it encodes
+    /// waits in its `outbox` for another sender to `forward`.
+    pub fn collecting(wire_format: WireFormat, legacy_market_price: bool) -> io::Result<Self> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        let dest = SockAddr::from(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0));
+        let mut sender = Self::with_socket(socket, dest, wire_format, None, legacy_market_price);
+        sender.outbox = Some(Outbox::default());
+        Ok(sender)
+    }
 
-        Ok(Self {
+    fn with_socket(
+        socket: Socket,
+        dest: SockAddr,
+        wire_format: WireFormat,
+        max_packet_bytes: Option<usize>,
+        legacy_market_price: bool,
+    ) -> Self {
+        Self {
             socket,
             dest,
             wire_format,
+            max_packet_bytes,
+            legacy_market_price,
+            wire_v1: false,
+            packets: Vec::new(),
+            current: Vec::new(),
+            current_count: 0,
+            spare: Vec::new(),
+            scratch: vec![0; MAX_MESSAGE_LEN],
+            text: String::new(),
+            send_gaps: GapTracker::new(),
+            exports: Vec::new(),
+            pcap: None,
+            corruptor: None,
+            datagrams: 0,
+            muted: false,
+            withhold_orders: false,
+            withheld: 0,
+            tap: None,
+            outbox: None,
+            scheduler: None,
+            delay: None,
+            queue: None,
+            clock: None,
+            sent_ns: false,
+            crc: false,
+            #[cfg(target_os = "linux")]
+            use_sendmmsg: true,
+            ring: None,
+        }
+    }
+
+    pub fn send<M: WireMessage>(&mut self, msg: &M) -> io::Result<()> {
+        match self.wire_format {
+            WireFormat::Text => self.emit_text(|w| msg.write_text(w)),
+            _ => self.emit_encoded(|out| msg.encode_into(out)),
+        }
+    }
+
+    /// Emit the text message `write` formats, built in the reused buffer.
+    fn emit_text(&mut self, write: impl FnOnce(&mut String) -> fmt::Result) -> io::Result<()> {
+        let mut text = std::mem::take(&mut self.text);
+        text.clear();
+        // Formatting into a String can't fail
+        let _ = write(&mut text);
+        let result = self.emit(text.as_bytes());
+        self.text = text;
+        result
+    }
+
+    /// Emit the binary message `encode` writes into the reused buffer.
+    fn emit_encoded(&mut self, encode: impl FnOnce(&mut [u8]) -> usize) -> io::Result<()> {
+        let mut scratch = std::mem::take(&mut self.scratch);
+        let len = encode(&mut scratch);
+        let result = self.emit(&scratch[..len]);
+        self.scratch = scratch;
+        result
+    }
+
+    /// Also record every order, cancel and trade sent from now on to `sink`.
+    pub fn add_export(&mut self, sink: Box<dyn Export>) {
+        self.exports.push(sink);
+    }
+
+    /// Also write every datagram sent from now on to `pcap`.
+    pub fn capture_pcap(&mut self, pcap: PcapWriter) {
+        self.pcap = Some(pcap);
+    }
+
+    /// Sim time for the capture to stamp the next datagrams with (`--no-realtime`).
+    pub fn set_sim_time(&mut self, t: f64) {
+        if let Some(pcap) = self.pcap.as_mut() {
+            pcap.set_sim_time(t);
+        }
+        if let Some(s) = self.scheduler.as_mut() {
+            s.advance_to(t);
+        }
+    }
+
+    /// Damage datagrams sent from now on as decided by `corruptor`.
+    pub fn corrupt_with(&mut self, corruptor: Corruptor) {
+        self.corruptor = Some(corruptor);
+    }
+
+    /// Change the corruption probability mid-run; the corruptor keeps its RNG.
+    pub fn set_corrupt_prob(&mut self, prob: f64, seed: u64) {
+        match &mut self.corruptor {
+            Some(c) => c.set_prob(prob),
+            None if prob > 0.0 => self.corruptor = Some(Corruptor::new(prob, seed)),
+            None => {}
+        }
+    }
+
+    /// Encode and count messages as usual, but publish nothing while `muted`.
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    /// Pass orders, cancels and mass cancels to the exports only from now on.
+    pub fn withhold_orders(&mut self) {
+        self.withhold_orders = true;
+    }
+
+    /// Number of messages withheld since the last call, which callers
+    /// counting messages sent have to take back off.
+    pub fn take_withheld(&mut self) -> u64 {
+        std::mem::take(&mut self.withheld)
+    }
+
+    /// Whether the message just given to the exports stays off the feed.
+    fn withheld(&mut self) -> bool {
+        self.withheld += self.withhold_orders as u64;
+        self.withhold_orders
+    }
+
+    /// Hand every datagram from now on to `tap` instead of the socket.
+    pub fn tap(&mut self, tap: SyncSender<Vec<u8>>) {
+        self.tap = Some(tap);
+    }
+
+    /// Messages collected since the outbox was last cleared; `None` unless
+    /// the sender is `collecting`.
+    pub fn outbox(&mut self) -> Option<&mut Outbox> {
+        self.outbox.as_mut()
+    }
+
+    /// Hold every message from now on for `scheduler` to interleave with
+    /// those forwarded to it, this sender's own on lane 0, until the next
+    /// flush sends what its rate caps allow.
+    pub fn schedule(&mut self, scheduler: FairScheduler) {
+        self.scheduler = Some(scheduler);
+    }
+
+    /// Let the next flush send every message the scheduler still holds.
+    pub fn lift_rate_caps(&mut self) {
+        if let Some(s) = self.scheduler.as_mut() {
+            s.lift_caps();
+        }
+    }
+
+    /// Publish messages another sender collected as if they were sent here,
+    /// on the scheduler's `lane` if there is one.
+    pub fn forward(&mut self, lane: usize, outbox: &Outbox) -> io::Result<()> {
+        match self.scheduler.as_mut() {
+            Some(s) => outbox.iter().for_each(|msg| s.push(lane, msg)),
+            None => {
+                for msg in outbox.iter() {
+                    self.emit(msg)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Send every datagram from now on `latency` after it is handed over,
+    /// from a relay thread so the engine never waits for it.
+    pub fn delay_by(&mut self, latency: Duration) -> io::Result<()> {
+        let socket = self.socket.try_clone()?;
+        let dest = self.dest.clone();
+        let (queue, pending) = mpsc::channel::<(Instant, Vec<u8>)>();
+        let relay = thread::spawn(move || {
+            for (due, datagram) in pending {
+                if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                    thread::sleep(wait);
+                }
+                let _ = socket.send_to(&datagram, &dest);
+            }
+        });
+        self.delay = Some(Delay {
+            latency,
+            queue,
+            relay,
+        });
+        Ok(())
+    }
+
+    /// Send every datagram from now on from a thread of its own, through a
+    /// queue of `capacity` datagrams. Generation never waits on the socket:
+    /// a datagram that finds the queue full is dropped and counted. The
+    /// thread is pinned to `core` and, where permitted, runs under
+    /// SCHED_FIFO at `rt_priority`.
+    pub fn send_from_thread(
+        &mut self,
+        capacity: usize,
+        core: Option<usize>,
+        rt_priority: Option<i32>,
+    ) -> io::Result<()> {
+        let socket = self.socket.try_clone()?;
+        let dest = self.dest.clone();
+        let (queue, pending) = mpsc::sync_channel(capacity);
+        let depth = Arc::new(AtomicUsize::new(0));
+        let sent = Arc::clone(&depth);
+        let (pinned_tx, pinned) = mpsc::sync_channel(1);
+        let ring = self.ring.take();
+        let thread = thread::spawn(move || {
+            let pin = core.map_or(Ok(()), |c| {
+                cpu::pin_current(c)
+                    .map_err(|e| io::Error::new(e.kind(), format!("CPU {}: {}", c, e)))
+            });
+            let ok = pin.is_ok();
+            let _ = pinned_tx.send(pin);
+            if !ok {
+                return;
+            }
+            if let Some(priority) = rt_priority {
+                // The generator thread reports a refusal; it gets the same answer
+                let _ = cpu::set_fifo(priority);
+            }
+            drain(&socket, &dest, ring, &pending, &sent);
+        });
+        if let Err(e) = pinned.recv().unwrap_or(Ok(())) {
+            let _ = thread.join();
+            return Err(e);
+        }
+        self.queue = Some(SendQueue {
+            queue,
+            depth,
+            peak: 0,
+            dropped: 0,
+            thread,
+        });
+        Ok(())
+    }
+
+    /// Send through an io_uring from now on: each datagram becomes a
+    /// SENDMSG submission, and a tick's worth go in with one system call.
+    /// Fails where io_uring isn't available, leaving the socket path as is.
+    pub fn use_io_uring(&mut self) -> io::Result<()> {
+        self.ring = Some(uring::Ring::new(self.socket.try_clone()?)?);
+        Ok(())
+    }
+
+    /// Deepest the sender thread's queue got and datagrams dropped on a
+    /// full queue, since the last call; `None` without a sender thread.
+    pub fn take_queue_stats(&mut self) -> Option<(usize, u64)> {
+        let q = self.queue.as_mut()?;
+        let peak = std::mem::replace(&mut q.peak, q.depth.load(Ordering::Relaxed));
+        Some((peak, std::mem::take(&mut q.dropped)))
+    }
+
+    /// Publish every timestamp from now on as `t + offset + drift·t`, as
+    /// read off a venue clock that is `offset` seconds out and drifting by
+    /// `drift` seconds per second. Exports keep engine time.
+    pub fn skew_clock(&mut self, offset: f64, drift: f64) {
+        self.clock = Some((offset, drift));
+    }
+
+    /// `msg` with its timestamps moved onto the skewed clock; `None` if it
+    /// doesn't decode, which the engine's own messages always do.
+    fn restamp(&self, msg: &[u8], (offset, drift): (f64, f64)) -> Option<Vec<u8>> {
+        let mut decoded = match self.wire_format {
+            WireFormat::Text => decode::decode_text(std::str::from_utf8(msg).ok()?).ok()?,
+            // SBE and protobuf are still binary here
+            _ => decode::decode_binary(msg).ok()?,
+        };
+        decoded.map_times(|t| t + offset + drift * t);
+        Some(match (&decoded, self.wire_format) {
+            (Message::Order(o), WireFormat::Text) => {
+                o.to_wire_text_with(self.legacy_market_price).into_bytes()
+            }
+            (Message::Order(o), _) if self.wire_v1 && o.has_v1_layout() => {
+                let mut out = vec![0; order::ORDER_V1_LEN];
+                o.encode_v1_into(&mut out);
+                out
+            }
+            (Message::Cancel { id, time, .. }, _) if self.wire_v1 => {
+                let mut out = vec![0; order::CANCEL_V1_LEN];
+                order::cancel_encode_v1_into(*id, *time, &mut out);
+                out
+            }
+            (Message::Order(o), _) => o.to_wire_binary_with(self.legacy_market_price),
+            (_, WireFormat::Text) => decoded.to_wire_text().into_bytes(),
+            _ => decoded.to_wire_binary(),
         })
     }
 
-    pub fn send_order(&self, order: &Order) -> io::Result<()> {
+    /// Encode ORDERs and CANCELs from now on in the original v1 binary
+    /// layout, for consumers that predate v2. Stops, which v1 has no
+    /// layout for, and every other message keep their v2 frames.
+    pub fn encode_v1(&mut self) {
+        self.wire_v1 = true;
+    }
+
+    /// Append the wall-clock time in nanoseconds since the UNIX epoch to
+    /// every message from now on, taken as it is encoded.
+    pub fn stamp_sent_ns(&mut self) {
+        self.sent_ns = true;
+    }
+
+    /// End every message from now on with a CRC32C of its bytes. Binary
+    /// only; applied after any send timestamp, which it covers.
+    pub fn append_crc(&mut self) {
+        self.crc = true;
+    }
+
+    /// Export sinks, or none while muted.
+    fn sinks(&mut self) -> &mut [Box<dyn Export>] {
+        if self.muted {
+            &mut []
+        } else {
+            &mut self.exports
+        }
+    }
+
+    /// Datagrams corrupted since the last call.
+    pub fn take_corruptions(&mut self) -> Vec<Corrupted> {
+        self.corruptor
+            .as_mut()
+            .map_or_else(Vec::new, Corruptor::take_log)
+    }
+
+    /// Finish all export sinks; call once at shutdown.
+    pub fn finish_exports(&mut self) -> io::Result<()> {
+        for sink in &mut self.exports {
+            sink.finish()?;
+        }
+        Ok(())
+    }
+
+    pub fn send_order(&mut self, order: &Order) -> io::Result<()> {
+        for sink in self.sinks() {
+            sink.order(order)?;
+        }
+        if self.withheld() {
+            return Ok(());
+        }
+        let legacy = self.legacy_market_price;
+        match self.wire_format {
+            WireFormat::Text => self.emit_text(|w| order.write_text_with(w, legacy)),
+            _ if self.wire_v1 && order.has_v1_layout() => {
+                self.emit_encoded(|out| order.encode_v1_into(out))
+            }
+            _ => self.emit_encoded(|out| order.encode_into(out, legacy)),
+        }
+    }
+
+    pub fn send_cancel(
+        &mut self,
+        order_id: u64,
+        instrument: u16,
+        current_time: f64,
+    ) -> io::Result<()> {
+        for sink in self.sinks() {
+            sink.cancel(order_id, current_time)?;
+        }
+        if self.withheld() {
+            return Ok(());
+        }
         match self.wire_format {
             WireFormat::Text => {
-                let msg = order.to_wire_text();
-                self.socket.send_to(msg.as_bytes(), &self.dest)?;
+                self.emit_text(|w| order::write_cancel_text(w, order_id, instrument, current_time))
             }
-            WireFormat::Binary => {
-                let msg = order.to_wire_binary();
-                self.socket.send_to(&msg, &self.dest)?;
+            _ if self.wire_v1 => {
+                self.emit_encoded(|out| order::cancel_encode_v1_into(order_id, current_time, out))
+            }
+            _ => self.emit_encoded(|out| {
+                order::cancel_encode_into(order_id, instrument, current_time, out)
+            }),
+        }
+    }
+
+    pub fn send_trade(&mut self, trade: &Trade) -> io::Result<()> {
+        for sink in self.sinks() {
+            sink.trade(trade)?;
+        }
+        self.send(trade)
+    }
+
+    pub fn send_self_match(&mut self, sm: &SelfMatch) -> io::Result<()> {
+        for sink in self.sinks() {
+            sink.self_match(sm)?;
+        }
+        self.send(sm)
+    }
+
+    /// `ids` are the orders the mass cancel removed, for the export sinks.
+    pub fn send_mass_cancel(&mut self, mass: &MassCancel, ids: &[u64]) -> io::Result<()> {
+        for sink in self.sinks() {
+            sink.mass_cancel(mass, ids)?;
+        }
+        if self.withheld() {
+            return Ok(());
+        }
+        self.send(mass)
+    }
+
+    /// Send any batched (or scheduled) messages. Called once per tick; a no-op
+    /// when batching is off.
+    pub fn flush(&mut self) -> io::Result<()> {
+        for sink in self.sinks() {
+            sink.flush()?;
+        }
+        let mut result = Ok(());
+        if let Some(mut scheduler) = self.scheduler.take() {
+            scheduler.drain(|msg| {
+                if let Err(e) = self.emit(msg) {
+                    result = Err(e);
+                }
+            });
+            self.scheduler = Some(scheduler);
+        }
+        self.finish_packet();
+        if !self.packets.is_empty() {
+            let mut packets = std::mem::take(&mut self.packets);
+            for p in &mut packets {
+                self.datagrams += 1;
+                if let Some(c) = self.corruptor.as_mut() {
+                    if let Cow::Owned(damaged) = c.apply(self.datagrams, p) {
+                        *p = damaged;
+                    }
+                }
             }
+            result = result.and(self.send_batch(&packets));
+            self.spare.extend(packets.drain(..).map(|mut p| {
+                p.clear();
+                p
+            }));
+            self.packets = packets;
+        }
+        if let Some(pcap) = self.pcap.as_mut() {
+            pcap.flush()?;
+        }
+        result
+    }
+
+    /// Send a whole tick's datagrams. On Linux this is a single `sendmmsg`
+    /// call per 1024 packets; elsewhere (or if unsupported) one `send_to` each.
+    pub fn send_batch(&mut self, packets: &[Vec<u8>]) -> io::Result<()> {
+        if self.muted {
+            return Ok(());
+        }
+        if let Some(pcap) = self.pcap.as_mut() {
+            for p in packets {
+                pcap.write(p)?;
+            }
+        }
+        if let Some(tap) = &self.tap {
+            for p in packets {
+                // The receiving end hangs up once it has seen enough
+                let _ = tap.send(p.clone());
+            }
+            return Ok(());
+        }
+        if let Some(delay) = &self.delay {
+            self.send_gaps.mark();
+            let due = Instant::now() + delay.latency;
+            for p in packets {
+                let _ = delay.queue.send((due, p.clone()));
+            }
+            return Ok(());
+        }
+        if let Some(queue) = self.queue.as_mut() {
+            self.send_gaps.mark();
+            for p in packets {
+                queue.push(p.clone());
+            }
+            return Ok(());
+        }
+        if let Some(ring) = self.ring.as_mut() {
+            self.send_gaps.mark();
+            return ring.send_all(&self.dest, packets);
+        }
+        #[cfg(target_os = "linux")]
+        if self.use_sendmmsg {
+            self.send_gaps.mark();
+            match mmsg::send_all(&self.socket, &self.dest, packets) {
+                Err(e) if e.raw_os_error() == Some(libc::ENOSYS) => self.use_sendmmsg = false,
+                result => return result,
+            }
+        }
+
+        for p in packets {
+            self.send_gaps.mark();
+            self.socket.send_to(p, &self.dest)?;
         }
         Ok(())
     }
 
-    pub fn send_cancel(&self, order_id: u64, current_time: f64) -> io::Result<()> {
+    /// Histogram of gaps between socket sends since the last reset.
+    pub fn send_gaps(&mut self) -> &mut LatencyHistogram {
+        &mut self.send_gaps.gaps
+    }
+
+    fn emit(&mut self, msg: &[u8]) -> io::Result<()> {
+        if let Some(outbox) = self.outbox.as_mut() {
+            if !self.muted {
+                outbox.push(msg);
+            }
+            return Ok(());
+        }
+        if let Some(s) = self.scheduler.as_mut() {
+            if !self.muted {
+                s.push(0, msg);
+            }
+            return Ok(());
+        }
+        let restamped = self.clock.and_then(|clock| self.restamp(msg, clock));
+        let msg = restamped.as_deref().unwrap_or(msg);
+        let reframed = match self.wire_format {
+            WireFormat::Sbe => Some(sbe::from_binary(msg)),
+            WireFormat::Protobuf => Some(
+                decode::decode_binary(msg).map(|m| protobuf::encode(&m, self.legacy_market_price)),
+            ),
+            #[cfg(feature = "flatbuffers")]
+            WireFormat::FlatBuffers => Some(
+                decode::decode_binary(msg)
+                    .map(|m| wire::flatbuffers::encode(&m, self.legacy_market_price)),
+            ),
+            _ => None,
+        }
+        .transpose()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        let msg = reframed.as_deref().unwrap_or(msg);
+        let stamped = self.sent_ns.then(|| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            wire::with_sent_ns(msg, now.as_nanos() as u64)
+        });
+        let msg = stamped.as_deref().unwrap_or(msg);
+        let checked = self.crc.then(|| wire::with_crc(msg));
+        let msg = checked.as_deref().unwrap_or(msg);
+        let Some(max) = self.max_packet_bytes else {
+            self.datagrams += 1;
+            let msg = match self.corruptor.as_mut() {
+                Some(c) => c.apply(self.datagrams, msg),
+                None => Cow::Borrowed(msg),
+            };
+            if self.muted {
+                return Ok(());
+            }
+            if let Some(pcap) = self.pcap.as_mut() {
+                pcap.write(&msg)?;
+            }
+            if let Some(tap) = &self.tap {
+                let _ = tap.send(msg.into_owned());
+                return Ok(());
+            }
+            self.send_gaps.mark();
+            if let Some(delay) = &self.delay {
+                let _ = delay
+                    .queue
+                    .send((Instant::now() + delay.latency, msg.into_owned()));
+                return Ok(());
+            }
+            if let Some(queue) = self.queue.as_mut() {
+                queue.push(msg.into_owned());
+                return Ok(());
+            }
+            if let Some(ring) = self.ring.as_mut() {
+                return ring.send_all(&self.dest, &[&*msg]);
+            }
+            self.socket.send_to(&msg, &self.dest)?;
+            return Ok(());
+        };
+
+        // Text sub-messages are newline-separated; binary ones carry a u16
+        // length prefix. SBE and FlatBuffers messages go back to back, as they
+        // carry their lengths, and so do protobuf ones, which concatenate into
+        // one `Datagram`.
+        let framed = match self.wire_format {
+            WireFormat::Text => msg.len() + usize::from(!self.current.is_empty()),
+            WireFormat::Binary => 2 + msg.len(),
+            WireFormat::Sbe | WireFormat::Protobuf | WireFormat::FlatBuffers => msg.len(),
+        };
+        if !self.current.is_empty()
+            && (self.current.len() + framed > max || self.current_count == u16::MAX)
+        {
+            self.finish_packet();
+        }
+
         match self.wire_format {
             WireFormat::Text => {
-                let msg = crate::order::cancel_to_wire_text(order_id, current_time);
-                self.socket.send_to(msg.as_bytes(), &self.dest)?;
+                if !self.current.is_empty() {
+                    self.current.push(b'\n');
+                }
+                self.current.extend_from_slice(msg);
             }
             WireFormat::Binary => {
-                let msg = crate::order::cancel_to_wire_binary(order_id, current_time);
-                self.socket.send_to(&msg, &self.dest)?;
+                if self.current.is_empty() {
+                    wire::write_header(&mut self.current, MSG_BATCH);
+                    self.current.extend_from_slice(&0u16.to_le_bytes());
+                }
+                self.current
+                    .extend_from_slice(&(msg.len() as u16).to_le_bytes());
+                self.current.extend_from_slice(msg);
+            }
+            WireFormat::Sbe | WireFormat::Protobuf | WireFormat::FlatBuffers => {
+                self.current.extend_from_slice(msg)
             }
         }
+        self.current_count += 1;
+        if self.wire_format == WireFormat::Binary {
+            self.current[4..BATCH_HEADER_LEN].copy_from_slice(&self.current_count.to_le_bytes());
+        }
         Ok(())
     }
+
+    fn finish_packet(&mut self) {
+        if self.current.is_empty() {
+            return;
+        }
+        let max = self.max_packet_bytes.unwrap_or(0);
+        let next = self.spare.pop().unwrap_or_else(|| Vec::with_capacity(max));
+        self.packets
+            .push(std::mem::replace(&mut self.current, next));
+        self.current_count = 0;
+    }
+}
+
+impl Drop for MulticastSender {
+    /// Let the relay and sender threads send what they still hold.
+    fn drop(&mut self) {
+        if let Some(Delay { queue, relay, .. }) = self.delay.take() {
+            drop(queue);
+            let _ = relay.join();
+        }
+        if let Some(SendQueue { queue, thread, .. }) = self.queue.take() {
+            drop(queue);
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Encoded messages a `collecting` sender holds, back to back in one
+/// buffer so a steady state reuses it.
+#[derive(Debug, Default)]
+pub struct Outbox {
+    bytes: Vec<u8>,
+    /// End offset of each message in `bytes`.
+    ends: Vec<usize>,
+}
+
+impl Outbox {
+    fn push(&mut self, msg: &[u8]) {
+        self.bytes.extend_from_slice(msg);
+        self.ends.push(self.bytes.len());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        let starts = std::iter::once(0).chain(self.ends.iter().copied());
+        starts
+            .zip(&self.ends)
+            .map(|(start, &end)| &self.bytes[start..end])
+    }
+
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+        self.ends.clear();
+    }
+}
+
+/// Relay thread behind `delay_by` and the queue feeding it.
+struct Delay {
+    latency: Duration,
+    queue: Sender<(Instant, Vec<u8>)>,
+    relay: JoinHandle<()>,
+}
+
+/// Sender thread behind `send_from_thread`, the bounded queue feeding it
+/// and its backpressure counters.
+struct SendQueue {
+    queue: SyncSender<Vec<u8>>,
+    /// Datagrams queued and not yet sent; the thread takes off what it sends.
+    depth: Arc<AtomicUsize>,
+    /// Deepest `depth` seen since the last `take_queue_stats`.
+    peak: usize,
+    /// Datagrams dropped on a full queue since the last `take_queue_stats`.
+    dropped: u64,
+    thread: JoinHandle<()>,
+}
+
+impl SendQueue {
+    fn push(&mut self, datagram: Vec<u8>) {
+        // Counted before the thread can see it, so `depth` never goes below 0
+        let depth = self.depth.fetch_add(1, Ordering::Relaxed) + 1;
+        match self.queue.try_send(datagram) {
+            Ok(()) => self.peak = self.peak.max(depth),
+            Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => {
+                self.depth.fetch_sub(1, Ordering::Relaxed);
+                self.dropped += 1;
+            }
+        }
+    }
+}
+
+/// Sender thread: send queued datagrams until the queue is closed, taking
+/// whatever has piled up (up to `SEND_BURST`) per `sendmmsg` call.
+fn drain(
+    socket: &Socket,
+    dest: &SockAddr,
+    mut ring: Option<uring::Ring>,
+    pending: &Receiver<Vec<u8>>,
+    depth: &AtomicUsize,
+) {
+    #[cfg(target_os = "linux")]
+    let mut use_sendmmsg = true;
+    let mut burst = Vec::with_capacity(SEND_BURST);
+    while let Ok(first) = pending.recv() {
+        burst.push(first);
+        burst.extend(pending.try_iter().take(SEND_BURST - 1));
+        if let Some(ring) = ring.as_mut() {
+            let _ = ring.send_all(dest, &burst);
+            depth.fetch_sub(burst.len(), Ordering::Relaxed);
+            burst.clear();
+            continue;
+        }
+        #[cfg(target_os = "linux")]
+        if use_sendmmsg {
+            match mmsg::send_all(socket, dest, &burst) {
+                Err(e) if e.raw_os_error() == Some(libc::ENOSYS) => use_sendmmsg = false,
+                _ => {
+                    depth.fetch_sub(burst.len(), Ordering::Relaxed);
+                    burst.clear();
+                    continue;
+                }
+            }
+        }
+        for p in &burst {
+            let _ = socket.send_to(p, dest);
+        }
+        depth.fetch_sub(burst.len(), Ordering::Relaxed);
+        burst.clear();
+    }
+}
+
+/// Outgoing multicast socket options (`[network]`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MulticastOptions {
+    /// Router hops datagrams may cross; 1 keeps them on the local subnet.
+    pub ttl: u32,
+    /// Address of the interface to send from; `None` lets the OS pick.
+    pub interface: Option<Ipv4Addr>,
+    /// Deliver datagrams to receivers on this host too.
+    pub loopback: bool,
+}
+
+impl Default for MulticastOptions {
+    fn default() -> Self {
+        Self {
+            ttl: 1,
+            interface: None,
+            loopback: true,
+        }
+    }
+}
+
+impl MulticastOptions {
+    /// Set these options on a sending socket.
+    pub fn apply(&self, socket: &Socket) -> io::Result<()> {
+        socket.set_multicast_ttl_v4(self.ttl)?;
+        socket.set_multicast_loop_v4(self.loopback)?;
+        if let Some(addr) = self.interface {
+            socket.set_multicast_if_v4(&addr)?;
+        }
+        Ok(())
+    }
+}
+
+/// IPv4 address of the interface `name_or_ip`: an address is taken as is,
+/// a name (e.g. `eth1`) is looked up.
+pub fn interface_addr(name_or_ip: &str) -> io::Result<Ipv4Addr> {
+    if let Ok(addr) = name_or_ip.parse() {
+        return Ok(addr);
+    }
+    interface_by_name(name_or_ip)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no interface '{}' with an IPv4 address", name_or_ip),
+        )
+    })
+}
+
+/// First IPv4 address of the interface called `name`.
+#[cfg(target_os = "linux")]
+fn interface_by_name(name: &str) -> io::Result<Option<Ipv4Addr>> {
+    let mut addrs: *mut libc::ifaddrs = std::ptr::null_mut();
+    // SAFETY: on success `addrs` is a list getifaddrs allocated, read here
+    // and released with freeifaddrs.
+    unsafe {
+        if libc::getifaddrs(&mut addrs) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut found = None;
+        let mut ifa = addrs;
+        while let Some(entry) = ifa.as_ref() {
+            ifa = entry.ifa_next;
+            let addr = entry.ifa_addr;
+            if addr.is_null() || i32::from((*addr).sa_family) != libc::AF_INET {
+                continue;
+            }
+            if std::ffi::CStr::from_ptr(entry.ifa_name).to_bytes() == name.as_bytes() {
+                let sin = &*(addr as *const libc::sockaddr_in);
+                found = Some(Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr)));
+                break;
+            }
+        }
+        libc::freeifaddrs(addrs);
+        Ok(found)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn interface_by_name(name: &str) -> io::Result<Option<Ipv4Addr>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "interface names need Linux; give the IPv4 address of '{}'",
+            name
+        ),
+    ))
+}
+
+/// Receiving socket joined to `group` on all interfaces. Address reuse lets
+/// it share the port with other subscribers on the same host.
+pub fn join(group: Ipv4Addr, port: u16) -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.bind(&SockAddr::from(SocketAddrV4::new(
+        Ipv4Addr::UNSPECIFIED,
+        port,
+    )))?;
+    socket.join_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)?;
+    Ok(socket.into())
+}
+
+#[cfg(target_os = "linux")]
+mod mmsg {
+    use socket2::{SockAddr, Socket};
+    use std::io;
+    use std::os::fd::AsRawFd;
+
+    /// Kernel limit on messages per `sendmmsg` call (UIO_MAXIOV).
+    const MAX_VLEN: usize = 1024;
+
+    pub fn send_all(socket: &Socket, dest: &SockAddr, packets: &[Vec<u8>]) -> io::Result<()> {
+        let mut iovecs: Vec<libc::iovec> = packets
+            .iter()
+            .map(|p| libc::iovec {
+                iov_base: p.as_ptr() as *mut libc::c_void,
+                iov_len: p.len(),
+            })
+            .collect();
+
+        let mut headers: Vec<libc::mmsghdr> = iovecs
+            .iter_mut()
+            .map(|iov| {
+                // SAFETY: mmsghdr is a plain C struct; all-zero is a valid empty header.
+                let mut h: libc::mmsghdr = unsafe { std::mem::zeroed() };
+                h.msg_hdr.msg_name = dest.as_ptr() as *mut libc::c_void;
+                h.msg_hdr.msg_namelen = dest.len();
+                h.msg_hdr.msg_iov = iov;
+                h.msg_hdr.msg_iovlen = 1;
+                h
+            })
+            .collect();
+
+        let mut sent = 0;
+        while sent < headers.len() {
+            let vlen = (headers.len() - sent).min(MAX_VLEN);
+            // SAFETY: headers[sent..sent + vlen] point at iovecs and packet buffers
+            // that outlive this call, and dest outlives it too.
+            let n = unsafe {
+                libc::sendmmsg(
+                    socket.as_raw_fd(),
+                    headers[sent..].as_mut_ptr(),
+                    vlen as libc::c_uint,
+                    0,
+                )
+            };
+            if n < 0 {
+                let e = io::Error::last_os_error();
+                if e.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(e);
+            }
+            sent += n as usize;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod uring {
+    use io_uring::{opcode, types, IoUring};
+    use socket2::{SockAddr, Socket};
+    use std::io;
+    use std::os::fd::AsRawFd;
+
+    /// Submission queue size; larger batches go in several rounds.
+    const ENTRIES: u32 = 256;
+
+    /// io_uring sending on its own handle to the multicast socket.
+    pub struct Ring {
+        ring: IoUring,
+        socket: Socket,
+        iovecs: Vec<libc::iovec>,
+        headers: Vec<libc::msghdr>,
+    }
+
+    // SAFETY: the pointers in `iovecs` and `headers` are only used within
+    // one `send_all` call and are rebuilt by the next, on whichever thread.
+    unsafe impl Send for Ring {}
+
+    impl Ring {
+        pub fn new(socket: Socket) -> io::Result<Self> {
+            Ok(Self {
+                ring: IoUring::new(ENTRIES)?,
+                socket,
+                iovecs: Vec::with_capacity(ENTRIES as usize),
+                headers: Vec::with_capacity(ENTRIES as usize),
+            })
+        }
+
+        /// Send every packet to `dest`, waiting for all of them to complete.
+        /// Returns the first failed send's error, if any.
+        pub fn send_all<P: AsRef<[u8]>>(
+            &mut self,
+            dest: &SockAddr,
+            packets: &[P],
+        ) -> io::Result<()> {
+            let fd = types::Fd(self.socket.as_raw_fd());
+            let mut result = Ok(());
+            for chunk in packets.chunks(ENTRIES as usize) {
+                self.iovecs.clear();
+                self.iovecs.extend(chunk.iter().map(|p| libc::iovec {
+                    iov_base: p.as_ref().as_ptr() as *mut libc::c_void,
+                    iov_len: p.as_ref().len(),
+                }));
+                // Built once `iovecs` is complete, as they point into it
+                self.headers.clear();
+                for iov in &mut self.iovecs {
+                    // SAFETY: msghdr is a plain C struct; all-zero is a valid empty header.
+                    let mut h: libc::msghdr = unsafe { std::mem::zeroed() };
+                    h.msg_name = dest.as_ptr() as *mut libc::c_void;
+                    h.msg_namelen = dest.len();
+                    h.msg_iov = iov;
+                    h.msg_iovlen = 1;
+                    self.headers.push(h);
+                }
+                let mut sq = self.ring.submission();
+                for h in &self.headers {
+                    let entry = opcode::SendMsg::new(fd, h).build();
+                    // SAFETY: the header, its iovec, the packet and dest stay alive
+                    // and unmoved until the completions below have all been reaped.
+                    // The queue holds ENTRIES and is empty, so the push can't fail.
+                    let _ = unsafe { sq.push(&entry) };
+                }
+                drop(sq);
+
+                let mut done = 0;
+                while done < chunk.len() {
+                    match self.ring.submit_and_wait(chunk.len() - done) {
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(e) => return Err(e),
+                        Ok(_) => {}
+                    }
+                    for cqe in self.ring.completion() {
+                        done += 1;
+                        if cqe.result() < 0 && result.is_ok() {
+                            result = Err(io::Error::from_raw_os_error(-cqe.result()));
+                        }
+                    }
+                }
+            }
+            result
+        }
+    }
+}
+
+/// Stand-in where io_uring isn't built: a ring can never be created.
+#[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+mod uring {
+    use socket2::{SockAddr, Socket};
+    use std::io;
+
+    pub enum Ring {}
+
+    impl Ring {
+        pub fn new(_socket: Socket) -> io::Result<Self> {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "io_uring needs Linux and a build with `--features io-uring`",
+            ))
+        }
+
+        pub fn send_all<P: AsRef<[u8]>>(
+            &mut self,
+            _dest: &SockAddr,
+            _packets: &[P],
+        ) -> io::Result<()> {
+            match *self {}
+        }
+    }
 }
@@ -0,0 +1,283 @@
+//! Option chain (`[options]`): calls and puts on a strike grid around the
+//! simulated underlying, quoted with Black-Scholes off a skewed vol surface
+//! whose level and skew follow the regime.
+
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::price;
+use crate::regime::{Regime, RegimeValues};
+use crate::wire::{self, Price, WireMessage, MSG_OPTION_QUOTE};
+
+/// Sim seconds in one trading day, the unit expiries are listed in. The
+/// same 6.5-hour, 252-day year the price models annualise with.
+const TRADING_DAY: f64 = 6.5 * 3600.0;
+/// Lowest implied vol the surface returns, however steep the skew.
+const MIN_VOL: f64 = 0.01;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OptionsConfig {
+    pub enabled: bool,
+    /// Sim seconds between chain updates.
+    pub interval: f64,
+    /// Strikes quoted each side of the at-the-money one.
+    pub strikes: usize,
+    /// Strike spacing as a fraction of the initial price.
+    pub strike_step_pct: f64,
+    /// Trading days to each expiry, counted from the start of the run.
+    pub expiries: Vec<u32>,
+    /// Continuously compounded risk-free rate.
+    pub rate: f64,
+    /// Short-dated at-the-money vol, annualised.
+    pub atm_vol: RegimeValues,
+    /// At-the-money vol the long end of the term structure tends to.
+    pub long_vol: f64,
+    /// Trading days over which the term structure goes from `atm_vol` to
+    /// `long_vol` (e-folding time).
+    pub term_days: f64,
+    /// Vol change per unit of log-moneyness ln(K/F); negative makes
+    /// downside strikes dearer.
+    pub skew: RegimeValues,
+    /// Vol added per unit of squared log-moneyness.
+    pub smile: f64,
+    /// Quoted half-spread as a fraction of the theoretical price, at least
+    /// one tick.
+    pub spread_pct: f64,
+    pub tick_size: f64,
+}
+
+impl Default for OptionsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 1.0,
+            strikes: 5,
+            strike_step_pct: 0.025,
+            expiries: vec![5, 21, 63],
+            rate: 0.03,
+            atm_vol: RegimeValues {
+                calm: 0.15,
+                volatile: 0.30,
+                crash: 0.65,
+                rally: 0.28,
+                recovery: 0.22,
+            },
+            long_vol: 0.20,
+            term_days: 30.0,
+            skew: RegimeValues {
+                calm: -0.6,
+                volatile: -1.0,
+                crash: -1.8,
+                rally: -0.3,
+                recovery: -0.8,
+            },
+            smile: 1.5,
+            spread_pct: 0.02,
+            tick_size: 0.01,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+    Call,
+    Put,
+}
+
+impl fmt::Display for OptionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionKind::Call => write!(f, "CALL"),
+            OptionKind::Put => write!(f, "PUT"),
+        }
+    }
+}
+
+/// Two-sided quote for one option of the chain. `iv` is the surface vol
+/// the quote was priced at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionQuote {
+    pub expiry: u32,
+    pub strike: f64,
+    pub kind: OptionKind,
+    pub bid: f64,
+    pub ask: f64,
+    pub iv: f64,
+    pub underlying: f64,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl WireMessage for OptionQuote {
+    fn to_wire_text(&self) -> String {
+        let mut msg = format!(
+            "OPTION_QUOTE|expiry={}|strike={}|type={}|bid={}|ask={}|iv={:.4}|underlying={}|time={:.3}",
+            self.expiry,
+            Price(self.strike),
+            self.kind,
+            Price(self.bid),
+            Price(self.ask),
+            self.iv,
+            Price(self.underlying),
+            self.time,
+        );
+        let _ = wire::write_instrument(&mut msg, self.instrument);
+        msg
+    }
+
+    /// Binary option quote (v2), little-endian:
+    /// header msg_type=23, expiry:u32 (trading days), strike:f64,
+    /// type:u8 (1 call, 2 put), bid:f64, ask:f64, iv:f64, underlying:f64, time:f64,
+    /// instrument:u16 (the underlying's)
+    fn to_wire_binary(&self) -> Vec<u8> {
+        let mut out = wire::binary_frame(MSG_OPTION_QUOTE, 4 + 8 + 1 + 8 * 5 + 2);
+        out.extend_from_slice(&self.expiry.to_le_bytes());
+        out.extend_from_slice(&self.strike.to_le_bytes());
+        out.push(match self.kind {
+            OptionKind::Call => 1,
+            OptionKind::Put => 2,
+        });
+        out.extend_from_slice(&self.bid.to_le_bytes());
+        out.extend_from_slice(&self.ask.to_le_bytes());
+        out.extend_from_slice(&self.iv.to_le_bytes());
+        out.extend_from_slice(&self.underlying.to_le_bytes());
+        out.extend_from_slice(&self.time.to_le_bytes());
+        out.extend_from_slice(&self.instrument.to_le_bytes());
+        out
+    }
+}
+
+/// Standard normal CDF (Abramowitz and Stegun 26.2.17, error below 7.5e-8).
+fn norm_cdf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.2316419 * x.abs());
+    let poly = t
+        * (0.319381530
+            + t * (-0.356563782 + t * (1.781477937 + t * (-1.821255978 + t * 1.330274429))));
+    let upper = (-0.5 * x * x).exp() / (2.0 * std::f64::consts::PI).sqrt() * poly;
+    if x >= 0.0 {
+        1.0 - upper
+    } else {
+        upper
+    }
+}
+
+/// Black-Scholes price of a European option with `t` years to expiry.
+fn black_scholes(kind: OptionKind, spot: f64, strike: f64, t: f64, rate: f64, vol: f64) -> f64 {
+    let discount = (-rate * t).exp();
+    let sd = vol * t.sqrt();
+    let d1 = ((spot / strike).ln() + (rate + 0.5 * vol * vol) * t) / sd;
+    let d2 = d1 - sd;
+    match kind {
+        OptionKind::Call => spot * norm_cdf(d1) - strike * discount * norm_cdf(d2),
+        OptionKind::Put => strike * discount * norm_cdf(-d2) - spot * norm_cdf(-d1),
+    }
+}
+
+/// Lists the chain and reprices it every `interval`. Strikes sit on a
+/// fixed grid; the quoted window follows the underlying, and expiries
+/// drop out once they pass.
+pub struct OptionChain {
+    cfg: OptionsConfig,
+    /// Directory index of the underlying.
+    instrument: u16,
+    strike_step: f64,
+    next_at: f64,
+}
+
+impl OptionChain {
+    pub fn new(cfg: OptionsConfig, initial_price: f64, instrument: u16) -> Self {
+        let strike_step = ((initial_price * cfg.strike_step_pct / cfg.tick_size).round()
+            * cfg.tick_size)
+            .max(cfg.tick_size);
+        Self {
+            cfg,
+            instrument,
+            strike_step,
+            next_at: 0.0,
+        }
+    }
+
+    /// Implied vol at `strike` for `t` years to expiry: the regime's
+    /// short-dated level decaying towards `long_vol`, plus skew and smile
+    /// in log-moneyness against the forward.
+    fn vol(&self, regime: Regime, underlying: f64, strike: f64, t: f64) -> f64 {
+        let cfg = &self.cfg;
+        let term_years = price::dt_years(cfg.term_days * TRADING_DAY);
+        let atm = cfg.long_vol + (cfg.atm_vol.get(regime) - cfg.long_vol) * (-t / term_years).exp();
+        let k = (strike / (underlying * (cfg.rate * t).exp())).ln();
+        (atm + cfg.skew.get(regime) * k + cfg.smile * k * k).max(MIN_VOL)
+    }
+
+    /// The whole chain priced at `underlying`, or nothing until the next
+    /// update is due.
+    pub fn quotes(&mut self, underlying: f64, regime: Regime, time: f64) -> Vec<OptionQuote> {
+        // (epsilon absorbs float drift in the accumulated sim clock)
+        if time + 1e-9 < self.next_at {
+            return Vec::new();
+        }
+        self.next_at = (self.next_at + self.cfg.interval).max(time);
+
+        let cfg = &self.cfg;
+        let tick = cfg.tick_size;
+        let round = |p: f64| (p / tick).round() * tick;
+        let atm = (underlying / self.strike_step).round() as i64;
+        let first = (atm - cfg.strikes as i64).max(1);
+        let mut out = Vec::new();
+        for &expiry in &cfg.expiries {
+            let t = price::dt_years(expiry as f64 * TRADING_DAY - time);
+            if t <= 0.0 {
+                continue;
+            }
+            for n in first..=atm + cfg.strikes as i64 {
+                let strike = round(n as f64 * self.strike_step);
+                let iv = self.vol(regime, underlying, strike, t);
+                for kind in [OptionKind::Call, OptionKind::Put] {
+                    let theo = black_scholes(kind, underlying, strike, t, cfg.rate, iv);
+                    let half = (theo * cfg.spread_pct).max(tick);
+                    out.push(OptionQuote {
+                        expiry,
+                        strike,
+                        kind,
+                        bid: round((theo - half).max(0.0)),
+                        ask: round(theo + half).max(tick),
+                        iv,
+                        underlying,
+                        instrument: self.instrument,
+                        time,
+                    });
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn black_scholes_matches_reference_and_parity() {
+        // Hull's example: S=42, K=40, r=10%, vol=20%, six months.
+        let call = black_scholes(OptionKind::Call, 42.0, 40.0, 0.5, 0.1, 0.2);
+        let put = black_scholes(OptionKind::Put, 42.0, 40.0, 0.5, 0.1, 0.2);
+        assert!((call - 4.76).abs() < 0.005, "call {call}");
+        assert!((put - 0.81).abs() < 0.005, "put {put}");
+        let parity = 42.0 - 40.0 * (-0.1f64 * 0.5).exp();
+        assert!((call - put - parity).abs() < 1e-9);
+    }
+
+    #[test]
+    fn surface_is_skewed_and_follows_the_regime() {
+        let chain = OptionChain::new(OptionsConfig::default(), 100.0, 0);
+        let t = price::dt_years(21.0 * TRADING_DAY);
+        let calm = chain.vol(Regime::Calm, 100.0, 100.0, t);
+        let crash = chain.vol(Regime::Crash, 100.0, 100.0, t);
+        assert!(crash > calm, "crash {crash} vs calm {calm}");
+        let downside = chain.vol(Regime::Calm, 100.0, 90.0, t);
+        let upside = chain.vol(Regime::Calm, 100.0, 110.0, t);
+        assert!(downside > upside, "90 {downside} vs 110 {upside}");
+    }
+}
@@ -1,5 +1,10 @@
 use std::fmt;
 
+use crate::wire::{
+    self, FrameWriter, Price, WireMessage, MSG_CANCEL, MSG_DARK_TRADE, MSG_MASS_CANCEL, MSG_ORDER,
+    MSG_TRADE, VERSION_1,
+};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Side {
     Buy,
@@ -19,6 +24,10 @@ impl fmt::Display for Side {
 pub enum OrderType {
     Limit,
     Market,
+    /// Stop order; `price` is the trigger price.
+    Stop,
+    /// Limit order showing `size` with `reserve` more hidden behind it.
+    Iceberg,
 }
 
 impl fmt::Display for OrderType {
@@ -26,66 +35,399 @@ impl fmt::Display for OrderType {
         match self {
             OrderType::Limit => write!(f, "LIMIT"),
             OrderType::Market => write!(f, "MARKET"),
+            OrderType::Stop => write!(f, "STOP"),
+            OrderType::Iceberg => write!(f, "ICEBERG"),
         }
     }
 }
 
+/// Binary ORDER flag: the order has no limit price (market order) and
+/// the `price` field carries no information.
+pub const ORDER_FLAG_NO_PRICE: u8 = 0x01;
+
+/// Binary ORDER payload before the variable-length cl_ord_id.
+const ORDER_FIXED_LEN: usize = 8 + 1 + 1 + 8 + 4 + 8 + 4 + 4 + 1 + 2 + 1;
+/// Binary CANCEL length.
+pub const CANCEL_LEN: usize = 4 + 8 + 8 + 2;
+/// v1 binary CANCEL length.
+pub const CANCEL_V1_LEN: usize = 4 + 8 + 8;
+/// v1 binary ORDER length.
+pub const ORDER_V1_LEN: usize = 4 + 8 + 1 + 1 + 8 + 4 + 8;
+
+/// Sentinel price market orders carried on the wire before they were
+/// flagged explicitly; still emitted when `legacy_market_price` is set.
+pub(crate) fn legacy_market_price(side: Side) -> f64 {
+    match side {
+        Side::Buy => 999_999.0,
+        Side::Sell => 0.0,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Order {
     pub id: u64,
     pub side: Side,
     pub order_type: OrderType,
+    /// Limit price (trigger price for stops; unused for market orders).
     pub price: f64,
     pub size: u32,
+    /// Hidden quantity behind the displayed `size` (icebergs only).
+    pub reserve: u32,
+    pub participant_id: u32,
+    /// Client order ID assigned by the participant; empty when not used.
+    pub cl_ord_id: String,
+    /// Index of the order's instrument in the symbol directory.
+    pub instrument: u16,
     pub created_at: f64,
     pub ttl: f64,
 }
 
 impl Order {
-    pub fn to_wire_text(&self) -> String {
-        format!(
-            "ORDER|id={}|side={}|type={}|price={:.2}|size={}|time={:.3}",
-            self.id, self.side, self.order_type, self.price, self.size, self.created_at,
-        )
+    /// Whether the order rests in the book (limit or iceberg).
+    pub fn is_resting(&self) -> bool {
+        matches!(self.order_type, OrderType::Limit | OrderType::Iceberg)
+    }
+
+    /// Displayed plus hidden quantity, saturating at the wire's u32.
+    pub fn total_size(&self) -> u32 {
+        self.size.saturating_add(self.reserve)
+    }
+}
+
+/// An execution of a market order against a resting order in the internal book.
+#[derive(Debug, Clone)]
+pub struct Trade {
+    pub maker_id: u64,
+    pub taker_id: u64,
+    pub aggressor: Side,
+    pub price: f64,
+    pub size: u32,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl WireMessage for Trade {
+    fn to_wire_text(&self) -> String {
+        wire::text_of(self)
+    }
+
+    fn to_wire_binary(&self) -> Vec<u8> {
+        wire::binary_of(self, 4 + 8 + 8 + 1 + 8 + 4 + 8 + 2)
+    }
+
+    fn write_text(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        write!(
+            w,
+            "TRADE|maker={}|taker={}|side={}|price={}|size={}|time={:.3}",
+            self.maker_id,
+            self.taker_id,
+            self.aggressor,
+            Price(self.price),
+            self.size,
+            self.time,
+        )?;
+        wire::write_instrument(w, self.instrument)
+    }
+
+    /// Binary trade (v2), little-endian:
+    /// header msg_type=7, maker_id:u64, taker_id:u64, aggressor:u8 (1 buy, 2 sell),
+    /// price:f64, size:u32, time:f64, instrument:u16
+    fn encode_into(&self, out: &mut [u8]) -> usize {
+        let mut w = FrameWriter::new(out, MSG_TRADE);
+        w.put(&self.maker_id.to_le_bytes());
+        w.put(&self.taker_id.to_le_bytes());
+        w.put_u8(match self.aggressor {
+            Side::Buy => 1,
+            Side::Sell => 2,
+        });
+        w.put(&self.price.to_le_bytes());
+        w.put(&self.size.to_le_bytes());
+        w.put(&self.time.to_le_bytes());
+        w.put(&self.instrument.to_le_bytes());
+        w.len()
+    }
+}
+
+/// A market order executed off-book against hidden liquidity
+/// (`[book] dark_fraction`). No ORDER is published for it and the lit book
+/// is left untouched; the print is all a consumer sees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DarkTrade {
+    pub aggressor: Side,
+    pub price: f64,
+    pub size: u32,
+    pub participant_id: u32,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl WireMessage for DarkTrade {
+    fn to_wire_text(&self) -> String {
+        wire::text_of(self)
+    }
+
+    fn to_wire_binary(&self) -> Vec<u8> {
+        wire::binary_of(self, 4 + 1 + 8 + 4 + 4 + 8 + 2)
+    }
+
+    fn write_text(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        write!(
+            w,
+            "DARK_TRADE|side={}|price={}|size={}|participant={}|time={:.3}",
+            self.aggressor,
+            Price(self.price),
+            self.size,
+            self.participant_id,
+            self.time,
+        )?;
+        wire::write_instrument(w, self.instrument)
+    }
+
+    /// Binary dark trade (v2), little-endian:
+    /// header msg_type=22, aggressor:u8 (1 buy, 2 sell), price:f64, size:u32,
+    /// participant_id:u32, time:f64, instrument:u16
+    fn encode_into(&self, out: &mut [u8]) -> usize {
+        let mut w = FrameWriter::new(out, MSG_DARK_TRADE);
+        w.put_u8(match self.aggressor {
+            Side::Buy => 1,
+            Side::Sell => 2,
+        });
+        w.put(&self.price.to_le_bytes());
+        w.put(&self.size.to_le_bytes());
+        w.put(&self.participant_id.to_le_bytes());
+        w.put(&self.time.to_le_bytes());
+        w.put(&self.instrument.to_le_bytes());
+        w.len()
+    }
+}
+
+impl Order {
+    /// Text encoding. Market orders are written as `price=MKT`, or with the
+    /// old sentinel price when `legacy_market_price` is set.
+    pub fn to_wire_text_with(&self, legacy_market_price: bool) -> String {
+        let mut msg = String::new();
+        let _ = self.write_text_with(&mut msg, legacy_market_price);
+        msg
+    }
+
+    /// Text encoding written to `w`, without allocating.
+    pub fn write_text_with(
+        &self,
+        w: &mut impl fmt::Write,
+        legacy_market_price: bool,
+    ) -> fmt::Result {
+        write!(
+            w,
+            "ORDER|id={}|side={}|type={}|price=",
+            self.id, self.side, self.order_type
+        )?;
+        match self.order_type {
+            OrderType::Market if legacy_market_price => {
+                write!(w, "{}", Price(self::legacy_market_price(self.side)))?
+            }
+            OrderType::Market => w.write_str("MKT")?,
+            _ => write!(w, "{}", Price(self.price))?,
+        }
+        write!(
+            w,
+            "|size={}|participant={}|time={:.3}",
+            self.size, self.participant_id, self.created_at,
+        )?;
+        wire::write_instrument(w, self.instrument)?;
+        if self.order_type == OrderType::Iceberg {
+            write!(w, "|total={}", self.total_size())?;
+        }
+        if !self.cl_ord_id.is_empty() {
+            write!(w, "|cl_ord_id={}", self.cl_ord_id)?;
+        }
+        Ok(())
+    }
+
+    /// Binary wire format (v2), little-endian:
+    /// magic[2]="OF", version:u8=2, msg_type:u8=1 (order),
+    /// id:u64, side:u8 (1 buy, 2 sell), order_type:u8 (1 limit, 2 market, 3 stop, 4 iceberg),
+    /// price:f64, size:u32, time:f64, participant_id:u32,
+    /// total_size:u32 (display + hidden; equals size for non-icebergs),
+    /// flags:u8 (bit 0 = no price; market orders send price 0.0, or the
+    /// old sentinel when `legacy_market_price` is set), instrument:u16,
+    /// cl_ord_id_len:u8, cl_ord_id:[u8; cl_ord_id_len] (UTF-8, at most 255 bytes)
+    pub fn to_wire_binary_with(&self, legacy_market_price: bool) -> Vec<u8> {
+        let mut out = vec![0; self.binary_len()];
+        self.encode_into(&mut out, legacy_market_price);
+        out
+    }
+
+    /// Length of the binary encoding.
+    pub fn binary_len(&self) -> usize {
+        4 + ORDER_FIXED_LEN + self.cl_ord_id.len().min(u8::MAX as usize)
+    }
+
+    /// Binary encoding written to the start of `out`, without allocating;
+    /// returns its length. `out` must hold `binary_len()` bytes.
+    pub fn encode_into(&self, out: &mut [u8], legacy_market_price: bool) -> usize {
+        let (price, flags) = match self.order_type {
+            OrderType::Market if legacy_market_price => {
+                (self::legacy_market_price(self.side), ORDER_FLAG_NO_PRICE)
+            }
+            OrderType::Market => (0.0, ORDER_FLAG_NO_PRICE),
+            _ => (self.price, 0),
+        };
+        let cl_ord_id = &self.cl_ord_id.as_bytes()[..self.cl_ord_id.len().min(u8::MAX as usize)];
+        let mut w = FrameWriter::new(out, MSG_ORDER);
+        w.put(&self.id.to_le_bytes());
+        w.put_u8(match self.side {
+            Side::Buy => 1,
+            Side::Sell => 2,
+        });
+        w.put_u8(match self.order_type {
+            OrderType::Limit => 1,
+            OrderType::Market => 2,
+            OrderType::Stop => 3,
+            OrderType::Iceberg => 4,
+        });
+        w.put(&price.to_le_bytes());
+        w.put(&self.size.to_le_bytes());
+        w.put(&self.created_at.to_le_bytes());
+        w.put(&self.participant_id.to_le_bytes());
+        w.put(&self.total_size().to_le_bytes());
+        w.put_u8(flags);
+        w.put(&self.instrument.to_le_bytes());
+        w.put_u8(cl_ord_id.len() as u8);
+        w.put(cl_ord_id);
+        w.len()
+    }
+
+    /// Whether the order can be written in the v1 layout, which has no stops.
+    pub fn has_v1_layout(&self) -> bool {
+        self.order_type != OrderType::Stop
     }
 
     /// Binary wire format (v1), little-endian:
     /// magic[2]="OF", version:u8=1, msg_type:u8=1 (order),
     /// id:u64, side:u8 (1 buy, 2 sell), order_type:u8 (1 limit, 2 market),
     /// price:f64, size:u32, time:f64
-    pub fn to_wire_binary(&self) -> Vec<u8> {
-        let mut out = Vec::with_capacity(2 + 1 + 1 + 8 + 1 + 1 + 8 + 4 + 8);
-        out.extend_from_slice(b"OF");
-        out.push(1);
-        out.push(1);
-        out.extend_from_slice(&self.id.to_le_bytes());
-        out.push(match self.side {
+    ///
+    /// Market orders carry the 999999/0 sentinel price and icebergs show
+    /// as limits of their displayed size. Written to the start of `out`
+    /// (at least `ORDER_V1_LEN` bytes); returns its length. Only for
+    /// orders that `has_v1_layout`.
+    pub fn encode_v1_into(&self, out: &mut [u8]) -> usize {
+        debug_assert!(self.has_v1_layout());
+        let (order_type, price) = match self.order_type {
+            OrderType::Market => (2, self::legacy_market_price(self.side)),
+            _ => (1, self.price),
+        };
+        let mut w = FrameWriter::with_version(out, VERSION_1, MSG_ORDER);
+        w.put(&self.id.to_le_bytes());
+        w.put_u8(match self.side {
             Side::Buy => 1,
             Side::Sell => 2,
         });
-        out.push(match self.order_type {
-            OrderType::Limit => 1,
-            OrderType::Market => 2,
-        });
-        out.extend_from_slice(&self.price.to_le_bytes());
-        out.extend_from_slice(&self.size.to_le_bytes());
-        out.extend_from_slice(&self.created_at.to_le_bytes());
-        out
+        w.put_u8(order_type);
+        w.put(&price.to_le_bytes());
+        w.put(&self.size.to_le_bytes());
+        w.put(&self.created_at.to_le_bytes());
+        w.len()
+    }
+}
+
+impl WireMessage for Order {
+    fn to_wire_text(&self) -> String {
+        self.to_wire_text_with(false)
+    }
+
+    fn to_wire_binary(&self) -> Vec<u8> {
+        self.to_wire_binary_with(false)
     }
 }
 
-pub fn cancel_to_wire_text(order_id: u64, current_time: f64) -> String {
-    format!("CANCEL|id={}|time={:.3}", order_id, current_time)
+pub fn cancel_to_wire_text(order_id: u64, instrument: u16, current_time: f64) -> String {
+    let mut msg = String::new();
+    let _ = write_cancel_text(&mut msg, order_id, instrument, current_time);
+    msg
 }
 
-/// Binary cancel wire format (v1), little-endian:
-/// magic[2]="OF", version:u8=1, msg_type:u8=2 (cancel), id:u64, time:f64
-pub fn cancel_to_wire_binary(order_id: u64, current_time: f64) -> Vec<u8> {
-    let mut out = Vec::with_capacity(2 + 1 + 1 + 8 + 8);
-    out.extend_from_slice(b"OF");
-    out.push(1);
-    out.push(2);
-    out.extend_from_slice(&order_id.to_le_bytes());
-    out.extend_from_slice(&current_time.to_le_bytes());
+/// Text cancel written to `w`, without allocating.
+pub fn write_cancel_text(
+    w: &mut impl fmt::Write,
+    order_id: u64,
+    instrument: u16,
+    current_time: f64,
+) -> fmt::Result {
+    write!(w, "CANCEL|id={}|time={:.3}", order_id, current_time)?;
+    wire::write_instrument(w, instrument)
+}
+
+/// Binary cancel wire format (v2), little-endian:
+/// magic[2]="OF", version:u8=2, msg_type:u8=2 (cancel), id:u64, time:f64,
+/// instrument:u16
+pub fn cancel_to_wire_binary(order_id: u64, instrument: u16, current_time: f64) -> Vec<u8> {
+    let mut out = vec![0; CANCEL_LEN];
+    cancel_encode_into(order_id, instrument, current_time, &mut out);
     out
 }
+
+/// Binary cancel written to the start of `out` (at least `CANCEL_LEN`
+/// bytes); returns its length.
+pub fn cancel_encode_into(
+    order_id: u64,
+    instrument: u16,
+    current_time: f64,
+    out: &mut [u8],
+) -> usize {
+    let mut w = FrameWriter::new(out, MSG_CANCEL);
+    w.put(&order_id.to_le_bytes());
+    w.put(&current_time.to_le_bytes());
+    w.put(&instrument.to_le_bytes());
+    w.len()
+}
+
+/// Binary cancel wire format (v1): id:u64, time:f64 under a version 1
+/// header.
+pub fn cancel_encode_v1_into(order_id: u64, current_time: f64, out: &mut [u8]) -> usize {
+    let mut w = FrameWriter::with_version(out, VERSION_1, MSG_CANCEL);
+    w.put(&order_id.to_le_bytes());
+    w.put(&current_time.to_le_bytes());
+    w.len()
+}
+
+/// Cancels every resting order of one participant, optionally on one side only.
+#[derive(Debug, Clone)]
+pub struct MassCancel {
+    pub participant_id: u32,
+    /// `None` cancels both sides.
+    pub side: Option<Side>,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl WireMessage for MassCancel {
+    fn to_wire_text(&self) -> String {
+        let side = match self.side {
+            Some(side) => side.to_string(),
+            None => "ALL".to_string(),
+        };
+        let mut msg = format!(
+            "MASS_CANCEL|participant={}|side={}|time={:.3}",
+            self.participant_id, side, self.time
+        );
+        let _ = wire::write_instrument(&mut msg, self.instrument);
+        msg
+    }
+
+    /// Binary mass cancel (v2), little-endian:
+    /// header msg_type=8, participant_id:u32, side:u8 (0 both, 1 buy, 2 sell), time:f64,
+    /// instrument:u16
+    fn to_wire_binary(&self) -> Vec<u8> {
+        let mut out = wire::binary_frame(MSG_MASS_CANCEL, 4 + 1 + 8 + 2);
+        out.extend_from_slice(&self.participant_id.to_le_bytes());
+        out.push(match self.side {
+            None => 0,
+            Some(Side::Buy) => 1,
+            Some(Side::Sell) => 2,
+        });
+        out.extend_from_slice(&self.time.to_le_bytes());
+        out.extend_from_slice(&self.instrument.to_le_bytes());
+        out
+    }
+}
@@ -0,0 +1,257 @@
+//! OUCH 4.2-style order-entry acknowledgements (`[ouch]`): an Accepted
+//! message for every order published and a Canceled message for every order
+//! cancelled, on a multicast group of their own in MoldUDP64 packets.
+
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+use crate::book::{SelfMatch, SelfMatchMode};
+use crate::export::Export;
+use crate::multicast::MulticastOptions;
+use crate::order::{MassCancel, Order, OrderType, Side, Trade};
+use crate::refdata::SYMBOL_LEN;
+
+/// MoldUDP64 header: session[10], sequence number:u64, message count:u16.
+const MOLD_HEADER_LEN: usize = 20;
+const MOLD_SESSION: &[u8; 10] = b"ORDERFLOW ";
+/// Keeps a packet within one Ethernet frame.
+const MAX_PACKET: usize = 1400;
+
+const TOKEN_LEN: usize = 14;
+const ACCEPTED_LEN: usize = 66;
+const CANCELED_LEN: usize = 28;
+/// Price field of a market order, as in OUCH.
+const MARKET_PRICE: u32 = 0x7FFF_FFFF;
+/// Time-in-force values: immediate-or-cancel and system hours (no expiry).
+const TIF_IOC: u32 = 0;
+const TIF_SYSTEM_HOURS: u32 = 99_999;
+/// Canceled reasons: user requested, and self-match prevention.
+const REASON_USER: u8 = b'U';
+const REASON_SELF_MATCH: u8 = b'Q';
+
+/// What a Canceled message needs to know about a live order.
+struct Live {
+    token: [u8; TOKEN_LEN],
+    /// Shares still open: displayed plus reserve, less fills.
+    open: u32,
+}
+
+pub struct OuchEcho {
+    socket: Socket,
+    dest: SockAddr,
+    stock: [u8; SYMBOL_LEN],
+    /// Resting orders by ID. Market orders are IOC and never rest; stops
+    /// have no OUCH equivalent and are echoed when they trigger.
+    live: HashMap<u64, Live>,
+    /// Messages waiting for `flush`.
+    pending: Vec<Vec<u8>>,
+    /// Sequence number of the next message.
+    next_seq: u64,
+}
+
+impl OuchEcho {
+    pub fn new(
+        group: Ipv4Addr,
+        port: u16,
+        symbol: &str,
+        options: &MulticastOptions,
+    ) -> io::Result<Self> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        options.apply(&socket)?;
+        let mut stock = [b' '; SYMBOL_LEN];
+        let len = symbol.len().min(SYMBOL_LEN);
+        stock[..len].copy_from_slice(&symbol.as_bytes()[..len]);
+        Ok(Self {
+            socket,
+            dest: SockAddr::from(SocketAddrV4::new(group, port)),
+            stock,
+            live: HashMap::new(),
+            pending: Vec::new(),
+            next_seq: 1,
+        })
+    }
+
+    fn accepted(&self, order: &Order, token: &[u8; TOKEN_LEN]) -> Vec<u8> {
+        let market = order.order_type == OrderType::Market;
+        let price = if market {
+            MARKET_PRICE
+        } else {
+            (order.price * 10_000.0).round().min(MARKET_PRICE as f64) as u32
+        };
+        let tif = if market {
+            TIF_IOC
+        } else if order.ttl > 0.0 {
+            (order.ttl.ceil() as u32).clamp(1, TIF_SYSTEM_HOURS - 2)
+        } else {
+            TIF_SYSTEM_HOURS
+        };
+        let mut firm = [b' '; 4];
+        firm.copy_from_slice(format!("{:04}", order.participant_id % 10_000).as_bytes());
+
+        let mut msg = Vec::with_capacity(ACCEPTED_LEN);
+        msg.push(b'A');
+        msg.extend_from_slice(&timestamp(order.created_at).to_be_bytes());
+        msg.extend_from_slice(token);
+        msg.push(side_code(order.side));
+        msg.extend_from_slice(&order.total_size().to_be_bytes());
+        msg.extend_from_slice(&self.stock);
+        msg.extend_from_slice(&price.to_be_bytes());
+        msg.extend_from_slice(&tif.to_be_bytes());
+        msg.extend_from_slice(&firm);
+        msg.push(b'Y'); // display: visible
+        msg.extend_from_slice(&order.id.to_be_bytes());
+        msg.push(b'A'); // capacity: agency
+        msg.push(b'N'); // intermarket sweep eligibility
+        msg.extend_from_slice(&0u32.to_be_bytes()); // minimum quantity
+        msg.push(b'N'); // cross type: continuous market
+        msg.push(b'L'); // order state: live
+        msg.push(b' '); // BBO weighting indicator: unspecified
+        msg
+    }
+
+    fn cancel_live(&mut self, id: u64, time: f64) {
+        let Some(live) = self.live.remove(&id) else {
+            return;
+        };
+        let msg = canceled(&live.token, live.open, REASON_USER, time);
+        self.pending.push(msg);
+    }
+
+    fn decrement_live(&mut self, id: u64, shares: u32, time: f64) {
+        let Some(live) = self.live.get_mut(&id) else {
+            return;
+        };
+        let decrement = shares.min(live.open);
+        live.open -= decrement;
+        let msg = canceled(&live.token, decrement, REASON_SELF_MATCH, time);
+        if live.open == 0 {
+            self.live.remove(&id);
+        }
+        self.pending.push(msg);
+    }
+}
+
+fn canceled(token: &[u8; TOKEN_LEN], decrement: u32, reason: u8, time: f64) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(CANCELED_LEN);
+    msg.push(b'C');
+    msg.extend_from_slice(&timestamp(time).to_be_bytes());
+    msg.extend_from_slice(token);
+    msg.extend_from_slice(&decrement.to_be_bytes());
+    msg.push(reason);
+    msg
+}
+
+/// Nanoseconds of sim time, where OUCH has nanoseconds since midnight.
+fn timestamp(time: f64) -> u64 {
+    (time.max(0.0) * 1e9).round() as u64
+}
+
+fn side_code(side: Side) -> u8 {
+    match side {
+        Side::Buy => b'B',
+        Side::Sell => b'S',
+    }
+}
+
+/// The order's client order ID when it has one, otherwise its ID, left
+/// justified and space padded.
+fn token(order: &Order) -> [u8; TOKEN_LEN] {
+    let id = order.id.to_string();
+    let text = if order.cl_ord_id.is_empty() {
+        id.as_bytes()
+    } else {
+        order.cl_ord_id.as_bytes()
+    };
+    let mut token = [b' '; TOKEN_LEN];
+    let len = text.len().min(TOKEN_LEN);
+    token[..len].copy_from_slice(&text[..len]);
+    token
+}
+
+impl Export for OuchEcho {
+    fn order(&mut self, order: &Order) -> io::Result<()> {
+        if order.order_type == OrderType::Stop {
+            return Ok(());
+        }
+        // An iceberg refreshing its display keeps its ID and is not a new order
+        if let Some(live) = self.live.get_mut(&order.id) {
+            live.open = order.total_size();
+            return Ok(());
+        }
+        let token = token(order);
+        self.pending.push(self.accepted(order, &token));
+        if order.is_resting() {
+            self.live.insert(
+                order.id,
+                Live {
+                    token,
+                    open: order.total_size(),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn cancel(&mut self, order_id: u64, time: f64) -> io::Result<()> {
+        self.cancel_live(order_id, time);
+        Ok(())
+    }
+
+    /// A limit taker (a fat-finger order) is live too.
+    fn trade(&mut self, trade: &Trade) -> io::Result<()> {
+        for id in [trade.maker_id, trade.taker_id] {
+            if let Some(live) = self.live.get_mut(&id) {
+                live.open = live.open.saturating_sub(trade.size);
+                if live.open == 0 {
+                    self.live.remove(&id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A Canceled for the shares each live order lost: the resting order,
+    /// and for `decrement` the taker too when it is a live limit order.
+    fn self_match(&mut self, sm: &SelfMatch) -> io::Result<()> {
+        self.decrement_live(sm.maker_id, sm.size, sm.time);
+        if sm.mode == SelfMatchMode::Decrement {
+            self.decrement_live(sm.taker_id, sm.size, sm.time);
+        }
+        Ok(())
+    }
+
+    /// One Canceled per order the mass cancel removed.
+    fn mass_cancel(&mut self, mass: &MassCancel, ids: &[u64]) -> io::Result<()> {
+        for &id in ids {
+            self.cancel_live(id, mass.time);
+        }
+        Ok(())
+    }
+
+    /// Send this tick's messages, packed into as few MoldUDP64 packets as fit.
+    fn flush(&mut self) -> io::Result<()> {
+        let mut messages = std::mem::take(&mut self.pending).into_iter().peekable();
+        while messages.peek().is_some() {
+            let mut packet = Vec::with_capacity(MAX_PACKET);
+            packet.extend_from_slice(MOLD_SESSION);
+            packet.extend_from_slice(&self.next_seq.to_be_bytes());
+            packet.extend_from_slice(&[0, 0]);
+            let mut count: u16 = 0;
+            while let Some(msg) = messages.peek() {
+                if count > 0 && packet.len() + 2 + msg.len() > MAX_PACKET {
+                    break;
+                }
+                packet.extend_from_slice(&(msg.len() as u16).to_be_bytes());
+                packet.extend_from_slice(msg);
+                messages.next();
+                count += 1;
+            }
+            packet[MOLD_HEADER_LEN - 2..MOLD_HEADER_LEN].copy_from_slice(&count.to_be_bytes());
+            self.next_seq += count as u64;
+            self.socket.send_to(&packet, &self.dest)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use std::time::{Duration, Instant};
+
+use crate::config::Pacing;
+
+/// Below this remaining time, hybrid pacing stops sleeping and spins.
+const HYBRID_SPIN_WINDOW: Duration = Duration::from_micros(1500);
+
+/// Holds the tick loop to a fixed schedule. Deadlines are absolute, so time
+/// spent generating and sending a tick doesn't accumulate as drift.
+pub struct Pacer {
+    mode: Pacing,
+    interval: Duration,
+    deadline: Instant,
+}
+
+impl Pacer {
+    pub fn new(mode: Pacing, interval_secs: f64) -> Self {
+        let interval = Duration::from_secs_f64(interval_secs);
+        Self {
+            mode,
+            interval,
+            deadline: Instant::now() + interval,
+        }
+    }
+
+    /// Block until the end of the current tick interval.
+    pub fn wait(&mut self) {
+        let now = Instant::now();
+        if now >= self.deadline {
+            // Fell behind: re-anchor instead of bursting to catch up.
+            self.deadline = now + self.interval;
+            return;
+        }
+
+        match self.mode {
+            Pacing::Sleep => std::thread::sleep(self.deadline - now),
+            Pacing::Spin => spin_until(self.deadline),
+            Pacing::Hybrid => {
+                let remaining = self.deadline - now;
+                if remaining > HYBRID_SPIN_WINDOW {
+                    std::thread::sleep(remaining - HYBRID_SPIN_WINDOW);
+                }
+                spin_until(self.deadline);
+            }
+        }
+        self.deadline += self.interval;
+    }
+
+    /// Restart the schedule from now, e.g. after a pause.
+    pub fn reset(&mut self) {
+        self.deadline = Instant::now() + self.interval;
+    }
+}
+
+fn spin_until(deadline: Instant) {
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
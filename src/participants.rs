@@ -0,0 +1,61 @@
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+
+/// Pool of synthetic trader IDs with per-participant activity weights.
+pub struct ParticipantPool {
+    ids: Vec<u32>,
+    weights: WeightedIndex<f64>,
+}
+
+impl ParticipantPool {
+    /// Build `count` participants numbered from `first_id`. Explicit `weights`
+    /// (exactly one per participant) take precedence; otherwise activity
+    /// follows a Zipf-like curve `1 / rank^exponent` (0 = uniform).
+    pub fn new(
+        first_id: u32,
+        count: usize,
+        weights: &[f64],
+        zipf_exponent: f64,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if !weights.is_empty() && weights.len() != count {
+            return Err(format!(
+                "participant weights must have one entry per participant: got {} for count {}",
+                weights.len(),
+                count
+            )
+            .into());
+        }
+        let last_id = u32::try_from(count.saturating_sub(1))
+            .ok()
+            .and_then(|n| first_id.checked_add(n));
+        if last_id.is_none() {
+            return Err(format!(
+                "participant IDs {}.. overflow u32 with count {}",
+                first_id, count
+            )
+            .into());
+        }
+        let weights: Vec<f64> = if weights.is_empty() {
+            (1..=count)
+                .map(|rank| 1.0 / (rank as f64).powf(zipf_exponent))
+                .collect()
+        } else {
+            weights.to_vec()
+        };
+        if weights.is_empty() {
+            return Err("participant pool must contain at least one participant".into());
+        }
+        let ids = (0..count as u32).map(|i| first_id + i).collect();
+        let weights = WeightedIndex::new(&weights)
+            .map_err(|e| format!("invalid participant weights: {}", e))?;
+        Ok(Self { ids, weights })
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> u32 {
+        self.ids[self.weights.sample(rng)]
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+}
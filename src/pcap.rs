@@ -0,0 +1,204 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Classic pcap, microsecond timestamps, native byte order.
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+/// Same layout with nanosecond timestamps (tcpdump `--time-stamp-precision=nano`).
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+const LINKTYPE_ETHERNET: u32 = 1;
+const SNAPLEN: u32 = 65_535;
+
+const ETH_HEADER_LEN: usize = 14;
+const IPV4_HEADER_LEN: usize = 20;
+const UDP_HEADER_LEN: usize = 8;
+
+/// Synthetic sender: a locally administered MAC and a TEST-NET-1 address.
+const SRC_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+const SRC_IP: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 1);
+
+/// Writes every datagram the sender emits as an Ethernet/IPv4/UDP frame
+/// addressed to the multicast group, for tcpreplay or Wireshark.
+pub struct PcapWriter {
+    out: BufWriter<File>,
+    group: Ipv4Addr,
+    port: u16,
+    ip_id: u16,
+    /// IP TTL the packets carry, as set on the sending socket.
+    ttl: u8,
+    /// With `--no-realtime`: wall clock at the start of the run, and the
+    /// sim time the next packets are stamped at relative to it.
+    sim_clock: Option<(Duration, f64)>,
+}
+
+impl PcapWriter {
+    pub fn create(path: &Path, group: Ipv4Addr, port: u16) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(&PCAP_MAGIC.to_ne_bytes())?;
+        out.write_all(&2u16.to_ne_bytes())?; // version 2.4
+        out.write_all(&4u16.to_ne_bytes())?;
+        out.write_all(&0i32.to_ne_bytes())?; // thiszone
+        out.write_all(&0u32.to_ne_bytes())?; // sigfigs
+        out.write_all(&SNAPLEN.to_ne_bytes())?;
+        out.write_all(&LINKTYPE_ETHERNET.to_ne_bytes())?;
+        Ok(Self {
+            out,
+            group,
+            port,
+            ip_id: 0,
+            ttl: 1,
+            sim_clock: None,
+        })
+    }
+
+    /// IP TTL for the packets that follow (default 1).
+    pub fn set_ttl(&mut self, ttl: u8) {
+        self.ttl = ttl;
+    }
+
+    /// Stamp packets with sim time from now on (offset from the current
+    /// wall clock) instead of the time they were written.
+    pub fn use_sim_time(&mut self) {
+        self.sim_clock = Some((wall_clock(), 0.0));
+    }
+
+    /// Sim time for the packets that follow; ignored unless `use_sim_time` was called.
+    pub fn set_sim_time(&mut self, t: f64) {
+        if let Some((_, sim_time)) = self.sim_clock.as_mut() {
+            *sim_time = t;
+        }
+    }
+
+    /// Record one UDP payload, timestamped with the current wall clock (or sim time).
+    pub fn write(&mut self, payload: &[u8]) -> io::Result<()> {
+        let udp_len = UDP_HEADER_LEN + payload.len();
+        let ip_len = IPV4_HEADER_LEN + udp_len;
+        let frame_len = (ETH_HEADER_LEN + ip_len) as u32;
+
+        let now = match self.sim_clock {
+            Some((start, t)) => start + Duration::from_secs_f64(t.max(0.0)),
+            None => wall_clock(),
+        };
+        self.out.write_all(&(now.as_secs() as u32).to_ne_bytes())?;
+        self.out.write_all(&now.subsec_micros().to_ne_bytes())?;
+        self.out.write_all(&frame_len.min(SNAPLEN).to_ne_bytes())?;
+        self.out.write_all(&frame_len.to_ne_bytes())?;
+
+        // Ethernet: IPv4 multicast MAC is 01:00:5e + the group's low 23 bits.
+        let g = self.group.octets();
+        self.out
+            .write_all(&[0x01, 0x00, 0x5e, g[1] & 0x7f, g[2], g[3]])?;
+        self.out.write_all(&SRC_MAC)?;
+        self.out.write_all(&0x0800u16.to_be_bytes())?;
+
+        let mut ip = [0u8; IPV4_HEADER_LEN];
+        ip[0] = 0x45; // version 4, 5-word header
+        ip[2..4].copy_from_slice(&(ip_len as u16).to_be_bytes());
+        ip[4..6].copy_from_slice(&self.ip_id.to_be_bytes());
+        ip[8] = self.ttl;
+        ip[9] = 17; // UDP
+        ip[12..16].copy_from_slice(&SRC_IP.octets());
+        ip[16..20].copy_from_slice(&g);
+        let checksum = ipv4_checksum(&ip);
+        ip[10..12].copy_from_slice(&checksum.to_be_bytes());
+        self.out.write_all(&ip)?;
+        self.ip_id = self.ip_id.wrapping_add(1);
+
+        // UDP checksum 0 = not computed (allowed over IPv4).
+        self.out.write_all(&self.port.to_be_bytes())?;
+        self.out.write_all(&self.port.to_be_bytes())?;
+        self.out.write_all(&(udp_len as u16).to_be_bytes())?;
+        self.out.write_all(&0u16.to_be_bytes())?;
+        self.out.write_all(payload)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+fn wall_clock() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Whether `buf` starts with a classic pcap file header, in either byte order.
+pub fn is_pcap(buf: &[u8]) -> bool {
+    buf.get(..4).is_some_and(|m| {
+        let m = [m[0], m[1], m[2], m[3]];
+        [PCAP_MAGIC, PCAP_MAGIC_NANOS]
+            .iter()
+            .any(|&magic| u32::from_le_bytes(m) == magic || u32::from_be_bytes(m) == magic)
+    })
+}
+
+/// UDP payloads of every Ethernet/IPv4/UDP frame in a classic pcap capture,
+/// in capture order. Other frames are skipped.
+pub fn udp_payloads(buf: &[u8]) -> io::Result<Vec<&[u8]>> {
+    if buf.len() < 24 || !is_pcap(buf) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a classic pcap file",
+        ));
+    }
+    let magic = [buf[0], buf[1], buf[2], buf[3]];
+    let big_endian =
+        u32::from_le_bytes(magic) != PCAP_MAGIC && u32::from_le_bytes(magic) != PCAP_MAGIC_NANOS;
+    let u32_at = |at: usize| {
+        let w = [buf[at], buf[at + 1], buf[at + 2], buf[at + 3]];
+        if big_endian {
+            u32::from_be_bytes(w)
+        } else {
+            u32::from_le_bytes(w)
+        }
+    };
+    if u32_at(20) != LINKTYPE_ETHERNET {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "only Ethernet captures are supported",
+        ));
+    }
+
+    let mut payloads = Vec::new();
+    let mut pos = 24;
+    while pos + 16 <= buf.len() {
+        let incl_len = u32_at(pos + 8) as usize;
+        let start = pos + 16;
+        let Some(frame) = buf.get(start..start + incl_len) else {
+            break; // truncated final record
+        };
+        pos = start + incl_len;
+        if let Some(payload) = udp_payload(frame) {
+            payloads.push(payload);
+        }
+    }
+    Ok(payloads)
+}
+
+fn udp_payload(frame: &[u8]) -> Option<&[u8]> {
+    if frame.get(12..14)? != [0x08, 0x00] {
+        return None;
+    }
+    let ip = frame.get(ETH_HEADER_LEN..)?;
+    let ihl = usize::from(ip.first()? & 0x0f) * 4;
+    if ip.first()? >> 4 != 4 || *ip.get(9)? != 17 {
+        return None;
+    }
+    let udp = ip.get(ihl..)?;
+    let udp_len = usize::from(u16::from_be_bytes([*udp.get(4)?, *udp.get(5)?]));
+    udp.get(UDP_HEADER_LEN..udp_len)
+}
+
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum: u32 = header
+        .chunks(2)
+        .map(|w| u32::from(u16::from_be_bytes([w[0], w[1]])))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
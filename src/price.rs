@@ -0,0 +1,617 @@
+use rand::{Rng, RngCore};
+use rand_distr::{Distribution, Normal, Poisson};
+use serde::Deserialize;
+use std::fmt;
+
+use crate::regime::{Regime, RegimeParams};
+
+/// Which `PriceModel` drives the mid (`[simulation] price_model`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PriceModelKind {
+    /// Geometric Brownian motion with the regime's drift and volatility.
+    Gbm,
+    /// Merton jump diffusion: GBM plus Poisson jumps with lognormal sizes.
+    JumpDiffusion,
+    /// Ornstein-Uhlenbeck: the mid reverts towards a long-run mean with
+    /// additive noise, for spreads and rates rather than equities.
+    Ou,
+}
+
+impl fmt::Display for PriceModelKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PriceModelKind::Gbm => write!(f, "gbm"),
+            PriceModelKind::JumpDiffusion => write!(f, "jump-diffusion"),
+            PriceModelKind::Ou => write!(f, "ou"),
+        }
+    }
+}
+
+/// Jump arrivals per second of sim time in each regime.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct JumpIntensity {
+    pub calm: f64,
+    pub volatile: f64,
+    pub crash: f64,
+    pub rally: f64,
+    pub recovery: f64,
+}
+
+impl Default for JumpIntensity {
+    fn default() -> Self {
+        Self {
+            calm: 0.005,
+            volatile: 0.02,
+            crash: 0.1,
+            rally: 0.05,
+            recovery: 0.01,
+        }
+    }
+}
+
+impl JumpIntensity {
+    pub fn get(&self, regime: Regime) -> f64 {
+        match regime {
+            Regime::Calm => self.calm,
+            Regime::Volatile => self.volatile,
+            Regime::Crash => self.crash,
+            Regime::Rally => self.rally,
+            Regime::Recovery => self.recovery,
+        }
+    }
+}
+
+/// `[jumps]`: used when `price_model = "jump-diffusion"`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct JumpConfig {
+    pub intensity: JumpIntensity,
+    /// Mean of the log jump size.
+    pub mean: f64,
+    /// Standard deviation of the log jump size.
+    pub std: f64,
+}
+
+impl Default for JumpConfig {
+    fn default() -> Self {
+        Self {
+            intensity: JumpIntensity::default(),
+            mean: 0.0,
+            std: 0.02,
+        }
+    }
+}
+
+/// `[ou]`: used when `price_model = "ou"`. Applies to the single simulated
+/// instrument; there is no per-symbol override yet.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OuConfig {
+    /// Long-run mean the mid reverts to (defaults to `initial_price`).
+    pub mean: Option<f64>,
+    /// Reversion speed per second: a deviation decays by `e^(-speed·t)`.
+    pub speed: f64,
+    /// Noise in price units per √second.
+    pub volatility: f64,
+}
+
+impl Default for OuConfig {
+    fn default() -> Self {
+        Self {
+            mean: None,
+            speed: 0.01,
+            volatility: 0.05,
+        }
+    }
+}
+
+/// `[garch]`: GARCH(1,1) volatility for the GBM and jump-diffusion models.
+/// Variances are annualized like the regime `sigma`:
+/// `h = omega + alpha·r²/dt + beta·h_prev`, where `r` is last tick's
+/// return excluding drift.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GarchConfig {
+    pub enabled: bool,
+    /// Constant term. When omitted it is `(1 - alpha - beta)·sigma²` of the
+    /// current regime, so volatility clusters but still reverts to the
+    /// regime's level.
+    pub omega: Option<f64>,
+    /// Weight of the last squared return.
+    pub alpha: f64,
+    /// Weight of the previous variance.
+    pub beta: f64,
+}
+
+impl Default for GarchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            omega: None,
+            alpha: 0.05,
+            beta: 0.9,
+        }
+    }
+}
+
+/// `[fundamental]`: a latent fair value the mid is pulled towards, so long
+/// runs stay anchored and crashes tend to recover.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FundamentalConfig {
+    pub enabled: bool,
+    /// Annualized volatility of the fundamental's own random walk.
+    pub sigma: f64,
+    /// Reversion speed per second: each tick closes `1 - e^(-strength·dt)`
+    /// of the log gap between mid and the fundamental.
+    pub strength: f64,
+}
+
+impl Default for FundamentalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sigma: 0.1,
+            strength: 0.01,
+        }
+    }
+}
+
+/// Latent fundamental value: a driftless GBM starting at `initial_price`.
+/// Applied after the price model, whichever one is selected.
+pub struct Fundamental {
+    value: f64,
+    sigma: f64,
+    strength: f64,
+}
+
+impl Fundamental {
+    pub fn new(cfg: &FundamentalConfig, initial_price: f64) -> Self {
+        Self {
+            value: initial_price,
+            sigma: cfg.sigma,
+            strength: cfg.strength,
+        }
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Evolves the fundamental over `dt` seconds and returns `mid` pulled towards it.
+    pub fn step(&mut self, mid: f64, dt: f64, rng: &mut dyn RngCore) -> f64 {
+        let var = self.sigma.powi(2) * dt_years(dt);
+        // (-var/2 keeps the fundamental a martingale rather than drifting up)
+        self.value *= (var.sqrt() * standard_normal(rng) - 0.5 * var).exp();
+        let pull = 1.0 - (-self.strength * dt).exp();
+        mid * (pull * (self.value / mid).ln()).exp()
+    }
+}
+
+/// `[basis]`: with `--symbols-file`, the second symbol is a future on the
+/// first, its mid the spot's plus a mean-reverting basis.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BasisConfig {
+    pub enabled: bool,
+    /// Long-run basis (futures minus spot) in price units.
+    pub mean: f64,
+    /// Reversion speed per second: a deviation decays by `e^(-speed·t)`.
+    pub speed: f64,
+    /// Noise in price units per √second.
+    pub volatility: f64,
+}
+
+impl Default for BasisConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mean: 0.5,
+            speed: 0.05,
+            volatility: 0.02,
+        }
+    }
+}
+
+/// Futures minus spot: an Ornstein-Uhlenbeck process from the gap between
+/// the two legs' initial prices, for the futures leg to add to the last
+/// spot mid it saw.
+pub struct Basis {
+    value: f64,
+    mean: f64,
+    speed: f64,
+    volatility: f64,
+    spot: f64,
+}
+
+impl Basis {
+    pub fn new(cfg: &BasisConfig, spot: f64, future: f64) -> Self {
+        Self {
+            value: future - spot,
+            mean: cfg.mean,
+            speed: cfg.speed,
+            volatility: cfg.volatility,
+            spot,
+        }
+    }
+
+    pub fn set_spot(&mut self, spot: f64) {
+        self.spot = spot;
+    }
+
+    /// Evolves the basis over `dt` seconds; the step's mid is the future's.
+    pub fn step(&mut self, dt: f64, rng: &mut dyn RngCore) -> PriceStep {
+        self.value = ou_step(
+            self.value,
+            self.mean,
+            self.speed,
+            self.volatility,
+            dt,
+            standard_normal(rng),
+        );
+        let mid = self.spot + self.value;
+        PriceStep::to(mid, self.volatility / mid / dt_years(1.0).sqrt())
+    }
+}
+
+/// Result of one tick's price update.
+pub struct PriceStep {
+    /// The new mid, before clamping to the tick size.
+    pub mid: f64,
+    /// Number of jumps this tick and their summed log size.
+    pub jumps: u32,
+    pub jump_return: f64,
+    /// Annualized volatility the diffusion ran at.
+    pub sigma: f64,
+}
+
+impl PriceStep {
+    fn to(mid: f64, sigma: f64) -> Self {
+        Self {
+            mid,
+            jumps: 0,
+            jump_return: 0.0,
+            sigma,
+        }
+    }
+}
+
+/// A stochastic process for the mid. Called once per tick; anything else
+/// that moves the mid (shocks, stops, uncrosses) happens outside it.
+pub trait PriceModel {
+    /// Advances `mid` over `dt` seconds in `regime`. `params.mu` is
+    /// per-second and `params.sigma` annualized; models are free to ignore
+    /// either.
+    fn step(
+        &mut self,
+        mid: f64,
+        dt: f64,
+        regime: Regime,
+        params: &RegimeParams,
+        rng: &mut dyn RngCore,
+    ) -> PriceStep;
+}
+
+/// Builds the configured model.
+pub fn build(
+    kind: PriceModelKind,
+    jumps: &JumpConfig,
+    ou: &OuConfig,
+    garch: &GarchConfig,
+    initial_price: f64,
+) -> Result<Box<dyn PriceModel>, Box<dyn std::error::Error>> {
+    let gbm = Gbm::new(garch);
+    Ok(match kind {
+        PriceModelKind::Gbm => Box::new(gbm),
+        PriceModelKind::JumpDiffusion => Box::new(JumpDiffusion::new(gbm, jumps)?),
+        PriceModelKind::Ou => Box::new(OrnsteinUhlenbeck::new(ou, initial_price)),
+    })
+}
+
+/// GBM dt: tick interval expressed in years.
+pub fn dt_years(tick_interval: f64) -> f64 {
+    1.0 / (252.0 * 6.5 * 3600.0) * tick_interval / 0.1 * 0.1
+}
+
+/// Standard normal draw (Box-Muller).
+fn standard_normal(rng: &mut dyn RngCore) -> f64 {
+    let u1: f64 = rng.gen::<f64>().max(1e-15);
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+/// GARCH(1,1) state: the current annualized variance.
+struct Garch {
+    cfg: GarchConfig,
+    /// Seeded from the regime on the first step.
+    variance: Option<f64>,
+}
+
+impl Garch {
+    fn sigma(&self, params: &RegimeParams) -> f64 {
+        self.variance.map_or(params.sigma, f64::sqrt)
+    }
+
+    /// Folds in one tick's return excluding drift.
+    fn observe(&mut self, shock: f64, dt_years: f64, params: &RegimeParams) {
+        let g = &self.cfg;
+        let h = self.variance.unwrap_or(params.sigma.powi(2));
+        let omega = g
+            .omega
+            .unwrap_or((1.0 - g.alpha - g.beta) * params.sigma.powi(2));
+        self.variance = Some(omega + g.alpha * shock * shock / dt_years + g.beta * h);
+    }
+}
+
+/// Geometric Brownian motion with the regime's drift and volatility, or a
+/// GARCH volatility when `[garch]` is enabled.
+pub struct Gbm {
+    garch: Option<Garch>,
+}
+
+impl Gbm {
+    pub fn new(garch: &GarchConfig) -> Self {
+        Self {
+            garch: garch.enabled.then(|| Garch {
+                cfg: garch.clone(),
+                variance: None,
+            }),
+        }
+    }
+
+    /// Drift and diffusion parts of one tick's log return, and the sigma
+    /// the diffusion ran at.
+    fn terms(&self, dt: f64, params: &RegimeParams, rng: &mut dyn RngCore) -> (f64, f64, f64) {
+        let z = standard_normal(rng);
+        let sigma = self
+            .garch
+            .as_ref()
+            .map_or(params.sigma, |g| g.sigma(params));
+        (params.mu * dt, sigma * dt_years(dt).sqrt() * z, sigma)
+    }
+
+    fn observe(&mut self, shock: f64, dt: f64, params: &RegimeParams) {
+        if let Some(g) = &mut self.garch {
+            g.observe(shock, dt_years(dt), params);
+        }
+    }
+}
+
+impl PriceModel for Gbm {
+    fn step(
+        &mut self,
+        mid: f64,
+        dt: f64,
+        _regime: Regime,
+        params: &RegimeParams,
+        rng: &mut dyn RngCore,
+    ) -> PriceStep {
+        let (drift, diffusion, sigma) = self.terms(dt, params, rng);
+        self.observe(diffusion, dt, params);
+        PriceStep::to(mid * (drift + diffusion).exp(), sigma)
+    }
+}
+
+/// Merton jump diffusion: GBM plus Poisson jumps with lognormal sizes.
+pub struct JumpDiffusion {
+    gbm: Gbm,
+    intensity: JumpIntensity,
+    /// E[e^Y - 1] for Y ~ N(mean, std²): the drift the jumps add on average.
+    compensator: f64,
+    jump_size: Normal<f64>,
+}
+
+impl JumpDiffusion {
+    pub fn new(gbm: Gbm, jumps: &JumpConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let jump_size = Normal::new(jumps.mean, jumps.std)
+            .map_err(|e| format!("invalid jump size distribution: {}", e))?;
+        Ok(Self {
+            gbm,
+            intensity: jumps.intensity.clone(),
+            compensator: (jumps.mean + 0.5 * jumps.std.powi(2)).exp() - 1.0,
+            jump_size,
+        })
+    }
+}
+
+impl PriceModel for JumpDiffusion {
+    /// Jumps are compensated so the expected drift is still the regime's `mu`.
+    fn step(
+        &mut self,
+        mid: f64,
+        dt: f64,
+        regime: Regime,
+        params: &RegimeParams,
+        rng: &mut dyn RngCore,
+    ) -> PriceStep {
+        let (drift, diffusion, sigma) = self.gbm.terms(dt, params, rng);
+        let mut step = PriceStep::to(mid, sigma);
+        let mut log_return = drift + diffusion;
+        let lambda = self.intensity.get(regime);
+        if lambda > 0.0 {
+            log_return -= lambda * self.compensator * dt;
+            let arrivals = Poisson::new(lambda * dt).map_or(0.0, |p| p.sample(rng)) as u32;
+            for _ in 0..arrivals {
+                step.jump_return += self.jump_size.sample(rng);
+            }
+            step.jumps = arrivals;
+            log_return += step.jump_return;
+        }
+        self.gbm.observe(log_return - drift, dt, params);
+        step.mid = mid * log_return.exp();
+        step
+    }
+}
+
+/// Ornstein-Uhlenbeck: the mid reverts to a long-run mean with additive
+/// noise. Ignores the regime parameters.
+pub struct OrnsteinUhlenbeck {
+    mean: f64,
+    speed: f64,
+    volatility: f64,
+}
+
+impl OrnsteinUhlenbeck {
+    pub fn new(ou: &OuConfig, initial_price: f64) -> Self {
+        Self {
+            mean: ou.mean.unwrap_or(initial_price),
+            speed: ou.speed,
+            volatility: ou.volatility,
+        }
+    }
+}
+
+impl PriceModel for OrnsteinUhlenbeck {
+    fn step(
+        &mut self,
+        mid: f64,
+        dt: f64,
+        _regime: Regime,
+        _params: &RegimeParams,
+        rng: &mut dyn RngCore,
+    ) -> PriceStep {
+        let z = standard_normal(rng);
+        // Additive noise, as the log volatility it amounts to at this mid
+        let sigma = self.volatility / mid / dt_years(1.0).sqrt();
+        PriceStep::to(
+            ou_step(mid, self.mean, self.speed, self.volatility, dt, z),
+            sigma,
+        )
+    }
+}
+
+/// `x` after `dt` seconds of reverting to `mean` at `speed` with additive
+/// noise `volatility`, given a standard normal draw `z`. Exact
+/// discretization, so large ticks or fast reversion stay stable.
+fn ou_step(x: f64, mean: f64, speed: f64, volatility: f64, dt: f64, z: f64) -> f64 {
+    let decay = (-speed * dt).exp();
+    let variance = if speed > 0.0 {
+        (1.0 - decay * decay) / (2.0 * speed)
+    } else {
+        dt
+    };
+    mean + (x - mean) * decay + volatility * variance.sqrt() * z
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regime;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const STEPS: usize = 200_000;
+    const DT: f64 = 0.1;
+
+    fn mean_var(xs: &[f64]) -> (f64, f64) {
+        let n = xs.len() as f64;
+        let mean = xs.iter().sum::<f64>() / n;
+        let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        (mean, var)
+    }
+
+    fn log_returns(model: &mut dyn PriceModel, regime: Regime) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(1);
+        let params = regime::params(regime);
+        (0..STEPS)
+            .map(|_| model.step(1.0, DT, regime, params, &mut rng).mid.ln())
+            .collect()
+    }
+
+    #[test]
+    fn gbm_log_returns_have_the_regime_moments() {
+        let params = regime::params(Regime::Crash);
+        let (mean, var) = mean_var(&log_returns(
+            &mut Gbm::new(&GarchConfig::default()),
+            Regime::Crash,
+        ));
+        let expected_var = params.sigma.powi(2) * dt_years(DT);
+        assert!(
+            (var / expected_var - 1.0).abs() < 0.02,
+            "var {var} vs {expected_var}"
+        );
+        // Standard error of the mean is sqrt(var / STEPS).
+        let se = (expected_var / STEPS as f64).sqrt();
+        assert!((mean - params.mu * DT).abs() < 4.0 * se, "mean {mean}");
+    }
+
+    #[test]
+    fn jump_diffusion_is_compensated_and_jumps_at_the_configured_rate() {
+        let jumps = JumpConfig {
+            intensity: JumpIntensity {
+                calm: 0.5,
+                ..JumpIntensity::default()
+            },
+            mean: -0.01,
+            std: 0.02,
+        };
+        let mut model = JumpDiffusion::new(Gbm::new(&GarchConfig::default()), &jumps).unwrap();
+        let mut rng = StdRng::seed_from_u64(2);
+        let params = regime::params(Regime::Calm);
+        let mut count = 0u32;
+        let mut gross = Vec::with_capacity(STEPS);
+        for _ in 0..STEPS {
+            let step = model.step(1.0, DT, Regime::Calm, params, &mut rng);
+            count += step.jumps;
+            gross.push(step.mid);
+        }
+        let expected = 0.5 * DT * STEPS as f64;
+        assert!(
+            (count as f64 - expected).abs() < 4.0 * expected.sqrt(),
+            "{count} jumps"
+        );
+        // E[mid'/mid] = e^(mu·dt + sigma²·dt/2) with or without jumps.
+        let (mean, var) = mean_var(&gross);
+        let expected_mean = (params.mu * DT + 0.5 * params.sigma.powi(2) * dt_years(DT)).exp();
+        let se = (var / STEPS as f64).sqrt();
+        assert!(
+            (mean - expected_mean).abs() < 4.0 * se,
+            "mean {mean} vs {expected_mean}"
+        );
+    }
+
+    #[test]
+    fn ou_settles_into_its_stationary_distribution() {
+        let ou = OuConfig {
+            mean: Some(50.0),
+            speed: 0.5,
+            volatility: 0.2,
+        };
+        let mut model = OrnsteinUhlenbeck::new(&ou, 100.0);
+        let mut rng = StdRng::seed_from_u64(3);
+        let params = regime::params(Regime::Calm);
+        let mut mid = 100.0;
+        let mut path = Vec::with_capacity(STEPS);
+        for _ in 0..STEPS {
+            mid = model.step(mid, DT, Regime::Calm, params, &mut rng).mid;
+            path.push(mid);
+        }
+        // Skip the first 100s while it reverts from 100 towards 50.
+        let (mean, var) = mean_var(&path[1000..]);
+        let expected_var = 0.2f64.powi(2) / (2.0 * 0.5);
+        assert!((mean - 50.0).abs() < 0.01, "mean {mean}");
+        assert!(
+            (var / expected_var - 1.0).abs() < 0.05,
+            "var {var} vs {expected_var}"
+        );
+    }
+
+    #[test]
+    fn garch_targets_the_regime_variance() {
+        let garch = GarchConfig {
+            enabled: true,
+            omega: None,
+            alpha: 0.1,
+            beta: 0.85,
+        };
+        let params = regime::params(Regime::Volatile);
+        let (_, var) = mean_var(&log_returns(&mut Gbm::new(&garch), Regime::Volatile));
+        let expected_var = params.sigma.powi(2) * dt_years(DT);
+        assert!(
+            (var / expected_var - 1.0).abs() < 0.05,
+            "var {var} vs {expected_var}"
+        );
+    }
+}
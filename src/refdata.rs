@@ -0,0 +1,244 @@
+use std::path::Path;
+
+use crate::wire::{self, WireMessage, MSG_SYMBOL};
+
+/// Width of the space-padded symbol field in binary SYMBOL messages.
+pub const SYMBOL_LEN: usize = 8;
+pub const ISIN_LEN: usize = 12;
+
+/// Static reference data for one instrument, published as a SYMBOL
+/// directory message so consumers can bootstrap before the first order.
+#[derive(Debug, Clone)]
+pub struct Instrument {
+    pub symbol: String,
+    pub isin: String,
+    pub tick_size: f64,
+    pub lot_size: u32,
+}
+
+impl Instrument {
+    /// `isin` defaults to a synthetic identifier derived from the symbol.
+    pub fn new(symbol: &str, isin: Option<&str>, tick_size: f64, lot_size: u32) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            isin: isin.map_or_else(|| synthetic_isin(symbol), str::to_string),
+            tick_size,
+            lot_size,
+        }
+    }
+}
+
+/// ISIN-shaped identifier: country code `XS`, the symbol padded with zeros
+/// to nine characters, and a valid ISIN check digit.
+pub fn synthetic_isin(symbol: &str) -> String {
+    let body: String = symbol
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .chain(std::iter::repeat('0'))
+        .take(9)
+        .collect();
+    let base = format!("XS{}", body);
+    format!("{}{}", base, isin_check_digit(&base))
+}
+
+/// Luhn check digit over the ISIN's letters expanded to two-digit numbers.
+fn isin_check_digit(base: &str) -> u32 {
+    let digits: Vec<u32> = base
+        .chars()
+        .flat_map(|c| {
+            let v = c.to_digit(36).unwrap_or(0);
+            if v >= 10 {
+                vec![v / 10, v % 10]
+            } else {
+                vec![v]
+            }
+        })
+        .collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| {
+            if i % 2 == 0 {
+                let d2 = d * 2;
+                d2 / 10 + d2 % 10
+            } else {
+                d
+            }
+        })
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+/// One row of a `--symbols-file` universe.
+#[derive(Debug, Clone)]
+pub struct UniverseRow {
+    pub symbol: String,
+    pub initial_price: f64,
+    pub tick_size: f64,
+    /// Relative average daily volume; scales the symbol's generation rate
+    /// and its share of the feed's send slots.
+    pub adv_weight: f64,
+    /// Messages per second the feed sends for the symbol at most.
+    pub max_rate: Option<f64>,
+}
+
+/// Read a symbol universe from `path`; see `parse_universe`.
+pub fn load_universe(path: &Path) -> Result<Vec<UniverseRow>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read symbols file {}: {}", path.display(), e))?;
+    parse_universe(&contents, &path.display().to_string())
+}
+
+/// Parse a symbol universe: one `symbol,initial_price,tick_size,adv_weight`
+/// row per line, with an optional `max_rate` column, after an optional
+/// header row. Blank lines and lines
+/// starting with `#` are skipped. Row order gives each symbol its index.
+/// `source` names the file in errors.
+pub fn parse_universe(contents: &str, source: &str) -> Result<Vec<UniverseRow>, String> {
+    let mut rows: Vec<UniverseRow> = Vec::new();
+    for (n, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if rows.is_empty() && fields.first() == Some(&"symbol") {
+            continue;
+        }
+        let bad = |what: &str| format!("{} line {}: {}", source, n + 1, what);
+        let (symbol, initial_price, tick_size, adv_weight, max_rate) = match fields[..] {
+            [s, p, t, w] => (s, p, t, w, ""),
+            [s, p, t, w, r] => (s, p, t, w, r),
+            _ => {
+                return Err(bad(&format!(
+                    "expected symbol,initial_price,tick_size,adv_weight[,max_rate], got '{}'",
+                    line
+                )))
+            }
+        };
+        if symbol.is_empty() || symbol.len() > SYMBOL_LEN || !symbol.is_ascii() {
+            return Err(bad(&format!(
+                "symbol must be 1-{} ASCII characters, got '{}'",
+                SYMBOL_LEN, symbol
+            )));
+        }
+        if rows.iter().any(|r| r.symbol == symbol) {
+            return Err(bad(&format!("duplicate symbol '{}'", symbol)));
+        }
+        let positive = |name: &str, v: &str| match v.parse::<f64>() {
+            Ok(x) if x > 0.0 && x.is_finite() => Ok(x),
+            _ => Err(bad(&format!(
+                "{} must be a positive number, got '{}'",
+                name, v
+            ))),
+        };
+        rows.push(UniverseRow {
+            symbol: symbol.to_string(),
+            initial_price: positive("initial_price", initial_price)?,
+            tick_size: positive("tick_size", tick_size)?,
+            adv_weight: positive("adv_weight", adv_weight)?,
+            max_rate: match max_rate {
+                "" => None,
+                r => Some(positive("max_rate", r)?),
+            },
+        });
+    }
+    if rows.is_empty() {
+        return Err(format!("symbols file {} lists no symbols", source));
+    }
+    if rows.len() > usize::from(u16::MAX) {
+        return Err(format!(
+            "symbols file {} lists {} symbols, at most {} are supported",
+            source,
+            rows.len(),
+            u16::MAX
+        ));
+    }
+    Ok(rows)
+}
+
+/// Symbol directory entry. `index` is the instrument index its ORDERs,
+/// TRADEs and QUOTEs carry.
+#[derive(Debug, Clone)]
+pub struct SymbolDirectory<'a> {
+    pub index: u16,
+    pub instrument: &'a Instrument,
+    pub time: f64,
+}
+
+impl WireMessage for SymbolDirectory<'_> {
+    fn to_wire_text(&self) -> String {
+        let i = self.instrument;
+        let mut msg = format!(
+            "SYMBOL|symbol={}|isin={}|tick_size={}|lot_size={}|time={:.3}",
+            i.symbol, i.isin, i.tick_size, i.lot_size, self.time
+        );
+        let _ = wire::write_instrument(&mut msg, self.index);
+        msg
+    }
+
+    /// Binary symbol directory (v2), little-endian:
+    /// header msg_type=15, symbol:[u8; 8] (space-padded ASCII), isin:[u8; 12],
+    /// tick_size:f64, lot_size:u32, time:f64, instrument:u16
+    fn to_wire_binary(&self) -> Vec<u8> {
+        let i = self.instrument;
+        let mut out = wire::binary_frame(MSG_SYMBOL, SYMBOL_LEN + ISIN_LEN + 8 + 4 + 8 + 2);
+        let mut symbol = [b' '; SYMBOL_LEN];
+        for (dst, src) in symbol.iter_mut().zip(i.symbol.bytes()) {
+            *dst = src;
+        }
+        out.extend_from_slice(&symbol);
+        let mut isin = [b' '; ISIN_LEN];
+        for (dst, src) in isin.iter_mut().zip(i.isin.bytes()) {
+            *dst = src;
+        }
+        out.extend_from_slice(&isin);
+        out.extend_from_slice(&i.tick_size.to_le_bytes());
+        out.extend_from_slice(&i.lot_size.to_le_bytes());
+        out.extend_from_slice(&self.time.to_le_bytes());
+        out.extend_from_slice(&self.index.to_le_bytes());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn universe_rows_parse_in_order_and_reject_bad_lines() {
+        let rows = parse_universe(
+            "symbol,initial_price,tick_size,adv_weight\n\
+             # comment\n\
+             AAPL, 190.5, 0.01, 3\n\
+             \n\
+             BRK,612000,1,0.5,2500\n",
+            "u.csv",
+        )
+        .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].symbol, "AAPL");
+        assert_eq!(rows[0].initial_price, 190.5);
+        assert_eq!((rows[0].adv_weight, rows[0].max_rate), (3.0, None));
+        assert_eq!(rows[1].symbol, "BRK");
+        assert_eq!((rows[1].tick_size, rows[1].max_rate), (1.0, Some(2500.0)));
+
+        for (contents, error) in [
+            ("", "lists no symbols"),
+            ("AAPL,190,0.01\n", "u.csv line 1: expected"),
+            (
+                "AAPL,190,0.01,1\nAAPL,191,0.01,1\n",
+                "line 2: duplicate symbol",
+            ),
+            ("TOOLONGSYM,1,0.01,1\n", "1-8 ASCII"),
+            ("AAPL,-1,0.01,1\n", "initial_price must be a positive"),
+            ("AAPL,1,0.01,nan\n", "adv_weight must be a positive"),
+            ("AAPL,1,0.01,1,0\n", "max_rate must be a positive"),
+        ] {
+            let e = parse_universe(contents, "u.csv").unwrap_err();
+            assert!(e.contains(error), "{contents:?}: {e}");
+        }
+    }
+}
@@ -59,36 +59,92 @@ pub struct RegimeParams {
     pub max_duration: f64,
 }
 
+impl RegimeParams {
+    /// Rates, sigma and half-spread non-negative, `buy_prob` a probability,
+    /// offset lambda and size multiplier positive.
+    pub fn is_valid(&self) -> bool {
+        self.sigma >= 0.0
+            && self.mu.is_finite()
+            && self.limit_rate >= 0.0
+            && self.market_rate >= 0.0
+            && self.cancel_rate >= 0.0
+            && (0.0..=1.0).contains(&self.buy_prob)
+            && self.half_spread >= 0.0
+            && self.offset_lambda > 0.0
+            && self.size_mult > 0.0
+    }
+}
+
 pub const REGIME_TABLE: [RegimeParams; 5] = [
     // CALM
     RegimeParams {
-        sigma: 0.15, mu: 0.0, limit_rate: 50.0, market_rate: 5.0, cancel_rate: 20.0,
-        buy_prob: 0.50, half_spread: 0.03, offset_lambda: 5.0, size_mult: 1.0,
-        min_duration: 5.0, max_duration: 30.0,
+        sigma: 0.15,
+        mu: 0.0,
+        limit_rate: 50.0,
+        market_rate: 5.0,
+        cancel_rate: 20.0,
+        buy_prob: 0.50,
+        half_spread: 0.03,
+        offset_lambda: 5.0,
+        size_mult: 1.0,
+        min_duration: 5.0,
+        max_duration: 30.0,
     },
     // VOLATILE
     RegimeParams {
-        sigma: 0.80, mu: 0.0, limit_rate: 80.0, market_rate: 15.0, cancel_rate: 40.0,
-        buy_prob: 0.50, half_spread: 0.08, offset_lambda: 2.5, size_mult: 1.5,
-        min_duration: 3.0, max_duration: 15.0,
+        sigma: 0.80,
+        mu: 0.0,
+        limit_rate: 80.0,
+        market_rate: 15.0,
+        cancel_rate: 40.0,
+        buy_prob: 0.50,
+        half_spread: 0.08,
+        offset_lambda: 2.5,
+        size_mult: 1.5,
+        min_duration: 3.0,
+        max_duration: 15.0,
     },
     // CRASH — mu=-0.045/s → exp(-0.045*5) ≈ 0.80, so ~100→80 over 5s
     RegimeParams {
-        sigma: 2.00, mu: -0.045, limit_rate: 15.0, market_rate: 45.0, cancel_rate: 80.0,
-        buy_prob: 0.12, half_spread: 0.25, offset_lambda: 1.2, size_mult: 3.0,
-        min_duration: 2.0, max_duration: 10.0,
+        sigma: 2.00,
+        mu: -0.045,
+        limit_rate: 15.0,
+        market_rate: 45.0,
+        cancel_rate: 80.0,
+        buy_prob: 0.12,
+        half_spread: 0.25,
+        offset_lambda: 1.2,
+        size_mult: 3.0,
+        min_duration: 2.0,
+        max_duration: 10.0,
     },
     // RALLY — mu=+0.035/s → exp(0.035*5) ≈ 1.19, so ~100→119 over 5s
     RegimeParams {
-        sigma: 1.50, mu: 0.035, limit_rate: 25.0, market_rate: 35.0, cancel_rate: 50.0,
-        buy_prob: 0.88, half_spread: 0.15, offset_lambda: 1.8, size_mult: 2.5,
-        min_duration: 2.0, max_duration: 12.0,
+        sigma: 1.50,
+        mu: 0.035,
+        limit_rate: 25.0,
+        market_rate: 35.0,
+        cancel_rate: 50.0,
+        buy_prob: 0.88,
+        half_spread: 0.15,
+        offset_lambda: 1.8,
+        size_mult: 2.5,
+        min_duration: 2.0,
+        max_duration: 12.0,
     },
     // RECOVERY — mu=+0.005/s → gentle upward drift
     RegimeParams {
-        sigma: 0.50, mu: 0.005, limit_rate: 60.0, market_rate: 8.0, cancel_rate: 25.0,
-        buy_prob: 0.55, half_spread: 0.05, offset_lambda: 4.0, size_mult: 1.0,
-        min_duration: 3.0, max_duration: 15.0,
+        sigma: 0.50,
+        mu: 0.005,
+        limit_rate: 60.0,
+        market_rate: 8.0,
+        cancel_rate: 25.0,
+        buy_prob: 0.55,
+        half_spread: 0.05,
+        offset_lambda: 4.0,
+        size_mult: 1.0,
+        min_duration: 3.0,
+        max_duration: 15.0,
     },
 ];
 
@@ -96,11 +152,11 @@ pub const REGIME_TABLE: [RegimeParams; 5] = [
 /// Rows = from regime, columns = to regime.
 /// Order: CALM, VOLATILE, CRASH, RALLY, RECOVERY
 pub const TRANSITION_PROB: [[f64; 5]; 5] = [
-    /* CALM     */ [0.0,   0.008, 0.003, 0.003, 0.0  ],
-    /* VOLATILE */ [0.005, 0.0,   0.008, 0.006, 0.004],
-    /* CRASH    */ [0.0,   0.004, 0.0,   0.002, 0.020],
-    /* RALLY    */ [0.0,   0.006, 0.002, 0.0,   0.015],
-    /* RECOVERY */ [0.015, 0.004, 0.001, 0.002, 0.0  ],
+    /* CALM     */ [0.0, 0.008, 0.003, 0.003, 0.0],
+    /* VOLATILE */ [0.005, 0.0, 0.008, 0.006, 0.004],
+    /* CRASH    */ [0.0, 0.004, 0.0, 0.002, 0.020],
+    /* RALLY    */ [0.0, 0.006, 0.002, 0.0, 0.015],
+    /* RECOVERY */ [0.015, 0.004, 0.001, 0.002, 0.0],
 ];
 
 pub struct RegimeState {
@@ -132,7 +188,14 @@ pub fn params(regime: Regime) -> &'static RegimeParams {
     &REGIME_TABLE[regime.index()]
 }
 
-pub fn try_transition(state: &RegimeState, allow_transitions: bool, rng: &mut impl Rng) -> Regime {
+/// `boost` multiplies the probability of moving into one regime (see
+/// `RegimeFeedback`).
+pub fn try_transition(
+    state: &RegimeState,
+    allow_transitions: bool,
+    boost: Option<(Regime, f64)>,
+    rng: &mut impl Rng,
+) -> Regime {
     if !allow_transitions {
         return state.current;
     }
@@ -145,7 +208,10 @@ pub fn try_transition(state: &RegimeState, allow_transitions: bool, rng: &mut im
     let mut cumulative = 0.0;
 
     for (to, &prob) in TRANSITION_PROB[from].iter().enumerate() {
-        cumulative += prob;
+        cumulative += match boost {
+            Some((target, factor)) if target.index() == to => prob * factor,
+            _ => prob,
+        };
         if roll < cumulative {
             return Regime::ALL[to];
         }
@@ -158,3 +224,156 @@ pub fn random_regime_duration(regime: Regime, rng: &mut impl Rng) -> f64 {
     let p = params(regime);
     rng.gen_range(p.min_duration..=p.max_duration)
 }
+
+/// Units of the regime half-spread and limit order offsets (`[spreads] units`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpreadUnits {
+    /// Price units from the regime table.
+    Absolute,
+    /// Basis points of the current mid, from `[spreads]`.
+    Bps,
+}
+
+/// One value per regime.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegimeValues {
+    pub calm: f64,
+    pub volatile: f64,
+    pub crash: f64,
+    pub rally: f64,
+    pub recovery: f64,
+}
+
+impl RegimeValues {
+    pub fn get(&self, regime: Regime) -> f64 {
+        match regime {
+            Regime::Calm => self.calm,
+            Regime::Volatile => self.volatile,
+            Regime::Crash => self.crash,
+            Regime::Rally => self.rally,
+            Regime::Recovery => self.recovery,
+        }
+    }
+}
+
+/// `[spreads]`: optionally express the half-spread and mean limit order
+/// offset in basis points of mid, so they scale with the price level.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SpreadConfig {
+    pub units: SpreadUnits,
+    pub half_spread_bps: RegimeValues,
+    /// Mean offset of limit orders beyond the half-spread.
+    pub offset_bps: RegimeValues,
+}
+
+/// Matches the regime table at a mid of 100.
+impl Default for SpreadConfig {
+    fn default() -> Self {
+        Self {
+            units: SpreadUnits::Absolute,
+            half_spread_bps: RegimeValues {
+                calm: 3.0,
+                volatile: 8.0,
+                crash: 25.0,
+                rally: 15.0,
+                recovery: 5.0,
+            },
+            offset_bps: RegimeValues {
+                calm: 20.0,
+                volatile: 40.0,
+                crash: 83.3,
+                rally: 55.6,
+                recovery: 25.0,
+            },
+        }
+    }
+}
+
+impl SpreadConfig {
+    /// `params` with the half-spread and offset rate converted from basis
+    /// points at `mid`, or `None` when spreads are absolute.
+    pub fn at(&self, params: &RegimeParams, regime: Regime, mid: f64) -> Option<RegimeParams> {
+        (self.units == SpreadUnits::Bps).then(|| RegimeParams {
+            half_spread: mid * self.half_spread_bps.get(regime) / 10_000.0,
+            offset_lambda: 10_000.0 / (mid * self.offset_bps.get(regime)),
+            ..params.clone()
+        })
+    }
+}
+
+/// `[regime_feedback]`: lets price action feed back into regime transitions.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RegimeFeedbackConfig {
+    pub enabled: bool,
+    /// Drawdown from the session high (fraction) beyond which CRASH is favoured.
+    pub drawdown_pct: f64,
+    /// Multiplier on the per-tick probability of entering CRASH during a drawdown.
+    pub crash_boost: f64,
+    /// Rebound off the low (fraction) after a drawdown beyond which RECOVERY is favoured.
+    pub rebound_pct: f64,
+    /// Multiplier on the per-tick probability of entering RECOVERY on a rebound.
+    pub recovery_boost: f64,
+}
+
+impl Default for RegimeFeedbackConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            drawdown_pct: 0.05,
+            crash_boost: 5.0,
+            rebound_pct: 0.03,
+            recovery_boost: 5.0,
+        }
+    }
+}
+
+/// Tracks the session high and the low since it to bias transitions: a
+/// drawdown favours CRASH, and a rebound after one favours RECOVERY.
+pub struct RegimeFeedback {
+    cfg: RegimeFeedbackConfig,
+    high: f64,
+    low: f64,
+}
+
+impl RegimeFeedback {
+    pub fn new(cfg: &RegimeFeedbackConfig, mid: f64) -> Self {
+        Self {
+            cfg: cfg.clone(),
+            high: mid,
+            low: mid,
+        }
+    }
+
+    /// Start a new session at `mid` (the high is per session).
+    pub fn reset(&mut self, mid: f64) {
+        self.high = mid;
+        self.low = mid;
+    }
+
+    pub fn update(&mut self, mid: f64) {
+        if mid > self.high {
+            self.high = mid;
+            self.low = mid;
+        }
+        self.low = self.low.min(mid);
+    }
+
+    /// The regime to favour and by how much, given the current one.
+    pub fn boost(&self, mid: f64, current: Regime) -> Option<(Regime, f64)> {
+        let drawdown = 1.0 - mid / self.high;
+        let worst = 1.0 - self.low / self.high;
+        let rebound = mid / self.low - 1.0;
+        let (target, factor) = if worst >= self.cfg.drawdown_pct && rebound >= self.cfg.rebound_pct
+        {
+            (Regime::Recovery, self.cfg.recovery_boost)
+        } else if drawdown >= self.cfg.drawdown_pct {
+            (Regime::Crash, self.cfg.crash_boost)
+        } else {
+            return None;
+        };
+        (target != current).then_some((target, factor))
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+
+use crate::regime::Regime;
+
+/// Sum and sum of squares of per-tick log returns.
+#[derive(Default, Clone, Copy)]
+struct Returns {
+    sum: f64,
+    sum_sq: f64,
+}
+
+impl Returns {
+    fn add(&mut self, log_return: f64) {
+        self.sum += log_return;
+        self.sum_sq += log_return * log_return;
+    }
+
+    /// Annualized standard deviation of `ticks` returns.
+    fn sigma(&self, ticks: u64, dt_years: f64) -> Option<f64> {
+        if ticks < 2 {
+            return None;
+        }
+        let n = ticks as f64;
+        let mean = self.sum / n;
+        let var = (self.sum_sq / n - mean * mean).max(0.0) * n / (n - 1.0);
+        Some(var.sqrt() / dt_years.sqrt())
+    }
+}
+
+/// Per-regime accumulators for the end-of-run report.
+#[derive(Default, Clone, Copy)]
+struct RegimeAccum {
+    seconds: f64,
+    ticks: u64,
+    /// Returns of mid over whole ticks: the price model plus jumps, shocks,
+    /// stops, uncrosses and everything else that moved it.
+    total: Returns,
+    /// Returns of the price model's diffusion alone.
+    diffusion: Returns,
+    /// Sum of the sigma the price model ran at each tick, after overrides,
+    /// scenario windows, seasonality and GARCH.
+    sigma_sum: f64,
+}
+
+impl RegimeAccum {
+    fn realized_sigma(&self, dt_years: f64) -> Option<f64> {
+        self.total.sigma(self.ticks, dt_years)
+    }
+
+    fn diffusion_sigma(&self, dt_years: f64) -> Option<f64> {
+        self.diffusion.sigma(self.ticks, dt_years)
+    }
+
+    /// Average sigma the price model ran at; `None` before any tick.
+    fn effective_sigma(&self) -> Option<f64> {
+        (self.ticks > 0).then(|| self.sigma_sum / self.ticks as f64)
+    }
+}
+
+/// Whole-run statistics, printed (and optionally written as JSON) on exit.
+pub struct RunReport {
+    regimes: [RegimeAccum; 5],
+    orders: u64,
+    cancels: u64,
+    trades: u64,
+    messages: u64,
+    /// Random and control shocks; scenario and squeeze shocks are scripted.
+    shocks: u64,
+    /// Order size -> count, for exact percentiles.
+    sizes: BTreeMap<u32, u64>,
+    size_count: u64,
+    peak_rate: f64,
+}
+
+impl RunReport {
+    pub fn new() -> Self {
+        Self {
+            regimes: [RegimeAccum::default(); 5],
+            orders: 0,
+            cancels: 0,
+            trades: 0,
+            messages: 0,
+            shocks: 0,
+            sizes: BTreeMap::new(),
+            size_count: 0,
+            peak_rate: 0.0,
+        }
+    }
+
+    /// Record one tick spent in `regime`: the log return of mid over it,
+    /// the part of it the price model's diffusion made, and the sigma the
+    /// model ran at.
+    pub fn record_tick(
+        &mut self,
+        regime: Regime,
+        dt_seconds: f64,
+        log_return: f64,
+        diffusion_return: f64,
+        sigma: f64,
+    ) {
+        let acc = &mut self.regimes[regime.index()];
+        acc.seconds += dt_seconds;
+        acc.ticks += 1;
+        acc.total.add(log_return);
+        acc.diffusion.add(diffusion_return);
+        acc.sigma_sum += sigma;
+    }
+
+    pub fn record_shock(&mut self) {
+        self.shocks += 1;
+    }
+
+    pub fn record_size(&mut self, size: u32) {
+        *self.sizes.entry(size).or_insert(0) += 1;
+        self.size_count += 1;
+    }
+
+    /// Fold in one display interval's counters; `secs = 0` skips the peak-rate sample.
+    pub fn record_interval(
+        &mut self,
+        orders: u64,
+        cancels: u64,
+        trades: u64,
+        messages: u64,
+        secs: f64,
+    ) {
+        self.orders += orders;
+        self.cancels += cancels;
+        self.trades += trades;
+        self.messages += messages;
+        if secs > 0.0 {
+            self.peak_rate = self.peak_rate.max(messages as f64 / secs);
+        }
+    }
+
+    /// Smallest size with at least `q` of orders at or below it.
+    fn size_percentile(&self, q: f64) -> u32 {
+        let target = (q * self.size_count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (&size, &count) in &self.sizes {
+            seen += count;
+            if seen >= target {
+                return size;
+            }
+        }
+        0
+    }
+
+    fn percentiles(&self) -> [(&'static str, u32); 5] {
+        [
+            ("p50", self.size_percentile(0.50)),
+            ("p90", self.size_percentile(0.90)),
+            ("p99", self.size_percentile(0.99)),
+            ("p999", self.size_percentile(0.999)),
+            ("max", self.sizes.keys().next_back().copied().unwrap_or(0)),
+        ]
+    }
+
+    /// Human-readable report lines.
+    pub fn lines(&self, dt_years: f64) -> Vec<String> {
+        let mut lines = vec![
+            format!(
+                "orders: {}  cancels: {}  trades: {}",
+                self.orders, self.cancels, self.trades
+            ),
+            format!(
+                "messages: {}  peak: {:.0} msgs/s  shocks: {}",
+                self.messages, self.peak_rate, self.shocks
+            ),
+            format!(
+                "sizes: {}",
+                self.percentiles()
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            format!(
+                "{:<9} {:>8}  {:>6}  {:>7}  {:>10}",
+                "regime", "time", "σ cfg", "σ diff", "σ realized"
+            ),
+        ];
+        for regime in Regime::ALL {
+            let acc = &self.regimes[regime.index()];
+            if acc.ticks == 0 {
+                continue;
+            }
+            let fmt = |s: Option<f64>| s.map_or("-".to_string(), |s| format!("{:.3}", s));
+            lines.push(format!(
+                "{:<9} {:>7.1}s  {:>6.2}  {:>7}  {:>10}",
+                regime.to_string(),
+                acc.seconds,
+                acc.effective_sigma().unwrap_or(0.0),
+                fmt(acc.diffusion_sigma(dt_years)),
+                fmt(acc.realized_sigma(dt_years))
+            ));
+        }
+        lines
+    }
+
+    /// JSON rendering for `--report`.
+    pub fn to_json(&self, dt_years: f64, sim_seconds: f64, seed: u64) -> String {
+        let regimes = Regime::ALL
+            .iter()
+            .map(|&regime| {
+                let acc = &self.regimes[regime.index()];
+                let json = |s: Option<f64>| s.map_or("null".to_string(), |s| s.to_string());
+                format!(
+                    "\"{}\": {{\"seconds\": {}, \"ticks\": {}, \"sigma_configured\": {}, \"sigma_diffusion\": {}, \"sigma_realized\": {}}}",
+                    regime,
+                    acc.seconds,
+                    acc.ticks,
+                    json(acc.effective_sigma()),
+                    json(acc.diffusion_sigma(dt_years)),
+                    json(acc.realized_sigma(dt_years))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sizes = self
+            .percentiles()
+            .iter()
+            .map(|(k, v)| format!("\"{}\": {}", k, v))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "{{\"seed\": {}, \"sim_seconds\": {}, \"orders\": {}, \"cancels\": {}, \"trades\": {}, \"messages\": {}, \"shocks\": {}, \"peak_msgs_per_sec\": {}, \"order_sizes\": {{{}}}, \"regimes\": {{{}}}}}\n",
+            seed,
+            sim_seconds,
+            self.orders,
+            self.cancels,
+            self.trades,
+            self.messages,
+            self.shocks,
+            self.peak_rate,
+            sizes,
+            regimes
+        )
+    }
+}
@@ -0,0 +1,64 @@
+//! Independent random number streams for the engine's subsystems.
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// One RNG per subsystem, each seeded from the run seed and its own salt.
+/// A subsystem that starts drawing more or fewer numbers (a new order
+/// field, another event) leaves every other stream untouched, so the price
+/// path and regime sequence of a seed survive changes to order generation.
+/// New streams take the next salt; changing an existing one changes the
+/// output of every seed.
+///
+/// Extra venues (`[[venues]]`) share the price, regime and shock streams
+/// with the primary venue (0) and mix their index into the rest, so every
+/// venue follows the same random walk with its own order flow.
+pub struct RngStreams {
+    /// Mid-price model, fundamental anchor and overnight gaps.
+    pub price: StdRng,
+    /// Regime transitions and how long each regime lasts.
+    pub regimes: StdRng,
+    /// Whether a shock fires, its size and direction.
+    pub shocks: StdRng,
+    /// Limit and market order flow, including injected orders and the per-tick shuffle.
+    pub orders: StdRng,
+    /// How many resting orders are cancelled and which.
+    pub cancels: StdRng,
+    /// Stop order arrivals and placement.
+    pub stops: StdRng,
+    /// The agent layer (`[agents]`).
+    pub agents: StdRng,
+    /// Mass-cancel purges, scenario cancel waves and quote stuffing.
+    pub events: StdRng,
+    /// Erroneous orders (`[rejects]`).
+    pub errors: StdRng,
+    /// Which market orders execute off-book (`[book] dark_fraction`).
+    pub dark: StdRng,
+    /// Systemic shocks across a `--symbols-file` universe (the first
+    /// symbol's stream decides for all).
+    pub systemic: StdRng,
+    /// Which leader market orders draw follow-on orders, and their sizes
+    /// (`[lead_lag]`).
+    pub lead_lag: StdRng,
+}
+
+impl RngStreams {
+    pub fn new(seed: u64, venue: u64) -> Self {
+        let stream = |salt: u64| StdRng::seed_from_u64(seed ^ salt);
+        let flow = |salt: u64| stream(salt ^ (venue << 32));
+        Self {
+            price: stream(0x5EED_0001),
+            regimes: stream(0x5EED_0002),
+            shocks: stream(0x5EED_0003),
+            orders: flow(0x5EED_0004),
+            cancels: flow(0x5EED_0005),
+            stops: flow(0x5EED_0006),
+            agents: flow(0x5EED_0007),
+            events: flow(0x5EED_0008),
+            errors: flow(0x5EED_0009),
+            dark: flow(0x5EED_000A),
+            systemic: stream(0x5EED_000B),
+            lead_lag: flow(0x5EED_000C),
+        }
+    }
+}
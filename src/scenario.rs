@@ -1,7 +1,8 @@
 use serde::Deserialize;
 use std::fmt;
 
-use crate::regime::Regime;
+use crate::config::AppConfig;
+use crate::regime::{Regime, RegimeParams, REGIME_TABLE};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -11,6 +12,11 @@ pub enum Scenario {
     Volatile,
     FlashCrash,
     Rally,
+    StopCascade,
+    NewsEvent,
+    Drought,
+    Squeeze,
+    Custom,
 }
 
 impl fmt::Display for Scenario {
@@ -21,50 +27,565 @@ impl fmt::Display for Scenario {
             Scenario::Volatile => write!(f, "volatile"),
             Scenario::FlashCrash => write!(f, "flash-crash"),
             Scenario::Rally => write!(f, "rally"),
+            Scenario::StopCascade => write!(f, "stop-cascade"),
+            Scenario::NewsEvent => write!(f, "news-event"),
+            Scenario::Drought => write!(f, "drought"),
+            Scenario::Squeeze => write!(f, "squeeze"),
+            Scenario::Custom => write!(f, "custom"),
         }
     }
 }
 
+/// One scenario of a chain (`--scenario normal:60,flash-crash:30` or
+/// `[simulation] scenario_chain`). Without a duration the scenario runs for the
+/// rest of the session.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScenarioStep {
+    pub scenario: Scenario,
+    pub duration: Option<f64>,
+}
+
+impl fmt::Display for ScenarioStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.duration {
+            Some(d) => write!(f, "{}:{}", self.scenario, d),
+            None => write!(f, "{}", self.scenario),
+        }
+    }
+}
+
+/// `[news]`: the scheduled announcement of the `news-event` scenario.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NewsConfig {
+    /// Sim time of the announcement.
+    pub at: f64,
+    /// Seconds before the announcement during which liquidity is withdrawn.
+    pub pre_window: f64,
+    /// Fraction of resting orders cancelled when the withdrawal starts.
+    pub cancel_fraction: f64,
+    /// Half-spread and order offset multiplier during the withdrawal.
+    pub spread_mult: f64,
+    /// Seconds after the announcement with elevated volatility and aggression.
+    pub window: f64,
+    pub sigma_mult: f64,
+    pub market_mult: f64,
+}
+
+impl Default for NewsConfig {
+    fn default() -> Self {
+        Self {
+            at: 20.0,
+            pre_window: 5.0,
+            cancel_fraction: 0.5,
+            spread_mult: 3.0,
+            window: 15.0,
+            sigma_mult: 4.0,
+            market_mult: 3.0,
+        }
+    }
+}
+
+/// `[drought]`: the liquidity drought of the `drought` scenario.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DroughtConfig {
+    /// Sim time the drought starts.
+    pub at: f64,
+    /// Seconds it lasts.
+    pub duration: f64,
+    /// Limit order rate multiplier during the drought.
+    pub limit_mult: f64,
+    /// Cancel rate multiplier during the drought.
+    pub cancel_mult: f64,
+}
+
+impl Default for DroughtConfig {
+    fn default() -> Self {
+        Self {
+            at: 10.0,
+            duration: 20.0,
+            limit_mult: 0.05,
+            cancel_mult: 5.0,
+        }
+    }
+}
+
+/// `[squeeze]`: the short squeeze of the `squeeze` scenario.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SqueezeConfig {
+    /// Sim time the squeeze starts with an up-shock and a forced RALLY.
+    pub at: f64,
+    /// Seconds the squeeze lasts. The RALLY is then left to the regime
+    /// transitions, and escalation, up-shocks and cover bursts stop.
+    pub duration: f64,
+    /// Buy probability added per second of squeeze, up to `max_buy_prob`.
+    pub buy_prob_ramp: f64,
+    pub max_buy_prob: f64,
+    /// Fractional growth of the market order size multiplier per second,
+    /// up to `max_size_mult`.
+    pub size_ramp: f64,
+    pub max_size_mult: f64,
+    /// Seconds between further up-shocks (0 = only the opening one).
+    pub shock_interval: f64,
+    /// Size of each up-shock as a fraction of mid.
+    pub shock_pct: f64,
+    /// Buy market orders sent as shorts cover after each upward shock or jump.
+    pub cover_orders: u64,
+}
+
+impl Default for SqueezeConfig {
+    fn default() -> Self {
+        Self {
+            at: 10.0,
+            duration: 20.0,
+            buy_prob_ramp: 0.002,
+            max_buy_prob: 0.97,
+            size_ramp: 0.05,
+            max_size_mult: 6.0,
+            shock_interval: 8.0,
+            shock_pct: 0.01,
+            cover_orders: 20,
+        }
+    }
+}
+
+impl SqueezeConfig {
+    /// `p` with buying pressure and market order size escalated for
+    /// `elapsed` seconds of squeeze.
+    pub fn escalate(&self, p: &RegimeParams, elapsed: f64) -> RegimeParams {
+        RegimeParams {
+            buy_prob: (p.buy_prob + self.buy_prob_ramp * elapsed).min(self.max_buy_prob),
+            size_mult: (p.size_mult * (1.0 + self.size_ramp * elapsed)).min(self.max_size_mult),
+            ..p.clone()
+        }
+    }
+}
+
+/// `[custom_scenario]`: a scenario defined in the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CustomScenarioConfig {
+    pub starting_regime: Regime,
+    pub allow_transitions: bool,
+    /// Minimum stop-order arrival rate.
+    pub stop_rate: f64,
+    /// Regime changes and price shocks at fixed times.
+    pub events: Vec<ScenarioEvent>,
+    /// Stretches of time with parameter multipliers and an optional cancel wave.
+    pub windows: Vec<Window>,
+    /// Replacement values for the built-in regime parameters.
+    pub params: RegimeOverrides,
+}
+
+impl Default for CustomScenarioConfig {
+    fn default() -> Self {
+        Self {
+            starting_regime: Regime::Calm,
+            allow_transitions: true,
+            stop_rate: 0.0,
+            events: Vec::new(),
+            windows: Vec::new(),
+            params: RegimeOverrides::default(),
+        }
+    }
+}
+
+/// A scheduled regime change and/or price shock.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScenarioEvent {
+    pub at: f64,
+    pub regime: Option<Regime>,
+    /// Fractional move of mid (negative = down).
+    #[serde(default)]
+    pub shock_pct: f64,
+}
+
+/// Regime parameters to replace; unset ones keep the built-in value.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ParamOverrides {
+    pub sigma: Option<f64>,
+    pub mu: Option<f64>,
+    pub limit_rate: Option<f64>,
+    pub market_rate: Option<f64>,
+    pub cancel_rate: Option<f64>,
+    pub buy_prob: Option<f64>,
+    pub half_spread: Option<f64>,
+    pub offset_lambda: Option<f64>,
+    pub size_mult: Option<f64>,
+}
+
+impl ParamOverrides {
+    /// Set one parameter by name; false if there is no such parameter.
+    pub fn set(&mut self, field: &str, value: f64) -> bool {
+        let slot = match field {
+            "sigma" => &mut self.sigma,
+            "mu" => &mut self.mu,
+            "limit_rate" => &mut self.limit_rate,
+            "market_rate" => &mut self.market_rate,
+            "cancel_rate" => &mut self.cancel_rate,
+            "buy_prob" => &mut self.buy_prob,
+            "half_spread" => &mut self.half_spread,
+            "offset_lambda" => &mut self.offset_lambda,
+            "size_mult" => &mut self.size_mult,
+            _ => return false,
+        };
+        *slot = Some(value);
+        true
+    }
+
+    pub fn apply(&self, p: &RegimeParams) -> RegimeParams {
+        RegimeParams {
+            sigma: self.sigma.unwrap_or(p.sigma),
+            mu: self.mu.unwrap_or(p.mu),
+            limit_rate: self.limit_rate.unwrap_or(p.limit_rate),
+            market_rate: self.market_rate.unwrap_or(p.market_rate),
+            cancel_rate: self.cancel_rate.unwrap_or(p.cancel_rate),
+            buy_prob: self.buy_prob.unwrap_or(p.buy_prob),
+            half_spread: self.half_spread.unwrap_or(p.half_spread),
+            offset_lambda: self.offset_lambda.unwrap_or(p.offset_lambda),
+            size_mult: self.size_mult.unwrap_or(p.size_mult),
+            ..p.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RegimeOverrides {
+    pub calm: ParamOverrides,
+    pub volatile: ParamOverrides,
+    pub crash: ParamOverrides,
+    pub rally: ParamOverrides,
+    pub recovery: ParamOverrides,
+}
+
+impl RegimeOverrides {
+    pub fn get(&self, regime: Regime) -> &ParamOverrides {
+        match regime {
+            Regime::Calm => &self.calm,
+            Regime::Volatile => &self.volatile,
+            Regime::Crash => &self.crash,
+            Regime::Rally => &self.rally,
+            Regime::Recovery => &self.recovery,
+        }
+    }
+
+    pub fn get_mut(&mut self, regime: Regime) -> &mut ParamOverrides {
+        match regime {
+            Regime::Calm => &mut self.calm,
+            Regime::Volatile => &mut self.volatile,
+            Regime::Crash => &mut self.crash,
+            Regime::Rally => &mut self.rally,
+            Regime::Recovery => &mut self.recovery,
+        }
+    }
+}
+
+/// Multipliers on the current regime's parameters. In `[custom_scenario]`
+/// windows, unset multipliers are 1.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Multipliers {
+    pub sigma: f64,
+    pub limit_rate: f64,
+    pub market_rate: f64,
+    pub cancel_rate: f64,
+    /// Applies to the half-spread and the mean limit order offset.
+    pub spread: f64,
+}
+
+impl Multipliers {
+    pub const NONE: Multipliers = Multipliers {
+        sigma: 1.0,
+        limit_rate: 1.0,
+        market_rate: 1.0,
+        cancel_rate: 1.0,
+        spread: 1.0,
+    };
+
+    fn combine(self, other: Multipliers) -> Multipliers {
+        Multipliers {
+            sigma: self.sigma * other.sigma,
+            limit_rate: self.limit_rate * other.limit_rate,
+            market_rate: self.market_rate * other.market_rate,
+            cancel_rate: self.cancel_rate * other.cancel_rate,
+            spread: self.spread * other.spread,
+        }
+    }
+
+    /// Whether any arrival rate is changed.
+    pub fn changes_rates(&self) -> bool {
+        self.limit_rate != 1.0 || self.market_rate != 1.0 || self.cancel_rate != 1.0
+    }
+
+    pub fn apply(&self, p: &RegimeParams) -> RegimeParams {
+        RegimeParams {
+            sigma: p.sigma * self.sigma,
+            limit_rate: p.limit_rate * self.limit_rate,
+            market_rate: p.market_rate * self.market_rate,
+            cancel_rate: p.cancel_rate * self.cancel_rate,
+            half_spread: p.half_spread * self.spread,
+            offset_lambda: p.offset_lambda / self.spread,
+            ..p.clone()
+        }
+    }
+}
+
+impl Default for Multipliers {
+    fn default() -> Self {
+        Multipliers::NONE
+    }
+}
+
+/// A stretch of sim time `[start, end)` during which `mult` applies.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Window {
+    #[serde(default = "default_window_label")]
+    pub label: String,
+    pub start: f64,
+    pub end: f64,
+    #[serde(flatten)]
+    pub mult: Multipliers,
+    /// Fraction of resting orders cancelled when the window opens.
+    #[serde(default)]
+    pub cancel_wave: f64,
+}
+
+fn default_window_label() -> String {
+    "window".to_string()
+}
+
+/// A window opening or closing, reported by `Timeline::poll`.
+pub enum TimelineEvent<'a> {
+    Open(&'a Window),
+    Close(&'a Window),
+}
+
+/// A scenario's scheduled windows and which of them are active.
+pub struct Timeline {
+    windows: Vec<Window>,
+    active: Vec<bool>,
+}
+
+impl Timeline {
+    pub fn new(windows: Vec<Window>) -> Self {
+        let active = vec![false; windows.len()];
+        Self { windows, active }
+    }
+
+    /// Windows that opened or closed by `time`, in schedule order.
+    pub fn poll(&mut self, time: f64) -> Vec<TimelineEvent<'_>> {
+        let mut events = Vec::new();
+        for (w, active) in self.windows.iter().zip(self.active.iter_mut()) {
+            let now = time >= w.start && time < w.end;
+            if now != *active {
+                *active = now;
+                events.push(if now {
+                    TimelineEvent::Open(w)
+                } else {
+                    TimelineEvent::Close(w)
+                });
+            }
+        }
+        events
+    }
+
+    /// Combined multipliers of the active windows.
+    pub fn multipliers(&self) -> Multipliers {
+        self.windows
+            .iter()
+            .zip(&self.active)
+            .filter(|(_, &active)| active)
+            .fold(Multipliers::NONE, |m, (w, _)| m.combine(w.mult))
+    }
+}
+
 pub struct ScenarioConfig {
     pub starting_regime: Regime,
     pub forced_event_time: f64,
     pub forced_regime: Regime,
     pub allow_transitions: bool,
+    /// Price shock applied at the forced event (fraction; negative = down).
+    pub forced_shock_pct: f64,
+    /// Minimum stop-order arrival rate the scenario relies on.
+    pub stop_rate: f64,
+    /// Scheduled parameter windows.
+    pub windows: Vec<Window>,
+    /// Scheduled regime changes and shocks, in time order.
+    pub events: Vec<ScenarioEvent>,
+    /// Parameters of each regime, indexed by `Regime::index`.
+    pub regime_params: [RegimeParams; 5],
 }
 
 impl ScenarioConfig {
-    pub fn from_scenario(scenario: Scenario) -> Self {
+    pub fn from_scenario(scenario: Scenario, cfg: &AppConfig) -> Self {
+        let (news, drought, squeeze) = (&cfg.news, &cfg.drought, &cfg.squeeze);
         match scenario {
             Scenario::Normal => Self {
                 starting_regime: Regime::Calm,
                 forced_event_time: -1.0,
                 forced_regime: Regime::Calm,
                 allow_transitions: true,
+                forced_shock_pct: 0.0,
+                stop_rate: 0.0,
+                windows: Vec::new(),
+                events: Vec::new(),
+                regime_params: REGIME_TABLE,
             },
             Scenario::Crash => Self {
                 starting_regime: Regime::Calm,
                 forced_event_time: 10.0,
                 forced_regime: Regime::Crash,
                 allow_transitions: true,
+                forced_shock_pct: 0.0,
+                stop_rate: 0.0,
+                windows: Vec::new(),
+                events: Vec::new(),
+                regime_params: REGIME_TABLE,
             },
             Scenario::Volatile => Self {
                 starting_regime: Regime::Volatile,
                 forced_event_time: -1.0,
                 forced_regime: Regime::Volatile,
                 allow_transitions: false,
+                forced_shock_pct: 0.0,
+                stop_rate: 0.0,
+                windows: Vec::new(),
+                events: Vec::new(),
+                regime_params: REGIME_TABLE,
             },
             Scenario::FlashCrash => Self {
                 starting_regime: Regime::Calm,
                 forced_event_time: 8.0,
                 forced_regime: Regime::Crash,
                 allow_transitions: true,
+                forced_shock_pct: 0.0,
+                stop_rate: 0.0,
+                windows: Vec::new(),
+                events: Vec::new(),
+                regime_params: REGIME_TABLE,
             },
             Scenario::Rally => Self {
                 starting_regime: Regime::Calm,
                 forced_event_time: 10.0,
                 forced_regime: Regime::Rally,
                 allow_transitions: true,
+                forced_shock_pct: 0.0,
+                stop_rate: 0.0,
+                windows: Vec::new(),
+                events: Vec::new(),
+                regime_params: REGIME_TABLE,
+            },
+            // Stops accumulate below the market for 15s, then a moderate
+            // down-shock sets off waves of stop-triggered market sells.
+            Scenario::StopCascade => Self {
+                starting_regime: Regime::Calm,
+                forced_event_time: 15.0,
+                forced_regime: Regime::Volatile,
+                allow_transitions: true,
+                forced_shock_pct: -0.025,
+                stop_rate: 20.0,
+                windows: Vec::new(),
+                events: Vec::new(),
+                regime_params: REGIME_TABLE,
+            },
+            // Market makers pull quotes and widen out ahead of a scheduled
+            // announcement, which then hits with a volatility spike and a
+            // burst of aggressive orders.
+            Scenario::NewsEvent => Self {
+                starting_regime: Regime::Calm,
+                forced_event_time: news.at,
+                forced_regime: Regime::Volatile,
+                allow_transitions: true,
+                forced_shock_pct: 0.0,
+                stop_rate: 0.0,
+                windows: vec![
+                    Window {
+                        label: "pre-announcement".to_string(),
+                        start: news.at - news.pre_window,
+                        end: news.at,
+                        mult: Multipliers {
+                            spread: news.spread_mult,
+                            ..Multipliers::NONE
+                        },
+                        cancel_wave: news.cancel_fraction,
+                    },
+                    Window {
+                        label: "announcement".to_string(),
+                        start: news.at,
+                        end: news.at + news.window,
+                        mult: Multipliers {
+                            sigma: news.sigma_mult,
+                            market_rate: news.market_mult,
+                            ..Multipliers::NONE
+                        },
+                        cancel_wave: 0.0,
+                    },
+                ],
+                events: Vec::new(),
+                regime_params: REGIME_TABLE,
+            },
+            // Liquidity providers stop quoting and pull what they have while
+            // market orders keep arriving, so the book thins out.
+            Scenario::Drought => Self {
+                starting_regime: Regime::Calm,
+                forced_event_time: -1.0,
+                forced_regime: Regime::Calm,
+                allow_transitions: true,
+                forced_shock_pct: 0.0,
+                stop_rate: 0.0,
+                windows: vec![Window {
+                    label: "drought".to_string(),
+                    start: drought.at,
+                    end: drought.at + drought.duration,
+                    mult: Multipliers {
+                        limit_rate: drought.limit_mult,
+                        cancel_rate: drought.cancel_mult,
+                        ..Multipliers::NONE
+                    },
+                    cancel_wave: 0.0,
+                }],
+                events: Vec::new(),
+                regime_params: REGIME_TABLE,
+            },
+            // An up-shock tips the market into a RALLY held for the squeeze's
+            // duration: shorts are squeezed as buying keeps escalating (see
+            // `SqueezeConfig`), then the regime moves on as usual.
+            Scenario::Squeeze => Self {
+                starting_regime: Regime::Calm,
+                forced_event_time: squeeze.at,
+                forced_regime: Regime::Rally,
+                allow_transitions: true,
+                forced_shock_pct: squeeze.shock_pct,
+                stop_rate: 0.0,
+                windows: Vec::new(),
+                events: Vec::new(),
+                regime_params: REGIME_TABLE,
             },
+            Scenario::Custom => {
+                let c = &cfg.custom_scenario;
+                let mut events = c.events.clone();
+                events.sort_by(|a, b| a.at.total_cmp(&b.at));
+                Self {
+                    starting_regime: c.starting_regime,
+                    forced_event_time: -1.0,
+                    forced_regime: c.starting_regime,
+                    allow_transitions: c.allow_transitions,
+                    forced_shock_pct: 0.0,
+                    stop_rate: c.stop_rate,
+                    windows: c.windows.clone(),
+                    events,
+                    regime_params: Regime::ALL
+                        .map(|r| c.params.get(r).apply(&REGIME_TABLE[r.index()])),
+                }
+            }
         }
     }
+
+    pub fn params(&self, regime: Regime) -> &RegimeParams {
+        &self.regime_params[regime.index()]
+    }
 }
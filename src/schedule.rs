@@ -0,0 +1,200 @@
+//! Fair interleaving of many symbols' messages on one feed (`--symbols-file`).
+
+/// Weighted round-robin over one lane of queued messages per symbol, so a
+/// hot symbol can't take a tick's send slots from the others.
+///
+/// Lanes take turns by deficit round-robin: each turn a lane earns
+/// `weight / min(weight)` sends and spends them while it has messages. A
+/// lane with a rate cap also spends an allowance that refills with sim time
+/// at `max_rate` per second; what it can't send waits, in order, for a
+/// later tick.
+#[derive(Debug)]
+pub struct FairScheduler {
+    lanes: Vec<Lane>,
+    /// Sim time the allowances were last refilled at.
+    time: Option<f64>,
+}
+
+#[derive(Debug)]
+struct Lane {
+    quantum: f64,
+    max_rate: Option<f64>,
+    deficit: f64,
+    allowance: f64,
+    queue: Queue,
+}
+
+impl FairScheduler {
+    /// One lane per `(weight, max_rate)`, in instrument order.
+    pub fn new(lanes: impl IntoIterator<Item = (f64, Option<f64>)>) -> Self {
+        let lanes: Vec<_> = lanes.into_iter().collect();
+        let min_weight = lanes.iter().map(|l| l.0).fold(f64::INFINITY, f64::min);
+        let lanes = lanes
+            .into_iter()
+            .map(|(weight, max_rate)| Lane {
+                quantum: weight / min_weight,
+                max_rate,
+                deficit: 0.0,
+                allowance: 1.0,
+                queue: Queue::default(),
+            })
+            .collect();
+        Self { lanes, time: None }
+    }
+
+    /// Queue `msg` on `lane`'s symbol.
+    pub fn push(&mut self, lane: usize, msg: &[u8]) {
+        self.lanes[lane].queue.push(msg);
+    }
+
+    /// Refill the capped lanes' allowances for the sim time since the last
+    /// call. Beyond the refill a lane keeps at most one message's worth, so
+    /// a quiet spell doesn't bank a burst.
+    pub fn advance_to(&mut self, t: f64) {
+        let dt = self.time.map_or(0.0, |last| (t - last).max(0.0));
+        self.time = Some(t);
+        for lane in &mut self.lanes {
+            if let Some(rate) = lane.max_rate {
+                lane.allowance = (lane.allowance + rate * dt).min(rate * dt + 1.0);
+            }
+        }
+    }
+
+    /// Stop capping, so the next `drain` sends everything still queued.
+    pub fn lift_caps(&mut self) {
+        for lane in &mut self.lanes {
+            lane.max_rate = None;
+        }
+    }
+
+    /// Hand every message the caps allow to `send`, interleaved by weight.
+    pub fn drain(&mut self, mut send: impl FnMut(&[u8])) {
+        loop {
+            let mut sent = false;
+            for lane in &mut self.lanes {
+                if !lane.ready() {
+                    // An idle lane doesn't bank turns
+                    lane.deficit = 0.0;
+                    continue;
+                }
+                lane.deficit += lane.quantum;
+                while lane.deficit >= 1.0 && lane.ready() {
+                    send(lane.queue.pop());
+                    lane.deficit -= 1.0;
+                    if lane.max_rate.is_some() {
+                        lane.allowance -= 1.0;
+                    }
+                    sent = true;
+                }
+            }
+            if !sent {
+                break;
+            }
+        }
+        for lane in &mut self.lanes {
+            lane.queue.compact();
+        }
+    }
+}
+
+impl Lane {
+    fn ready(&self) -> bool {
+        !self.queue.is_empty() && (self.max_rate.is_none() || self.allowance >= 1.0)
+    }
+}
+
+/// Messages back to back in one buffer, taken from the front.
+#[derive(Debug, Default)]
+struct Queue {
+    bytes: Vec<u8>,
+    /// End offset of each message in `bytes`.
+    ends: Vec<usize>,
+    /// Index of the first message not yet taken.
+    head: usize,
+}
+
+impl Queue {
+    fn push(&mut self, msg: &[u8]) {
+        self.bytes.extend_from_slice(msg);
+        self.ends.push(self.bytes.len());
+    }
+
+    fn len(&self) -> usize {
+        self.ends.len() - self.head
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn pop(&mut self) -> &[u8] {
+        let start = match self.head {
+            0 => 0,
+            h => self.ends[h - 1],
+        };
+        let end = self.ends[self.head];
+        self.head += 1;
+        &self.bytes[start..end]
+    }
+
+    /// Drop the messages taken, keeping the rest (and the buffers).
+    fn compact(&mut self) {
+        if self.head == 0 {
+            return;
+        }
+        let taken = self.ends[self.head - 1];
+        self.bytes.drain(..taken);
+        self.ends.drain(..self.head);
+        for end in &mut self.ends {
+            *end -= taken;
+        }
+        self.head = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drained(s: &mut FairScheduler) -> Vec<u8> {
+        let mut out = Vec::new();
+        s.drain(|m| out.push(m[0]));
+        out
+    }
+
+    #[test]
+    fn lanes_interleave_by_weight() {
+        let mut s = FairScheduler::new([(1.0, None), (2.0, None), (1.0, None)]);
+        for _ in 0..6 {
+            s.push(0, b"a");
+        }
+        s.push(1, b"b");
+        s.push(1, b"b");
+        s.push(1, b"b");
+        s.push(2, b"c");
+        assert_eq!(drained(&mut s), b"abbcabaaaa");
+        assert!(s.lanes.iter().all(|l| l.queue.is_empty()));
+    }
+
+    #[test]
+    fn capped_lanes_wait_for_their_allowance() {
+        let mut s = FairScheduler::new([(1.0, None), (1.0, Some(64.0))]);
+        s.advance_to(0.0);
+        for _ in 0..250 {
+            s.push(1, b"x");
+        }
+        s.push(1, b"y");
+        s.push(0, b"a");
+        // One message to start with, then 64 per second of sim time
+        assert_eq!(drained(&mut s), b"ax");
+        s.advance_to(0.015625);
+        assert_eq!(drained(&mut s), b"x");
+        s.advance_to(1.015625);
+        assert_eq!(drained(&mut s).len(), 64);
+        assert_eq!(s.lanes[1].queue.len(), 185);
+
+        s.lift_caps();
+        let rest = drained(&mut s);
+        assert_eq!((rest.len(), rest.last()), (185, Some(&b'y')));
+    }
+}
@@ -0,0 +1,64 @@
+use serde::Deserialize;
+
+/// `[seasonality]`: time-of-day activity curve. Sim time is mapped onto a
+/// trading day of `session_length` seconds (repeating), and the curve gives a
+/// multiplier for the limit, market and cancel rates and the regime sigma at
+/// each point of the day.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SeasonalityConfig {
+    pub enabled: bool,
+    /// Seconds of sim time per trading day.
+    pub session_length: f64,
+    /// `[fraction of day, multiplier]` points, fractions ascending in `0..=1`.
+    /// Linear in between, flat before the first and after the last point.
+    pub curve: Vec<(f64, f64)>,
+}
+
+impl Default for SeasonalityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            session_length: 23_400.0,
+            curve: vec![
+                (0.0, 2.0),
+                (0.1, 1.2),
+                (0.3, 0.8),
+                (0.5, 0.6),
+                (0.7, 0.8),
+                (0.9, 1.2),
+                (1.0, 2.0),
+            ],
+        }
+    }
+}
+
+pub struct Seasonality {
+    session_length: f64,
+    curve: Vec<(f64, f64)>,
+}
+
+impl Seasonality {
+    pub fn new(cfg: &SeasonalityConfig) -> Self {
+        Self {
+            session_length: cfg.session_length,
+            curve: cfg.curve.clone(),
+        }
+    }
+
+    /// Multiplier at sim time `t`, rounded to 1% so the per-regime rate
+    /// distributions only need rebuilding when it actually moves.
+    pub fn multiplier(&self, t: f64) -> f64 {
+        let x = (t % self.session_length) / self.session_length;
+        let m = match self.curve.iter().position(|&(px, _)| px > x) {
+            Some(0) => self.curve[0].1,
+            Some(i) => {
+                let (x0, m0) = self.curve[i - 1];
+                let (x1, m1) = self.curve[i];
+                m0 + (m1 - m0) * (x - x0) / (x1 - x0)
+            }
+            None => self.curve[self.curve.len() - 1].1,
+        };
+        (m * 100.0).round() / 100.0
+    }
+}
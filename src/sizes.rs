@@ -0,0 +1,76 @@
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use rand_distr::{LogNormal, Pareto};
+use serde::Deserialize;
+use std::fmt;
+
+/// Shape of generated order sizes (`[orders] size_distribution`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SizeDistributionKind {
+    /// Lognormal with `size_mean_log` / `size_std_log`.
+    Lognormal,
+    /// Power law: `pareto_scale` minimum with tail exponent `pareto_shape`.
+    Pareto,
+    /// Weighted pick from the `round_lots` table.
+    Lots,
+}
+
+impl fmt::Display for SizeDistributionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SizeDistributionKind::Lognormal => write!(f, "lognormal"),
+            SizeDistributionKind::Pareto => write!(f, "pareto"),
+            SizeDistributionKind::Lots => write!(f, "lots"),
+        }
+    }
+}
+
+/// Order size sampler shared by the statistical flow, stops and agents.
+pub enum SizeDist {
+    LogNormal(LogNormal<f64>),
+    Pareto(Pareto<f64>),
+    Lots {
+        sizes: Vec<u32>,
+        weights: WeightedIndex<f64>,
+    },
+}
+
+impl SizeDist {
+    pub fn new(
+        kind: SizeDistributionKind,
+        mean_log: f64,
+        std_log: f64,
+        pareto_scale: f64,
+        pareto_shape: f64,
+        lots: &[(u32, f64)],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(match kind {
+            SizeDistributionKind::Lognormal => {
+                SizeDist::LogNormal(LogNormal::new(mean_log, std_log)?)
+            }
+            SizeDistributionKind::Pareto => SizeDist::Pareto(
+                Pareto::new(pareto_scale, pareto_shape)
+                    .map_err(|e| format!("invalid pareto size distribution: {}", e))?,
+            ),
+            SizeDistributionKind::Lots => SizeDist::Lots {
+                sizes: lots.iter().map(|&(size, _)| size).collect(),
+                weights: WeightedIndex::new(lots.iter().map(|&(_, w)| w))
+                    .map_err(|e| format!("invalid round lot weights: {}", e))?,
+            },
+        })
+    }
+
+    /// One order size, scaled by `scale` (e.g. the regime's size multiplier)
+    /// and at least 1. Round lots are scaled by a whole number of lots so
+    /// sizes stay on the lot grid.
+    pub fn sample(&self, scale: f64, rng: &mut impl Rng) -> u32 {
+        match self {
+            SizeDist::LogNormal(d) => ((d.sample(rng) * scale).round() as u32).max(1),
+            SizeDist::Pareto(d) => ((d.sample(rng) * scale).round() as u32).max(1),
+            SizeDist::Lots { sizes, weights } => {
+                sizes[weights.sample(rng)].saturating_mul((scale.round() as u32).max(1))
+            }
+        }
+    }
+}
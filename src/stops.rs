@@ -0,0 +1,98 @@
+use serde::Deserialize;
+
+use crate::order::{Order, Side};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StopConfig {
+    /// Stop orders per second (0 disables them unless the scenario needs them).
+    pub rate: f64,
+    /// Trigger distance from mid, as a fraction of price.
+    pub min_distance: f64,
+    pub max_distance: f64,
+    /// Fraction of stops that are sell stops (below the market).
+    pub sell_fraction: f64,
+    /// Fractional mid move per unit of size when a triggered stop executes.
+    pub impact_per_unit: f64,
+    /// Most stops converted to market orders per tick; the rest wait for the
+    /// next tick, so a cascade unfolds in waves.
+    pub max_triggers_per_tick: usize,
+    /// Seconds before an untriggered stop is cancelled; 0 keeps it until it
+    /// triggers.
+    pub ttl: f64,
+}
+
+impl Default for StopConfig {
+    fn default() -> Self {
+        Self {
+            rate: 0.0,
+            min_distance: 0.005,
+            max_distance: 0.05,
+            sell_fraction: 0.8,
+            impact_per_unit: 0.00001,
+            max_triggers_per_tick: 10,
+            ttl: 120.0,
+        }
+    }
+}
+
+/// Resting stop orders, invisible to the limit book until triggered.
+/// Sell stops trigger when mid falls to their price, buy stops when it rises to it.
+#[derive(Default)]
+pub struct StopBook {
+    orders: Vec<Order>,
+    /// Reused by `trigger` and `expire`, so polling every tick doesn't allocate.
+    fired: Vec<Order>,
+    expired: Vec<u64>,
+}
+
+impl StopBook {
+    pub fn insert(&mut self, order: Order) {
+        self.orders.push(order);
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn orders(&self) -> &[Order] {
+        &self.orders
+    }
+
+    /// Remove and return up to `max` stops whose trigger price `mid` has
+    /// crossed, those crossed first (nearest the old price) first.
+    pub fn trigger(&mut self, mid: f64, max: usize) -> &[Order] {
+        self.fired.clear();
+        self.fired
+            .extend(self.orders.extract_if(.., |o| match o.side {
+                Side::Sell => mid <= o.price,
+                Side::Buy => mid >= o.price,
+            }));
+        self.fired
+            .sort_by(|a, b| (b.price - mid).abs().total_cmp(&(a.price - mid).abs()));
+        if self.fired.len() > max {
+            self.orders.extend(self.fired.drain(max..));
+        }
+        &self.fired
+    }
+
+    /// Remove every stop, returning their IDs.
+    pub fn drain(&mut self) -> Vec<u64> {
+        self.orders.drain(..).map(|o| o.id).collect()
+    }
+
+    /// Remove and return the IDs of stops older than their TTL. A TTL of 0
+    /// keeps a stop until it triggers, as it does for limit orders.
+    pub fn expire(&mut self, now: f64) -> &[u64] {
+        let expired = &mut self.expired;
+        expired.clear();
+        self.orders.retain(|o| {
+            let keep = o.ttl <= 0.0 || now - o.created_at < o.ttl;
+            if !keep {
+                expired.push(o.id);
+            }
+            keep
+        });
+        expired
+    }
+}
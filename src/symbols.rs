@@ -0,0 +1,86 @@
+//! A universe of instruments loaded from `--symbols-file`.
+
+use std::sync::atomic::AtomicBool;
+
+use crate::config::{AppConfig, Cli, OutputMode};
+use crate::engine;
+
+/// Run one generator per symbol in step, on `cfg.threads` threads, all
+/// publishing on `cfg`'s feed. `cfg` publishes the first symbol.
+///
+/// Every symbol follows its own price path from a seed derived from the
+/// primary's, starting at its `initial_price` with rates scaled by its
+/// `adv_weight`, and tags every message it publishes with its directory
+/// index. The feed interleaves the symbols by `adv_weight`, holding each to
+/// its `max_rate`. The first symbol announces the whole directory. Only the first
+/// symbol logs, serves the control API, crypto feed and OUCH echo, and
+/// writes exports and the run report. The others end with it.
+pub fn run(
+    cfg: &AppConfig,
+    cli: &Cli,
+    running: &AtomicBool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let configs: Vec<_> = (1..cfg.universe.len())
+        .map(|index| symbol_config(cfg, index))
+        .collect();
+    engine::simulate_universe(cfg, &configs, cli, running, None)
+}
+
+/// The config of the `index`th symbol, published on `primary`'s feed.
+pub(crate) fn symbol_config(primary: &AppConfig, index: usize) -> AppConfig {
+    let mut cfg = primary.symbol(index);
+    cfg.output_mode = OutputMode::Quiet;
+    cfg.control_enabled = false;
+    cfg.report_path = None;
+    cfg.csv_path = None;
+    cfg.pcap_path = None;
+    cfg.parquet_path = None;
+    cfg.crypto_feed = None;
+    cfg.ouch = None;
+    cfg.max_messages = None;
+    cfg.stop_at_price = None;
+    cfg.corrupt_prob = 0.0;
+    cfg.pin_cores.clear();
+    cfg.rt_priority = None;
+    cfg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn symbols_scale_by_price_and_weight_on_their_own_seeds() {
+        let path = std::env::temp_dir().join(format!("universe-{}.csv", std::process::id()));
+        std::fs::write(&path, "AAA,100,0.01,1\nBBB,50,0.05,3\nCCC,400,0.1,2\n").unwrap();
+        let cli = Cli::parse_from([
+            "orderflow-rs",
+            "--seed",
+            "7",
+            "--throughput-scale",
+            "4",
+            "--symbols-file",
+            path.to_str().unwrap(),
+        ]);
+        let base = AppConfig::resolve(&Cli::parse_from(["orderflow-rs"])).unwrap();
+        let cfg = AppConfig::resolve(&cli).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Rates are configured for the mean weight, 2
+        assert_eq!(cfg.instruments.len(), 3);
+        assert_eq!((cfg.seed, cfg.instrument_index), (7, 0));
+        assert!((cfg.throughput_scale - 2.0).abs() < 1e-12);
+        assert!((cfg.ou.volatility - base.ou.volatility).abs() < 1e-12);
+
+        let c = symbol_config(&cfg, 2);
+        assert_eq!((c.seed, c.instrument_index), (9, 2));
+        assert_eq!((c.initial_price, c.tick_size), (400.0, 0.1));
+        assert!((c.throughput_scale - 4.0).abs() < 1e-12);
+        assert!((c.ou.volatility - 4.0 * base.ou.volatility).abs() < 1e-12);
+        assert!(!c.control_enabled && c.report_path.is_none());
+        let b = symbol_config(&cfg, 1);
+        assert_eq!(b.seed, 8);
+        assert!((b.throughput_scale - 6.0).abs() < 1e-12);
+    }
+}
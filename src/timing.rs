@@ -0,0 +1,82 @@
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Longest duration tracked; anything above is clamped.
+const MAX_TRACKED_NS: u64 = 60_000_000_000;
+
+/// Nanosecond-resolution HDR histogram of wall-clock durations.
+pub struct LatencyHistogram {
+    hist: Histogram<u64>,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            hist: Histogram::new_with_bounds(1, MAX_TRACKED_NS, 3)
+                .expect("static histogram bounds are valid"),
+        }
+    }
+
+    pub fn record(&mut self, d: Duration) {
+        let ns = (d.as_nanos() as u64).clamp(1, MAX_TRACKED_NS);
+        self.hist.saturating_record(ns);
+    }
+
+    pub fn len(&self) -> u64 {
+        self.hist.len()
+    }
+
+    /// Number of samples longer than `d`.
+    pub fn count_above(&self, d: Duration) -> u64 {
+        let ns = d.as_nanos() as u64;
+        self.hist
+            .iter_recorded()
+            .filter(|v| v.value_iterated_to() > ns)
+            .map(|v| v.count_at_value())
+            .sum()
+    }
+
+    /// p50 / p99 / p999 in microseconds, or `None` with no samples.
+    pub fn percentiles_us(&self) -> Option<[f64; 3]> {
+        if self.hist.is_empty() {
+            return None;
+        }
+        Some([0.50, 0.99, 0.999].map(|q| self.hist.value_at_quantile(q) as f64 / 1_000.0))
+    }
+
+    pub fn reset(&mut self) {
+        self.hist.reset();
+    }
+}
+
+/// Records the gap between successive calls to `mark`.
+pub struct GapTracker {
+    last: Option<Instant>,
+    pub gaps: LatencyHistogram,
+}
+
+impl GapTracker {
+    pub fn new() -> Self {
+        Self {
+            last: None,
+            gaps: LatencyHistogram::new(),
+        }
+    }
+
+    pub fn mark(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last {
+            self.gaps.record(now - last);
+        }
+        self.last = Some(now);
+    }
+}
+
+/// Short `p50/p99/p999` rendering for summaries, formatted in place.
+pub fn format_percentiles(p: Option<[f64; 3]>) -> impl fmt::Display {
+    fmt::from_fn(move |f| match p {
+        Some([p50, p99, p999]) => write!(f, "{:.0}/{:.0}/{:.0}", p50, p99, p999),
+        None => f.write_str("-"),
+    })
+}
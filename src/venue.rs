@@ -0,0 +1,404 @@
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::wire::{
+    self, Price, WireMessage, MSG_ADMIN, MSG_REFERENCE_PRICE, MSG_SESSION_END, MSG_SESSION_START,
+    MSG_SNAPSHOT_END, MSG_SNAPSHOT_START, MSG_VENUE_STATE,
+};
+
+/// Venue trading state, published as HALT / AUCTION / RESUME messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradingState {
+    /// Continuous trading (announced with RESUME).
+    Open,
+    Halted,
+    Auction,
+    /// Session over after the closing auction.
+    Closed,
+}
+
+impl fmt::Display for TradingState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TradingState::Open => write!(f, "RESUME"),
+            TradingState::Halted => write!(f, "HALT"),
+            TradingState::Auction => write!(f, "AUCTION"),
+            TradingState::Closed => write!(f, "CLOSE"),
+        }
+    }
+}
+
+/// Venue state change.
+#[derive(Debug, Clone)]
+pub struct VenueState {
+    pub state: TradingState,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl WireMessage for VenueState {
+    fn to_wire_text(&self) -> String {
+        let mut msg = format!("{}|time={:.3}", self.state, self.time);
+        let _ = wire::write_instrument(&mut msg, self.instrument);
+        msg
+    }
+
+    /// Binary venue state (v2), little-endian:
+    /// header msg_type=9, state:u8 (1 halt, 2 resume, 3 auction, 4 close), time:f64,
+    /// instrument:u16
+    fn to_wire_binary(&self) -> Vec<u8> {
+        let mut out = wire::binary_frame(MSG_VENUE_STATE, 1 + 8 + 2);
+        out.push(match self.state {
+            TradingState::Halted => 1,
+            TradingState::Open => 2,
+            TradingState::Auction => 3,
+            TradingState::Closed => 4,
+        });
+        out.extend_from_slice(&self.time.to_le_bytes());
+        out.extend_from_slice(&self.instrument.to_le_bytes());
+        out
+    }
+}
+
+/// First message of a run: instrument reference data consumers need to
+/// interpret the stream. Consumers should reset any state on receipt.
+#[derive(Debug, Clone)]
+pub struct SessionStart {
+    pub tick_size: f64,
+    pub lot_size: u32,
+    pub initial_price: f64,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl WireMessage for SessionStart {
+    fn to_wire_text(&self) -> String {
+        let mut msg = format!(
+            "SESSION_START|tick_size={}|lot_size={}|initial_price={}|time={:.3}",
+            self.tick_size,
+            self.lot_size,
+            Price(self.initial_price),
+            self.time
+        );
+        let _ = wire::write_instrument(&mut msg, self.instrument);
+        msg
+    }
+
+    /// Binary session start (v2), little-endian:
+    /// header msg_type=13, tick_size:f64, lot_size:u32, initial_price:f64, time:f64,
+    /// instrument:u16
+    fn to_wire_binary(&self) -> Vec<u8> {
+        let mut out = wire::binary_frame(MSG_SESSION_START, 8 + 4 + 8 + 8 + 2);
+        out.extend_from_slice(&self.tick_size.to_le_bytes());
+        out.extend_from_slice(&self.lot_size.to_le_bytes());
+        out.extend_from_slice(&self.initial_price.to_le_bytes());
+        out.extend_from_slice(&self.time.to_le_bytes());
+        out.extend_from_slice(&self.instrument.to_le_bytes());
+        out
+    }
+}
+
+/// Last message of a run. `messages` counts everything sent before it.
+#[derive(Debug, Clone)]
+pub struct SessionEnd {
+    pub messages: u64,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl WireMessage for SessionEnd {
+    fn to_wire_text(&self) -> String {
+        let mut msg = format!(
+            "SESSION_END|messages={}|time={:.3}",
+            self.messages, self.time
+        );
+        let _ = wire::write_instrument(&mut msg, self.instrument);
+        msg
+    }
+
+    /// Binary session end (v2), little-endian:
+    /// header msg_type=14, messages:u64, time:f64, instrument:u16
+    fn to_wire_binary(&self) -> Vec<u8> {
+        let mut out = wire::binary_frame(MSG_SESSION_END, 8 + 8 + 2);
+        out.extend_from_slice(&self.messages.to_le_bytes());
+        out.extend_from_slice(&self.time.to_le_bytes());
+        out.extend_from_slice(&self.instrument.to_le_bytes());
+        out
+    }
+}
+
+/// Opens the book snapshot published after a warmup: the next `orders`
+/// messages are ORDERs for every order resting at the start of the session,
+/// LEVELs for every price level with `book_view = "mbp"`, or both.
+#[derive(Debug, Clone)]
+pub struct SnapshotStart {
+    pub orders: u64,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl WireMessage for SnapshotStart {
+    fn to_wire_text(&self) -> String {
+        let mut msg = format!(
+            "SNAPSHOT_START|orders={}|time={:.3}",
+            self.orders, self.time
+        );
+        let _ = wire::write_instrument(&mut msg, self.instrument);
+        msg
+    }
+
+    /// Binary snapshot start (v2), little-endian:
+    /// header msg_type=17, orders:u64, time:f64, instrument:u16
+    fn to_wire_binary(&self) -> Vec<u8> {
+        let mut out = wire::binary_frame(MSG_SNAPSHOT_START, 8 + 8 + 2);
+        out.extend_from_slice(&self.orders.to_le_bytes());
+        out.extend_from_slice(&self.time.to_le_bytes());
+        out.extend_from_slice(&self.instrument.to_le_bytes());
+        out
+    }
+}
+
+/// Closes the book snapshot; live order flow follows.
+#[derive(Debug, Clone)]
+pub struct SnapshotEnd {
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl WireMessage for SnapshotEnd {
+    fn to_wire_text(&self) -> String {
+        let mut msg = format!("SNAPSHOT_END|time={:.3}", self.time);
+        let _ = wire::write_instrument(&mut msg, self.instrument);
+        msg
+    }
+
+    /// Binary snapshot end (v2), little-endian:
+    /// header msg_type=18, time:f64, instrument:u16
+    fn to_wire_binary(&self) -> Vec<u8> {
+        let mut out = wire::binary_frame(MSG_SNAPSHOT_END, 8 + 2);
+        out.extend_from_slice(&self.time.to_le_bytes());
+        out.extend_from_slice(&self.instrument.to_le_bytes());
+        out
+    }
+}
+
+/// Published at each day's open in a multi-day run, before the venue state:
+/// the previous close and the price trading reopens around after the
+/// overnight gap.
+#[derive(Debug, Clone)]
+pub struct ReferencePrice {
+    pub price: f64,
+    pub prev_close: f64,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl WireMessage for ReferencePrice {
+    fn to_wire_text(&self) -> String {
+        let mut msg = format!(
+            "REFERENCE_PRICE|price={}|prev_close={}|time={:.3}",
+            Price(self.price),
+            Price(self.prev_close),
+            self.time
+        );
+        let _ = wire::write_instrument(&mut msg, self.instrument);
+        msg
+    }
+
+    /// Binary reference price (v2), little-endian:
+    /// header msg_type=16, price:f64, prev_close:f64, time:f64, instrument:u16
+    fn to_wire_binary(&self) -> Vec<u8> {
+        let mut out = wire::binary_frame(MSG_REFERENCE_PRICE, 8 + 8 + 8 + 2);
+        out.extend_from_slice(&self.price.to_le_bytes());
+        out.extend_from_slice(&self.prev_close.to_le_bytes());
+        out.extend_from_slice(&self.time.to_le_bytes());
+        out.extend_from_slice(&self.instrument.to_le_bytes());
+        out
+    }
+}
+
+/// How much an ADMIN message matters to consumers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Alert,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "INFO"),
+            Severity::Warning => write!(f, "WARNING"),
+            Severity::Alert => write!(f, "ALERT"),
+        }
+    }
+}
+
+/// ADMIN `code`s: what the message is about.
+pub const ADMIN_SCENARIO: u16 = 1;
+pub const ADMIN_THROUGHPUT: u16 = 2;
+pub const ADMIN_QUOTE_STUFFING: u16 = 3;
+pub const ADMIN_CORRUPTION: u16 = 4;
+
+/// Feed-side context for consumers to log alongside the market data:
+/// scenario switches, throughput changes and impairments switching on.
+#[derive(Debug, Clone)]
+pub struct Admin {
+    pub severity: Severity,
+    pub code: u16,
+    /// At most 255 bytes, without `|` or control characters.
+    pub text: String,
+    pub instrument: u16,
+    pub time: f64,
+}
+
+impl Admin {
+    /// `text` has `|` and control characters replaced by spaces and is cut
+    /// to 255 bytes, so it fits either format.
+    pub fn new(severity: Severity, code: u16, text: &str, instrument: u16, time: f64) -> Self {
+        let mut text: String = text
+            .chars()
+            .map(|c| if c == '|' || c.is_control() { ' ' } else { c })
+            .collect();
+        let mut len = text.len().min(u8::MAX as usize);
+        while !text.is_char_boundary(len) {
+            len -= 1;
+        }
+        text.truncate(len);
+        Self {
+            severity,
+            code,
+            text,
+            instrument,
+            time,
+        }
+    }
+}
+
+impl WireMessage for Admin {
+    fn to_wire_text(&self) -> String {
+        let mut msg = format!(
+            "ADMIN|severity={}|code={}|text={}|time={:.3}",
+            self.severity, self.code, self.text, self.time
+        );
+        let _ = wire::write_instrument(&mut msg, self.instrument);
+        msg
+    }
+
+    /// Binary admin message (v2), little-endian:
+    /// header msg_type=20, severity:u8 (1 info, 2 warning, 3 alert), code:u16,
+    /// time:f64, instrument:u16, text_len:u8, text:[u8; text_len] (UTF-8)
+    fn to_wire_binary(&self) -> Vec<u8> {
+        let text = &self.text.as_bytes()[..self.text.len().min(u8::MAX as usize)];
+        let mut out = wire::binary_frame(MSG_ADMIN, 1 + 2 + 8 + 2 + 1 + text.len());
+        out.push(match self.severity {
+            Severity::Info => 1,
+            Severity::Warning => 2,
+            Severity::Alert => 3,
+        });
+        out.extend_from_slice(&self.code.to_le_bytes());
+        out.extend_from_slice(&self.time.to_le_bytes());
+        out.extend_from_slice(&self.instrument.to_le_bytes());
+        out.push(text.len() as u8);
+        out.extend_from_slice(text);
+        out
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    pub enabled: bool,
+    /// Mid move (fraction of price) within `window` that triggers a halt.
+    pub move_pct: f64,
+    /// Look-back window in seconds.
+    pub window: f64,
+    /// Seconds trading stays halted.
+    pub halt_duration: f64,
+    /// Seconds of reopening auction between the halt and RESUME (0 = none).
+    pub reopen_auction: f64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            move_pct: 0.05,
+            window: 5.0,
+            halt_duration: 10.0,
+            reopen_auction: 0.0,
+        }
+    }
+}
+
+/// Halts trading when mid moves more than `move_pct` from any price seen in
+/// the last `window` seconds, then reopens (optionally via an auction).
+pub struct CircuitBreaker {
+    cfg: CircuitBreakerConfig,
+    state: TradingState,
+    /// When the current halt or auction ends.
+    until: f64,
+    /// (time, mid) samples within the window, oldest first.
+    history: VecDeque<(f64, f64)>,
+}
+
+impl CircuitBreaker {
+    pub fn new(cfg: &CircuitBreakerConfig) -> Self {
+        Self {
+            cfg: cfg.clone(),
+            state: TradingState::Open,
+            until: 0.0,
+            history: VecDeque::new(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state == TradingState::Open
+    }
+
+    /// Feed the current mid. Returns the new state when it changes.
+    pub fn update(&mut self, now: f64, mid: f64) -> Option<TradingState> {
+        if !self.cfg.enabled {
+            return None;
+        }
+        match self.state {
+            TradingState::Open => {
+                while self
+                    .history
+                    .front()
+                    .is_some_and(|&(t, _)| now - t > self.cfg.window)
+                {
+                    self.history.pop_front();
+                }
+                let breached = self
+                    .history
+                    .iter()
+                    .any(|&(_, p)| (mid / p - 1.0).abs() > self.cfg.move_pct);
+                if breached {
+                    self.history.clear();
+                    self.enter(TradingState::Halted, now + self.cfg.halt_duration)
+                } else {
+                    self.history.push_back((now, mid));
+                    None
+                }
+            }
+            TradingState::Halted if now >= self.until => {
+                if self.cfg.reopen_auction > 0.0 {
+                    self.enter(TradingState::Auction, now + self.cfg.reopen_auction)
+                } else {
+                    self.enter(TradingState::Open, now)
+                }
+            }
+            TradingState::Auction if now >= self.until => self.enter(TradingState::Open, now),
+            _ => None,
+        }
+    }
+
+    fn enter(&mut self, state: TradingState, until: f64) -> Option<TradingState> {
+        self.state = state;
+        self.until = until;
+        Some(state)
+    }
+}
@@ -0,0 +1,99 @@
+//! Extra venues publishing the same instrument (`[[venues]]`).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::config::{AppConfig, Cli, OutputMode, Venue};
+use crate::engine;
+
+/// Run the primary venue and one generator per `[[venues]]` entry side by
+/// side, each on its own thread and multicast group.
+///
+/// Every venue follows the primary's price path (see `RngStreams`) skewed
+/// by its `price_skew_bps`, with its own order flow scaled by `liquidity`.
+/// Venues log nothing and serve no control API, crypto feed or OUCH echo;
+/// control commands, exports and the run report cover the primary only.
+/// They end with the primary: on their own when it runs out of time or
+/// scenarios, otherwise when it stops.
+pub fn run(
+    cfg: &AppConfig,
+    cli: &Cli,
+    running: &AtomicBool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let configs = cfg
+        .venues
+        .iter()
+        .map(|v| venue_config(cfg, cli, v))
+        .collect::<Result<Vec<_>, _>>()?;
+    // Stopping points the venues don't share
+    let ends_early = cfg.max_messages.is_some() || cfg.stop_at_price.is_some();
+
+    std::thread::scope(|s| {
+        let handles: Vec<_> = configs
+            .iter()
+            .map(|v| {
+                s.spawn(move || {
+                    let result = engine::simulate(v, cli, running, None).map_err(|e| e.to_string());
+                    if result.is_err() {
+                        running.store(false, Ordering::SeqCst);
+                    }
+                    result
+                })
+            })
+            .collect();
+
+        let result = engine::simulate(cfg, cli, running, None);
+        if result.is_err() || ends_early {
+            running.store(false, Ordering::SeqCst);
+        }
+        for (venue, handle) in cfg.venues.iter().zip(handles) {
+            match handle.join() {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => return Err(format!("venue '{}' failed: {}", venue.name, e).into()),
+                Err(_) => return Err(format!("venue '{}' panicked", venue.name).into()),
+            }
+        }
+        result
+    })
+}
+
+/// The primary's config republished as `venue`.
+fn venue_config(
+    primary: &AppConfig,
+    cli: &Cli,
+    venue: &Venue,
+) -> Result<AppConfig, Box<dyn std::error::Error>> {
+    let mut cfg = AppConfig::resolve(cli)?;
+    // Without --seed each resolve picks its own
+    cfg.seed = primary.seed;
+    cfg.venues = Vec::new();
+    cfg.venue = Some(venue.clone());
+    cfg.multicast_group = venue.multicast_group;
+    cfg.multicast_port = venue.multicast_port;
+    cfg.output_mode = OutputMode::Quiet;
+    cfg.control_enabled = false;
+    cfg.report_path = None;
+    cfg.csv_path = None;
+    cfg.pcap_path = None;
+    cfg.parquet_path = None;
+    // The WebSocket feed binds one address, and the OUCH echo's one stream
+    // would mix venues' order IDs: both follow the primary
+    cfg.crypto_feed = None;
+    cfg.ouch = None;
+    cfg.max_messages = None;
+    cfg.stop_at_price = None;
+
+    // Scaling every price level scales the whole GBM path; OU needs its mean
+    // and noise scaled too
+    let skew = 1.0 + venue.price_skew_bps / 10_000.0;
+    cfg.initial_price *= skew;
+    if let Some(mean) = cfg.ou.mean.as_mut() {
+        *mean *= skew;
+    }
+    cfg.ou.volatility *= skew;
+
+    cfg.throughput_scale *= venue.liquidity;
+    if let Some(rate) = cfg.target_rate.as_mut() {
+        *rate *= venue.liquidity;
+    }
+    Ok(cfg)
+}
@@ -0,0 +1,182 @@
+//! `verify`: a determinism check that runs two generators side by side.
+
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+
+use crate::config::{AppConfig, Cli, OutputMode, VerifyArgs};
+use crate::engine;
+use crate::wire::decode;
+
+/// Datagrams each instance may run ahead of the comparison.
+const TAP_DEPTH: usize = 1024;
+
+/// Run two instances of the configured generator in-process with the same
+/// seed and compare their datagrams one by one, stopping at the first
+/// difference. Both publish nothing: no multicast, OUCH echo, crypto feed,
+/// extra venues, exports, logs or control API, and ticks are unpaced.
+pub fn verify(args: &VerifyArgs, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    if !(args.span > 0.0 && args.span.is_finite()) {
+        return Err("--span must be > 0".into());
+    }
+    let mut a = AppConfig::resolve(cli)?;
+    let mut b = AppConfig::resolve(cli)?;
+    // Without --seed each resolve picks its own
+    b.seed = a.seed;
+    for cfg in [&mut a, &mut b] {
+        cfg.output_mode = OutputMode::Quiet;
+        cfg.control_enabled = false;
+        cfg.checkpoint_path = None;
+        cfg.report_path = None;
+        cfg.csv_path = None;
+        cfg.pcap_path = None;
+        cfg.parquet_path = None;
+        cfg.ouch = None;
+        cfg.crypto_feed = None;
+        cfg.venues.clear();
+        cfg.epoch_timestamps = false;
+        cfg.duration.get_or_insert(args.span);
+    }
+    eprintln!(
+        "Verifying seed {} over {:.1}s of sim time",
+        a.seed,
+        a.duration.unwrap_or(args.span)
+    );
+
+    let running = Arc::new(AtomicBool::new(true));
+    {
+        let running = Arc::clone(&running);
+        ctrlc::set_handler(move || {
+            running.store(false, Ordering::SeqCst);
+        })?;
+    }
+
+    std::thread::scope(|s| {
+        let (tx_a, rx_a) = mpsc::sync_channel(TAP_DEPTH);
+        let (tx_b, rx_b) = mpsc::sync_channel(TAP_DEPTH);
+        let run_a =
+            s.spawn(|| engine::simulate(&a, cli, &running, Some(tx_a)).map_err(|e| e.to_string()));
+        let run_b =
+            s.spawn(|| engine::simulate(&b, cli, &running, Some(tx_b)).map_err(|e| e.to_string()));
+
+        let mut datagrams = 0u64;
+        let mut bytes = 0u64;
+        let divergence = loop {
+            match (rx_a.recv().ok(), rx_b.recv().ok()) {
+                (None, None) => break None,
+                (x, y) if x == y => {
+                    datagrams += 1;
+                    bytes += x.map_or(0, |d| d.len() as u64);
+                }
+                (x, y) => break Some(describe(datagrams, x.as_deref(), y.as_deref())),
+            }
+        };
+        // After Ctrl+C the two instances stop at different ticks
+        let interrupted = !running.swap(false, Ordering::SeqCst);
+        drop((rx_a, rx_b));
+        let results = [run_a.join(), run_b.join()];
+
+        if interrupted {
+            return Err(format!("interrupted after {} identical datagrams", datagrams).into());
+        }
+        if let Some(report) = divergence {
+            return Err(report.into());
+        }
+        for (name, result) in ["A", "B"].iter().zip(results) {
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => return Err(format!("instance {} failed: {}", name, e).into()),
+                Err(_) => return Err(format!("instance {} panicked", name).into()),
+            }
+        }
+        println!(
+            "identical: {} datagrams ({} bytes) from seed {}",
+            datagrams, bytes, a.seed
+        );
+        Ok(())
+    })
+}
+
+/// Where the streams first differ, with both datagrams decoded.
+fn describe(index: u64, a: Option<&[u8]>, b: Option<&[u8]>) -> String {
+    let mut report = format!("streams diverge at datagram #{}", index + 1);
+    if let (Some(a), Some(b)) = (a, b) {
+        let offset = a
+            .iter()
+            .zip(b)
+            .position(|(x, y)| x != y)
+            .unwrap_or(a.len().min(b.len()));
+        let _ = write!(
+            report,
+            " (byte {}, lengths {} and {})",
+            offset,
+            a.len(),
+            b.len()
+        );
+    }
+    for (name, datagram) in [("A", a), ("B", b)] {
+        let _ = write!(report, "\n  {}: {}", name, render(datagram));
+    }
+    report
+}
+
+fn render(datagram: Option<&[u8]>) -> String {
+    let Some(d) = datagram else {
+        return "<stream ended>".to_string();
+    };
+    match decode::decode_datagram(d) {
+        Ok(msgs) => msgs
+            .iter()
+            .map(|m| m.to_string())
+            .collect::<Vec<_>>()
+            .join(" | "),
+        Err(_) => format!("{:?}", String::from_utf8_lossy(d)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn verify_switches_off_the_side_streams() {
+        let path = std::env::temp_dir().join(format!("verify-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+[ouch]
+enabled = true
+
+[crypto_feed]
+enabled = true
+
+[[venues]]
+name = "B"
+multicast_group = "239.255.0.2"
+multicast_port = 5556
+"#,
+        )
+        .unwrap();
+        let cli = Cli::parse_from([
+            "orderflow-rs",
+            "-c",
+            path.to_str().unwrap(),
+            "--seed",
+            "7",
+            "--control-enabled",
+            "false",
+            "verify",
+            "--span",
+            "2",
+        ]);
+        let Some(crate::config::Command::Verify(args)) = &cli.command else {
+            unreachable!()
+        };
+        // Both instances binding the crypto feed's port would fail the run
+        let result = verify(args, &cli);
+        std::fs::remove_file(&path).unwrap();
+        result.unwrap();
+    }
+}
@@ -0,0 +1,222 @@
+//! Wire protocol constants and the encoding trait shared by every message type.
+
+pub mod decode;
+#[cfg(feature = "flatbuffers")]
+pub mod flatbuffers;
+pub mod protobuf;
+pub mod sbe;
+pub mod vectors;
+
+use std::fmt;
+
+pub const MAGIC: &[u8; 2] = b"OF";
+pub const VERSION: u8 = 2;
+/// The original binary layout (`[network] wire_version = 1`): ORDER and
+/// CANCEL only, without the fields added since, one per datagram.
+pub const VERSION_1: u8 = 1;
+
+pub const MSG_ORDER: u8 = 1;
+pub const MSG_CANCEL: u8 = 2;
+pub const MSG_BATCH: u8 = 3;
+pub const MSG_BAR: u8 = 4;
+pub const MSG_QUOTE: u8 = 5;
+pub const MSG_DEPTH: u8 = 6;
+pub const MSG_TRADE: u8 = 7;
+pub const MSG_MASS_CANCEL: u8 = 8;
+pub const MSG_VENUE_STATE: u8 = 9;
+pub const MSG_REJECT: u8 = 10;
+pub const MSG_IMBALANCE: u8 = 11;
+pub const MSG_UNCROSS: u8 = 12;
+pub const MSG_SESSION_START: u8 = 13;
+pub const MSG_SESSION_END: u8 = 14;
+pub const MSG_SYMBOL: u8 = 15;
+pub const MSG_REFERENCE_PRICE: u8 = 16;
+pub const MSG_SNAPSHOT_START: u8 = 17;
+pub const MSG_SNAPSHOT_END: u8 = 18;
+pub const MSG_LEVEL: u8 = 19;
+pub const MSG_ADMIN: u8 = 20;
+pub const MSG_SELF_MATCH: u8 = 21;
+pub const MSG_DARK_TRADE: u8 = 22;
+pub const MSG_OPTION_QUOTE: u8 = 23;
+
+/// Set in a binary header's msg_type when the payload is followed by
+/// sent_ns:u64, the wall-clock send time in nanoseconds since the UNIX epoch.
+pub const MSG_FLAG_SENT_NS: u8 = 0x80;
+
+/// Set in a binary header's msg_type when the message ends in crc32c:u32,
+/// the CRC32C (Castagnoli) of every byte before it, header included.
+pub const MSG_FLAG_CRC: u8 = 0x40;
+
+/// Largest UDP payload over IPv4; no message or datagram can be longer.
+pub const MAX_MESSAGE_LEN: usize = 65_507;
+
+/// A message that can be published in either wire format.
+pub trait WireMessage {
+    fn to_wire_text(&self) -> String;
+    fn to_wire_binary(&self) -> Vec<u8>;
+
+    /// Text encoding written to `w`. Messages on the hot path format
+    /// straight into `w`; the default goes through `to_wire_text`.
+    fn write_text(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        w.write_str(&self.to_wire_text())
+    }
+
+    /// Binary encoding written to the start of `out`; returns its length.
+    /// Panics if `out` is too short, which `MAX_MESSAGE_LEN` never is.
+    /// Messages on the hot path encode in place; the default copies
+    /// `to_wire_binary`.
+    fn encode_into(&self, out: &mut [u8]) -> usize {
+        let msg = self.to_wire_binary();
+        out[..msg.len()].copy_from_slice(&msg);
+        msg.len()
+    }
+}
+
+/// Text encoding of a message whose `write_text` is its encoder.
+pub fn text_of(msg: &impl WireMessage) -> String {
+    let mut out = String::new();
+    let _ = msg.write_text(&mut out);
+    out
+}
+
+/// Binary encoding of a message whose `encode_into` is its encoder, at
+/// most `max_len` bytes long.
+pub fn binary_of(msg: &impl WireMessage, max_len: usize) -> Vec<u8> {
+    let mut out = vec![0; max_len];
+    let len = msg.encode_into(&mut out);
+    out.truncate(len);
+    out
+}
+
+/// Text `|instrument=` field every message ends with (ORDER's comes before
+/// its optional fields). Left out for instrument 0, so a single-instrument
+/// feed reads as it always has.
+pub fn write_instrument(w: &mut impl fmt::Write, instrument: u16) -> fmt::Result {
+    if instrument == 0 {
+        return Ok(());
+    }
+    write!(w, "|instrument={}", instrument)
+}
+
+/// A price in the text format: to eight decimals, with trailing zeros
+/// trimmed down to two, so a cent-tick price reads as it always has and a
+/// finer tick keeps its digits.
+pub struct Price(pub f64);
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut digits = Digits::default();
+        if fmt::Write::write_fmt(&mut digits, format_args!("{:.8}", self.0)).is_err() {
+            return write!(f, "{:.2}", self.0);
+        }
+        let full = std::str::from_utf8(&digits.buf[..digits.len]).map_err(|_| fmt::Error)?;
+        let Some(point) = full.find('.') else {
+            return f.write_str(full);
+        };
+        let trimmed = full.trim_end_matches('0');
+        f.write_str(&full[..trimmed.len().max(point + 3)])
+    }
+}
+
+/// Stack buffer `Price` formats into before trimming.
+struct Digits {
+    buf: [u8; 48],
+    len: usize,
+}
+
+impl Default for Digits {
+    fn default() -> Self {
+        Self {
+            buf: [0; 48],
+            len: 0,
+        }
+    }
+}
+
+impl fmt::Write for Digits {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Append a binary header: magic[2], version:u8, msg_type:u8.
+pub fn write_header(out: &mut Vec<u8>, msg_type: u8) {
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.push(msg_type);
+}
+
+/// Start a binary frame with room for a payload of `payload_len` bytes.
+pub fn binary_frame(msg_type: u8, payload_len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(4 + payload_len);
+    write_header(&mut out, msg_type);
+    out
+}
+
+/// Writes one binary frame into a caller's buffer, the counterpart of
+/// `binary_frame` for encoding without allocating. Panics if the buffer is
+/// too short for what is written.
+pub struct FrameWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> FrameWriter<'a> {
+    /// Start a frame of `msg_type` at the beginning of `buf`.
+    pub fn new(buf: &'a mut [u8], msg_type: u8) -> Self {
+        Self::with_version(buf, VERSION, msg_type)
+    }
+
+    /// Start a frame with another header version (`VERSION_1`).
+    pub fn with_version(buf: &'a mut [u8], version: u8, msg_type: u8) -> Self {
+        let mut w = Self { buf, len: 0 };
+        w.put(MAGIC);
+        w.put(&[version, msg_type]);
+        w
+    }
+
+    pub fn put(&mut self, bytes: &[u8]) {
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    pub fn put_u8(&mut self, v: u8) {
+        self.put(&[v]);
+    }
+
+    /// Bytes written so far, header included.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// `msg` (one encoded message, in either format) with a send timestamp:
+/// a trailing `|sent_ns=` field in text, `MSG_FLAG_SENT_NS` and a trailing
+/// u64 in binary.
+pub fn with_sent_ns(msg: &[u8], sent_ns: u64) -> Vec<u8> {
+    let mut out = Vec::with_capacity(msg.len() + 30);
+    out.extend_from_slice(msg);
+    if msg.starts_with(MAGIC) {
+        out[3] |= MSG_FLAG_SENT_NS;
+        out.extend_from_slice(&sent_ns.to_le_bytes());
+    } else {
+        out.extend_from_slice(format!("|sent_ns={}", sent_ns).as_bytes());
+    }
+    out
+}
+
+/// Binary `msg` with `MSG_FLAG_CRC` set and its CRC32C appended.
+pub fn with_crc(msg: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(msg.len() + 4);
+    out.extend_from_slice(msg);
+    out[3] |= MSG_FLAG_CRC;
+    let crc = crc32c::crc32c(&out);
+    out.extend_from_slice(&crc.to_le_bytes());
+    out
+}
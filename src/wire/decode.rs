@@ -0,0 +1,1699 @@
+//! Inverse of the encoders: parse text and binary (v2) messages back into
+//! the structs they were built from.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use super::*;
+use crate::auction::{Imbalance, Indicative, Uncross};
+use crate::bands::{Reject, RejectReason};
+use crate::bars::Bar;
+use crate::book::{Depth, Level, LevelUpdate, Quote, SelfMatch, SelfMatchMode};
+use crate::options::{OptionKind, OptionQuote};
+use crate::order::{DarkTrade, MassCancel, Order, OrderType, Side, Trade, ORDER_FLAG_NO_PRICE};
+use crate::refdata::{Instrument, SymbolDirectory, ISIN_LEN, SYMBOL_LEN};
+use crate::venue::{
+    Admin, ReferencePrice, SessionEnd, SessionStart, Severity, SnapshotEnd, SnapshotStart,
+    TradingState, VenueState,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Any message the engine publishes.
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// Decoded orders have no TTL (it is not on the wire).
+    Order(Order),
+    Cancel {
+        id: u64,
+        instrument: u16,
+        time: f64,
+    },
+    Bar(Bar),
+    Quote(Quote),
+    Depth(Depth),
+    Trade(Trade),
+    MassCancel(MassCancel),
+    VenueState(VenueState),
+    Reject(Reject),
+    Imbalance(Imbalance),
+    Uncross(Uncross),
+    SessionStart(SessionStart),
+    SessionEnd(SessionEnd),
+    Symbol {
+        index: u16,
+        instrument: Instrument,
+        time: f64,
+    },
+    ReferencePrice(ReferencePrice),
+    SnapshotStart(SnapshotStart),
+    SnapshotEnd(SnapshotEnd),
+    Level(LevelUpdate),
+    Admin(Admin),
+    SelfMatch(SelfMatch),
+    DarkTrade(DarkTrade),
+    OptionQuote(OptionQuote),
+}
+
+impl Message {
+    /// Name as used in the text format (`ORDER`, `CANCEL`, ...).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Message::Order(_) => "ORDER",
+            Message::Cancel { .. } => "CANCEL",
+            Message::Bar(_) => "BAR",
+            Message::Quote(_) => "QUOTE",
+            Message::Depth(_) => "DEPTH",
+            Message::Trade(_) => "TRADE",
+            Message::MassCancel(_) => "MASS_CANCEL",
+            Message::VenueState(v) => match v.state {
+                TradingState::Open => "RESUME",
+                TradingState::Halted => "HALT",
+                TradingState::Auction => "AUCTION",
+                TradingState::Closed => "CLOSE",
+            },
+            Message::Reject(_) => "REJECT",
+            Message::Imbalance(_) => "IMBALANCE",
+            Message::Uncross(_) => "UNCROSS",
+            Message::SessionStart(_) => "SESSION_START",
+            Message::SessionEnd(_) => "SESSION_END",
+            Message::Symbol { .. } => "SYMBOL",
+            Message::ReferencePrice(_) => "REFERENCE_PRICE",
+            Message::SnapshotStart(_) => "SNAPSHOT_START",
+            Message::SnapshotEnd(_) => "SNAPSHOT_END",
+            Message::Level(_) => "LEVEL",
+            Message::Admin(_) => "ADMIN",
+            Message::SelfMatch(_) => "SELF_MATCH",
+            Message::DarkTrade(_) => "DARK_TRADE",
+            Message::OptionQuote(_) => "OPTION_QUOTE",
+        }
+    }
+
+    /// Replace every timestamp `t` in the message with `f(t)`.
+    pub fn map_times(&mut self, f: impl Fn(f64) -> f64) {
+        let time = match self {
+            Message::Order(o) => &mut o.created_at,
+            Message::Cancel { time, .. } | Message::Symbol { time, .. } => time,
+            Message::Bar(m) => {
+                m.start = f(m.start);
+                &mut m.end
+            }
+            Message::Quote(m) => &mut m.time,
+            Message::Depth(m) => &mut m.time,
+            Message::Trade(m) => &mut m.time,
+            Message::MassCancel(m) => &mut m.time,
+            Message::VenueState(m) => &mut m.time,
+            Message::Reject(m) => &mut m.time,
+            Message::Imbalance(m) => &mut m.time,
+            Message::Uncross(m) => &mut m.time,
+            Message::SessionStart(m) => &mut m.time,
+            Message::SessionEnd(m) => &mut m.time,
+            Message::ReferencePrice(m) => &mut m.time,
+            Message::SnapshotStart(m) => &mut m.time,
+            Message::SnapshotEnd(m) => &mut m.time,
+            Message::Level(m) => &mut m.time,
+            Message::Admin(m) => &mut m.time,
+            Message::SelfMatch(m) => &mut m.time,
+            Message::DarkTrade(m) => &mut m.time,
+            Message::OptionQuote(m) => &mut m.time,
+        };
+        *time = f(*time);
+    }
+}
+
+/// Re-encoding a decoded message reproduces the bytes it was decoded from.
+impl WireMessage for Message {
+    fn to_wire_text(&self) -> String {
+        match self {
+            Message::Order(o) => o.to_wire_text(),
+            Message::Cancel {
+                id,
+                instrument,
+                time,
+            } => crate::order::cancel_to_wire_text(*id, *instrument, *time),
+            Message::Bar(m) => m.to_wire_text(),
+            Message::Quote(m) => m.to_wire_text(),
+            Message::Depth(m) => m.to_wire_text(),
+            Message::Trade(m) => m.to_wire_text(),
+            Message::MassCancel(m) => m.to_wire_text(),
+            Message::VenueState(m) => m.to_wire_text(),
+            Message::Reject(m) => m.to_wire_text(),
+            Message::Imbalance(m) => m.to_wire_text(),
+            Message::Uncross(m) => m.to_wire_text(),
+            Message::SessionStart(m) => m.to_wire_text(),
+            Message::SessionEnd(m) => m.to_wire_text(),
+            Message::Symbol {
+                index,
+                instrument,
+                time,
+            } => SymbolDirectory {
+                index: *index,
+                instrument,
+                time: *time,
+            }
+            .to_wire_text(),
+            Message::ReferencePrice(m) => m.to_wire_text(),
+            Message::SnapshotStart(m) => m.to_wire_text(),
+            Message::SnapshotEnd(m) => m.to_wire_text(),
+            Message::Level(m) => m.to_wire_text(),
+            Message::Admin(m) => m.to_wire_text(),
+            Message::SelfMatch(m) => m.to_wire_text(),
+            Message::DarkTrade(m) => m.to_wire_text(),
+            Message::OptionQuote(m) => m.to_wire_text(),
+        }
+    }
+
+    fn to_wire_binary(&self) -> Vec<u8> {
+        match self {
+            Message::Order(o) => o.to_wire_binary(),
+            Message::Cancel {
+                id,
+                instrument,
+                time,
+            } => crate::order::cancel_to_wire_binary(*id, *instrument, *time),
+            Message::Bar(m) => m.to_wire_binary(),
+            Message::Quote(m) => m.to_wire_binary(),
+            Message::Depth(m) => m.to_wire_binary(),
+            Message::Trade(m) => m.to_wire_binary(),
+            Message::MassCancel(m) => m.to_wire_binary(),
+            Message::VenueState(m) => m.to_wire_binary(),
+            Message::Reject(m) => m.to_wire_binary(),
+            Message::Imbalance(m) => m.to_wire_binary(),
+            Message::Uncross(m) => m.to_wire_binary(),
+            Message::SessionStart(m) => m.to_wire_binary(),
+            Message::SessionEnd(m) => m.to_wire_binary(),
+            Message::Symbol {
+                index,
+                instrument,
+                time,
+            } => SymbolDirectory {
+                index: *index,
+                instrument,
+                time: *time,
+            }
+            .to_wire_binary(),
+            Message::ReferencePrice(m) => m.to_wire_binary(),
+            Message::SnapshotStart(m) => m.to_wire_binary(),
+            Message::SnapshotEnd(m) => m.to_wire_binary(),
+            Message::Level(m) => m.to_wire_binary(),
+            Message::Admin(m) => m.to_wire_binary(),
+            Message::SelfMatch(m) => m.to_wire_binary(),
+            Message::DarkTrade(m) => m.to_wire_binary(),
+            Message::OptionQuote(m) => m.to_wire_binary(),
+        }
+    }
+}
+
+/// Human-readable form: the text encoding.
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_wire_text())
+    }
+}
+
+/// Decode one UDP datagram in either format: a single message, a binary
+/// BATCH, or newline-separated text messages.
+pub fn decode_datagram(buf: &[u8]) -> Result<Vec<Message>> {
+    // Before protobuf: a size prefix may start with protobuf's first byte.
+    #[cfg(feature = "flatbuffers")]
+    if flatbuffers::is_flatbuffers(buf) {
+        return flatbuffers::decode(buf);
+    }
+    if protobuf::is_protobuf(buf) {
+        return protobuf::decode(buf);
+    }
+    if sbe::is_sbe(buf) {
+        return decode_sbe_stream(buf);
+    }
+    if buf.starts_with(MAGIC) {
+        let mut r = Reader::new(buf);
+        let msgs = read_binary(&mut r)?;
+        r.finish()?;
+        return Ok(msgs);
+    }
+    let text = std::str::from_utf8(buf).map_err(|e| format!("not UTF-8 text: {}", e))?;
+    text.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(decode_text)
+        .collect()
+}
+
+/// Decode back-to-back binary messages, e.g. datagram payloads dumped to a file.
+/// Every binary message's length follows from its header and counts.
+pub fn decode_binary_stream(buf: &[u8]) -> Result<Vec<Message>> {
+    let mut r = Reader::new(buf);
+    let mut out = Vec::new();
+    while r.remaining() > 0 {
+        out.extend(read_binary(&mut r)?);
+    }
+    Ok(out)
+}
+
+/// Decode back-to-back SBE messages: one datagram, or a dump of several.
+pub fn decode_sbe_stream(buf: &[u8]) -> Result<Vec<Message>> {
+    let mut out = Vec::new();
+    let mut rest = buf;
+    while !rest.is_empty() {
+        let (msg, len) = sbe::to_binary(rest)?;
+        out.push(decode_binary(&msg)?);
+        rest = &rest[len..];
+    }
+    Ok(out)
+}
+
+/// Decode one binary message (not a BATCH).
+pub fn decode_binary(buf: &[u8]) -> Result<Message> {
+    let mut r = Reader::new(buf);
+    let msg_type = r.header()?;
+    if msg_type == MSG_BATCH {
+        return Err("BATCH holds several messages; use decode_datagram".into());
+    }
+    let msg = read_body(&mut r, msg_type)?;
+    r.finish()?;
+    Ok(msg)
+}
+
+/// One message, or every message of a BATCH.
+fn read_binary(r: &mut Reader) -> Result<Vec<Message>> {
+    let msg_type = r.header()?;
+    if msg_type != MSG_BATCH {
+        return Ok(vec![read_body(r, msg_type)?]);
+    }
+    let count = r.u16()?;
+    (0..count)
+        .map(|_| {
+            let len = r.u16()? as usize;
+            decode_binary(r.bytes(len)?)
+        })
+        .collect()
+}
+
+fn side(code: u8) -> Result<Side> {
+    match code {
+        1 => Ok(Side::Buy),
+        2 => Ok(Side::Sell),
+        _ => Err(format!("invalid side code {}", code).into()),
+    }
+}
+
+fn optional_side(code: u8) -> Result<Option<Side>> {
+    match code {
+        0 => Ok(None),
+        c => side(c).map(Some),
+    }
+}
+
+pub(super) fn severity(code: u8) -> Result<Severity> {
+    match code {
+        1 => Ok(Severity::Info),
+        2 => Ok(Severity::Warning),
+        3 => Ok(Severity::Alert),
+        _ => Err(format!("invalid severity code {}", code).into()),
+    }
+}
+
+pub(super) fn option_kind(code: u8) -> Result<OptionKind> {
+    match code {
+        1 => Ok(OptionKind::Call),
+        2 => Ok(OptionKind::Put),
+        _ => Err(format!("invalid option type code {}", code).into()),
+    }
+}
+
+pub(super) fn self_match_mode(code: u8) -> Result<SelfMatchMode> {
+    match code {
+        1 => Ok(SelfMatchMode::CancelOldest),
+        2 => Ok(SelfMatchMode::Decrement),
+        _ => Err(format!("invalid self-match action code {}", code).into()),
+    }
+}
+
+/// Payload of a message whose header has been read.
+fn read_body(r: &mut Reader, msg_type: u8) -> Result<Message> {
+    // Back to the magic
+    let start = r.pos - 4;
+    let msg = match msg_type & !(MSG_FLAG_SENT_NS | MSG_FLAG_CRC) {
+        MSG_ORDER if r.version == VERSION_1 => {
+            let id = r.u64()?;
+            let side = side(r.u8()?)?;
+            let order_type = match r.u8()? {
+                1 => OrderType::Limit,
+                2 => OrderType::Market,
+                c => return Err(format!("invalid v1 order type code {}", c).into()),
+            };
+            let price = r.f64()?;
+            let size = r.u32()?;
+            Message::Order(Order {
+                id,
+                side,
+                order_type,
+                // The sentinel price of a v1 market order means nothing
+                price: if order_type == OrderType::Market {
+                    0.0
+                } else {
+                    price
+                },
+                size,
+                reserve: 0,
+                participant_id: 0,
+                cl_ord_id: String::new(),
+                instrument: 0,
+                created_at: r.f64()?,
+                ttl: 0.0,
+            })
+        }
+        MSG_ORDER => {
+            let id = r.u64()?;
+            let side = side(r.u8()?)?;
+            let order_type = match r.u8()? {
+                1 => OrderType::Limit,
+                2 => OrderType::Market,
+                3 => OrderType::Stop,
+                4 => OrderType::Iceberg,
+                c => return Err(format!("invalid order type code {}", c).into()),
+            };
+            let price = r.f64()?;
+            let size = r.u32()?;
+            let created_at = r.f64()?;
+            let participant_id = r.u32()?;
+            let total = r.u32()?;
+            let flags = r.u8()?;
+            let instrument = r.u16()?;
+            let len = r.u8()? as usize;
+            let cl_ord_id = std::str::from_utf8(r.bytes(len)?)
+                .map_err(|e| format!("invalid cl_ord_id: {}", e))?
+                .to_string();
+            Message::Order(Order {
+                id,
+                side,
+                order_type,
+                price: if flags & ORDER_FLAG_NO_PRICE != 0 {
+                    0.0
+                } else {
+                    price
+                },
+                size,
+                reserve: total.saturating_sub(size),
+                participant_id,
+                cl_ord_id,
+                instrument,
+                created_at,
+                ttl: 0.0,
+            })
+        }
+        MSG_CANCEL if r.version == VERSION_1 => Message::Cancel {
+            id: r.u64()?,
+            time: r.f64()?,
+            instrument: 0,
+        },
+        MSG_CANCEL => Message::Cancel {
+            id: r.u64()?,
+            time: r.f64()?,
+            instrument: r.u16()?,
+        },
+        MSG_BAR => Message::Bar(Bar {
+            start: r.f64()?,
+            end: r.f64()?,
+            open: r.f64()?,
+            high: r.f64()?,
+            low: r.f64()?,
+            close: r.f64()?,
+            volume: r.u64()?,
+            trades: r.u32()?,
+            instrument: r.u16()?,
+        }),
+        MSG_QUOTE => Message::Quote(Quote {
+            bid_price: r.f64()?,
+            bid_size: r.u64()?,
+            ask_price: r.f64()?,
+            ask_size: r.u64()?,
+            time: r.f64()?,
+            instrument: r.u16()?,
+        }),
+        MSG_DEPTH => {
+            let time = r.f64()?;
+            let instrument = r.u16()?;
+            let bid_levels = r.u8()? as usize;
+            let ask_levels = r.u8()? as usize;
+            let level = |r: &mut Reader| -> Result<(f64, Level)> {
+                Ok((
+                    r.f64()?,
+                    Level {
+                        size: r.u64()?,
+                        count: r.u32()?,
+                    },
+                ))
+            };
+            let bids = (0..bid_levels).map(|_| level(r)).collect::<Result<_>>()?;
+            let asks = (0..ask_levels).map(|_| level(r)).collect::<Result<_>>()?;
+            Message::Depth(Depth {
+                bids,
+                asks,
+                instrument,
+                time,
+            })
+        }
+        MSG_TRADE => Message::Trade(Trade {
+            maker_id: r.u64()?,
+            taker_id: r.u64()?,
+            aggressor: side(r.u8()?)?,
+            price: r.f64()?,
+            size: r.u32()?,
+            time: r.f64()?,
+            instrument: r.u16()?,
+        }),
+        MSG_MASS_CANCEL => Message::MassCancel(MassCancel {
+            participant_id: r.u32()?,
+            side: optional_side(r.u8()?)?,
+            time: r.f64()?,
+            instrument: r.u16()?,
+        }),
+        MSG_VENUE_STATE => Message::VenueState(VenueState {
+            state: match r.u8()? {
+                1 => TradingState::Halted,
+                2 => TradingState::Open,
+                3 => TradingState::Auction,
+                4 => TradingState::Closed,
+                c => return Err(format!("invalid venue state code {}", c).into()),
+            },
+            time: r.f64()?,
+            instrument: r.u16()?,
+        }),
+        MSG_REJECT => {
+            let id = r.u64()?;
+            let side = side(r.u8()?)?;
+            let price = r.f64()?;
+            let size = r.u32()?;
+            let participant_id = r.u32()?;
+            let reason = match r.u8()? {
+                1 => RejectReason::PriceBand,
+                2 => RejectReason::ZeroSize,
+                3 => RejectReason::PriceCollar,
+                c => return Err(format!("invalid reject reason code {}", c).into()),
+            };
+            let (lower, upper, time) = (r.f64()?, r.f64()?, r.f64()?);
+            let instrument = r.u16()?;
+            Message::Reject(Reject {
+                order: rejected_order(id, side, price, size, participant_id, instrument),
+                reason,
+                lower,
+                upper,
+                time,
+            })
+        }
+        MSG_IMBALANCE => {
+            let price = r.f64()?;
+            let matched = r.u64()?;
+            let imbalance = r.u64()?;
+            let side = optional_side(r.u8()?)?;
+            Message::Imbalance(Imbalance {
+                indicative: indicative(price, matched, imbalance, side),
+                time: r.f64()?,
+                instrument: r.u16()?,
+            })
+        }
+        MSG_UNCROSS => Message::Uncross(Uncross {
+            price: r.f64()?,
+            volume: r.u64()?,
+            time: r.f64()?,
+            instrument: r.u16()?,
+        }),
+        MSG_SESSION_START => Message::SessionStart(SessionStart {
+            tick_size: r.f64()?,
+            lot_size: r.u32()?,
+            initial_price: r.f64()?,
+            time: r.f64()?,
+            instrument: r.u16()?,
+        }),
+        MSG_SESSION_END => Message::SessionEnd(SessionEnd {
+            messages: r.u64()?,
+            time: r.f64()?,
+            instrument: r.u16()?,
+        }),
+        MSG_SYMBOL => {
+            let symbol = r.padded(SYMBOL_LEN)?;
+            let isin = r.padded(ISIN_LEN)?;
+            let tick_size = r.f64()?;
+            let lot_size = r.u32()?;
+            let time = r.f64()?;
+            Message::Symbol {
+                index: r.u16()?,
+                instrument: Instrument::new(&symbol, Some(&isin), tick_size, lot_size),
+                time,
+            }
+        }
+        MSG_REFERENCE_PRICE => Message::ReferencePrice(ReferencePrice {
+            price: r.f64()?,
+            prev_close: r.f64()?,
+            time: r.f64()?,
+            instrument: r.u16()?,
+        }),
+        MSG_SNAPSHOT_START => Message::SnapshotStart(SnapshotStart {
+            orders: r.u64()?,
+            time: r.f64()?,
+            instrument: r.u16()?,
+        }),
+        MSG_SNAPSHOT_END => Message::SnapshotEnd(SnapshotEnd {
+            time: r.f64()?,
+            instrument: r.u16()?,
+        }),
+        MSG_LEVEL => Message::Level(LevelUpdate {
+            side: side(r.u8()?)?,
+            price: r.f64()?,
+            size: r.u64()?,
+            count: r.u32()?,
+            time: r.f64()?,
+            instrument: r.u16()?,
+        }),
+        MSG_ADMIN => {
+            let severity = severity(r.u8()?)?;
+            let code = r.u16()?;
+            let time = r.f64()?;
+            let instrument = r.u16()?;
+            let len = r.u8()? as usize;
+            let text = std::str::from_utf8(r.bytes(len)?)
+                .map_err(|e| format!("invalid ADMIN text: {}", e))?
+                .to_string();
+            Message::Admin(Admin {
+                severity,
+                code,
+                text,
+                instrument,
+                time,
+            })
+        }
+        MSG_SELF_MATCH => Message::SelfMatch(SelfMatch {
+            maker_id: r.u64()?,
+            taker_id: r.u64()?,
+            participant_id: r.u32()?,
+            mode: self_match_mode(r.u8()?)?,
+            size: r.u32()?,
+            time: r.f64()?,
+            instrument: r.u16()?,
+        }),
+        MSG_DARK_TRADE => Message::DarkTrade(DarkTrade {
+            aggressor: side(r.u8()?)?,
+            price: r.f64()?,
+            size: r.u32()?,
+            participant_id: r.u32()?,
+            time: r.f64()?,
+            instrument: r.u16()?,
+        }),
+        MSG_OPTION_QUOTE => Message::OptionQuote(OptionQuote {
+            expiry: r.u32()?,
+            strike: r.f64()?,
+            kind: option_kind(r.u8()?)?,
+            bid: r.f64()?,
+            ask: r.f64()?,
+            iv: r.f64()?,
+            underlying: r.f64()?,
+            time: r.f64()?,
+            instrument: r.u16()?,
+        }),
+        MSG_BATCH => return Err("nested BATCH".into()),
+        t => return Err(format!("unknown message type {}", t).into()),
+    };
+    // The send timestamp isn't kept
+    if msg_type & MSG_FLAG_SENT_NS != 0 {
+        r.u64()?;
+    }
+    if msg_type & MSG_FLAG_CRC != 0 {
+        let expected = crc32c::crc32c(&r.buf[start..r.pos]);
+        let crc = r.u32()?;
+        if crc != expected {
+            return Err(format!(
+                "{} failed its CRC check (carries {:#010x}, content gives {:#010x})",
+                msg.kind(),
+                crc,
+                expected
+            )
+            .into());
+        }
+    }
+    Ok(msg)
+}
+
+/// Rejects carry only some order fields; the rest take neutral values.
+pub(super) fn rejected_order(
+    id: u64,
+    side: Side,
+    price: f64,
+    size: u32,
+    participant_id: u32,
+    instrument: u16,
+) -> Order {
+    Order {
+        id,
+        side,
+        order_type: OrderType::Limit,
+        price,
+        size,
+        reserve: 0,
+        participant_id,
+        cl_ord_id: String::new(),
+        instrument,
+        created_at: 0.0,
+        ttl: 0.0,
+    }
+}
+
+/// The wire carries only the larger surplus, so the other side decodes as 0.
+pub(super) fn indicative(
+    price: f64,
+    matched: u64,
+    imbalance: u64,
+    side: Option<Side>,
+) -> Indicative {
+    Indicative {
+        price: (price != 0.0).then_some(price),
+        matched,
+        buy_surplus: if side == Some(Side::Buy) {
+            imbalance
+        } else {
+            0
+        },
+        sell_surplus: if side == Some(Side::Sell) {
+            imbalance
+        } else {
+            0
+        },
+    }
+}
+
+/// Little-endian cursor over a binary message.
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    /// Header version of the message being read.
+    version: u8,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self {
+            buf,
+            pos: 0,
+            version: VERSION,
+        }
+    }
+
+    /// Check magic and version; returns the message type. Version 1 is
+    /// accepted for the ORDER and CANCEL it had.
+    fn header(&mut self) -> Result<u8> {
+        if self.bytes(2)? != MAGIC {
+            return Err("bad magic".into());
+        }
+        self.version = self.u8()?;
+        let msg_type = self.u8()?;
+        match self.version {
+            VERSION => Ok(msg_type),
+            VERSION_1 if matches!(msg_type, MSG_ORDER | MSG_CANCEL) => Ok(msg_type),
+            VERSION_1 => Err(format!("message type {} has no version 1 layout", msg_type).into()),
+            v => Err(format!("unsupported version {} (expected {})", v, VERSION).into()),
+        }
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos + n;
+        let Some(b) = self.buf.get(self.pos..end) else {
+            return Err(format!(
+                "truncated message: need {} bytes, have {}",
+                end,
+                self.buf.len()
+            )
+            .into());
+        };
+        self.pos = end;
+        Ok(b)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.bytes(N)?.try_into().expect("slice has length N"))
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+
+    /// Space-padded ASCII field.
+    fn padded(&mut self, n: usize) -> Result<String> {
+        let b = self.bytes(n)?;
+        let s = std::str::from_utf8(b).map_err(|e| format!("invalid text field: {}", e))?;
+        Ok(s.trim_end_matches(' ').to_string())
+    }
+
+    fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    fn finish(&self) -> Result<()> {
+        if self.remaining() > 0 {
+            return Err(format!("{} trailing bytes", self.remaining()).into());
+        }
+        Ok(())
+    }
+}
+
+/// `KEY=value` fields of one text message.
+struct Fields<'a> {
+    kind: &'a str,
+    map: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Fields<'a> {
+    fn parse(line: &'a str) -> Result<Self> {
+        let mut parts = line.trim().split('|');
+        let kind = parts.next().unwrap_or_default();
+        let mut map = HashMap::new();
+        for part in parts {
+            let (k, v) = part
+                .split_once('=')
+                .ok_or_else(|| format!("malformed field '{}' in {}", part, kind))?;
+            map.insert(k, v);
+        }
+        Ok(Self { kind, map })
+    }
+
+    fn str(&self, key: &str) -> Result<&'a str> {
+        self.map
+            .get(key)
+            .copied()
+            .ok_or_else(|| format!("{} missing field '{}'", self.kind, key).into())
+    }
+
+    fn num<T: std::str::FromStr>(&self, key: &str) -> Result<T>
+    where
+        T::Err: fmt::Display,
+    {
+        let v = self.str(key)?;
+        v.parse()
+            .map_err(|e| format!("{} field '{}'='{}': {}", self.kind, key, v, e).into())
+    }
+
+    fn side(&self, key: &str) -> Result<Side> {
+        match self.str(key)? {
+            "BUY" => Ok(Side::Buy),
+            "SELL" => Ok(Side::Sell),
+            s => Err(format!("{} invalid side '{}'", self.kind, s).into()),
+        }
+    }
+
+    /// The `instrument` index, 0 when it is left out.
+    fn instrument(&self) -> Result<u16> {
+        if self.map.contains_key("instrument") {
+            self.num("instrument")
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// `BUY`/`SELL`, or `none_word` for no side.
+    fn optional_side(&self, key: &str, none_word: &str) -> Result<Option<Side>> {
+        if self.str(key)? == none_word {
+            return Ok(None);
+        }
+        self.side(key).map(Some)
+    }
+}
+
+fn levels_from_text(s: &str) -> Result<Vec<(f64, Level)>> {
+    s.split(';')
+        .filter(|l| !l.is_empty())
+        .map(|l| {
+            let mut it = l.split(':');
+            let mut next = || {
+                it.next()
+                    .ok_or_else(|| format!("malformed depth level '{}'", l))
+            };
+            let price = next()?.parse::<f64>()?;
+            let size = next()?.parse::<u64>()?;
+            let count = next()?.parse::<u32>()?;
+            Ok((price, Level { size, count }))
+        })
+        .collect()
+}
+
+/// Decode one text message (a single line).
+pub fn decode_text(line: &str) -> Result<Message> {
+    let f = Fields::parse(line)?;
+    let msg = match f.kind {
+        "ORDER" => {
+            let order_type = match f.str("type")? {
+                "LIMIT" => OrderType::Limit,
+                "MARKET" => OrderType::Market,
+                "STOP" => OrderType::Stop,
+                "ICEBERG" => OrderType::Iceberg,
+                t => return Err(format!("ORDER invalid type '{}'", t).into()),
+            };
+            // Market prices are MKT, or a legacy sentinel; either way there is none.
+            let price = if order_type == OrderType::Market {
+                0.0
+            } else {
+                f.num("price")?
+            };
+            let size: u32 = f.num("size")?;
+            let total: u32 = if order_type == OrderType::Iceberg {
+                f.num("total")?
+            } else {
+                size
+            };
+            Message::Order(Order {
+                id: f.num("id")?,
+                side: f.side("side")?,
+                order_type,
+                price,
+                size,
+                reserve: total.saturating_sub(size),
+                participant_id: f.num("participant")?,
+                cl_ord_id: f.map.get("cl_ord_id").unwrap_or(&"").to_string(),
+                instrument: f.instrument()?,
+                created_at: f.num("time")?,
+                ttl: 0.0,
+            })
+        }
+        "CANCEL" => Message::Cancel {
+            id: f.num("id")?,
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        },
+        "BAR" => Message::Bar(Bar {
+            start: f.num("start")?,
+            end: f.num("end")?,
+            open: f.num("open")?,
+            high: f.num("high")?,
+            low: f.num("low")?,
+            close: f.num("close")?,
+            volume: f.num("volume")?,
+            trades: f.num("trades")?,
+            instrument: f.instrument()?,
+        }),
+        "QUOTE" => Message::Quote(Quote {
+            bid_price: f.num("bid")?,
+            bid_size: f.num("bid_size")?,
+            ask_price: f.num("ask")?,
+            ask_size: f.num("ask_size")?,
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "DEPTH" => Message::Depth(Depth {
+            bids: levels_from_text(f.str("bids")?)?,
+            asks: levels_from_text(f.str("asks")?)?,
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "TRADE" => Message::Trade(Trade {
+            maker_id: f.num("maker")?,
+            taker_id: f.num("taker")?,
+            aggressor: f.side("side")?,
+            price: f.num("price")?,
+            size: f.num("size")?,
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "MASS_CANCEL" => Message::MassCancel(MassCancel {
+            participant_id: f.num("participant")?,
+            side: f.optional_side("side", "ALL")?,
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "HALT" | "RESUME" | "AUCTION" | "CLOSE" => Message::VenueState(VenueState {
+            state: match f.kind {
+                "HALT" => TradingState::Halted,
+                "RESUME" => TradingState::Open,
+                "AUCTION" => TradingState::Auction,
+                _ => TradingState::Closed,
+            },
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "REJECT" => Message::Reject(Reject {
+            order: rejected_order(
+                f.num("id")?,
+                f.side("side")?,
+                f.num("price")?,
+                f.num("size")?,
+                f.num("participant")?,
+                f.instrument()?,
+            ),
+            reason: match f.str("reason")? {
+                "PRICE_BAND" => RejectReason::PriceBand,
+                "ZERO_SIZE" => RejectReason::ZeroSize,
+                "PRICE_COLLAR" => RejectReason::PriceCollar,
+                r => return Err(format!("REJECT invalid reason '{}'", r).into()),
+            },
+            lower: f.num("lower")?,
+            upper: f.num("upper")?,
+            time: f.num("time")?,
+        }),
+        "IMBALANCE" => Message::Imbalance(Imbalance {
+            indicative: indicative(
+                f.num("price")?,
+                f.num("matched")?,
+                f.num("imbalance")?,
+                f.optional_side("side", "NONE")?,
+            ),
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "UNCROSS" => Message::Uncross(Uncross {
+            price: f.num("price")?,
+            volume: f.num("volume")?,
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "SESSION_START" => Message::SessionStart(SessionStart {
+            tick_size: f.num("tick_size")?,
+            lot_size: f.num("lot_size")?,
+            initial_price: f.num("initial_price")?,
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "SESSION_END" => Message::SessionEnd(SessionEnd {
+            messages: f.num("messages")?,
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "SYMBOL" => Message::Symbol {
+            index: f.instrument()?,
+            instrument: Instrument::new(
+                f.str("symbol")?,
+                Some(f.str("isin")?),
+                f.num("tick_size")?,
+                f.num("lot_size")?,
+            ),
+            time: f.num("time")?,
+        },
+        "REFERENCE_PRICE" => Message::ReferencePrice(ReferencePrice {
+            price: f.num("price")?,
+            prev_close: f.num("prev_close")?,
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "SNAPSHOT_START" => Message::SnapshotStart(SnapshotStart {
+            orders: f.num("orders")?,
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "SNAPSHOT_END" => Message::SnapshotEnd(SnapshotEnd {
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "LEVEL" => Message::Level(LevelUpdate {
+            side: f.side("side")?,
+            price: f.num("price")?,
+            size: f.num("size")?,
+            count: f.num("count")?,
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "ADMIN" => Message::Admin(Admin {
+            severity: match f.str("severity")? {
+                "INFO" => Severity::Info,
+                "WARNING" => Severity::Warning,
+                "ALERT" => Severity::Alert,
+                s => return Err(format!("ADMIN invalid severity '{}'", s).into()),
+            },
+            code: f.num("code")?,
+            text: f.str("text")?.to_string(),
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "SELF_MATCH" => Message::SelfMatch(SelfMatch {
+            maker_id: f.num("maker")?,
+            taker_id: f.num("taker")?,
+            participant_id: f.num("participant")?,
+            mode: match f.str("action")? {
+                "CANCEL_OLDEST" => SelfMatchMode::CancelOldest,
+                "DECREMENT" => SelfMatchMode::Decrement,
+                a => return Err(format!("SELF_MATCH invalid action '{}'", a).into()),
+            },
+            size: f.num("size")?,
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "DARK_TRADE" => Message::DarkTrade(DarkTrade {
+            aggressor: f.side("side")?,
+            price: f.num("price")?,
+            size: f.num("size")?,
+            participant_id: f.num("participant")?,
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        "OPTION_QUOTE" => Message::OptionQuote(OptionQuote {
+            expiry: f.num("expiry")?,
+            strike: f.num("strike")?,
+            kind: match f.str("type")? {
+                "CALL" => OptionKind::Call,
+                "PUT" => OptionKind::Put,
+                k => return Err(format!("OPTION_QUOTE invalid type '{}'", k).into()),
+            },
+            bid: f.num("bid")?,
+            ask: f.num("ask")?,
+            iv: f.num("iv")?,
+            underlying: f.num("underlying")?,
+            instrument: f.instrument()?,
+            time: f.num("time")?,
+        }),
+        k => return Err(format!("unknown message type '{}'", k).into()),
+    };
+    Ok(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::vectors;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    fn side() -> impl Strategy<Value = Side> {
+        prop_oneof![Just(Side::Buy), Just(Side::Sell)]
+    }
+
+    fn severity() -> impl Strategy<Value = Severity> {
+        prop_oneof![
+            Just(Severity::Info),
+            Just(Severity::Warning),
+            Just(Severity::Alert),
+        ]
+    }
+
+    fn self_match_mode() -> impl Strategy<Value = SelfMatchMode> {
+        prop_oneof![
+            Just(SelfMatchMode::CancelOldest),
+            Just(SelfMatchMode::Decrement),
+        ]
+    }
+
+    fn reject_reason() -> impl Strategy<Value = RejectReason> {
+        prop_oneof![
+            Just(RejectReason::PriceBand),
+            Just(RejectReason::ZeroSize),
+            Just(RejectReason::PriceCollar),
+        ]
+    }
+
+    fn price() -> impl Strategy<Value = f64> {
+        0.0..1.0e6
+    }
+
+    fn time() -> impl Strategy<Value = f64> {
+        0.0..1.0e5
+    }
+
+    fn order() -> impl Strategy<Value = Order> {
+        let order_type = prop_oneof![
+            Just(OrderType::Limit),
+            Just(OrderType::Market),
+            Just(OrderType::Stop),
+            Just(OrderType::Iceberg),
+        ];
+        (
+            any::<u64>(),
+            side(),
+            order_type,
+            price(),
+            0..u32::MAX / 2,
+            0..u32::MAX / 2,
+            any::<u32>(),
+            "[A-Za-z0-9-]{0,20}",
+            any::<u16>(),
+            time(),
+        )
+            .prop_map(
+                |(
+                    id,
+                    side,
+                    order_type,
+                    price,
+                    size,
+                    reserve,
+                    participant_id,
+                    cl_ord_id,
+                    instrument,
+                    created_at,
+                )| {
+                    Order {
+                        id,
+                        side,
+                        order_type,
+                        price,
+                        size,
+                        reserve,
+                        participant_id,
+                        cl_ord_id,
+                        instrument,
+                        created_at,
+                        ttl: 0.0,
+                    }
+                },
+            )
+    }
+
+    fn levels() -> impl Strategy<Value = Vec<(f64, Level)>> {
+        vec(
+            (price(), any::<u64>(), any::<u32>())
+                .prop_map(|(p, size, count)| (p, Level { size, count })),
+            0..8,
+        )
+    }
+
+    fn message() -> impl Strategy<Value = Message> {
+        let state = prop_oneof![
+            Just(TradingState::Open),
+            Just(TradingState::Halted),
+            Just(TradingState::Auction),
+            Just(TradingState::Closed),
+        ];
+        prop_oneof![
+            order().prop_map(Message::Order),
+            (any::<u64>(), any::<u16>(), time()).prop_map(|(id, instrument, time)| {
+                Message::Cancel {
+                    id,
+                    instrument,
+                    time,
+                }
+            }),
+            (
+                any::<u64>(),
+                any::<u64>(),
+                side(),
+                price(),
+                any::<u32>(),
+                any::<u16>(),
+                time()
+            )
+                .prop_map(
+                    |(maker_id, taker_id, aggressor, price, size, instrument, time)| {
+                        Message::Trade(Trade {
+                            maker_id,
+                            taker_id,
+                            aggressor,
+                            price,
+                            size,
+                            instrument,
+                            time,
+                        })
+                    }
+                ),
+            (
+                any::<u32>(),
+                proptest::option::of(side()),
+                any::<u16>(),
+                time()
+            )
+                .prop_map(|(participant_id, side, instrument, time)| {
+                    Message::MassCancel(MassCancel {
+                        participant_id,
+                        side,
+                        instrument,
+                        time,
+                    })
+                }),
+            (
+                time(),
+                time(),
+                price(),
+                price(),
+                price(),
+                price(),
+                any::<u64>(),
+                any::<u32>(),
+                any::<u16>()
+            )
+                .prop_map(
+                    |(start, end, open, high, low, close, volume, trades, instrument)| {
+                        Message::Bar(Bar {
+                            start,
+                            end,
+                            open,
+                            high,
+                            low,
+                            close,
+                            volume,
+                            trades,
+                            instrument,
+                        })
+                    }
+                ),
+            (
+                price(),
+                any::<u64>(),
+                price(),
+                any::<u64>(),
+                any::<u16>(),
+                time()
+            )
+                .prop_map(
+                    |(bid_price, bid_size, ask_price, ask_size, instrument, time)| Message::Quote(
+                        Quote {
+                            bid_price,
+                            bid_size,
+                            ask_price,
+                            ask_size,
+                            instrument,
+                            time,
+                        }
+                    )
+                ),
+            (levels(), levels(), any::<u16>(), time()).prop_map(
+                |(bids, asks, instrument, time)| Message::Depth(Depth {
+                    bids,
+                    asks,
+                    instrument,
+                    time,
+                })
+            ),
+            (state, any::<u16>(), time()).prop_map(|(state, instrument, time)| {
+                Message::VenueState(VenueState {
+                    state,
+                    instrument,
+                    time,
+                })
+            }),
+            (order(), reject_reason(), price(), price(), time()).prop_map(
+                |(order, reason, lower, upper, time)| {
+                    Message::Reject(Reject {
+                        order,
+                        reason,
+                        lower,
+                        upper,
+                        time,
+                    })
+                }
+            ),
+            (
+                proptest::option::of(1.0..1.0e6),
+                any::<u64>(),
+                any::<u64>(),
+                any::<u64>(),
+                any::<u16>(),
+                time()
+            )
+                .prop_map(
+                    |(price, matched, buy_surplus, sell_surplus, instrument, time)| {
+                        Message::Imbalance(Imbalance {
+                            indicative: Indicative {
+                                price,
+                                matched,
+                                buy_surplus,
+                                sell_surplus,
+                            },
+                            instrument,
+                            time,
+                        })
+                    }
+                ),
+            (price(), any::<u64>(), any::<u16>(), time()).prop_map(
+                |(price, volume, instrument, time)| Message::Uncross(Uncross {
+                    price,
+                    volume,
+                    instrument,
+                    time,
+                })
+            ),
+            (price(), any::<u32>(), price(), any::<u16>(), time()).prop_map(
+                |(tick_size, lot_size, initial_price, instrument, time)| {
+                    Message::SessionStart(SessionStart {
+                        tick_size,
+                        lot_size,
+                        initial_price,
+                        instrument,
+                        time,
+                    })
+                }
+            ),
+            (any::<u64>(), any::<u16>(), time()).prop_map(|(messages, instrument, time)| {
+                Message::SessionEnd(SessionEnd {
+                    messages,
+                    instrument,
+                    time,
+                })
+            }),
+            (
+                "[A-Z0-9]{1,8}",
+                "[A-Z0-9]{12}",
+                price(),
+                any::<u32>(),
+                any::<u16>(),
+                time()
+            )
+                .prop_map(|(symbol, isin, tick, lot, index, time)| Message::Symbol {
+                    index,
+                    instrument: Instrument::new(&symbol, Some(&isin), tick, lot),
+                    time,
+                }),
+            (price(), price(), any::<u16>(), time()).prop_map(
+                |(price, prev_close, instrument, time)| {
+                    Message::ReferencePrice(ReferencePrice {
+                        price,
+                        prev_close,
+                        instrument,
+                        time,
+                    })
+                }
+            ),
+            (any::<u64>(), any::<u16>(), time()).prop_map(|(orders, instrument, time)| {
+                Message::SnapshotStart(SnapshotStart {
+                    orders,
+                    instrument,
+                    time,
+                })
+            }),
+            (any::<u16>(), time()).prop_map(|(instrument, time)| {
+                Message::SnapshotEnd(SnapshotEnd { instrument, time })
+            }),
+            (
+                side(),
+                price(),
+                any::<u64>(),
+                any::<u32>(),
+                any::<u16>(),
+                time()
+            )
+                .prop_map(|(side, price, size, count, instrument, time)| {
+                    Message::Level(LevelUpdate {
+                        side,
+                        price,
+                        size,
+                        count,
+                        instrument,
+                        time,
+                    })
+                }),
+            (
+                severity(),
+                any::<u16>(),
+                "[ -{}~é]{0,40}",
+                any::<u16>(),
+                time()
+            )
+                .prop_map(|(severity, code, text, instrument, time)| {
+                    Message::Admin(Admin {
+                        severity,
+                        code,
+                        text,
+                        instrument,
+                        time,
+                    })
+                }),
+            (
+                any::<u64>(),
+                any::<u64>(),
+                any::<u32>(),
+                self_match_mode(),
+                any::<u32>(),
+                any::<u16>(),
+                time(),
+            )
+                .prop_map(
+                    |(maker_id, taker_id, participant_id, mode, size, instrument, time)| {
+                        Message::SelfMatch(SelfMatch {
+                            maker_id,
+                            taker_id,
+                            participant_id,
+                            mode,
+                            size,
+                            instrument,
+                            time,
+                        })
+                    }
+                ),
+            (
+                side(),
+                price(),
+                any::<u32>(),
+                any::<u32>(),
+                any::<u16>(),
+                time()
+            )
+                .prop_map(
+                    |(aggressor, price, size, participant_id, instrument, time)| {
+                        Message::DarkTrade(DarkTrade {
+                            aggressor,
+                            price,
+                            size,
+                            participant_id,
+                            instrument,
+                            time,
+                        })
+                    }
+                ),
+            (
+                any::<u32>(),
+                price(),
+                prop_oneof![Just(OptionKind::Call), Just(OptionKind::Put)],
+                price(),
+                price(),
+                0.0..5.0,
+                price(),
+                any::<u16>(),
+                time(),
+            )
+                .prop_map(
+                    |(expiry, strike, kind, bid, ask, iv, underlying, instrument, time)| {
+                        Message::OptionQuote(OptionQuote {
+                            expiry,
+                            strike,
+                            kind,
+                            bid,
+                            ask,
+                            iv,
+                            underlying,
+                            instrument,
+                            time,
+                        })
+                    }
+                ),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn binary_round_trip(msg in message()) {
+            let bytes = msg.to_wire_binary();
+            let decoded = decode_binary(&bytes).unwrap();
+            prop_assert_eq!(decoded.to_wire_binary(), bytes);
+            prop_assert_eq!(decoded.kind(), msg.kind());
+        }
+
+        #[test]
+        fn text_round_trip(msg in message()) {
+            let text = msg.to_wire_text();
+            let decoded = decode_text(&text).unwrap();
+            prop_assert_eq!(decoded.to_wire_text(), text);
+        }
+
+        /// Binary carries everything the text format shows.
+        #[test]
+        fn binary_decodes_to_same_text(msg in message()) {
+            let decoded = decode_binary(&msg.to_wire_binary()).unwrap();
+            prop_assert_eq!(decoded.to_wire_text(), msg.to_wire_text());
+        }
+
+        #[test]
+        fn batch_round_trip(msgs in vec(message(), 1..20)) {
+            let mut batch = Vec::new();
+            write_header(&mut batch, MSG_BATCH);
+            batch.extend_from_slice(&(msgs.len() as u16).to_le_bytes());
+            for msg in &msgs {
+                let bytes = msg.to_wire_binary();
+                batch.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+                batch.extend_from_slice(&bytes);
+            }
+            let decoded = decode_datagram(&batch).unwrap();
+            prop_assert_eq!(decoded.len(), msgs.len());
+            for (d, m) in decoded.iter().zip(&msgs) {
+                prop_assert_eq!(d.to_wire_binary(), m.to_wire_binary());
+            }
+        }
+
+        /// Send timestamps decode in either format and are skipped.
+        #[test]
+        fn sent_ns_is_skipped(msg in message(), ns in any::<u64>()) {
+            let binary = with_sent_ns(&msg.to_wire_binary(), ns);
+            let decoded = decode_binary(&binary).unwrap();
+            prop_assert_eq!(decoded.to_wire_binary(), msg.to_wire_binary());
+            let text = with_sent_ns(msg.to_wire_text().as_bytes(), ns);
+            let decoded = decode_text(std::str::from_utf8(&text).unwrap()).unwrap();
+            prop_assert_eq!(decoded.to_wire_text(), msg.to_wire_text());
+        }
+
+        /// A CRC'd message decodes as before; any flipped bit is caught.
+        #[test]
+        fn crc_catches_bit_flips(msg in message(), stamp in any::<bool>(), bit in any::<prop::sample::Index>()) {
+            let mut bytes = msg.to_wire_binary();
+            if stamp {
+                bytes = with_sent_ns(&bytes, 1);
+            }
+            let mut bytes = with_crc(&bytes);
+            let decoded = decode_binary(&bytes).unwrap();
+            prop_assert_eq!(decoded.to_wire_binary(), msg.to_wire_binary());
+            let bit = bit.index(bytes.len() * 8);
+            bytes[bit / 8] ^= 1 << (bit % 8);
+            prop_assert!(decode_binary(&bytes).is_err());
+        }
+
+        /// SBE carries the v2 message exactly, and every strict prefix is rejected.
+        #[test]
+        fn sbe_round_trip(msg in message(), cut in any::<prop::sample::Index>()) {
+            let bytes = msg.to_wire_binary();
+            let encoded = sbe::from_binary(&bytes).unwrap();
+            prop_assert!(sbe::is_sbe(&encoded));
+            prop_assert_eq!(sbe::to_binary(&encoded).unwrap(), (bytes, encoded.len()));
+            let len = cut.index(encoded.len());
+            prop_assert!(decode_sbe_stream(&encoded[..len]).map_or(true, |m| m.is_empty()));
+        }
+
+        /// Protobuf datagrams decode to the same messages, and concatenate.
+        #[test]
+        fn protobuf_round_trip(msgs in prop::collection::vec(message(), 1..5)) {
+            let encoded: Vec<u8> = msgs.iter().flat_map(|m| protobuf::encode(m, false)).collect();
+            prop_assert!(protobuf::is_protobuf(&encoded));
+            let decoded = decode_datagram(&encoded).unwrap();
+            prop_assert_eq!(decoded.len(), msgs.len());
+            for (d, m) in decoded.iter().zip(&msgs) {
+                prop_assert_eq!(d.to_wire_binary(), m.to_wire_binary());
+            }
+        }
+
+        /// FlatBuffers messages decode to the same messages back to back, and
+        /// any strict prefix fails cleanly.
+        #[cfg(feature = "flatbuffers")]
+        #[test]
+        fn flatbuffers_round_trip(
+            msgs in prop::collection::vec(message(), 1..5),
+            cut in any::<prop::sample::Index>(),
+        ) {
+            let encoded: Vec<u8> =
+                msgs.iter().flat_map(|m| flatbuffers::encode(m, false)).collect();
+            let decoded = decode_datagram(&encoded).unwrap();
+            prop_assert_eq!(decoded.len(), msgs.len());
+            for (d, m) in decoded.iter().zip(&msgs) {
+                prop_assert_eq!(d.to_wire_binary(), m.to_wire_binary());
+            }
+            let first = flatbuffers::encode(&msgs[0], false);
+            prop_assert!(flatbuffers::decode(&first[..cut.index(first.len())]).is_err()
+                || cut.index(first.len()) == 0);
+        }
+
+        /// Every strict prefix of a message is rejected, never misread.
+        #[test]
+        fn truncated_binary_is_an_error(msg in message(), cut in any::<prop::sample::Index>()) {
+            let bytes = msg.to_wire_binary();
+            let len = cut.index(bytes.len());
+            prop_assert!(decode_binary(&bytes[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn vectors_round_trip_in_both_formats() {
+        let msgs = vectors::canonical();
+        let stream: Vec<u8> = msgs.iter().flat_map(|m| m.to_wire_binary()).collect();
+        let decoded = decode_binary_stream(&stream).unwrap();
+        assert_eq!(decoded.len(), msgs.len());
+        for (d, m) in decoded.iter().zip(&msgs) {
+            assert_eq!(d.to_wire_binary(), m.to_wire_binary());
+            let from_text = decode_text(&m.to_wire_text()).unwrap();
+            assert_eq!(from_text.to_wire_binary(), m.to_wire_binary(), "{}", m);
+        }
+    }
+
+    #[test]
+    fn v1_orders_and_cancels_keep_the_original_layout() {
+        let order = Order {
+            id: 42,
+            side: Side::Buy,
+            order_type: OrderType::Market,
+            price: 0.0,
+            size: 9,
+            reserve: 0,
+            participant_id: 3,
+            cl_ord_id: "C3-1".to_string(),
+            instrument: 2,
+            created_at: 1.5,
+            ttl: 0.0,
+        };
+        let mut bytes = vec![0; crate::order::ORDER_V1_LEN];
+        assert_eq!(order.encode_v1_into(&mut bytes), bytes.len());
+        let mut expected = b"OF\x01\x01".to_vec();
+        expected.extend_from_slice(&42u64.to_le_bytes());
+        expected.extend_from_slice(&[1, 2]);
+        expected.extend_from_slice(&999_999.0f64.to_le_bytes());
+        expected.extend_from_slice(&9u32.to_le_bytes());
+        expected.extend_from_slice(&1.5f64.to_le_bytes());
+        assert_eq!(bytes, expected);
+        let Message::Order(decoded) = decode_binary(&bytes).unwrap() else {
+            panic!("v1 ORDER decodes to an order");
+        };
+        assert_eq!(
+            (decoded.id, decoded.order_type, decoded.price, decoded.size),
+            (42, OrderType::Market, 0.0, 9)
+        );
+
+        let mut cancel = vec![0; crate::order::CANCEL_V1_LEN];
+        crate::order::cancel_encode_v1_into(42, 2.5, &mut cancel);
+        assert_eq!(&cancel[..4], b"OF\x01\x02");
+        assert!(matches!(
+            decode_binary(&cancel).unwrap(),
+            Message::Cancel { id: 42, instrument: 0, time } if time == 2.5
+        ));
+
+        // Nothing else was ever published as v1
+        let mut trade = vectors::canonical()
+            .into_iter()
+            .find(|m| matches!(m, Message::Trade(_)))
+            .unwrap()
+            .to_wire_binary();
+        trade[2] = VERSION_1;
+        assert!(decode_binary(&trade).is_err());
+    }
+
+    #[test]
+    fn vectors_session_end_counts_the_vectors_before_it() {
+        let msgs = vectors::canonical();
+        let Some(Message::SessionEnd(end)) = msgs.last() else {
+            panic!("vectors end with SESSION_END");
+        };
+        assert_eq!(end.messages, msgs.len() as u64 - 1);
+    }
+
+    #[test]
+    fn text_prices_keep_sub_cent_ticks() {
+        let trade = Message::Trade(Trade {
+            maker_id: 1,
+            taker_id: 2,
+            aggressor: Side::Buy,
+            price: 1.2345,
+            size: 100,
+            instrument: 3,
+            time: 0.5,
+        });
+        let text = trade.to_wire_text();
+        assert!(text.contains("|price=1.2345|"), "{}", text);
+        let decoded = decode_text(&text).unwrap();
+        assert_eq!(decoded.to_wire_binary(), trade.to_wire_binary());
+
+        // Cent prices read as they always have.
+        assert_eq!(crate::wire::Price(100.1).to_string(), "100.10");
+        assert_eq!(crate::wire::Price(99.95).to_string(), "99.95");
+        assert_eq!(crate::wire::Price(0.0).to_string(), "0.00");
+    }
+}
@@ -0,0 +1,729 @@
+//! FlatBuffers (`wire_format = "flatbuffers"`, behind the `flatbuffers`
+//! feature): every message is a size-prefixed `Message` buffer of
+//! `schema/orderflow.fbs`, and batched ones go back to back.
+//!
+//! Messages are built with the `flatbuffers` crate. Decoding walks the
+//! tables with bounds-checked reads instead of the crate's unchecked
+//! accessors, so a corrupted datagram is an error rather than a bad read.
+
+use std::error::Error;
+
+use ::flatbuffers::{FlatBufferBuilder, Push, PushAlignment, UnionWIPOffset, VOffsetT, WIPOffset};
+
+use super::decode::{self, Message};
+use crate::auction::{self, Imbalance, Uncross};
+use crate::bands::{Reject, RejectReason};
+use crate::bars::Bar;
+use crate::book::{Depth, Level, LevelUpdate, Quote, SelfMatch, SelfMatchMode};
+use crate::options::{OptionKind, OptionQuote};
+use crate::order::{self, DarkTrade, MassCancel, Order, OrderType, Side, Trade};
+use crate::refdata::Instrument;
+use crate::venue::{
+    Admin, ReferencePrice, SessionEnd, SessionStart, Severity, SnapshotEnd, SnapshotStart,
+    TradingState, VenueState,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+pub const FILE_IDENTIFIER: &str = "OFFB";
+
+/// `Body` type codes, in schema declaration order.
+const BODY_ORDER: u8 = 1;
+const BODY_CANCEL: u8 = 2;
+const BODY_BAR: u8 = 3;
+const BODY_QUOTE: u8 = 4;
+const BODY_DEPTH: u8 = 5;
+const BODY_TRADE: u8 = 6;
+const BODY_MASS_CANCEL: u8 = 7;
+const BODY_VENUE_STATE: u8 = 8;
+const BODY_REJECT: u8 = 9;
+const BODY_IMBALANCE: u8 = 10;
+const BODY_UNCROSS: u8 = 11;
+const BODY_SESSION_START: u8 = 12;
+const BODY_SESSION_END: u8 = 13;
+const BODY_SYMBOL: u8 = 14;
+const BODY_REFERENCE_PRICE: u8 = 15;
+const BODY_SNAPSHOT_START: u8 = 16;
+const BODY_SNAPSHOT_END: u8 = 17;
+const BODY_LEVEL_UPDATE: u8 = 18;
+const BODY_ADMIN: u8 = 19;
+const BODY_SELF_MATCH: u8 = 20;
+const BODY_DARK_TRADE: u8 = 21;
+const BODY_OPTION_QUOTE: u8 = 22;
+
+/// `Level` struct: price:double, size:ulong, count:uint, then 4 bytes of padding.
+const LEVEL_SIZE: usize = 24;
+
+/// Whether `buf` starts with a size-prefixed buffer of this schema.
+pub fn is_flatbuffers(buf: &[u8]) -> bool {
+    buf.get(8..12) == Some(FILE_IDENTIFIER.as_bytes())
+}
+
+/// vtable offset of a table's `index`th field.
+fn slot(index: u16) -> VOffsetT {
+    4 + 2 * index
+}
+
+#[derive(Clone, Copy)]
+struct LevelStruct([u8; LEVEL_SIZE]);
+
+impl Push for LevelStruct {
+    type Output = LevelStruct;
+
+    unsafe fn push(&self, dst: &mut [u8], _written_len: usize) {
+        dst[..LEVEL_SIZE].copy_from_slice(&self.0);
+    }
+
+    fn alignment() -> PushAlignment {
+        PushAlignment::new(8)
+    }
+}
+
+/// `msg` as a size-prefixed `Message` buffer. Market orders carry the old
+/// sentinel price with `legacy_market_price`, and no price otherwise.
+pub fn encode(msg: &Message, legacy_market_price: bool) -> Vec<u8> {
+    let mut fbb = FlatBufferBuilder::with_capacity(128);
+    let (kind, body) = body(&mut fbb, msg, legacy_market_price);
+    let root = fbb.start_table();
+    fbb.push_slot_always(slot(1), body);
+    fbb.push_slot_always(slot(0), kind);
+    let root = fbb.end_table(root);
+    fbb.finish_size_prefixed(root, Some(FILE_IDENTIFIER));
+    fbb.finished_data().to_vec()
+}
+
+/// Every message of back-to-back size-prefixed buffers: one datagram, or a
+/// dump of several.
+pub fn decode(buf: &[u8]) -> Result<Vec<Message>> {
+    let mut out = Vec::new();
+    let mut rest = buf;
+    while !rest.is_empty() {
+        let len = u32::from_le_bytes(read(rest, 0)?) as usize;
+        let msg = rest
+            .get(4..4 + len)
+            .ok_or_else(|| format!("truncated FlatBuffers message: {} bytes", rest.len()))?;
+        out.push(message(msg)?);
+        rest = &rest[4 + len..];
+    }
+    Ok(out)
+}
+
+/// One table: its fields are pushed by `fields`, after any strings and
+/// vectors it refers to.
+fn table(
+    fbb: &mut FlatBufferBuilder<'_>,
+    kind: u8,
+    fields: impl FnOnce(&mut FlatBufferBuilder<'_>),
+) -> (u8, WIPOffset<UnionWIPOffset>) {
+    let start = fbb.start_table();
+    fields(fbb);
+    (kind, fbb.end_table(start).as_union_value())
+}
+
+fn side_code(side: Option<Side>) -> u8 {
+    match side {
+        None => 0,
+        Some(Side::Buy) => 1,
+        Some(Side::Sell) => 2,
+    }
+}
+
+fn body(
+    fbb: &mut FlatBufferBuilder<'_>,
+    msg: &Message,
+    legacy_market_price: bool,
+) -> (u8, WIPOffset<UnionWIPOffset>) {
+    match msg {
+        Message::Order(o) => {
+            let cl_ord_id = fbb.create_string(&o.cl_ord_id);
+            let price = match o.order_type {
+                OrderType::Market if legacy_market_price => {
+                    Some(order::legacy_market_price(o.side))
+                }
+                OrderType::Market => None,
+                _ => Some(o.price),
+            };
+            table(fbb, BODY_ORDER, |t| {
+                t.push_slot_always(slot(0), o.id);
+                t.push_slot_always(slot(1), side_code(Some(o.side)));
+                t.push_slot_always::<u8>(
+                    slot(2),
+                    match o.order_type {
+                        OrderType::Limit => 1,
+                        OrderType::Market => 2,
+                        OrderType::Stop => 3,
+                        OrderType::Iceberg => 4,
+                    },
+                );
+                if let Some(price) = price {
+                    t.push_slot_always(slot(3), price);
+                }
+                t.push_slot_always(slot(4), o.size);
+                t.push_slot_always(slot(5), o.created_at);
+                t.push_slot_always(slot(6), o.participant_id);
+                t.push_slot_always(slot(7), o.total_size());
+                t.push_slot_always(slot(8), cl_ord_id);
+                t.push_slot_always(slot(9), o.instrument);
+            })
+        }
+        Message::Cancel {
+            id,
+            instrument,
+            time,
+        } => table(fbb, BODY_CANCEL, |t| {
+            t.push_slot_always(slot(0), *id);
+            t.push_slot_always(slot(1), *time);
+            t.push_slot_always(slot(2), *instrument);
+        }),
+        Message::Bar(m) => table(fbb, BODY_BAR, |t| {
+            t.push_slot_always(slot(0), m.start);
+            t.push_slot_always(slot(1), m.end);
+            t.push_slot_always(slot(2), m.open);
+            t.push_slot_always(slot(3), m.high);
+            t.push_slot_always(slot(4), m.low);
+            t.push_slot_always(slot(5), m.close);
+            t.push_slot_always(slot(6), m.volume);
+            t.push_slot_always(slot(7), m.trades);
+            t.push_slot_always(slot(8), m.instrument);
+        }),
+        Message::Quote(m) => table(fbb, BODY_QUOTE, |t| {
+            t.push_slot_always(slot(0), m.bid_price);
+            t.push_slot_always(slot(1), m.bid_size);
+            t.push_slot_always(slot(2), m.ask_price);
+            t.push_slot_always(slot(3), m.ask_size);
+            t.push_slot_always(slot(4), m.time);
+            t.push_slot_always(slot(5), m.instrument);
+        }),
+        Message::Depth(m) => {
+            let mut levels = |side: &[(f64, Level)]| {
+                let structs: Vec<LevelStruct> = side
+                    .iter()
+                    .map(|(price, level)| {
+                        let mut s = [0; LEVEL_SIZE];
+                        s[..8].copy_from_slice(&price.to_le_bytes());
+                        s[8..16].copy_from_slice(&level.size.to_le_bytes());
+                        s[16..20].copy_from_slice(&level.count.to_le_bytes());
+                        LevelStruct(s)
+                    })
+                    .collect();
+                fbb.create_vector(&structs)
+            };
+            let (bids, asks) = (levels(&m.bids), levels(&m.asks));
+            table(fbb, BODY_DEPTH, |t| {
+                t.push_slot_always(slot(0), m.time);
+                t.push_slot_always(slot(1), bids);
+                t.push_slot_always(slot(2), asks);
+                t.push_slot_always(slot(3), m.instrument);
+            })
+        }
+        Message::Trade(m) => table(fbb, BODY_TRADE, |t| {
+            t.push_slot_always(slot(0), m.maker_id);
+            t.push_slot_always(slot(1), m.taker_id);
+            t.push_slot_always(slot(2), side_code(Some(m.aggressor)));
+            t.push_slot_always(slot(3), m.price);
+            t.push_slot_always(slot(4), m.size);
+            t.push_slot_always(slot(5), m.time);
+            t.push_slot_always(slot(6), m.instrument);
+        }),
+        Message::MassCancel(m) => table(fbb, BODY_MASS_CANCEL, |t| {
+            t.push_slot_always(slot(0), m.participant_id);
+            t.push_slot_always(slot(1), side_code(m.side));
+            t.push_slot_always(slot(2), m.time);
+            t.push_slot_always(slot(3), m.instrument);
+        }),
+        Message::VenueState(m) => table(fbb, BODY_VENUE_STATE, |t| {
+            t.push_slot_always::<u8>(
+                slot(0),
+                match m.state {
+                    TradingState::Halted => 1,
+                    TradingState::Open => 2,
+                    TradingState::Auction => 3,
+                    TradingState::Closed => 4,
+                },
+            );
+            t.push_slot_always(slot(1), m.time);
+            t.push_slot_always(slot(2), m.instrument);
+        }),
+        Message::Reject(m) => table(fbb, BODY_REJECT, |t| {
+            t.push_slot_always(slot(0), m.order.id);
+            t.push_slot_always(slot(1), side_code(Some(m.order.side)));
+            t.push_slot_always(slot(2), m.order.price);
+            t.push_slot_always(slot(3), m.order.size);
+            t.push_slot_always(slot(4), m.order.participant_id);
+            t.push_slot_always::<u8>(
+                slot(5),
+                match m.reason {
+                    RejectReason::PriceBand => 1,
+                    RejectReason::ZeroSize => 2,
+                    RejectReason::PriceCollar => 3,
+                },
+            );
+            t.push_slot_always(slot(6), m.lower);
+            t.push_slot_always(slot(7), m.upper);
+            t.push_slot_always(slot(8), m.time);
+            t.push_slot_always(slot(9), m.order.instrument);
+        }),
+        Message::Imbalance(m) => {
+            let (side, imbalance) = auction::surplus(&m.indicative);
+            table(fbb, BODY_IMBALANCE, |t| {
+                if let Some(price) = m.indicative.price {
+                    t.push_slot_always(slot(0), price);
+                }
+                t.push_slot_always(slot(1), m.indicative.matched);
+                t.push_slot_always(slot(2), imbalance);
+                t.push_slot_always(slot(3), side_code(side));
+                t.push_slot_always(slot(4), m.time);
+                t.push_slot_always(slot(5), m.instrument);
+            })
+        }
+        Message::Uncross(m) => table(fbb, BODY_UNCROSS, |t| {
+            t.push_slot_always(slot(0), m.price);
+            t.push_slot_always(slot(1), m.volume);
+            t.push_slot_always(slot(2), m.time);
+            t.push_slot_always(slot(3), m.instrument);
+        }),
+        Message::SessionStart(m) => table(fbb, BODY_SESSION_START, |t| {
+            t.push_slot_always(slot(0), m.tick_size);
+            t.push_slot_always(slot(1), m.lot_size);
+            t.push_slot_always(slot(2), m.initial_price);
+            t.push_slot_always(slot(3), m.time);
+            t.push_slot_always(slot(4), m.instrument);
+        }),
+        Message::SessionEnd(m) => table(fbb, BODY_SESSION_END, |t| {
+            t.push_slot_always(slot(0), m.messages);
+            t.push_slot_always(slot(1), m.time);
+            t.push_slot_always(slot(2), m.instrument);
+        }),
+        Message::Symbol {
+            index,
+            instrument,
+            time,
+        } => {
+            let symbol = fbb.create_string(&instrument.symbol);
+            let isin = fbb.create_string(&instrument.isin);
+            table(fbb, BODY_SYMBOL, |t| {
+                t.push_slot_always(slot(0), symbol);
+                t.push_slot_always(slot(1), isin);
+                t.push_slot_always(slot(2), instrument.tick_size);
+                t.push_slot_always(slot(3), instrument.lot_size);
+                t.push_slot_always(slot(4), *time);
+                t.push_slot_always(slot(5), *index);
+            })
+        }
+        Message::ReferencePrice(m) => table(fbb, BODY_REFERENCE_PRICE, |t| {
+            t.push_slot_always(slot(0), m.price);
+            t.push_slot_always(slot(1), m.prev_close);
+            t.push_slot_always(slot(2), m.time);
+            t.push_slot_always(slot(3), m.instrument);
+        }),
+        Message::SnapshotStart(m) => table(fbb, BODY_SNAPSHOT_START, |t| {
+            t.push_slot_always(slot(0), m.orders);
+            t.push_slot_always(slot(1), m.time);
+            t.push_slot_always(slot(2), m.instrument);
+        }),
+        Message::SnapshotEnd(m) => table(fbb, BODY_SNAPSHOT_END, |t| {
+            t.push_slot_always(slot(0), m.time);
+            t.push_slot_always(slot(1), m.instrument);
+        }),
+        Message::Level(m) => table(fbb, BODY_LEVEL_UPDATE, |t| {
+            t.push_slot_always(slot(0), side_code(Some(m.side)));
+            t.push_slot_always(slot(1), m.price);
+            t.push_slot_always(slot(2), m.size);
+            t.push_slot_always(slot(3), m.count);
+            t.push_slot_always(slot(4), m.time);
+            t.push_slot_always(slot(5), m.instrument);
+        }),
+        Message::Admin(m) => {
+            let text = fbb.create_string(&m.text);
+            table(fbb, BODY_ADMIN, |t| {
+                t.push_slot_always::<u8>(
+                    slot(0),
+                    match m.severity {
+                        Severity::Info => 1,
+                        Severity::Warning => 2,
+                        Severity::Alert => 3,
+                    },
+                );
+                t.push_slot_always(slot(1), m.code);
+                t.push_slot_always(slot(2), text);
+                t.push_slot_always(slot(3), m.time);
+                t.push_slot_always(slot(4), m.instrument);
+            })
+        }
+        Message::SelfMatch(m) => table(fbb, BODY_SELF_MATCH, |t| {
+            t.push_slot_always(slot(0), m.maker_id);
+            t.push_slot_always(slot(1), m.taker_id);
+            t.push_slot_always(slot(2), m.participant_id);
+            t.push_slot_always::<u8>(
+                slot(3),
+                match m.mode {
+                    SelfMatchMode::Allow => 0,
+                    SelfMatchMode::CancelOldest => 1,
+                    SelfMatchMode::Decrement => 2,
+                },
+            );
+            t.push_slot_always(slot(4), m.size);
+            t.push_slot_always(slot(5), m.time);
+            t.push_slot_always(slot(6), m.instrument);
+        }),
+        Message::DarkTrade(m) => table(fbb, BODY_DARK_TRADE, |t| {
+            t.push_slot_always(slot(0), side_code(Some(m.aggressor)));
+            t.push_slot_always(slot(1), m.price);
+            t.push_slot_always(slot(2), m.size);
+            t.push_slot_always(slot(3), m.participant_id);
+            t.push_slot_always(slot(4), m.time);
+            t.push_slot_always(slot(5), m.instrument);
+        }),
+        Message::OptionQuote(m) => table(fbb, BODY_OPTION_QUOTE, |t| {
+            t.push_slot_always(slot(0), m.expiry);
+            t.push_slot_always(slot(1), m.strike);
+            t.push_slot_always::<u8>(
+                slot(2),
+                match m.kind {
+                    OptionKind::Call => 1,
+                    OptionKind::Put => 2,
+                },
+            );
+            t.push_slot_always(slot(3), m.bid);
+            t.push_slot_always(slot(4), m.ask);
+            t.push_slot_always(slot(5), m.iv);
+            t.push_slot_always(slot(6), m.underlying);
+            t.push_slot_always(slot(7), m.time);
+            t.push_slot_always(slot(8), m.instrument);
+        }),
+    }
+}
+
+fn read<const N: usize>(buf: &[u8], at: usize) -> Result<[u8; N]> {
+    buf.get(at..at.saturating_add(N))
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| format!("FlatBuffers read of {} bytes at {} is out of bounds", N, at).into())
+}
+
+/// Follow the uoffset at `at`.
+fn follow(buf: &[u8], at: usize) -> Result<usize> {
+    Ok(at.saturating_add(u32::from_le_bytes(read(buf, at)?) as usize))
+}
+
+/// A table inside one message buffer.
+struct Table<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    vtable: usize,
+    vtable_len: usize,
+}
+
+impl<'a> Table<'a> {
+    fn at(buf: &'a [u8], pos: usize) -> Result<Self> {
+        let soffset = i32::from_le_bytes(read(buf, pos)?);
+        let vtable = usize::try_from(pos as i64 - i64::from(soffset))
+            .map_err(|_| "FlatBuffers vtable offset out of bounds")?;
+        let vtable_len = u16::from_le_bytes(read(buf, vtable)?) as usize;
+        Ok(Table {
+            buf,
+            pos,
+            vtable,
+            vtable_len,
+        })
+    }
+
+    /// Position of field `index`, if present.
+    fn field(&self, index: u16) -> Result<Option<usize>> {
+        let entry = slot(index) as usize;
+        if entry + 2 > self.vtable_len {
+            return Ok(None);
+        }
+        let offset = u16::from_le_bytes(read(self.buf, self.vtable + entry)?);
+        Ok((offset != 0).then(|| self.pos + offset as usize))
+    }
+
+    fn scalar<const N: usize>(&self, index: u16) -> Result<[u8; N]> {
+        match self.field(index)? {
+            Some(at) => read(self.buf, at),
+            None => Ok([0; N]),
+        }
+    }
+
+    fn u8(&self, index: u16) -> Result<u8> {
+        Ok(self.scalar::<1>(index)?[0])
+    }
+
+    fn u16(&self, index: u16) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.scalar(index)?))
+    }
+
+    fn u32(&self, index: u16) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.scalar(index)?))
+    }
+
+    fn u64(&self, index: u16) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.scalar(index)?))
+    }
+
+    fn f64(&self, index: u16) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.scalar(index)?))
+    }
+
+    fn optional_f64(&self, index: u16) -> Result<Option<f64>> {
+        self.field(index)?
+            .map(|at| Ok(f64::from_le_bytes(read(self.buf, at)?)))
+            .transpose()
+    }
+
+    /// Bytes of the vector at field `index` of `elem` bytes per element;
+    /// empty when absent.
+    fn vector(&self, index: u16, elem: usize) -> Result<&'a [u8]> {
+        let Some(at) = self.field(index)? else {
+            return Ok(&[]);
+        };
+        let start = follow(self.buf, at)?;
+        let len = u32::from_le_bytes(read(self.buf, start)?) as usize;
+        self.buf
+            .get(start + 4..start + 4 + len * elem)
+            .ok_or_else(|| "FlatBuffers vector out of bounds".into())
+    }
+
+    fn string(&self, index: u16) -> Result<String> {
+        let bytes = self.vector(index, 1)?;
+        Ok(std::str::from_utf8(bytes)
+            .map_err(|e| format!("invalid FlatBuffers string: {}", e))?
+            .to_string())
+    }
+
+    fn table(&self, index: u16) -> Result<Table<'a>> {
+        let at = self.field(index)?.ok_or("missing FlatBuffers table")?;
+        Table::at(self.buf, follow(self.buf, at)?)
+    }
+}
+
+fn optional_side(code: u8) -> Result<Option<Side>> {
+    match code {
+        0 => Ok(None),
+        1 => Ok(Some(Side::Buy)),
+        2 => Ok(Some(Side::Sell)),
+        c => Err(format!("invalid side {}", c).into()),
+    }
+}
+
+fn side(code: u8) -> Result<Side> {
+    optional_side(code)?.ok_or_else(|| "missing side".into())
+}
+
+/// One message buffer, without its size prefix.
+fn message(buf: &[u8]) -> Result<Message> {
+    if buf.get(4..8) != Some(FILE_IDENTIFIER.as_bytes()) {
+        return Err("FlatBuffers message without the OFFB identifier".into());
+    }
+    let root = Table::at(buf, follow(buf, 0)?)?;
+    let kind = root.u8(0)?;
+    let t = root.table(1)?;
+    Ok(match kind {
+        BODY_ORDER => {
+            let order_type = match t.u8(2)? {
+                1 => OrderType::Limit,
+                2 => OrderType::Market,
+                3 => OrderType::Stop,
+                4 => OrderType::Iceberg,
+                c => return Err(format!("invalid order type {}", c).into()),
+            };
+            // As in the binary format, a market order's price is dropped.
+            let price = match order_type {
+                OrderType::Market => 0.0,
+                _ => t.optional_f64(3)?.ok_or("ORDER without a price")?,
+            };
+            let size = t.u32(4)?;
+            Message::Order(Order {
+                id: t.u64(0)?,
+                side: side(t.u8(1)?)?,
+                order_type,
+                price,
+                size,
+                reserve: t.u32(7)?.saturating_sub(size),
+                participant_id: t.u32(6)?,
+                cl_ord_id: t.string(8)?,
+                instrument: t.u16(9)?,
+                created_at: t.f64(5)?,
+                ttl: 0.0,
+            })
+        }
+        BODY_CANCEL => Message::Cancel {
+            id: t.u64(0)?,
+            instrument: t.u16(2)?,
+            time: t.f64(1)?,
+        },
+        BODY_BAR => Message::Bar(Bar {
+            start: t.f64(0)?,
+            end: t.f64(1)?,
+            open: t.f64(2)?,
+            high: t.f64(3)?,
+            low: t.f64(4)?,
+            close: t.f64(5)?,
+            volume: t.u64(6)?,
+            trades: t.u32(7)?,
+            instrument: t.u16(8)?,
+        }),
+        BODY_QUOTE => Message::Quote(Quote {
+            bid_price: t.f64(0)?,
+            bid_size: t.u64(1)?,
+            ask_price: t.f64(2)?,
+            ask_size: t.u64(3)?,
+            instrument: t.u16(5)?,
+            time: t.f64(4)?,
+        }),
+        BODY_DEPTH => {
+            let levels = |index| -> Result<Vec<(f64, Level)>> {
+                Ok(t.vector(index, LEVEL_SIZE)?
+                    .chunks_exact(LEVEL_SIZE)
+                    .map(|s| {
+                        let price = f64::from_le_bytes(s[..8].try_into().unwrap());
+                        let size = u64::from_le_bytes(s[8..16].try_into().unwrap());
+                        let count = u32::from_le_bytes(s[16..20].try_into().unwrap());
+                        (price, Level { size, count })
+                    })
+                    .collect())
+            };
+            Message::Depth(Depth {
+                bids: levels(1)?,
+                asks: levels(2)?,
+                instrument: t.u16(3)?,
+                time: t.f64(0)?,
+            })
+        }
+        BODY_TRADE => Message::Trade(Trade {
+            maker_id: t.u64(0)?,
+            taker_id: t.u64(1)?,
+            aggressor: side(t.u8(2)?)?,
+            price: t.f64(3)?,
+            size: t.u32(4)?,
+            instrument: t.u16(6)?,
+            time: t.f64(5)?,
+        }),
+        BODY_MASS_CANCEL => Message::MassCancel(MassCancel {
+            participant_id: t.u32(0)?,
+            side: optional_side(t.u8(1)?)?,
+            instrument: t.u16(3)?,
+            time: t.f64(2)?,
+        }),
+        BODY_VENUE_STATE => Message::VenueState(VenueState {
+            state: match t.u8(0)? {
+                1 => TradingState::Halted,
+                2 => TradingState::Open,
+                3 => TradingState::Auction,
+                4 => TradingState::Closed,
+                c => return Err(format!("invalid venue state {}", c).into()),
+            },
+            instrument: t.u16(2)?,
+            time: t.f64(1)?,
+        }),
+        BODY_REJECT => Message::Reject(Reject {
+            order: decode::rejected_order(
+                t.u64(0)?,
+                side(t.u8(1)?)?,
+                t.f64(2)?,
+                t.u32(3)?,
+                t.u32(4)?,
+                t.u16(9)?,
+            ),
+            reason: match t.u8(5)? {
+                1 => RejectReason::PriceBand,
+                2 => RejectReason::ZeroSize,
+                3 => RejectReason::PriceCollar,
+                c => return Err(format!("invalid reject reason {}", c).into()),
+            },
+            lower: t.f64(6)?,
+            upper: t.f64(7)?,
+            time: t.f64(8)?,
+        }),
+        BODY_IMBALANCE => {
+            let price = t.optional_f64(0)?.unwrap_or(0.0);
+            let side = optional_side(t.u8(3)?)?;
+            Message::Imbalance(Imbalance {
+                indicative: decode::indicative(price, t.u64(1)?, t.u64(2)?, side),
+                instrument: t.u16(5)?,
+                time: t.f64(4)?,
+            })
+        }
+        BODY_UNCROSS => Message::Uncross(Uncross {
+            price: t.f64(0)?,
+            volume: t.u64(1)?,
+            instrument: t.u16(3)?,
+            time: t.f64(2)?,
+        }),
+        BODY_SESSION_START => Message::SessionStart(SessionStart {
+            tick_size: t.f64(0)?,
+            lot_size: t.u32(1)?,
+            initial_price: t.f64(2)?,
+            instrument: t.u16(4)?,
+            time: t.f64(3)?,
+        }),
+        BODY_SESSION_END => Message::SessionEnd(SessionEnd {
+            messages: t.u64(0)?,
+            instrument: t.u16(2)?,
+            time: t.f64(1)?,
+        }),
+        BODY_SYMBOL => Message::Symbol {
+            index: t.u16(5)?,
+            instrument: Instrument::new(&t.string(0)?, Some(&t.string(1)?), t.f64(2)?, t.u32(3)?),
+            time: t.f64(4)?,
+        },
+        BODY_REFERENCE_PRICE => Message::ReferencePrice(ReferencePrice {
+            price: t.f64(0)?,
+            prev_close: t.f64(1)?,
+            instrument: t.u16(3)?,
+            time: t.f64(2)?,
+        }),
+        BODY_SNAPSHOT_START => Message::SnapshotStart(SnapshotStart {
+            orders: t.u64(0)?,
+            instrument: t.u16(2)?,
+            time: t.f64(1)?,
+        }),
+        BODY_SNAPSHOT_END => Message::SnapshotEnd(SnapshotEnd {
+            instrument: t.u16(1)?,
+            time: t.f64(0)?,
+        }),
+        BODY_LEVEL_UPDATE => Message::Level(LevelUpdate {
+            side: side(t.u8(0)?)?,
+            price: t.f64(1)?,
+            size: t.u64(2)?,
+            count: t.u32(3)?,
+            instrument: t.u16(5)?,
+            time: t.f64(4)?,
+        }),
+        BODY_ADMIN => Message::Admin(Admin {
+            severity: decode::severity(t.u8(0)?)?,
+            code: t.u16(1)?,
+            text: t.string(2)?,
+            instrument: t.u16(4)?,
+            time: t.f64(3)?,
+        }),
+        BODY_SELF_MATCH => Message::SelfMatch(SelfMatch {
+            maker_id: t.u64(0)?,
+            taker_id: t.u64(1)?,
+            participant_id: t.u32(2)?,
+            mode: decode::self_match_mode(t.u8(3)?)?,
+            size: t.u32(4)?,
+            instrument: t.u16(6)?,
+            time: t.f64(5)?,
+        }),
+        BODY_DARK_TRADE => Message::DarkTrade(DarkTrade {
+            aggressor: side(t.u8(0)?)?,
+            price: t.f64(1)?,
+            size: t.u32(2)?,
+            participant_id: t.u32(3)?,
+            instrument: t.u16(5)?,
+            time: t.f64(4)?,
+        }),
+        BODY_OPTION_QUOTE => Message::OptionQuote(OptionQuote {
+            expiry: t.u32(0)?,
+            strike: t.f64(1)?,
+            kind: decode::option_kind(t.u8(2)?)?,
+            bid: t.f64(3)?,
+            ask: t.f64(4)?,
+            iv: t.f64(5)?,
+            underlying: t.f64(6)?,
+            instrument: t.u16(8)?,
+            time: t.f64(7)?,
+        }),
+        k => return Err(format!("unknown FlatBuffers body type {}", k).into()),
+    })
+}
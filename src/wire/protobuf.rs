@@ -0,0 +1,1009 @@
+//! Protocol Buffers (`wire_format = "protobuf"`): every datagram is a
+//! `Datagram` of `proto/orderflow.proto`. Encoded datagrams concatenate into
+//! one holding all their messages, which is how messages are batched.
+//!
+//! The `pb` types are what `prost-build` generates for the schema, written
+//! out so the build doesn't need `protoc`; keep the two in step.
+
+use std::error::Error;
+
+use prost::Message as _;
+
+use super::decode::{self, Message};
+use crate::auction::{self, Imbalance, Uncross};
+use crate::bands::{Reject, RejectReason};
+use crate::bars::Bar;
+use crate::book::{Depth, Level, LevelUpdate, Quote, SelfMatch, SelfMatchMode};
+use crate::options::{OptionKind, OptionQuote};
+use crate::order::{self, DarkTrade, MassCancel, Order, OrderType, Side, Trade};
+use crate::refdata::Instrument;
+use crate::venue::{
+    Admin, ReferencePrice, SessionEnd, SessionStart, Severity, SnapshotEnd, SnapshotStart,
+    TradingState, VenueState,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// First byte of every datagram: `Datagram.messages` (field 1, length-delimited).
+const DATAGRAM_TAG: u8 = 0x0A;
+
+#[allow(clippy::derive_partial_eq_without_eq)]
+pub mod pb {
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Datagram {
+        #[prost(message, repeated, tag = "1")]
+        pub messages: Vec<Message>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Message {
+        #[prost(
+            oneof = "message::Body",
+            tags = "1, 2, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23"
+        )]
+        pub body: Option<message::Body>,
+    }
+
+    pub mod message {
+        #[derive(Clone, PartialEq, ::prost::Oneof)]
+        pub enum Body {
+            #[prost(message, tag = "1")]
+            Order(super::Order),
+            #[prost(message, tag = "2")]
+            Cancel(super::Cancel),
+            #[prost(message, tag = "4")]
+            Bar(super::Bar),
+            #[prost(message, tag = "5")]
+            Quote(super::Quote),
+            #[prost(message, tag = "6")]
+            Depth(super::Depth),
+            #[prost(message, tag = "7")]
+            Trade(super::Trade),
+            #[prost(message, tag = "8")]
+            MassCancel(super::MassCancel),
+            #[prost(message, tag = "9")]
+            VenueState(super::VenueState),
+            #[prost(message, tag = "10")]
+            Reject(super::Reject),
+            #[prost(message, tag = "11")]
+            Imbalance(super::Imbalance),
+            #[prost(message, tag = "12")]
+            Uncross(super::Uncross),
+            #[prost(message, tag = "13")]
+            SessionStart(super::SessionStart),
+            #[prost(message, tag = "14")]
+            SessionEnd(super::SessionEnd),
+            #[prost(message, tag = "15")]
+            Symbol(super::Symbol),
+            #[prost(message, tag = "16")]
+            ReferencePrice(super::ReferencePrice),
+            #[prost(message, tag = "17")]
+            SnapshotStart(super::SnapshotStart),
+            #[prost(message, tag = "18")]
+            SnapshotEnd(super::SnapshotEnd),
+            #[prost(message, tag = "19")]
+            LevelUpdate(super::LevelUpdate),
+            #[prost(message, tag = "20")]
+            Admin(super::Admin),
+            #[prost(message, tag = "21")]
+            SelfMatch(super::SelfMatch),
+            #[prost(message, tag = "22")]
+            DarkTrade(super::DarkTrade),
+            #[prost(message, tag = "23")]
+            OptionQuote(super::OptionQuote),
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum OptionType {
+        Unspecified = 0,
+        Call = 1,
+        Put = 2,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Side {
+        Unspecified = 0,
+        Buy = 1,
+        Sell = 2,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum OrderType {
+        Unspecified = 0,
+        Limit = 1,
+        Market = 2,
+        Stop = 3,
+        Iceberg = 4,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum TradingState {
+        Unspecified = 0,
+        Halt = 1,
+        Resume = 2,
+        Auction = 3,
+        Close = 4,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum RejectReason {
+        Unspecified = 0,
+        PriceBand = 1,
+        ZeroSize = 2,
+        PriceCollar = 3,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Severity {
+        Unspecified = 0,
+        Info = 1,
+        Warning = 2,
+        Alert = 3,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum SelfMatchAction {
+        Unspecified = 0,
+        CancelOldest = 1,
+        Decrement = 2,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Order {
+        #[prost(uint64, tag = "1")]
+        pub id: u64,
+        #[prost(enumeration = "Side", tag = "2")]
+        pub side: i32,
+        #[prost(enumeration = "OrderType", tag = "3")]
+        pub r#type: i32,
+        #[prost(double, optional, tag = "4")]
+        pub price: Option<f64>,
+        #[prost(uint32, tag = "5")]
+        pub size: u32,
+        #[prost(double, tag = "6")]
+        pub time: f64,
+        #[prost(uint32, tag = "7")]
+        pub participant_id: u32,
+        #[prost(uint32, tag = "8")]
+        pub total_size: u32,
+        #[prost(string, tag = "9")]
+        pub cl_ord_id: String,
+        #[prost(uint32, tag = "10")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Cancel {
+        #[prost(uint64, tag = "1")]
+        pub id: u64,
+        #[prost(double, tag = "2")]
+        pub time: f64,
+        #[prost(uint32, tag = "3")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Bar {
+        #[prost(double, tag = "1")]
+        pub start: f64,
+        #[prost(double, tag = "2")]
+        pub end: f64,
+        #[prost(double, tag = "3")]
+        pub open: f64,
+        #[prost(double, tag = "4")]
+        pub high: f64,
+        #[prost(double, tag = "5")]
+        pub low: f64,
+        #[prost(double, tag = "6")]
+        pub close: f64,
+        #[prost(uint64, tag = "7")]
+        pub volume: u64,
+        #[prost(uint32, tag = "8")]
+        pub trades: u32,
+        #[prost(uint32, tag = "9")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Quote {
+        #[prost(double, tag = "1")]
+        pub bid: f64,
+        #[prost(uint64, tag = "2")]
+        pub bid_size: u64,
+        #[prost(double, tag = "3")]
+        pub ask: f64,
+        #[prost(uint64, tag = "4")]
+        pub ask_size: u64,
+        #[prost(double, tag = "5")]
+        pub time: f64,
+        #[prost(uint32, tag = "6")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Level {
+        #[prost(double, tag = "1")]
+        pub price: f64,
+        #[prost(uint64, tag = "2")]
+        pub size: u64,
+        #[prost(uint32, tag = "3")]
+        pub count: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Depth {
+        #[prost(double, tag = "1")]
+        pub time: f64,
+        #[prost(message, repeated, tag = "2")]
+        pub bids: Vec<Level>,
+        #[prost(message, repeated, tag = "3")]
+        pub asks: Vec<Level>,
+        #[prost(uint32, tag = "4")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Trade {
+        #[prost(uint64, tag = "1")]
+        pub maker_id: u64,
+        #[prost(uint64, tag = "2")]
+        pub taker_id: u64,
+        #[prost(enumeration = "Side", tag = "3")]
+        pub aggressor: i32,
+        #[prost(double, tag = "4")]
+        pub price: f64,
+        #[prost(uint32, tag = "5")]
+        pub size: u32,
+        #[prost(double, tag = "6")]
+        pub time: f64,
+        #[prost(uint32, tag = "7")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct MassCancel {
+        #[prost(uint32, tag = "1")]
+        pub participant_id: u32,
+        #[prost(enumeration = "Side", tag = "2")]
+        pub side: i32,
+        #[prost(double, tag = "3")]
+        pub time: f64,
+        #[prost(uint32, tag = "4")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct VenueState {
+        #[prost(enumeration = "TradingState", tag = "1")]
+        pub state: i32,
+        #[prost(double, tag = "2")]
+        pub time: f64,
+        #[prost(uint32, tag = "3")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Reject {
+        #[prost(uint64, tag = "1")]
+        pub id: u64,
+        #[prost(enumeration = "Side", tag = "2")]
+        pub side: i32,
+        #[prost(double, tag = "3")]
+        pub price: f64,
+        #[prost(uint32, tag = "4")]
+        pub size: u32,
+        #[prost(uint32, tag = "5")]
+        pub participant_id: u32,
+        #[prost(enumeration = "RejectReason", tag = "6")]
+        pub reason: i32,
+        #[prost(double, tag = "7")]
+        pub lower: f64,
+        #[prost(double, tag = "8")]
+        pub upper: f64,
+        #[prost(double, tag = "9")]
+        pub time: f64,
+        #[prost(uint32, tag = "10")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Imbalance {
+        #[prost(double, optional, tag = "1")]
+        pub price: Option<f64>,
+        #[prost(uint64, tag = "2")]
+        pub matched: u64,
+        #[prost(uint64, tag = "3")]
+        pub imbalance: u64,
+        #[prost(enumeration = "Side", tag = "4")]
+        pub side: i32,
+        #[prost(double, tag = "5")]
+        pub time: f64,
+        #[prost(uint32, tag = "6")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Uncross {
+        #[prost(double, tag = "1")]
+        pub price: f64,
+        #[prost(uint64, tag = "2")]
+        pub volume: u64,
+        #[prost(double, tag = "3")]
+        pub time: f64,
+        #[prost(uint32, tag = "4")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SessionStart {
+        #[prost(double, tag = "1")]
+        pub tick_size: f64,
+        #[prost(uint32, tag = "2")]
+        pub lot_size: u32,
+        #[prost(double, tag = "3")]
+        pub initial_price: f64,
+        #[prost(double, tag = "4")]
+        pub time: f64,
+        #[prost(uint32, tag = "5")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SessionEnd {
+        #[prost(uint64, tag = "1")]
+        pub messages: u64,
+        #[prost(double, tag = "2")]
+        pub time: f64,
+        #[prost(uint32, tag = "3")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Symbol {
+        #[prost(string, tag = "1")]
+        pub symbol: String,
+        #[prost(string, tag = "2")]
+        pub isin: String,
+        #[prost(double, tag = "3")]
+        pub tick_size: f64,
+        #[prost(uint32, tag = "4")]
+        pub lot_size: u32,
+        #[prost(double, tag = "5")]
+        pub time: f64,
+        #[prost(uint32, tag = "6")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ReferencePrice {
+        #[prost(double, tag = "1")]
+        pub price: f64,
+        #[prost(double, tag = "2")]
+        pub prev_close: f64,
+        #[prost(double, tag = "3")]
+        pub time: f64,
+        #[prost(uint32, tag = "4")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SnapshotStart {
+        #[prost(uint64, tag = "1")]
+        pub orders: u64,
+        #[prost(double, tag = "2")]
+        pub time: f64,
+        #[prost(uint32, tag = "3")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SnapshotEnd {
+        #[prost(double, tag = "1")]
+        pub time: f64,
+        #[prost(uint32, tag = "2")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct LevelUpdate {
+        #[prost(enumeration = "Side", tag = "1")]
+        pub side: i32,
+        #[prost(double, tag = "2")]
+        pub price: f64,
+        #[prost(uint64, tag = "3")]
+        pub size: u64,
+        #[prost(uint32, tag = "4")]
+        pub count: u32,
+        #[prost(double, tag = "5")]
+        pub time: f64,
+        #[prost(uint32, tag = "6")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Admin {
+        #[prost(enumeration = "Severity", tag = "1")]
+        pub severity: i32,
+        #[prost(uint32, tag = "2")]
+        pub code: u32,
+        #[prost(string, tag = "3")]
+        pub text: String,
+        #[prost(double, tag = "4")]
+        pub time: f64,
+        #[prost(uint32, tag = "5")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SelfMatch {
+        #[prost(uint64, tag = "1")]
+        pub maker_id: u64,
+        #[prost(uint64, tag = "2")]
+        pub taker_id: u64,
+        #[prost(uint32, tag = "3")]
+        pub participant_id: u32,
+        #[prost(enumeration = "SelfMatchAction", tag = "4")]
+        pub action: i32,
+        #[prost(uint32, tag = "5")]
+        pub size: u32,
+        #[prost(double, tag = "6")]
+        pub time: f64,
+        #[prost(uint32, tag = "7")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct DarkTrade {
+        #[prost(enumeration = "Side", tag = "1")]
+        pub aggressor: i32,
+        #[prost(double, tag = "2")]
+        pub price: f64,
+        #[prost(uint32, tag = "3")]
+        pub size: u32,
+        #[prost(uint32, tag = "4")]
+        pub participant_id: u32,
+        #[prost(double, tag = "5")]
+        pub time: f64,
+        #[prost(uint32, tag = "6")]
+        pub instrument: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct OptionQuote {
+        #[prost(uint32, tag = "1")]
+        pub expiry: u32,
+        #[prost(double, tag = "2")]
+        pub strike: f64,
+        #[prost(enumeration = "OptionType", tag = "3")]
+        pub option_type: i32,
+        #[prost(double, tag = "4")]
+        pub bid: f64,
+        #[prost(double, tag = "5")]
+        pub ask: f64,
+        #[prost(double, tag = "6")]
+        pub iv: f64,
+        #[prost(double, tag = "7")]
+        pub underlying: f64,
+        #[prost(double, tag = "8")]
+        pub time: f64,
+        #[prost(uint32, tag = "9")]
+        pub instrument: u32,
+    }
+}
+
+use pb::message::Body;
+
+/// Whether `buf` starts like a `Datagram`. Text messages start with a
+/// letter, binary ones with `OF` and SBE ones with a blockLength that is
+/// never 10.
+pub fn is_protobuf(buf: &[u8]) -> bool {
+    buf.first() == Some(&DATAGRAM_TAG)
+}
+
+/// `msg` as a one-message `Datagram`. Market orders carry the old sentinel
+/// price with `legacy_market_price`, and no price otherwise.
+pub fn encode(msg: &Message, legacy_market_price: bool) -> Vec<u8> {
+    pb::Datagram {
+        messages: vec![pb::Message {
+            body: Some(body(msg, legacy_market_price)),
+        }],
+    }
+    .encode_to_vec()
+}
+
+/// Every message of a `Datagram`, or of several concatenated.
+pub fn decode(buf: &[u8]) -> Result<Vec<Message>> {
+    let datagram =
+        pb::Datagram::decode(buf).map_err(|e| format!("invalid protobuf datagram: {}", e))?;
+    datagram
+        .messages
+        .into_iter()
+        .map(|m| message(m.body.ok_or("protobuf message without a body")?))
+        .collect()
+}
+
+fn pb_side(side: Side) -> i32 {
+    match side {
+        Side::Buy => pb::Side::Buy as i32,
+        Side::Sell => pb::Side::Sell as i32,
+    }
+}
+
+fn pb_optional_side(side: Option<Side>) -> i32 {
+    side.map_or(pb::Side::Unspecified as i32, pb_side)
+}
+
+fn body(msg: &Message, legacy_market_price: bool) -> Body {
+    match msg {
+        Message::Order(o) => Body::Order(pb::Order {
+            id: o.id,
+            side: pb_side(o.side),
+            r#type: match o.order_type {
+                OrderType::Limit => pb::OrderType::Limit,
+                OrderType::Market => pb::OrderType::Market,
+                OrderType::Stop => pb::OrderType::Stop,
+                OrderType::Iceberg => pb::OrderType::Iceberg,
+            } as i32,
+            price: match o.order_type {
+                OrderType::Market if legacy_market_price => {
+                    Some(order::legacy_market_price(o.side))
+                }
+                OrderType::Market => None,
+                _ => Some(o.price),
+            },
+            size: o.size,
+            time: o.created_at,
+            participant_id: o.participant_id,
+            total_size: o.total_size(),
+            cl_ord_id: o.cl_ord_id.clone(),
+            instrument: o.instrument.into(),
+        }),
+        Message::Cancel {
+            id,
+            instrument,
+            time,
+        } => Body::Cancel(pb::Cancel {
+            id: *id,
+            time: *time,
+            instrument: (*instrument).into(),
+        }),
+        Message::Bar(m) => Body::Bar(pb::Bar {
+            start: m.start,
+            end: m.end,
+            open: m.open,
+            high: m.high,
+            low: m.low,
+            close: m.close,
+            volume: m.volume,
+            trades: m.trades,
+            instrument: m.instrument.into(),
+        }),
+        Message::Quote(m) => Body::Quote(pb::Quote {
+            bid: m.bid_price,
+            bid_size: m.bid_size,
+            ask: m.ask_price,
+            ask_size: m.ask_size,
+            time: m.time,
+            instrument: m.instrument.into(),
+        }),
+        Message::Depth(m) => {
+            let levels = |side: &[(f64, Level)]| {
+                side.iter()
+                    .map(|(price, level)| pb::Level {
+                        price: *price,
+                        size: level.size,
+                        count: level.count,
+                    })
+                    .collect()
+            };
+            Body::Depth(pb::Depth {
+                time: m.time,
+                instrument: m.instrument.into(),
+                bids: levels(&m.bids),
+                asks: levels(&m.asks),
+            })
+        }
+        Message::Trade(m) => Body::Trade(pb::Trade {
+            maker_id: m.maker_id,
+            taker_id: m.taker_id,
+            aggressor: pb_side(m.aggressor),
+            price: m.price,
+            size: m.size,
+            time: m.time,
+            instrument: m.instrument.into(),
+        }),
+        Message::MassCancel(m) => Body::MassCancel(pb::MassCancel {
+            participant_id: m.participant_id,
+            side: pb_optional_side(m.side),
+            time: m.time,
+            instrument: m.instrument.into(),
+        }),
+        Message::VenueState(m) => Body::VenueState(pb::VenueState {
+            state: match m.state {
+                TradingState::Halted => pb::TradingState::Halt,
+                TradingState::Open => pb::TradingState::Resume,
+                TradingState::Auction => pb::TradingState::Auction,
+                TradingState::Closed => pb::TradingState::Close,
+            } as i32,
+            time: m.time,
+            instrument: m.instrument.into(),
+        }),
+        Message::Reject(m) => Body::Reject(pb::Reject {
+            id: m.order.id,
+            side: pb_side(m.order.side),
+            price: m.order.price,
+            size: m.order.size,
+            participant_id: m.order.participant_id,
+            reason: match m.reason {
+                RejectReason::PriceBand => pb::RejectReason::PriceBand,
+                RejectReason::ZeroSize => pb::RejectReason::ZeroSize,
+                RejectReason::PriceCollar => pb::RejectReason::PriceCollar,
+            } as i32,
+            lower: m.lower,
+            upper: m.upper,
+            time: m.time,
+            instrument: m.order.instrument.into(),
+        }),
+        Message::Imbalance(m) => {
+            let (side, imbalance) = auction::surplus(&m.indicative);
+            Body::Imbalance(pb::Imbalance {
+                price: m.indicative.price,
+                matched: m.indicative.matched,
+                imbalance,
+                side: pb_optional_side(side),
+                time: m.time,
+                instrument: m.instrument.into(),
+            })
+        }
+        Message::Uncross(m) => Body::Uncross(pb::Uncross {
+            price: m.price,
+            volume: m.volume,
+            time: m.time,
+            instrument: m.instrument.into(),
+        }),
+        Message::SessionStart(m) => Body::SessionStart(pb::SessionStart {
+            tick_size: m.tick_size,
+            lot_size: m.lot_size,
+            initial_price: m.initial_price,
+            time: m.time,
+            instrument: m.instrument.into(),
+        }),
+        Message::SessionEnd(m) => Body::SessionEnd(pb::SessionEnd {
+            messages: m.messages,
+            time: m.time,
+            instrument: m.instrument.into(),
+        }),
+        Message::Symbol {
+            index,
+            instrument,
+            time,
+        } => Body::Symbol(pb::Symbol {
+            symbol: instrument.symbol.clone(),
+            isin: instrument.isin.clone(),
+            tick_size: instrument.tick_size,
+            lot_size: instrument.lot_size,
+            time: *time,
+            instrument: (*index).into(),
+        }),
+        Message::ReferencePrice(m) => Body::ReferencePrice(pb::ReferencePrice {
+            price: m.price,
+            prev_close: m.prev_close,
+            time: m.time,
+            instrument: m.instrument.into(),
+        }),
+        Message::SnapshotStart(m) => Body::SnapshotStart(pb::SnapshotStart {
+            orders: m.orders,
+            time: m.time,
+            instrument: m.instrument.into(),
+        }),
+        Message::SnapshotEnd(m) => Body::SnapshotEnd(pb::SnapshotEnd {
+            time: m.time,
+            instrument: m.instrument.into(),
+        }),
+        Message::Level(m) => Body::LevelUpdate(pb::LevelUpdate {
+            side: pb_side(m.side),
+            price: m.price,
+            size: m.size,
+            count: m.count,
+            time: m.time,
+            instrument: m.instrument.into(),
+        }),
+        Message::Admin(m) => Body::Admin(pb::Admin {
+            severity: match m.severity {
+                Severity::Info => pb::Severity::Info,
+                Severity::Warning => pb::Severity::Warning,
+                Severity::Alert => pb::Severity::Alert,
+            } as i32,
+            code: u32::from(m.code),
+            text: m.text.clone(),
+            time: m.time,
+            instrument: m.instrument.into(),
+        }),
+        Message::SelfMatch(m) => Body::SelfMatch(pb::SelfMatch {
+            maker_id: m.maker_id,
+            taker_id: m.taker_id,
+            participant_id: m.participant_id,
+            action: match m.mode {
+                SelfMatchMode::Allow => pb::SelfMatchAction::Unspecified,
+                SelfMatchMode::CancelOldest => pb::SelfMatchAction::CancelOldest,
+                SelfMatchMode::Decrement => pb::SelfMatchAction::Decrement,
+            } as i32,
+            size: m.size,
+            time: m.time,
+            instrument: m.instrument.into(),
+        }),
+        Message::DarkTrade(m) => Body::DarkTrade(pb::DarkTrade {
+            aggressor: pb_side(m.aggressor),
+            price: m.price,
+            size: m.size,
+            participant_id: m.participant_id,
+            time: m.time,
+            instrument: m.instrument.into(),
+        }),
+        Message::OptionQuote(m) => Body::OptionQuote(pb::OptionQuote {
+            expiry: m.expiry,
+            strike: m.strike,
+            option_type: match m.kind {
+                OptionKind::Call => pb::OptionType::Call,
+                OptionKind::Put => pb::OptionType::Put,
+            } as i32,
+            bid: m.bid,
+            ask: m.ask,
+            iv: m.iv,
+            underlying: m.underlying,
+            time: m.time,
+            instrument: m.instrument.into(),
+        }),
+    }
+}
+
+fn optional_side(code: i32) -> Result<Option<Side>> {
+    match pb::Side::try_from(code) {
+        Ok(pb::Side::Unspecified) => Ok(None),
+        Ok(pb::Side::Buy) => Ok(Some(Side::Buy)),
+        Ok(pb::Side::Sell) => Ok(Some(Side::Sell)),
+        Err(_) => Err(format!("invalid side {}", code).into()),
+    }
+}
+
+fn side(code: i32) -> Result<Side> {
+    optional_side(code)?.ok_or_else(|| "missing side".into())
+}
+
+fn instrument(index: u32) -> Result<u16> {
+    u16::try_from(index).map_err(|_| format!("instrument {} out of range", index).into())
+}
+
+fn message(body: Body) -> Result<Message> {
+    Ok(match body {
+        Body::Order(o) => {
+            let order_type = match pb::OrderType::try_from(o.r#type) {
+                Ok(pb::OrderType::Limit) => OrderType::Limit,
+                Ok(pb::OrderType::Market) => OrderType::Market,
+                Ok(pb::OrderType::Stop) => OrderType::Stop,
+                Ok(pb::OrderType::Iceberg) => OrderType::Iceberg,
+                _ => return Err(format!("invalid order type {}", o.r#type).into()),
+            };
+            // As in the binary format, a market order's price is dropped.
+            let price = match order_type {
+                OrderType::Market => 0.0,
+                _ => o.price.ok_or("ORDER without a price")?,
+            };
+            Message::Order(Order {
+                id: o.id,
+                side: side(o.side)?,
+                order_type,
+                price,
+                size: o.size,
+                reserve: o.total_size.saturating_sub(o.size),
+                participant_id: o.participant_id,
+                cl_ord_id: o.cl_ord_id,
+                instrument: instrument(o.instrument)?,
+                created_at: o.time,
+                ttl: 0.0,
+            })
+        }
+        Body::Cancel(m) => Message::Cancel {
+            id: m.id,
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        },
+        Body::Bar(m) => Message::Bar(Bar {
+            start: m.start,
+            end: m.end,
+            open: m.open,
+            high: m.high,
+            low: m.low,
+            close: m.close,
+            volume: m.volume,
+            trades: m.trades,
+            instrument: instrument(m.instrument)?,
+        }),
+        Body::Quote(m) => Message::Quote(Quote {
+            bid_price: m.bid,
+            bid_size: m.bid_size,
+            ask_price: m.ask,
+            ask_size: m.ask_size,
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        }),
+        Body::Depth(m) => {
+            let levels = |side: Vec<pb::Level>| {
+                side.into_iter()
+                    .map(|l| {
+                        (
+                            l.price,
+                            Level {
+                                size: l.size,
+                                count: l.count,
+                            },
+                        )
+                    })
+                    .collect()
+            };
+            Message::Depth(Depth {
+                bids: levels(m.bids),
+                asks: levels(m.asks),
+                instrument: instrument(m.instrument)?,
+                time: m.time,
+            })
+        }
+        Body::Trade(m) => Message::Trade(Trade {
+            maker_id: m.maker_id,
+            taker_id: m.taker_id,
+            aggressor: side(m.aggressor)?,
+            price: m.price,
+            size: m.size,
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        }),
+        Body::MassCancel(m) => Message::MassCancel(MassCancel {
+            participant_id: m.participant_id,
+            side: optional_side(m.side)?,
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        }),
+        Body::VenueState(m) => Message::VenueState(VenueState {
+            state: match pb::TradingState::try_from(m.state) {
+                Ok(pb::TradingState::Halt) => TradingState::Halted,
+                Ok(pb::TradingState::Resume) => TradingState::Open,
+                Ok(pb::TradingState::Auction) => TradingState::Auction,
+                Ok(pb::TradingState::Close) => TradingState::Closed,
+                _ => return Err(format!("invalid venue state {}", m.state).into()),
+            },
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        }),
+        Body::Reject(m) => Message::Reject(Reject {
+            order: decode::rejected_order(
+                m.id,
+                side(m.side)?,
+                m.price,
+                m.size,
+                m.participant_id,
+                instrument(m.instrument)?,
+            ),
+            reason: match pb::RejectReason::try_from(m.reason) {
+                Ok(pb::RejectReason::PriceBand) => RejectReason::PriceBand,
+                Ok(pb::RejectReason::ZeroSize) => RejectReason::ZeroSize,
+                Ok(pb::RejectReason::PriceCollar) => RejectReason::PriceCollar,
+                _ => return Err(format!("invalid reject reason {}", m.reason).into()),
+            },
+            lower: m.lower,
+            upper: m.upper,
+            time: m.time,
+        }),
+        Body::Imbalance(m) => {
+            let price = m.price.unwrap_or(0.0);
+            let side = optional_side(m.side)?;
+            Message::Imbalance(Imbalance {
+                indicative: decode::indicative(price, m.matched, m.imbalance, side),
+                instrument: instrument(m.instrument)?,
+                time: m.time,
+            })
+        }
+        Body::Uncross(m) => Message::Uncross(Uncross {
+            price: m.price,
+            volume: m.volume,
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        }),
+        Body::SessionStart(m) => Message::SessionStart(SessionStart {
+            tick_size: m.tick_size,
+            lot_size: m.lot_size,
+            initial_price: m.initial_price,
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        }),
+        Body::SessionEnd(m) => Message::SessionEnd(SessionEnd {
+            messages: m.messages,
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        }),
+        Body::Symbol(m) => Message::Symbol {
+            index: instrument(m.instrument)?,
+            instrument: Instrument::new(&m.symbol, Some(&m.isin), m.tick_size, m.lot_size),
+            time: m.time,
+        },
+        Body::ReferencePrice(m) => Message::ReferencePrice(ReferencePrice {
+            price: m.price,
+            prev_close: m.prev_close,
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        }),
+        Body::SnapshotStart(m) => Message::SnapshotStart(SnapshotStart {
+            orders: m.orders,
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        }),
+        Body::SnapshotEnd(m) => Message::SnapshotEnd(SnapshotEnd {
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        }),
+        Body::LevelUpdate(m) => Message::Level(LevelUpdate {
+            side: side(m.side)?,
+            price: m.price,
+            size: m.size,
+            count: m.count,
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        }),
+        Body::Admin(m) => Message::Admin(Admin {
+            severity: match pb::Severity::try_from(m.severity) {
+                Ok(pb::Severity::Info) => Severity::Info,
+                Ok(pb::Severity::Warning) => Severity::Warning,
+                Ok(pb::Severity::Alert) => Severity::Alert,
+                _ => return Err(format!("invalid severity {}", m.severity).into()),
+            },
+            code: u16::try_from(m.code)
+                .map_err(|_| format!("ADMIN code {} out of range", m.code))?,
+            text: m.text,
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        }),
+        Body::SelfMatch(m) => Message::SelfMatch(SelfMatch {
+            maker_id: m.maker_id,
+            taker_id: m.taker_id,
+            participant_id: m.participant_id,
+            mode: match pb::SelfMatchAction::try_from(m.action) {
+                Ok(pb::SelfMatchAction::CancelOldest) => SelfMatchMode::CancelOldest,
+                Ok(pb::SelfMatchAction::Decrement) => SelfMatchMode::Decrement,
+                _ => return Err(format!("invalid self-match action {}", m.action).into()),
+            },
+            size: m.size,
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        }),
+        Body::DarkTrade(m) => Message::DarkTrade(DarkTrade {
+            aggressor: side(m.aggressor)?,
+            price: m.price,
+            size: m.size,
+            participant_id: m.participant_id,
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        }),
+        Body::OptionQuote(m) => Message::OptionQuote(OptionQuote {
+            expiry: m.expiry,
+            strike: m.strike,
+            kind: match pb::OptionType::try_from(m.option_type) {
+                Ok(pb::OptionType::Call) => OptionKind::Call,
+                Ok(pb::OptionType::Put) => OptionKind::Put,
+                _ => return Err(format!("invalid option type {}", m.option_type).into()),
+            },
+            bid: m.bid,
+            ask: m.ask,
+            iv: m.iv,
+            underlying: m.underlying,
+            instrument: instrument(m.instrument)?,
+            time: m.time,
+        }),
+    })
+}
@@ -0,0 +1,163 @@
+//! Simple Binary Encoding (`wire_format = "sbe"`): the binary v2 messages
+//! re-framed to the SBE 1.0 layout described by `schema/orderflow.xml`, so
+//! SBE tooling can generate decoders for the feed.
+//!
+//! Root blocks are the v2 payloads field for field. Only the header and
+//! DEPTH's levels (one repeating group per side) differ, and ORDER's
+//! `cl_ord_id` and ADMIN's `text` are SBE variable-length data with a uint8
+//! length.
+
+use std::error::Error;
+
+use super::*;
+use crate::refdata::{ISIN_LEN, SYMBOL_LEN};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// `id` of the schema. Its high byte is not ASCII, so SBE datagrams can't be
+/// mistaken for text ones.
+pub const SCHEMA_ID: u16 = 0xF00F;
+pub const SCHEMA_VERSION: u16 = 0;
+
+/// messageHeader: blockLength, templateId, schemaId, version (uint16 each).
+pub const HEADER_LEN: usize = 8;
+/// groupSizeEncoding: blockLength, numInGroup (uint16 each).
+const GROUP_HEADER_LEN: usize = 4;
+/// DEPTH level: price:double, size:uint64, count:uint32.
+const LEVEL_LEN: usize = 20;
+
+/// Root block length of each template (its v2 `msg_type`).
+fn block_length(template: u8) -> Option<usize> {
+    Some(match template {
+        MSG_ORDER => 41,
+        MSG_CANCEL => 18,
+        MSG_BAR => 62,
+        MSG_QUOTE => 42,
+        MSG_DEPTH => 10,
+        MSG_TRADE => 39,
+        MSG_MASS_CANCEL => 15,
+        MSG_VENUE_STATE => 11,
+        MSG_REJECT => 52,
+        MSG_IMBALANCE => 35,
+        MSG_UNCROSS => 26,
+        MSG_SESSION_START => 30,
+        MSG_SESSION_END => 18,
+        MSG_SYMBOL => SYMBOL_LEN + ISIN_LEN + 22,
+        MSG_REFERENCE_PRICE => 26,
+        MSG_SNAPSHOT_START => 18,
+        MSG_SNAPSHOT_END => 10,
+        MSG_LEVEL => 31,
+        MSG_ADMIN => 13,
+        MSG_SELF_MATCH => 35,
+        MSG_DARK_TRADE => 27,
+        MSG_OPTION_QUOTE => 55,
+        _ => return None,
+    })
+}
+
+/// Whether `buf` starts with an SBE header of this schema.
+pub fn is_sbe(buf: &[u8]) -> bool {
+    buf.len() >= HEADER_LEN && buf[4..6] == SCHEMA_ID.to_le_bytes()
+}
+
+/// One binary v2 message (not a BATCH, and without a send stamp or CRC)
+/// as an SBE message.
+pub fn from_binary(msg: &[u8]) -> Result<Vec<u8>> {
+    if msg.len() < 4 || !msg.starts_with(MAGIC) {
+        return Err("not a binary v2 message".into());
+    }
+    let template = msg[3];
+    let block = block_length(template)
+        .ok_or_else(|| format!("message type {} has no SBE template", template))?;
+    let payload = &msg[4..];
+
+    let mut out = Vec::with_capacity(HEADER_LEN + payload.len() + 2 * GROUP_HEADER_LEN);
+    for field in [block as u16, template as u16, SCHEMA_ID, SCHEMA_VERSION] {
+        out.extend_from_slice(&field.to_le_bytes());
+    }
+    if template != MSG_DEPTH {
+        out.extend_from_slice(payload);
+        return Ok(out);
+    }
+
+    // v2: time, instrument, bid_levels:u8, ask_levels:u8, bids, asks.
+    // SBE: time, instrument, then a group header and the levels for each side.
+    let counts = payload.get(block..block + 2).ok_or("truncated DEPTH")?;
+    out.extend_from_slice(&payload[..block]);
+    let mut levels = &payload[block + 2..];
+    for &n in counts {
+        let len = n as usize * LEVEL_LEN;
+        let side = levels.get(..len).ok_or("truncated DEPTH")?;
+        out.extend_from_slice(&(LEVEL_LEN as u16).to_le_bytes());
+        out.extend_from_slice(&u16::from(n).to_le_bytes());
+        out.extend_from_slice(side);
+        levels = &levels[len..];
+    }
+    Ok(out)
+}
+
+/// The first SBE message in `buf` as binary v2, and how many bytes it took.
+pub fn to_binary(buf: &[u8]) -> Result<(Vec<u8>, usize)> {
+    let field = |at: usize| -> Result<u16> {
+        let b = buf
+            .get(at..at + 2)
+            .ok_or_else(|| format!("truncated SBE message: {} bytes", buf.len()))?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    };
+    let (block, template, schema, version) = (field(0)?, field(2)?, field(4)?, field(6)?);
+    if schema != SCHEMA_ID || version != SCHEMA_VERSION {
+        return Err(format!(
+            "unsupported SBE schema {} version {} (expected {} version {})",
+            schema, version, SCHEMA_ID, SCHEMA_VERSION
+        )
+        .into());
+    }
+    let template = u8::try_from(template)
+        .ok()
+        .filter(|&t| block_length(t).is_some())
+        .ok_or_else(|| format!("unknown SBE template {}", template))?;
+    let expected = block_length(template).unwrap_or_default();
+    if block as usize != expected {
+        return Err(format!(
+            "template {} has blockLength {}, expected {}",
+            template, block, expected
+        )
+        .into());
+    }
+    let truncated = || format!("truncated SBE message of template {}", template);
+
+    let mut out = binary_frame(template, expected);
+    let mut pos = HEADER_LEN + expected;
+    out.extend_from_slice(buf.get(HEADER_LEN..pos).ok_or_else(truncated)?);
+    match template {
+        MSG_ORDER | MSG_ADMIN => {
+            let len = *buf.get(pos).ok_or_else(truncated)? as usize;
+            out.extend_from_slice(buf.get(pos..pos + 1 + len).ok_or_else(truncated)?);
+            pos += 1 + len;
+        }
+        MSG_DEPTH => {
+            let mut levels = Vec::new();
+            for _ in 0..2 {
+                let (level_len, n) = (field(pos)?, field(pos + 2)?);
+                if level_len as usize != LEVEL_LEN || n > u16::from(u8::MAX) {
+                    return Err(format!(
+                        "DEPTH group has blockLength {} and {} entries, expected {} and at most {}",
+                        level_len,
+                        n,
+                        LEVEL_LEN,
+                        u8::MAX
+                    )
+                    .into());
+                }
+                pos += GROUP_HEADER_LEN;
+                let len = n as usize * LEVEL_LEN;
+                out.push(n as u8);
+                levels.extend_from_slice(buf.get(pos..pos + len).ok_or_else(truncated)?);
+                pos += len;
+            }
+            out.extend_from_slice(&levels);
+        }
+        _ => {}
+    }
+    Ok((out, pos))
+}
@@ -0,0 +1,327 @@
+//! Canonical protocol test vectors: fixed messages covering every message
+//! type and the flag and edge-case encodings, for checking third-party
+//! encoders and decoders against this implementation.
+
+use super::decode::Message;
+use crate::auction::{Imbalance, Indicative, Uncross};
+use crate::bands::{Reject, RejectReason};
+use crate::bars::Bar;
+use crate::book::{Depth, Level, LevelUpdate, Quote, SelfMatch, SelfMatchMode};
+use crate::options::{OptionKind, OptionQuote};
+use crate::order::{DarkTrade, MassCancel, Order, OrderType, Side, Trade};
+use crate::refdata::Instrument;
+use crate::venue::{
+    Admin, ReferencePrice, SessionEnd, SessionStart, Severity, SnapshotEnd, SnapshotStart,
+    TradingState, VenueState,
+};
+
+fn order(id: u64, side: Side, order_type: OrderType, price: f64, size: u32, reserve: u32) -> Order {
+    Order {
+        id,
+        side,
+        order_type,
+        price,
+        size,
+        reserve,
+        participant_id: 7,
+        cl_ord_id: String::new(),
+        instrument: 0,
+        created_at: 0.25,
+        ttl: 0.0,
+    }
+}
+
+/// The vectors in publication order. Every value survives the text format's
+/// rounding, so both encodings of a vector carry identical content.
+pub fn canonical() -> Vec<Message> {
+    let mut v = vec![
+        Message::SessionStart(SessionStart {
+            tick_size: 0.01,
+            lot_size: 1,
+            initial_price: 100.0,
+            instrument: 0,
+            time: 0.0,
+        }),
+        // No ISIN given: the synthetic one derived from the symbol.
+        Message::Symbol {
+            index: 0,
+            instrument: Instrument::new("SIM", None, 0.01, 1),
+            time: 0.0,
+        },
+        // Full-width symbol and explicit ISIN, as instrument 1.
+        Message::Symbol {
+            index: 1,
+            instrument: Instrument::new("ABCDEFGH", Some("US0378331005"), 0.05, 100),
+            time: 0.0,
+        },
+        // Warmup snapshot: an order placed before t=0 carries a negative time.
+        Message::SnapshotStart(SnapshotStart {
+            orders: 1,
+            instrument: 0,
+            time: 0.0,
+        }),
+        Message::Order(Order {
+            created_at: -1.5,
+            ..order(100, Side::Buy, OrderType::Limit, 99.8, 30, 0)
+        }),
+        Message::SnapshotEnd(SnapshotEnd {
+            instrument: 0,
+            time: 0.0,
+        }),
+        Message::Order(order(1, Side::Buy, OrderType::Limit, 99.95, 10, 0)),
+        Message::Order(order(2, Side::Sell, OrderType::Limit, 100.05, 25, 0)),
+        // Market orders set the no-price flag and carry price 0.
+        Message::Order(order(3, Side::Buy, OrderType::Market, 0.0, 5, 0)),
+        Message::Order(order(4, Side::Sell, OrderType::Stop, 98.5, 40, 0)),
+        // Icebergs publish the displayed size and the total.
+        Message::Order(order(5, Side::Sell, OrderType::Iceberg, 100.1, 10, 90)),
+        // Client order ID alongside the exchange ID.
+        Message::Order(Order {
+            cl_ord_id: "C7-1".to_string(),
+            ..order(6, Side::Buy, OrderType::Limit, 99.9, 15, 0)
+        }),
+        // Instrument 1's book; every message carries its instrument's index.
+        Message::Order(Order {
+            instrument: 1,
+            ..order(7, Side::Sell, OrderType::Limit, 250.05, 100, 0)
+        }),
+        Message::Trade(Trade {
+            maker_id: 7,
+            taker_id: 8,
+            aggressor: Side::Buy,
+            price: 250.05,
+            size: 100,
+            instrument: 1,
+            time: 0.25,
+        }),
+        Message::Quote(Quote {
+            bid_price: 250.0,
+            bid_size: 200,
+            ask_price: 0.0,
+            ask_size: 0,
+            instrument: 1,
+            time: 0.25,
+        }),
+        Message::Cancel {
+            id: 1,
+            instrument: 0,
+            time: 0.5,
+        },
+        Message::Trade(Trade {
+            maker_id: 2,
+            taker_id: 3,
+            aggressor: Side::Buy,
+            price: 100.05,
+            size: 5,
+            instrument: 0,
+            time: 0.25,
+        }),
+        Message::MassCancel(MassCancel {
+            participant_id: 7,
+            side: None,
+            instrument: 0,
+            time: 0.75,
+        }),
+        Message::MassCancel(MassCancel {
+            participant_id: 8,
+            side: Some(Side::Sell),
+            instrument: 1,
+            time: 0.75,
+        }),
+        Message::Quote(Quote {
+            bid_price: 99.95,
+            bid_size: 10,
+            ask_price: 100.05,
+            ask_size: 20,
+            instrument: 0,
+            time: 0.25,
+        }),
+        // An empty side is price 0 / size 0.
+        Message::Quote(Quote {
+            bid_price: 0.0,
+            bid_size: 0,
+            ask_price: 100.05,
+            ask_size: 20,
+            instrument: 0,
+            time: 0.5,
+        }),
+        Message::Depth(Depth {
+            bids: vec![
+                (99.95, Level { size: 10, count: 1 }),
+                (99.9, Level { size: 35, count: 3 }),
+            ],
+            asks: vec![(100.05, Level { size: 20, count: 2 })],
+            instrument: 0,
+            time: 1.0,
+        }),
+        Message::Depth(Depth {
+            bids: Vec::new(),
+            asks: Vec::new(),
+            instrument: 1,
+            time: 1.0,
+        }),
+        Message::Level(LevelUpdate {
+            side: Side::Buy,
+            price: 99.9,
+            size: 35,
+            count: 3,
+            instrument: 0,
+            time: 1.0,
+        }),
+        // An emptied level is size 0 / count 0.
+        Message::Level(LevelUpdate {
+            side: Side::Sell,
+            price: 100.05,
+            size: 0,
+            count: 0,
+            instrument: 1,
+            time: 1.0,
+        }),
+        Message::Bar(Bar {
+            start: 0.0,
+            end: 1.0,
+            open: 100.0,
+            high: 100.25,
+            low: 99.75,
+            close: 100.1,
+            volume: 1234,
+            trades: 17,
+            instrument: 0,
+        }),
+        Message::Reject(Reject {
+            order: order(6, Side::Buy, OrderType::Limit, 120.0, 10, 0),
+            reason: RejectReason::PriceBand,
+            lower: 95.0,
+            upper: 105.0,
+            time: 1.25,
+        }),
+        Message::Reject(Reject {
+            order: order(7, Side::Sell, OrderType::Limit, 100.0, 0, 0),
+            reason: RejectReason::ZeroSize,
+            lower: 80.0,
+            upper: 120.0,
+            time: 1.25,
+        }),
+    ];
+    v.extend(
+        [
+            TradingState::Halted,
+            TradingState::Auction,
+            TradingState::Open,
+            TradingState::Closed,
+        ]
+        .into_iter()
+        .map(|state| {
+            Message::VenueState(VenueState {
+                state,
+                instrument: 0,
+                time: 2.0,
+            })
+        }),
+    );
+    v.extend([
+        Message::Imbalance(Imbalance {
+            indicative: Indicative {
+                price: Some(100.0),
+                matched: 500,
+                buy_surplus: 120,
+                sell_surplus: 0,
+            },
+            instrument: 0,
+            time: 2.5,
+        }),
+        // Nothing would trade: price 0 and no side.
+        Message::Imbalance(Imbalance {
+            indicative: Indicative {
+                price: None,
+                matched: 0,
+                buy_surplus: 0,
+                sell_surplus: 0,
+            },
+            instrument: 0,
+            time: 2.5,
+        }),
+        Message::Uncross(Uncross {
+            price: 100.0,
+            volume: 500,
+            instrument: 0,
+            time: 3.0,
+        }),
+        Message::ReferencePrice(ReferencePrice {
+            price: 101.5,
+            prev_close: 100.0,
+            instrument: 0,
+            time: 3.0,
+        }),
+        Message::Admin(Admin {
+            severity: Severity::Info,
+            code: 1,
+            text: "scenario -> flash_crash".to_string(),
+            instrument: 0,
+            time: 3.0,
+        }),
+        // Empty text.
+        Message::Admin(Admin {
+            severity: Severity::Alert,
+            code: 65535,
+            text: String::new(),
+            instrument: 1,
+            time: 3.0,
+        }),
+        Message::SelfMatch(SelfMatch {
+            maker_id: 2,
+            taker_id: 3,
+            participant_id: 7,
+            mode: SelfMatchMode::CancelOldest,
+            size: 25,
+            instrument: 0,
+            time: 3.0,
+        }),
+        Message::SelfMatch(SelfMatch {
+            maker_id: 1,
+            taker_id: 8,
+            participant_id: 7,
+            mode: SelfMatchMode::Decrement,
+            size: 5,
+            instrument: 0,
+            time: 3.0,
+        }),
+        Message::DarkTrade(DarkTrade {
+            aggressor: Side::Sell,
+            price: 100.25,
+            size: 40,
+            participant_id: 7,
+            instrument: 0,
+            time: 3.0,
+        }),
+        Message::OptionQuote(OptionQuote {
+            expiry: 21,
+            strike: 102.5,
+            kind: OptionKind::Call,
+            bid: 1.46,
+            ask: 1.52,
+            iv: 0.1812,
+            underlying: 100.25,
+            instrument: 0,
+            time: 3.0,
+        }),
+        // Deep out of the money: nothing bid.
+        Message::OptionQuote(OptionQuote {
+            expiry: 5,
+            strike: 87.5,
+            kind: OptionKind::Put,
+            bid: 0.0,
+            ask: 0.01,
+            iv: 0.3104,
+            underlying: 100.25,
+            instrument: 1,
+            time: 3.0,
+        }),
+        Message::SessionEnd(SessionEnd {
+            messages: 43,
+            instrument: 0,
+            time: 3.0,
+        }),
+    ]);
+    v
+}